@@ -1,37 +1,93 @@
-use core::panic;
+use super::Span;
+use crate::sema::ty::Type;
 
-#[derive(Debug)]
-pub enum Expr {
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+    /// annotated by the type checker
+    pub ty: Option<Type>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
     Unary(UnaryExpr),
     Binary(BinaryExpr),
     Integer(i32),
+    /// an `unsigned int`
+    Unsigned(u32),
+    /// a `long long`
+    Long(i64),
+    Float(f32),
+    /// bytes of a string literal, without the terminating null character
+    String(Vec<u8>),
     LVal(LVal),
+    /// a member access on an expression other than an `LVal`, e.g. `(*p).x` or `f()->x`
+    Member(Box<Expr>, Member),
+    CompoundAssign(CompoundAssign),
+    Conditional(Conditional),
+    Deref(Box<Expr>),
+    AddrOf(Box<Expr>),
+    /// implicit conversion to the type of this expression, inserted by the type checker
+    Cast(Box<Expr>),
     Error,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BinaryExpr {
     pub op: BinaryOp,
     pub lhs: Box<Expr>,
     pub rhs: Box<Expr>,
 }
 
-#[derive(Debug)]
+/// `lval op= val`, where `++` and `--` are `+= 1` and `-= 1`
+#[derive(Debug, Clone)]
+pub struct CompoundAssign {
+    pub op: BinaryOp,
+    /// the assigned place, i.e. an `LVal` or a dereference
+    pub lval: Box<Expr>,
+    pub val: Box<Expr>,
+    /// whether the value before the assignment is yielded, i.e. `x++` or `x--`
+    pub postfix: bool,
+}
+
+/// `cond ? true_val : false_val`, where only one of the values is evaluated
+#[derive(Debug, Clone)]
+pub struct Conditional {
+    pub cond: Box<Expr>,
+    pub true_val: Box<Expr>,
+    pub false_val: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
 pub enum UnaryExpr {
     Unary(UnaryOp, Box<Expr>),
     Call(Call),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Call {
     pub ident: String,
     pub args: Vec<Expr>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LVal {
     pub ident: String,
     pub dims: Vec<Expr>,
+    /// member accesses following the variable, e.g. `.y[1]` and `.z` in `x[0].y[1].z`
+    pub members: Vec<Member>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub ident: String,
+    /// whether it is accessed through a pointer, i.e. `->` rather than `.`
+    pub arrow: bool,
+    pub dims: Vec<Expr>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -40,6 +96,41 @@ pub enum InitVal {
     List(Vec<InitVal>),
 }
 
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            ty: None,
+        }
+    }
+
+    pub fn ty(&self) -> &Type {
+        self.ty.as_ref().expect("expression is not type checked")
+    }
+
+    /// Whether it designates a place in memory, which may be assigned or addressed
+    pub fn is_place(&self) -> bool {
+        match &self.kind {
+            ExprKind::LVal(_) | ExprKind::Deref(_) => true,
+            ExprKind::Member(base, member) => member.arrow || base.is_place(),
+            _ => false,
+        }
+    }
+}
+
+impl CompoundAssign {
+    /// `++` or `--`, i.e. `+= 1` or `-= 1`
+    pub fn new_inc_dec(op: BinaryOp, lval: Expr, postfix: bool, span: Span) -> Self {
+        Self {
+            op,
+            lval: Box::new(lval),
+            val: Box::new(Expr::new(ExprKind::Integer(1), span)),
+            postfix,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum BinaryOp {
     Add,
@@ -47,8 +138,16 @@ pub enum BinaryOp {
     Mul,
     Div,
     Mod,
+    /// logical `&&`
     And,
+    /// logical `||`
     Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    /// arithmetic right shift
+    Shr,
     Eq,
     Neq,
     Lt,
@@ -62,42 +161,190 @@ pub enum UnaryOp {
     Nop,
     Neg,
     Not,
+    BitNot,
 }
 
-pub fn eval_binary(op: BinaryOp, lhs: i32, rhs: i32) -> i32 {
-    match op {
-        BinaryOp::Add => lhs + rhs,
-        BinaryOp::Sub => lhs - rhs,
-        BinaryOp::Mul => lhs * rhs,
+impl BinaryOp {
+    /// Whether the result is an `int` of 0 or 1, i.e. a comparison or a logical operator
+    pub fn is_logical(self) -> bool {
+        matches!(
+            self,
+            Self::And | Self::Or | Self::Eq | Self::Neq | Self::Lt | Self::Le | Self::Gt | Self::Ge
+        )
+    }
+
+    /// Whether the operator only applies to integers, e.g. `%` and `<<`
+    pub fn is_integral(self) -> bool {
+        matches!(
+            self,
+            Self::Mod | Self::BitAnd | Self::BitOr | Self::BitXor | Self::Shl | Self::Shr
+        )
+    }
+}
+
+/// Evaluate an `int` operation with wrapping arithmetic, or `None` if it divides by zero
+pub fn eval_binary(op: BinaryOp, lhs: i32, rhs: i32) -> Option<i32> {
+    Some(match op {
+        BinaryOp::Add => lhs.wrapping_add(rhs),
+        BinaryOp::Sub => lhs.wrapping_sub(rhs),
+        BinaryOp::Mul => lhs.wrapping_mul(rhs),
+        BinaryOp::Div if rhs == 0 => return None,
+        BinaryOp::Div => lhs.wrapping_div(rhs),
+        BinaryOp::Mod if rhs == 0 => return None,
+        BinaryOp::Mod => lhs.wrapping_rem(rhs),
         BinaryOp::And => (lhs != 0 && rhs != 0) as i32,
         BinaryOp::Or => (lhs != 0 || rhs != 0) as i32,
+        BinaryOp::BitAnd => lhs & rhs,
+        BinaryOp::BitOr => lhs | rhs,
+        BinaryOp::BitXor => lhs ^ rhs,
+        // only the low 5 bits of the shift amount are used, as on RISC-V
+        BinaryOp::Shl => lhs.wrapping_shl(rhs as u32),
+        BinaryOp::Shr => lhs.wrapping_shr(rhs as u32),
         BinaryOp::Eq => (lhs == rhs) as i32,
         BinaryOp::Neq => (lhs != rhs) as i32,
         BinaryOp::Lt => (lhs < rhs) as i32,
         BinaryOp::Le => (lhs <= rhs) as i32,
         BinaryOp::Gt => (lhs > rhs) as i32,
         BinaryOp::Ge => (lhs >= rhs) as i32,
-        BinaryOp::Div => {
-            if rhs != 0 {
-                lhs / rhs
-            } else {
-                panic!("attempt to divide an integer by zero");
-            }
+    })
+}
+
+/// Evaluate an `unsigned int` operation, or `None` if it divides by zero
+pub fn eval_unsigned_binary(op: BinaryOp, lhs: u32, rhs: u32) -> Option<u32> {
+    Some(match op {
+        BinaryOp::Add => lhs.wrapping_add(rhs),
+        BinaryOp::Sub => lhs.wrapping_sub(rhs),
+        BinaryOp::Mul => lhs.wrapping_mul(rhs),
+        BinaryOp::Div => lhs.checked_div(rhs)?,
+        BinaryOp::Mod => lhs.checked_rem(rhs)?,
+        BinaryOp::And => (lhs != 0 && rhs != 0) as u32,
+        BinaryOp::Or => (lhs != 0 || rhs != 0) as u32,
+        BinaryOp::BitAnd => lhs & rhs,
+        BinaryOp::BitOr => lhs | rhs,
+        BinaryOp::BitXor => lhs ^ rhs,
+        // a right shift fills in zeros
+        BinaryOp::Shl => lhs.wrapping_shl(rhs),
+        BinaryOp::Shr => lhs.wrapping_shr(rhs),
+        BinaryOp::Eq => (lhs == rhs) as u32,
+        BinaryOp::Neq => (lhs != rhs) as u32,
+        BinaryOp::Lt => (lhs < rhs) as u32,
+        BinaryOp::Le => (lhs <= rhs) as u32,
+        BinaryOp::Gt => (lhs > rhs) as u32,
+        BinaryOp::Ge => (lhs >= rhs) as u32,
+    })
+}
+
+/// Evaluate a `long long` operation like `eval_binary`
+pub fn eval_long_binary(op: BinaryOp, lhs: i64, rhs: i64) -> Option<i64> {
+    Some(match op {
+        BinaryOp::Add => lhs.wrapping_add(rhs),
+        BinaryOp::Sub => lhs.wrapping_sub(rhs),
+        BinaryOp::Mul => lhs.wrapping_mul(rhs),
+        BinaryOp::Div if rhs == 0 => return None,
+        BinaryOp::Div => lhs.wrapping_div(rhs),
+        BinaryOp::Mod if rhs == 0 => return None,
+        BinaryOp::Mod => lhs.wrapping_rem(rhs),
+        BinaryOp::And => (lhs != 0 && rhs != 0) as i64,
+        BinaryOp::Or => (lhs != 0 || rhs != 0) as i64,
+        BinaryOp::BitAnd => lhs & rhs,
+        BinaryOp::BitOr => lhs | rhs,
+        BinaryOp::BitXor => lhs ^ rhs,
+        // only the low 6 bits of the shift amount are used, as on RV64
+        BinaryOp::Shl => lhs.wrapping_shl(rhs as u32),
+        BinaryOp::Shr => lhs.wrapping_shr(rhs as u32),
+        BinaryOp::Eq => (lhs == rhs) as i64,
+        BinaryOp::Neq => (lhs != rhs) as i64,
+        BinaryOp::Lt => (lhs < rhs) as i64,
+        BinaryOp::Le => (lhs <= rhs) as i64,
+        BinaryOp::Gt => (lhs > rhs) as i64,
+        BinaryOp::Ge => (lhs >= rhs) as i64,
+    })
+}
+
+/// Value of an integer literal in the given radix without its prefix, typed by its suffix and
+/// value as in C, or `None` if it fits no integer type
+pub fn int_literal(lit: &str, radix: u32) -> Option<ExprKind> {
+    let digits = lit.trim_end_matches(['l', 'L', 'u', 'U']);
+    let suffix = &lit[digits.len()..];
+    let value = u64::from_str_radix(digits, radix).ok()?;
+    let is_unsigned = suffix.eq_ignore_ascii_case("u");
+    Some(match value {
+        0x8000_0000_0000_0000.. => return None,
+        _ if suffix.eq_ignore_ascii_case("ll") => ExprKind::Long(value as i64),
+        0..=0x7fff_ffff if !is_unsigned => ExprKind::Integer(value as i32),
+        0..=0xffff_ffff if is_unsigned || radix != 10 => ExprKind::Unsigned(value as u32),
+        _ => ExprKind::Long(value as i64),
+    })
+}
+
+/// Bytes of a character or string literal between its quotes, with the escapes replaced
+pub fn unescape(lit: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut chars = lit.bytes().peekable();
+    while let Some(c) = chars.next() {
+        if c != b'\\' {
+            bytes.push(c);
+            continue;
         }
-        BinaryOp::Mod => {
-            if rhs != 0 {
-                lhs % rhs
-            } else {
-                panic!("attempt to calculate the remainder of integer with a divisor of zero");
+        let c = chars.next().unwrap();
+        let byte = match c {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'v' => 0x0b,
+            // at most 3 octal digits
+            b'0'..=b'7' => {
+                let mut val = (c - b'0') as u32;
+                for _ in 0..2 {
+                    match chars.next_if(|d| matches!(d, b'0'..=b'7')) {
+                        Some(d) => val = val * 8 + (d - b'0') as u32,
+                        None => break,
+                    }
+                }
+                val as u8
             }
-        }
+            // any number of hexadecimal digits, truncated to a byte
+            b'x' => {
+                let mut val = 0u32;
+                while let Some(d) = chars.next_if(u8::is_ascii_hexdigit) {
+                    val = val.wrapping_mul(16) + (d as char).to_digit(16).unwrap();
+                }
+                val as u8
+            }
+            // `\\`, `\'`, `\"` and `\?`
+            _ => c,
+        };
+        bytes.push(byte);
     }
+    bytes
 }
 
 pub fn eval_unary(op: UnaryOp, opr: i32) -> i32 {
     match op {
         UnaryOp::Nop => opr,
-        UnaryOp::Neg => -opr,
+        UnaryOp::Neg => opr.wrapping_neg(),
         UnaryOp::Not => (opr == 0) as i32,
+        UnaryOp::BitNot => !opr,
+    }
+}
+
+pub fn eval_unsigned_unary(op: UnaryOp, opr: u32) -> u32 {
+    match op {
+        UnaryOp::Nop => opr,
+        UnaryOp::Neg => opr.wrapping_neg(),
+        UnaryOp::Not => (opr == 0) as u32,
+        UnaryOp::BitNot => !opr,
+    }
+}
+
+pub fn eval_long_unary(op: UnaryOp, opr: i64) -> i64 {
+    match op {
+        UnaryOp::Nop => opr,
+        UnaryOp::Neg => opr.wrapping_neg(),
+        UnaryOp::Not => (opr == 0) as i64,
+        UnaryOp::BitNot => !opr,
     }
 }
@@ -1,8 +1,10 @@
-pub(crate) mod expr;
-pub(crate) mod visit;
+pub mod expr;
+pub mod visit;
 
-pub(crate) use expr::*;
-pub(crate) use visit::*;
+pub use expr::*;
+pub use visit::*;
+
+pub use crate::diag::Span;
 
 #[derive(Debug)]
 pub struct CompUnit {
@@ -13,26 +15,51 @@ pub struct CompUnit {
 pub enum GlobalItem {
     Decl(Decl),
     Func(FuncDef),
+    Struct(StructDef),
+}
+
+#[derive(Debug)]
+pub struct StructDef {
+    pub ident: String,
+    pub fields: Vec<Field>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Field {
+    /// type of the field, or of the elements of an array
+    pub kind: BType,
+    pub ident: String,
+    pub dims: Vec<Expr>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct FuncDef {
-    pub ret_kind: ExprKind,
+    pub ret_kind: BType,
     pub ident: String,
     pub params: Vec<FuncParam>,
-    pub block: Block,
+    /// `None` for a prototype, e.g. `int f(int a[], int n);`
+    pub block: Option<Block>,
+    /// span of the function signature, excluding the body
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct FuncParam {
-    pub kind: ExprKind,
-    pub ident: String,
-    pub dims: Vec<Expr>,
+    pub kind: BType,
+    /// `None` if it is left out in a prototype, e.g. `int f(int);`
+    pub ident: Option<String>,
+    /// `Some` for an array parameter, with the lengths of all but the first dimension
+    pub dims: Option<Vec<Expr>>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Block {
     pub items: Vec<BlockItem>,
+    /// from the `{` to the `}`
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -42,13 +69,40 @@ pub enum BlockItem {
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Decl {
     VarDecl(Vec<VarDecl>),
     ConstDecl(Vec<ConstDecl>),
+    Typedef(Vec<Typedef>),
+    Enum(EnumDef),
 }
 
+/// `typedef kind ident[dims];`, which names the type that `ident` would have as a variable
 #[derive(Debug)]
-pub enum Stmt {
+pub struct Typedef {
+    pub kind: BType,
+    pub ident: String,
+    pub dims: Vec<Expr>,
+    pub span: Span,
+}
+
+/// `enum ident { enumerators };`
+#[derive(Debug)]
+pub struct EnumDef {
+    pub ident: Option<String>,
+    /// the enumerators as `int` constants, so that they are folded like any other constant
+    pub consts: Vec<ConstDecl>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum StmtKind {
     Assign(Assign),
     Block(Block),
     Branch(Branch),
@@ -56,6 +110,13 @@ pub enum Stmt {
     Continue(Continue),
     Expr(Option<Expr>),
     While(While),
+    DoWhile(DoWhile),
+    For(For),
+    Switch(Switch),
+    /// `case value:`, which is only a label in the body of a `switch`
+    Case(Case),
+    /// `default:`
+    Default,
     Return(Return),
 }
 
@@ -76,6 +137,36 @@ pub struct While {
     pub stmt: Box<Stmt>,
 }
 
+#[derive(Debug)]
+pub struct DoWhile {
+    pub stmt: Box<Stmt>,
+    pub cond: Expr,
+}
+
+#[derive(Debug)]
+pub struct For {
+    /// a declaration, or an assignment or expression statement
+    pub init: Option<Box<BlockItem>>,
+    /// `None` if the loop has no condition, i.e. runs until `break`
+    pub cond: Option<Expr>,
+    /// an assignment or expression statement
+    pub step: Option<Box<Stmt>>,
+    pub stmt: Box<Stmt>,
+}
+
+/// `switch (cond) stmt`
+#[derive(Debug)]
+pub struct Switch {
+    pub cond: Expr,
+    pub stmt: Box<Stmt>,
+}
+
+#[derive(Debug)]
+pub struct Case {
+    /// an integer constant, which is folded by the evaluator
+    pub value: Expr,
+}
+
 #[derive(Debug)]
 pub struct Branch {
     pub cond: Expr,
@@ -85,8 +176,8 @@ pub struct Branch {
 
 #[derive(Debug)]
 pub struct Assign {
-    /// Assignment
-    pub lval: LVal,
+    /// the assigned place, i.e. an `LVal` or a dereference
+    pub lval: Box<Expr>,
     pub val: Expr,
 }
 
@@ -94,48 +185,193 @@ pub struct Assign {
 pub struct ConstDecl {
     pub lval: LVal,
     pub init: InitVal,
-    pub kind: ExprKind,
+    pub kind: BType,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct VarDecl {
     pub lval: LVal,
+    /// whether the length of the array is left out, as in `extern int a[];`
+    pub unknown_len: bool,
     pub init: Option<InitVal>,
-    pub kind: ExprKind,
+    pub kind: BType,
+    pub storage: Storage,
+    /// whether it is declared `extern const`, which cannot be assigned
+    pub is_const: bool,
+    pub span: Span,
+}
+
+/// Storage class of a variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    /// a local variable on the stack, or a global one defined in this file
+    Auto,
+    /// `static`, which is a global even inside a function
+    Static,
+    /// `extern`, a global defined in another file unless it is also defined in this one
+    Extern,
 }
 
 #[derive(Debug, Clone)]
-pub enum ExprKind {
-    Array,
+pub enum BType {
     Int,
+    Unsigned,
+    LongLong,
+    Float,
     Void,
+    /// `struct` with the given name
+    Struct(String),
+    /// `enum` with the given name, which is an `int`
+    Enum(String),
+    /// type named by a `typedef`, which is replaced with the type it names after renaming
+    Named(String),
+    Pointer(Box<BType>),
+}
+
+impl BType {
+    /// Wrap the type in the given levels of pointers, e.g. `int **` for 2
+    pub fn pointer_to(self, depth: usize) -> Self {
+        (0..depth).fold(self, |kind, _| Self::Pointer(Box::new(kind)))
+    }
+}
+
+impl Typedef {
+    /// `typedef kind lval;`
+    pub fn new(kind: BType, lval: LVal, span: Span) -> Self {
+        Self {
+            kind,
+            ident: lval.ident,
+            dims: lval.dims,
+            span,
+        }
+    }
+}
+
+impl EnumDef {
+    /// Turn the enumerators into constants, counting up from the last value given
+    pub fn new(
+        ident: Option<String>,
+        enumerators: Vec<(String, Option<Expr>, Span)>,
+        span: Span,
+    ) -> Self {
+        let mut consts: Vec<ConstDecl> = vec![];
+        for (ident, value, span) in enumerators {
+            let value = value.unwrap_or_else(|| match consts.last() {
+                Some(prev) => {
+                    let prev = LVal {
+                        ident: prev.lval.ident.clone(),
+                        dims: vec![],
+                        members: vec![],
+                        span,
+                    };
+                    let bxp = BinaryExpr {
+                        op: BinaryOp::Add,
+                        lhs: Box::new(Expr::new(ExprKind::LVal(prev), span)),
+                        rhs: Box::new(Expr::new(ExprKind::Integer(1), span)),
+                    };
+                    Expr::new(ExprKind::Binary(bxp), span)
+                }
+                None => Expr::new(ExprKind::Integer(0), span),
+            });
+            let lval = LVal {
+                ident,
+                dims: vec![],
+                members: vec![],
+                span,
+            };
+            consts.push(ConstDecl::new(lval, InitVal::Expr(value), BType::Int, span));
+        }
+        Self {
+            ident,
+            consts,
+            span,
+        }
+    }
 }
 
 impl Block {
-    pub fn new_with_vec(items: Vec<BlockItem>) -> Self {
-        Self { items }
+    pub fn new(items: Vec<BlockItem>, span: Span) -> Self {
+        Self { items, span }
+    }
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl Switch {
+    /// Values of the constant labels in the body in order, where `None` is the `default` label
+    pub fn labels(&self) -> Vec<Option<i64>> {
+        fn collect(stmt: &Stmt, labels: &mut Vec<Option<i64>>) {
+            match &stmt.kind {
+                StmtKind::Case(c) => match c.value.kind {
+                    ExprKind::Integer(i) => labels.push(Some(i as i64)),
+                    ExprKind::Unsigned(u) => labels.push(Some(u as i64)),
+                    ExprKind::Long(l) => labels.push(Some(l)),
+                    _ => {}
+                },
+                StmtKind::Default => labels.push(None),
+                StmtKind::Block(b) => b.items.iter().for_each(|item| {
+                    if let BlockItem::Stmt(s) = item {
+                        collect(s, labels);
+                    }
+                }),
+                StmtKind::Branch(b) => {
+                    collect(&b.if_stmt, labels);
+                    if let Some(el_stmt) = &b.el_stmt {
+                        collect(el_stmt, labels);
+                    }
+                }
+                StmtKind::While(w) => collect(&w.stmt, labels),
+                StmtKind::DoWhile(d) => collect(&d.stmt, labels),
+                StmtKind::For(f) => collect(&f.stmt, labels),
+                _ => {}
+            }
+        }
+
+        let mut labels = vec![];
+        collect(&self.stmt, &mut labels);
+        labels
     }
 }
 
 impl VarDecl {
-    pub fn new(lval: LVal, init: Option<InitVal>) -> Self {
-        let kind = if !lval.dims.is_empty() {
-            ExprKind::Array
-        } else {
-            ExprKind::Int
-        };
-        Self { lval, init, kind }
+    pub fn new(
+        lval: LVal,
+        unknown_len: bool,
+        init: Option<InitVal>,
+        kind: BType,
+        storage: Storage,
+        span: Span,
+    ) -> Self {
+        Self {
+            lval,
+            unknown_len,
+            init,
+            kind,
+            storage,
+            is_const: false,
+            span,
+        }
     }
 }
 
 impl ConstDecl {
-    pub fn new(lval: LVal, init: InitVal) -> Self {
-        let kind = if matches!(init, InitVal::List(_)) {
-            ExprKind::Array
-        } else {
-            ExprKind::Int
-        };
-        Self { lval, init, kind }
+    pub fn new(lval: LVal, init: InitVal, kind: BType, span: Span) -> Self {
+        Self {
+            lval,
+            init,
+            kind,
+            span,
+        }
+    }
+
+    /// Whether it is a scalar constant, which is folded into its uses
+    pub fn is_scalar(&self) -> bool {
+        self.lval.dims.is_empty() && !matches!(self.kind, BType::Struct(_))
     }
 }
 
@@ -21,10 +21,26 @@ pub trait MutVisitor<'ast>: Sized {
         walk_const_decl(self, c);
     }
 
+    fn visit_typedef(&mut self, t: &'ast mut Typedef) {
+        walk_typedef(self, t);
+    }
+
+    fn visit_enum_def(&mut self, e: &'ast mut EnumDef) {
+        walk_enum_def(self, e);
+    }
+
     fn visit_func_def(&mut self, f: &'ast mut FuncDef) {
         walk_func_def(self, f);
     }
 
+    fn visit_struct_def(&mut self, s: &'ast mut StructDef) {
+        walk_struct_def(self, s);
+    }
+
+    fn visit_field(&mut self, f: &'ast mut Field) {
+        walk_field(self, f);
+    }
+
     fn visit_func_param(&mut self, f: &'ast mut FuncParam) {
         walk_func_param(self, f);
     }
@@ -57,6 +73,22 @@ pub trait MutVisitor<'ast>: Sized {
         walk_while(self, w);
     }
 
+    fn visit_do_while(&mut self, d: &'ast mut DoWhile) {
+        walk_do_while(self, d);
+    }
+
+    fn visit_for(&mut self, f: &'ast mut For) {
+        walk_for(self, f);
+    }
+
+    fn visit_switch(&mut self, s: &'ast mut Switch) {
+        walk_switch(self, s);
+    }
+
+    fn visit_case(&mut self, c: &'ast mut Case) {
+        walk_case(self, c);
+    }
+
     fn visit_return(&mut self, r: &'ast mut Return) {
         walk_return(self, r);
     }
@@ -69,6 +101,14 @@ pub trait MutVisitor<'ast>: Sized {
         walk_binary_expr(self, b);
     }
 
+    fn visit_compound_assign(&mut self, c: &'ast mut CompoundAssign) {
+        walk_compound_assign(self, c);
+    }
+
+    fn visit_conditional(&mut self, c: &'ast mut Conditional) {
+        walk_conditional(self, c);
+    }
+
     fn visit_unary_expr(&mut self, u: &'ast mut UnaryExpr) {
         walk_unary_expr(self, u);
     }
@@ -105,16 +145,35 @@ pub fn walk_global_item<'a, V: MutVisitor<'a>>(visitor: &mut V, global_item: &'a
     match global_item {
         GlobalItem::Decl(decl) => visitor.visit_decl(decl),
         GlobalItem::Func(func) => visitor.visit_func_def(func),
+        GlobalItem::Struct(s) => visitor.visit_struct_def(s),
     }
 }
 
+pub fn walk_struct_def<'a, V: MutVisitor<'a>>(visitor: &mut V, struct_def: &'a mut StructDef) {
+    walk_list!(visitor, visit_field, &mut struct_def.fields);
+}
+
+pub fn walk_field<'a, V: MutVisitor<'a>>(visitor: &mut V, field: &'a mut Field) {
+    walk_list!(visitor, visit_expr, &mut field.dims);
+}
+
 pub fn walk_decl<'a, V: MutVisitor<'a>>(visitor: &mut V, decl: &'a mut Decl) {
     match decl {
         Decl::ConstDecl(const_decl) => walk_list!(visitor, visit_const_decl, const_decl),
         Decl::VarDecl(var_decl) => walk_list!(visitor, visit_var_decl, var_decl),
+        Decl::Typedef(typedef) => walk_list!(visitor, visit_typedef, typedef),
+        Decl::Enum(enum_def) => visitor.visit_enum_def(enum_def),
     }
 }
 
+pub fn walk_typedef<'a, V: MutVisitor<'a>>(visitor: &mut V, typedef: &'a mut Typedef) {
+    walk_list!(visitor, visit_expr, &mut typedef.dims);
+}
+
+pub fn walk_enum_def<'a, V: MutVisitor<'a>>(visitor: &mut V, enum_def: &'a mut EnumDef) {
+    walk_list!(visitor, visit_const_decl, &mut enum_def.consts);
+}
+
 pub fn walk_var_decl<'a, V: MutVisitor<'a>>(visitor: &mut V, var_decl: &'a mut VarDecl) {
     if let Some(initval) = &mut var_decl.init {
         visitor.visit_initval(initval);
@@ -129,11 +188,15 @@ pub fn walk_const_decl<'a, V: MutVisitor<'a>>(visitor: &mut V, const_decl: &'a m
 
 pub fn walk_func_def<'a, V: MutVisitor<'a>>(visitor: &mut V, func_def: &'a mut FuncDef) {
     walk_list!(visitor, visit_func_param, &mut func_def.params);
-    visitor.visit_block(&mut func_def.block);
+    if let Some(block) = &mut func_def.block {
+        visitor.visit_block(block);
+    }
 }
 
 pub fn walk_func_param<'a, V: MutVisitor<'a>>(visitor: &mut V, func_param: &'a mut FuncParam) {
-    walk_list!(visitor, visit_expr, &mut func_param.dims);
+    if let Some(dims) = &mut func_param.dims {
+        walk_list!(visitor, visit_expr, dims);
+    }
 }
 
 pub fn walk_block<'a, V: MutVisitor<'a>>(visitor: &mut V, block: &'a mut Block) {
@@ -148,25 +211,30 @@ pub fn walk_block_item<'a, V: MutVisitor<'a>>(visitor: &mut V, block_item: &'a m
 }
 
 pub fn walk_stmt<'a, V: MutVisitor<'a>>(visitor: &mut V, stmt: &'a mut Stmt) {
-    match stmt {
-        Stmt::Assign(assign) => visitor.visit_assign(assign),
-        Stmt::Block(block) => visitor.visit_block(block),
-        Stmt::Branch(br) => visitor.visit_branch(br),
-        Stmt::Expr(e) => {
+    match &mut stmt.kind {
+        StmtKind::Assign(assign) => visitor.visit_assign(assign),
+        StmtKind::Block(block) => visitor.visit_block(block),
+        StmtKind::Branch(br) => visitor.visit_branch(br),
+        StmtKind::Expr(e) => {
             if let Some(e) = e {
                 visitor.visit_expr(e);
             }
         }
-        Stmt::Return(ret) => visitor.visit_return(ret),
-        Stmt::While(w) => visitor.visit_while(w),
-        Stmt::Break(b) => visitor.visit_break(b),
-        Stmt::Continue(c) => visitor.visit_continue(c),
+        StmtKind::Return(ret) => visitor.visit_return(ret),
+        StmtKind::While(w) => visitor.visit_while(w),
+        StmtKind::DoWhile(d) => visitor.visit_do_while(d),
+        StmtKind::For(f) => visitor.visit_for(f),
+        StmtKind::Switch(s) => visitor.visit_switch(s),
+        StmtKind::Case(c) => visitor.visit_case(c),
+        StmtKind::Default => {}
+        StmtKind::Break(b) => visitor.visit_break(b),
+        StmtKind::Continue(c) => visitor.visit_continue(c),
     }
 }
 
 pub fn walk_assign<'a, V: MutVisitor<'a>>(visitor: &mut V, assign: &'a mut Assign) {
     visitor.visit_expr(&mut assign.val);
-    visitor.visit_lval(&mut assign.lval);
+    visitor.visit_expr(&mut assign.lval);
 }
 
 pub fn walk_branch<'a, V: MutVisitor<'a>>(visitor: &mut V, branch: &'a mut Branch) {
@@ -182,6 +250,33 @@ pub fn walk_while<'a, V: MutVisitor<'a>>(visitor: &mut V, w: &'a mut While) {
     visitor.visit_stmt(&mut w.stmt);
 }
 
+pub fn walk_do_while<'a, V: MutVisitor<'a>>(visitor: &mut V, d: &'a mut DoWhile) {
+    visitor.visit_stmt(&mut d.stmt);
+    visitor.visit_expr(&mut d.cond);
+}
+
+pub fn walk_for<'a, V: MutVisitor<'a>>(visitor: &mut V, f: &'a mut For) {
+    if let Some(init) = &mut f.init {
+        visitor.visit_block_item(init);
+    }
+    if let Some(cond) = &mut f.cond {
+        visitor.visit_expr(cond);
+    }
+    if let Some(step) = &mut f.step {
+        visitor.visit_stmt(step);
+    }
+    visitor.visit_stmt(&mut f.stmt);
+}
+
+pub fn walk_switch<'a, V: MutVisitor<'a>>(visitor: &mut V, s: &'a mut Switch) {
+    visitor.visit_expr(&mut s.cond);
+    visitor.visit_stmt(&mut s.stmt);
+}
+
+pub fn walk_case<'a, V: MutVisitor<'a>>(visitor: &mut V, c: &'a mut Case) {
+    visitor.visit_expr(&mut c.value);
+}
+
 pub fn walk_return<'a, V: MutVisitor<'a>>(visitor: &mut V, ret: &'a mut Return) {
     if let Some(e) = &mut ret.ret_val {
         visitor.visit_expr(e);
@@ -189,12 +284,23 @@ pub fn walk_return<'a, V: MutVisitor<'a>>(visitor: &mut V, ret: &'a mut Return)
 }
 
 pub fn walk_expr<'a, V: MutVisitor<'a>>(visitor: &mut V, exp: &'a mut Expr) {
-    match exp {
-        Expr::Binary(bxp) => visitor.visit_binary_expr(bxp),
-        Expr::Unary(uxp) => visitor.visit_unary_expr(uxp),
-        Expr::LVal(lval) => visitor.visit_lval(lval),
-        Expr::Integer(_) => {}
-        Expr::Error => panic!("expected an expression"),
+    match &mut exp.kind {
+        ExprKind::Binary(bxp) => visitor.visit_binary_expr(bxp),
+        ExprKind::Unary(uxp) => visitor.visit_unary_expr(uxp),
+        ExprKind::LVal(lval) => visitor.visit_lval(lval),
+        ExprKind::Member(base, member) => {
+            visitor.visit_expr(base);
+            walk_list!(visitor, visit_expr, &mut member.dims);
+        }
+        ExprKind::CompoundAssign(c) => visitor.visit_compound_assign(c),
+        ExprKind::Conditional(c) => visitor.visit_conditional(c),
+        ExprKind::Deref(e) | ExprKind::AddrOf(e) | ExprKind::Cast(e) => visitor.visit_expr(e),
+        ExprKind::Integer(_)
+        | ExprKind::Unsigned(_)
+        | ExprKind::Long(_)
+        | ExprKind::Float(_)
+        | ExprKind::String(_)
+        | ExprKind::Error => {}
     }
 }
 
@@ -203,6 +309,17 @@ pub fn walk_binary_expr<'a, V: MutVisitor<'a>>(visitor: &mut V, bxp: &'a mut Bin
     visitor.visit_expr(&mut bxp.rhs);
 }
 
+pub fn walk_conditional<'a, V: MutVisitor<'a>>(visitor: &mut V, c: &'a mut Conditional) {
+    visitor.visit_expr(&mut c.cond);
+    visitor.visit_expr(&mut c.true_val);
+    visitor.visit_expr(&mut c.false_val);
+}
+
+pub fn walk_compound_assign<'a, V: MutVisitor<'a>>(visitor: &mut V, c: &'a mut CompoundAssign) {
+    visitor.visit_expr(&mut c.val);
+    visitor.visit_expr(&mut c.lval);
+}
+
 pub fn walk_unary_expr<'a, V: MutVisitor<'a>>(visitor: &mut V, uxp: &'a mut UnaryExpr) {
     match uxp {
         UnaryExpr::Unary(_, exp) => visitor.visit_expr(exp),
@@ -216,6 +333,9 @@ pub fn walk_call<'a, V: MutVisitor<'a>>(visitor: &mut V, call: &'a mut Call) {
 
 pub fn walk_lval<'a, V: MutVisitor<'a>>(visitor: &mut V, lval: &'a mut LVal) {
     walk_list!(visitor, visit_expr, &mut lval.dims);
+    for member in &mut lval.members {
+        walk_list!(visitor, visit_expr, &mut member.dims);
+    }
 }
 
 pub fn walk_initval<'a, V: MutVisitor<'a>>(visitor: &mut V, initval: &'a mut InitVal) {
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use lsp_types::{Position, Range, SymbolKind};
+
+use rcompiler::ast::*;
+use rcompiler::diag::{SourceFile, SourceMap};
+use rcompiler::sema::ty::{Type, TypeKind};
+use rcompiler::sema::{Analysis, ConstValue, NameRef, SymbolTable};
+
+/// A name declared in a document, with where it is used
+#[derive(Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// the declaration in C, e.g. `int a[2][3]`
+    pub detail: String,
+    /// whether it is declared at file scope
+    pub is_global: bool,
+    /// the declaration, and the name in it
+    pub decl: Option<(Span, Span)>,
+    /// the spans of the name where it is declared or used, in order
+    pub refs: Vec<Span>,
+}
+
+/// The names in a document, with their spans in the document itself
+#[derive(Debug, Default)]
+pub struct Index {
+    pub symbols: Vec<Symbol>,
+}
+
+impl Index {
+    pub fn new(analysis: &mut Analysis) -> Self {
+        let mut decls = Decls::default();
+        for r in &analysis.refs {
+            decls.written.insert(r.new_name.clone(), r.name.clone());
+        }
+        if let Some((ast, symbols)) = &mut analysis.program {
+            decls.symbols = Some(symbols);
+            ast.accept(&mut decls);
+        } else if let Some(ast) = &mut analysis.recovered {
+            ast.accept(&mut decls);
+        }
+
+        let sources = &analysis.sources;
+        let mut symbols: Vec<Symbol> = vec![];
+        let mut by_name = HashMap::new();
+        for r in &analysis.refs {
+            let Some(span) = locate_name(sources, r) else {
+                continue;
+            };
+            let index = *by_name.entry(r.new_name.as_str()).or_insert_with(|| {
+                let (kind, detail, is_global) = decls.info.remove(&r.new_name).unwrap_or((
+                    SymbolKind::VARIABLE,
+                    r.name.clone(),
+                    false,
+                ));
+                symbols.push(Symbol {
+                    name: r.name.clone(),
+                    kind,
+                    detail,
+                    is_global,
+                    decl: None,
+                    refs: vec![],
+                });
+                symbols.len() - 1
+            });
+            let symbol = &mut symbols[index];
+            // a function is declared where it is defined, along with its body
+            let extent = decls.extents.get(&r.span).copied();
+            if (r.is_decl && symbol.decl.is_none()) || extent.is_some() {
+                let decl = match sources.locate_span(None, Some(extent.unwrap_or(r.span))) {
+                    (_, Some(decl)) if decl.start <= span.start && span.end <= decl.end => decl,
+                    _ => span,
+                };
+                symbol.decl = Some((decl, span));
+            }
+            // a declaration is also renamed as a use
+            if !symbol.refs.contains(&span) {
+                symbol.refs.push(span);
+            }
+        }
+        Self { symbols }
+    }
+
+    pub fn find(&self, offset: usize) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .find(|s| s.refs.iter().any(|r| r.start <= offset && offset <= r.end))
+    }
+
+    /// Move the spans from the old text of the document to the new one, dropping those that
+    /// touch the text in between the parts the two have in common at their start and end
+    pub fn remap(&mut self, old: &str, new: &str) {
+        let prefix = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = old.as_bytes()[prefix..]
+            .iter()
+            .rev()
+            .zip(new.as_bytes()[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (changed, moved) = (old.len() - suffix, new.len() as isize - old.len() as isize);
+        let remap = |span: Span| {
+            if span.end < prefix {
+                Some(span)
+            } else if span.start > changed {
+                let shift = |offset: usize| offset.checked_add_signed(moved).unwrap();
+                Some(Span::new(shift(span.start), shift(span.end)))
+            } else {
+                None
+            }
+        };
+        for symbol in &mut self.symbols {
+            symbol.refs = symbol.refs.iter().filter_map(|&r| remap(r)).collect();
+            symbol.decl = symbol
+                .decl
+                .and_then(|(decl, name)| Some((remap(decl)?, remap(name)?)));
+        }
+        self.symbols.retain(|s| !s.refs.is_empty());
+    }
+}
+
+/// Span of the name in the main file, unless it comes from a header or a macro
+fn locate_name(sources: &SourceMap, r: &NameRef) -> Option<Span> {
+    let text = sources.text();
+    let within = text.get(r.span.start..r.span.end)?;
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let start = within.match_indices(&r.name).find_map(|(i, _)| {
+        let before = within[..i].chars().next_back();
+        let after = within[i + r.name.len()..].chars().next();
+        (!before.is_some_and(is_ident) && !after.is_some_and(is_ident)).then_some(i)
+    })? + r.span.start;
+    let span = Span::new(start, start + r.name.len());
+
+    let (file, span) = sources.locate_span(None, Some(span));
+    let span = span?;
+    let is_main = std::ptr::eq(file, sources.main_file());
+    (is_main && file.src().get(span.start..span.end) == Some(&r.name)).then_some(span)
+}
+
+/// Kinds and declarations of the names by their new names
+#[derive(Default)]
+struct Decls<'a> {
+    symbols: Option<&'a SymbolTable>,
+    /// the names as written by their new names
+    written: HashMap<String, String>,
+    info: HashMap<String, (SymbolKind, String, bool)>,
+    /// the spans of the function definitions, by the spans of their signatures
+    extents: HashMap<Span, Span>,
+    in_func: bool,
+}
+
+impl Decls<'_> {
+    fn ty(&self, new_name: &str) -> Option<&Type> {
+        self.symbols?.data.get(new_name)
+    }
+
+    fn name<'n>(&'n self, new_name: &'n str) -> &'n str {
+        self.written.get(new_name).map_or(new_name, String::as_str)
+    }
+
+    /// The type named in a declaration in C, e.g. `struct s *`
+    fn btype(&self, kind: &BType) -> String {
+        match kind {
+            BType::Int => "int".to_string(),
+            BType::Unsigned => "unsigned int".to_string(),
+            BType::LongLong => "long long".to_string(),
+            BType::Float => "float".to_string(),
+            BType::Void => "void".to_string(),
+            BType::Struct(ident) => format!("struct {}", ident),
+            BType::Enum(ident) => format!("enum {}", ident),
+            BType::Named(ident) => self.name(ident).to_string(),
+            BType::Pointer(base) => format!("{} *", self.btype(base)),
+        }
+    }
+
+    /// Declaration of a variable in C, with the type as written if it has not been checked
+    fn declaration(&self, kind: &BType, new_name: &str) -> String {
+        match self.ty(new_name) {
+            Some(ty) => declaration(ty, self.name(new_name)),
+            None => format!("{} {}", self.btype(kind), self.name(new_name)),
+        }
+    }
+
+    fn insert(&mut self, new_name: &str, kind: SymbolKind, detail: String) {
+        let is_global = !self.in_func;
+        self.info
+            .entry(new_name.to_owned())
+            .or_insert((kind, detail, is_global));
+    }
+}
+
+impl<'ast> MutVisitor<'ast> for Decls<'_> {
+    fn visit_func_def(&mut self, f: &'ast mut FuncDef) {
+        let detail = match self.ty(&f.ident).map(Type::kind) {
+            Some(TypeKind::Func(ret_ty, param_tys, variadic)) => {
+                let mut params: Vec<_> = param_tys.iter().map(|t| t.to_string()).collect();
+                if *variadic {
+                    params.push("...".to_string());
+                }
+                format!("{} {}({})", ret_ty, self.name(&f.ident), params.join(", "))
+            }
+            _ => f.ident.clone(),
+        };
+        self.insert(&f.ident, SymbolKind::FUNCTION, detail);
+        if let Some(block) = &f.block {
+            let extent = Span::new(f.span.start, block.span.end);
+            self.extents.insert(f.span, extent);
+        }
+        self.in_func = true;
+        walk_func_def(self, f);
+        self.in_func = false;
+    }
+
+    fn visit_func_param(&mut self, f: &'ast mut FuncParam) {
+        let Some(ident) = &f.ident else {
+            return;
+        };
+        let detail = self.declaration(&f.kind, ident);
+        self.insert(ident, SymbolKind::VARIABLE, detail);
+    }
+
+    fn visit_var_decl(&mut self, v: &'ast mut VarDecl) {
+        let storage = match v.storage {
+            Storage::Auto => "",
+            Storage::Static => "static ",
+            Storage::Extern if v.is_const => "extern const ",
+            Storage::Extern => "extern ",
+        };
+        let detail = format!("{}{}", storage, self.declaration(&v.kind, &v.lval.ident));
+        self.insert(&v.lval.ident, SymbolKind::VARIABLE, detail);
+    }
+
+    fn visit_const_decl(&mut self, c: &'ast mut ConstDecl) {
+        let ident = self.name(&c.lval.ident);
+        let detail = match (&c.init, self.ty(&c.lval.ident)) {
+            // a scalar has been folded into its value
+            (InitVal::Expr(e), _) => match e.as_const() {
+                Some(ConstValue::Int(i)) => format!("const int {} = {}", ident, i),
+                Some(ConstValue::UInt(u)) => format!("const unsigned int {} = {}", ident, u),
+                Some(ConstValue::Long(l)) => format!("const long long {} = {}", ident, l),
+                Some(ConstValue::Float(f)) => format!("const float {} = {:?}", ident, f),
+                None => format!("const {}", ident),
+            },
+            (_, Some(ty)) => format!("const {}", declaration(ty, ident)),
+            _ => format!("const {}", ident),
+        };
+        self.insert(&c.lval.ident, SymbolKind::CONSTANT, detail);
+    }
+
+    fn visit_typedef(&mut self, t: &'ast mut Typedef) {
+        let dims: String = t
+            .dims
+            .iter()
+            .map(|d| match d.as_const() {
+                Some(len) => format!("[{}]", len.as_long()),
+                None => "[]".to_string(),
+            })
+            .collect();
+        let detail = format!(
+            "typedef {} {}{}",
+            self.btype(&t.kind),
+            self.name(&t.ident),
+            dims
+        );
+        self.insert(&t.ident, SymbolKind::TYPE_PARAMETER, detail);
+    }
+}
+
+/// Declaration of a name of the type in C, e.g. `int a[2][3]` or `int (*p)[3]`
+fn declaration(ty: &Type, name: &str) -> String {
+    let ty = ty.to_string();
+    if let Some(i) = ty.find("(*)") {
+        format!("{}(*{}){}", &ty[..i], name, &ty[i + 3..])
+    } else if let Some(i) = ty.find('[') {
+        format!("{} {}{}", &ty[..i], name, &ty[i..])
+    } else if ty.ends_with('*') {
+        format!("{}{}", ty, name)
+    } else {
+        format!("{} {}", ty, name)
+    }
+}
+
+/// Position of the byte offset in the file, where a character is counted in UTF-16 code units
+pub fn position(file: &SourceFile, offset: usize) -> Position {
+    let line = file.line_index(offset);
+    let start = file.line_start(line);
+    let character = file.src()[start..offset].encode_utf16().count();
+    Position::new(line as u32, character as u32)
+}
+
+pub fn range(file: &SourceFile, span: Span) -> Range {
+    Range::new(position(file, span.start), position(file, span.end))
+}
+
+/// Byte offset of the position in the file, which is clamped to the end of its line
+pub fn offset(file: &SourceFile, pos: Position) -> Option<usize> {
+    let line = pos.line as usize;
+    if line >= file.src().split('\n').count() {
+        return None;
+    }
+    let start = file.line_start(line);
+    let mut units = 0;
+    for (i, c) in file.line(line).char_indices() {
+        if units >= pos.character as usize {
+            return Some(start + i);
+        }
+        units += c.len_utf16();
+    }
+    Some(start + file.line(line).len())
+}
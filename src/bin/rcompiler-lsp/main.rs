@@ -0,0 +1,294 @@
+//! A language server for SysY over stdio, built on the front end of the compiler
+
+mod index;
+
+use std::collections::HashMap;
+use std::panic;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Request as RequestTrait,
+};
+use lsp_types::*;
+
+use rcompiler::diag::{Severity, SourceFile, WarningOptions};
+use rcompiler::sema::analyze;
+
+use index::{offset, range, Index};
+
+/// An open document, with the names in it as of its last change
+struct Document {
+    file: SourceFile,
+    index: Index,
+}
+
+struct Server {
+    connection: Connection,
+    documents: HashMap<Url, Document>,
+}
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncKind::FULL.into()),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    // the connection is closed before waiting for the threads serving it
+    server.run()?;
+    io_threads.join()?;
+    Ok(())
+}
+
+impl Server {
+    fn run(mut self) -> Result<()> {
+        while let Ok(msg) = self.connection.receiver.recv() {
+            match msg {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let resp = self.handle_request(req);
+                    self.connection.sender.send(resp.into())?;
+                }
+                Message::Notification(not) => self.handle_notification(not)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, req: Request) -> Response {
+        match req.method.as_str() {
+            HoverRequest::METHOD => self.reply::<HoverRequest>(req, Self::hover),
+            GotoDefinition::METHOD => self.reply::<GotoDefinition>(req, Self::definition),
+            References::METHOD => self.reply::<References>(req, Self::references),
+            DocumentSymbolRequest::METHOD => {
+                self.reply::<DocumentSymbolRequest>(req, Self::document_symbols)
+            }
+            method => {
+                let message = format!("unsupported request: {}", method);
+                Response::new_err(req.id, ErrorCode::MethodNotFound as i32, message)
+            }
+        }
+    }
+
+    fn reply<R: RequestTrait>(
+        &self,
+        req: Request,
+        handler: impl FnOnce(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(req.params) {
+            Ok(params) => Response::new_ok(req.id, handler(self, params)),
+            Err(err) => Response::new_err(req.id, ErrorCode::InvalidParams as i32, err.to_string()),
+        }
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> Result<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let p: DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+                self.update(p.text_document.uri, p.text_document.text)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let p: DidChangeTextDocumentParams = serde_json::from_value(not.params)?;
+                // the whole text is sent on each change
+                if let Some(change) = p.content_changes.into_iter().last() {
+                    self.update(p.text_document.uri, change.text)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let p: DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+                self.documents.remove(&p.text_document.uri);
+                self.publish(p.text_document.uri, vec![])?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, uri: Url, text: String) -> Result<()> {
+        let name = match uri.to_file_path() {
+            Ok(path) => path.display().to_string(),
+            Err(_) => uri.to_string(),
+        };
+        // the headers are looked for next to the document
+        let include_dirs: Vec<PathBuf> = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| path.parent().map(PathBuf::from))
+            .into_iter()
+            .collect();
+        // a bug in the front end should not bring down the server
+        let file = SourceFile::new(name, text);
+        let mut analysis = match panic::catch_unwind(|| analyze(file, &include_dirs)) {
+            Ok(analysis) => analysis,
+            Err(payload) => {
+                let cause = match payload.downcast_ref::<&str>() {
+                    Some(cause) => cause.to_string(),
+                    None => payload
+                        .downcast_ref::<String>()
+                        .cloned()
+                        .unwrap_or_default(),
+                };
+                let diag = Diagnostic {
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: Some(NumberOrString::String("internal-error".to_string())),
+                    source: Some("rcompiler".to_string()),
+                    message: format!("internal compiler error: {}", cause),
+                    ..Default::default()
+                };
+                return self.publish(uri, vec![diag]);
+            }
+        };
+
+        let mut warnings = WarningOptions::default();
+        warnings.parse("all");
+        warnings.apply(&mut analysis.diags);
+        let file = analysis.sources.main_file();
+        let mut diags = vec![];
+        for diag in analysis.diags.iter() {
+            // a diagnostic in a header is shown at the start of the document
+            let (in_file, span) = analysis.sources.locate(diag);
+            let is_main = std::ptr::eq(in_file, analysis.sources.main_file());
+            let (span, message) = match span {
+                Some(span) if is_main => (span, diag.message.clone()),
+                _ => (
+                    Default::default(),
+                    format!("{}: {}", in_file.name(), diag.message),
+                ),
+            };
+            let related = diag
+                .labels
+                .iter()
+                .filter_map(|label| {
+                    let (in_file, span) = analysis.sources.locate_span(diag.file, Some(label.span));
+                    let span =
+                        span.filter(|_| std::ptr::eq(in_file, analysis.sources.main_file()))?;
+                    Some(DiagnosticRelatedInformation {
+                        location: Location::new(uri.clone(), range(file, span)),
+                        message: label.message.clone(),
+                    })
+                })
+                .collect();
+            diags.push(Diagnostic {
+                range: range(file, span),
+                severity: Some(match diag.severity {
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                }),
+                code: Some(NumberOrString::String(diag.code())),
+                source: Some("rcompiler".to_string()),
+                message,
+                related_information: Some(related),
+                ..Default::default()
+            });
+        }
+
+        // the names are kept until the program can be parsed again, moved along with the text
+        // around them
+        if analysis.program.is_some() || analysis.recovered.is_some() {
+            let index = Index::new(&mut analysis);
+            let file = analysis.sources.into_main_file();
+            self.documents.insert(uri.clone(), Document { file, index });
+        } else if let Some(doc) = self.documents.get_mut(&uri) {
+            let file = analysis.sources.into_main_file();
+            doc.index.remap(doc.file.src(), file.src());
+            doc.file = file;
+        }
+        self.publish(uri, diags)
+    }
+
+    fn publish(&self, uri: Url, diagnostics: Vec<Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let not = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(not.into())?;
+        Ok(())
+    }
+
+    fn symbol_at(&self, pos: &TextDocumentPositionParams) -> Option<(&Document, &index::Symbol)> {
+        let doc = self.documents.get(&pos.text_document.uri)?;
+        let offset = offset(&doc.file, pos.position)?;
+        Some((doc, doc.index.find(offset)?))
+    }
+
+    fn hover(&self, p: HoverParams) -> Option<Hover> {
+        let pos = &p.text_document_position_params;
+        let (doc, symbol) = self.symbol_at(pos)?;
+        let offset = offset(&doc.file, pos.position)?;
+        let span = symbol
+            .refs
+            .iter()
+            .find(|r| r.start <= offset && offset <= r.end)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```c\n{}\n```", symbol.detail),
+            }),
+            range: Some(range(&doc.file, *span)),
+        })
+    }
+
+    fn definition(&self, p: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let pos = &p.text_document_position_params;
+        let (doc, symbol) = self.symbol_at(pos)?;
+        let (_, name) = symbol.decl?;
+        let location = Location::new(pos.text_document.uri.clone(), range(&doc.file, name));
+        Some(GotoDefinitionResponse::Scalar(location))
+    }
+
+    fn references(&self, p: ReferenceParams) -> Option<Vec<Location>> {
+        let pos = &p.text_document_position;
+        let (doc, symbol) = self.symbol_at(pos)?;
+        let decl = symbol.decl.map(|(_, name)| name);
+        let locations = symbol
+            .refs
+            .iter()
+            .filter(|&&r| p.context.include_declaration || Some(r) != decl)
+            .map(|&r| Location::new(pos.text_document.uri.clone(), range(&doc.file, r)))
+            .collect();
+        Some(locations)
+    }
+
+    /// The functions and the global variables, constants and types
+    fn document_symbols(&self, p: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let doc = self.documents.get(&p.text_document.uri)?;
+        let mut symbols: Vec<_> = doc
+            .index
+            .symbols
+            .iter()
+            .filter(|s| s.is_global)
+            .filter_map(|s| {
+                let (decl, name) = s.decl?;
+                #[allow(deprecated)]
+                Some(DocumentSymbol {
+                    name: s.name.clone(),
+                    detail: Some(s.detail.clone()),
+                    kind: s.kind,
+                    tags: None,
+                    deprecated: None,
+                    range: range(&doc.file, decl),
+                    selection_range: range(&doc.file, name),
+                    children: None,
+                })
+            })
+            .collect();
+        symbols.sort_by_key(|s| s.range.start);
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 
 use super::*;
@@ -11,6 +12,8 @@ pub struct RegAllocator {
     free_saved_regs: u32,
     free_temp_regs: u32,
     offset: usize,
+    /// size of a spilled value, which takes a whole register
+    slot_size: usize,
 }
 
 const SAVED_REGS: u32 = 12;
@@ -86,7 +89,7 @@ impl RegAllocator {
             expired.push((idx, reg));
         }
 
-        expired.sort_by(|a, b| b.0.cmp(&a.0));
+        expired.sort_by_key(|e| Reverse(e.0));
         for &(idx, reg) in &expired {
             self.free_reg(reg);
             self.active.remove(idx);
@@ -105,13 +108,13 @@ impl RegAllocator {
                 .entry(last_val)
                 .and_modify(|p| *p = Place::Mem(self.offset as i32));
             self.sort_active();
-            self.offset += 4;
+            self.offset += self.slot_size;
         } else {
             self.places
                 .get_mut(&f)
                 .unwrap()
                 .insert(val, Place::Mem(self.offset as i32));
-            self.offset += 4;
+            self.offset += self.slot_size;
         }
     }
 
@@ -132,7 +135,7 @@ impl RegAllocator {
     }
 
     fn sort_active(&mut self) {
-        self.active.sort_by(|a, b| a.0.end.cmp(&b.0.end));
+        self.active.sort_by_key(|a| a.0.end);
     }
 
     fn alloc_saved_reg(&mut self) -> RegID {
@@ -173,7 +176,7 @@ impl RegAllocator {
         }
     }
 
-    pub fn new() -> Self {
+    pub fn new(target: Target) -> Self {
         Self {
             places: HashMap::new(),
             spill_size: HashMap::new(),
@@ -183,6 +186,7 @@ impl RegAllocator {
             free_saved_regs: 0,
             free_temp_regs: 0,
             offset: 0,
+            slot_size: target.word_size() as usize,
         }
     }
 }
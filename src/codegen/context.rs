@@ -26,9 +26,11 @@ pub enum Place {
 
 pub struct Context<'i> {
     program: &'i Program,
+    float_sigs: &'i FloatSignatures,
     global_values: HashMap<Value, String>,
     allocator: RegAllocator,
     cur_func: Option<FunctionInfo>,
+    target: Target,
 }
 
 pub struct FunctionInfo {
@@ -44,16 +46,18 @@ pub struct FunctionInfo {
 
 impl<'i> Context<'i> {
     thread_local! {
-        static FUNCID: Cell<i32> = Cell::new(-1);
-        static NAMETAG: Cell<u32> = Cell::new(0);
+        static FUNCID: Cell<i32> = const { Cell::new(-1) };
+        static NAMETAG: Cell<u32> = const { Cell::new(0) };
     }
 
-    pub fn new(program: &'i Program) -> Self {
+    pub fn new(program: &'i Program, float_sigs: &'i FloatSignatures, target: Target) -> Self {
         let mut ctx = Self {
             program,
+            float_sigs,
             global_values: HashMap::new(),
-            allocator: RegAllocator::new(),
+            allocator: RegAllocator::new(target),
             cur_func: None,
+            target,
         };
 
         let mut live_ranges = LiveRange::new();
@@ -63,6 +67,10 @@ impl<'i> Context<'i> {
         ctx
     }
 
+    pub fn target(&self) -> Target {
+        self.target
+    }
+
     pub fn value_kind(&self, val: Value) -> ValueKind {
         if self.is_global(val) {
             self.program.borrow_value(val).kind().clone()
@@ -83,7 +91,7 @@ impl<'i> Context<'i> {
         self.cur_func_data().dfg().value(val)
     }
 
-    pub fn global_value_data(&self, val: Value) -> Ref<ValueData> {
+    pub fn global_value_data(&self, val: Value) -> Ref<'_, ValueData> {
         self.program.borrow_value(val)
     }
 
@@ -105,6 +113,11 @@ impl<'i> Context<'i> {
         }
     }
 
+    /// Which parameters and return value of the function are passed in floating-point registers
+    pub fn float_sig(&self, id: Function) -> Option<&FloatSignature> {
+        self.float_sigs.get(&id)
+    }
+
     pub fn func_data(&self, id: Function) -> &FunctionData {
         self.program.func(id)
     }
@@ -123,8 +136,11 @@ impl<'i> Context<'i> {
 
     pub fn set_func(&mut self, f: Function) {
         self.cur_func = Some(FunctionInfo::new(f));
-        self.cur_func_mut().saved_regs =
-            (0, *self.allocator.max_reg.get(&f).unwrap_or(&0) as i32 * 4);
+        let word = self.target.word_size();
+        self.cur_func_mut().saved_regs = (
+            0,
+            *self.allocator.max_reg.get(&f).unwrap_or(&0) as i32 * word,
+        );
         self.cur_func_mut().spilled_size = *self.allocator.spill_size.get(&f).unwrap_or(&0) as i32;
 
         Self::NAMETAG.with(|id| id.set(0));
@@ -226,7 +242,8 @@ lazy_static! {
     static ref REG_NAMES: Vec<&'static str> = vec![
         "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
         "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
-        "t5", "t6",
+        "t5", "t6", // floating-point registers are only used to pass and operate on `float`s
+        "ft0", "ft1", "fa0", "fa1", "fa2", "fa3", "fa4", "fa5", "fa6", "fa7",
     ];
     static ref NAME_ID_MAPPING: HashMap<&'static str, RegID> = {
         let mut m = HashMap::new();
@@ -1,5 +1,3 @@
-use std::cmp::max;
-
 use lazy_static_include::lazy_static::lazy_static;
 
 use super::*;
@@ -9,18 +7,63 @@ lazy_static! {
     static ref TMP2: RegID = "t1".into_id();
 }
 
+/// Where an argument of a function call is passed
+#[derive(Debug, Clone, Copy)]
+enum ArgPlace {
+    Reg(RegID),
+    /// a `float` in a floating-point register
+    FloatReg(RegID),
+    /// offset from the stack pointer of the caller
+    Stack(i32),
+}
+
+/// Assign the arguments of a call to registers as the ILP32F calling convention does: `float`s
+/// go to fa0-fa7 and then to the integer registers left, and the rest to a0-a7 and the stack.
+/// Variable arguments always go to the integer registers.
+fn arg_places(sig: Option<&FloatSignature>, arg_num: usize, word: i32) -> Vec<ArgPlace> {
+    let (mut int_regs, mut float_regs, mut stack) = (0, 0, 0);
+    (0..arg_num)
+        .map(|i| {
+            let is_float = sig.is_some_and(|sig| sig.params.get(i) == Some(&true));
+            if is_float && float_regs < 8 {
+                float_regs += 1;
+                ArgPlace::FloatReg(format!("fa{}", float_regs - 1).into_id())
+            } else if int_regs < 8 {
+                int_regs += 1;
+                ArgPlace::Reg(format!("a{}", int_regs - 1).into_id())
+            } else {
+                stack += 1;
+                ArgPlace::Stack((stack - 1) * word)
+            }
+        })
+        .collect()
+}
+
 pub trait GenerateAsm {
     fn generate(&self, ctx: &mut Context, p: &mut AsmProgram);
 }
 
 impl GenerateAsm for Program {
     fn generate(&self, ctx: &mut Context, p: &mut AsmProgram) {
-        Type::set_ptr_size(4);
+        Type::set_ptr_size(ctx.target().word_size() as usize);
 
         self.inst_layout().iter().for_each(|&g| {
-            let name = ctx.global_value_data(g).name().as_ref().unwrap()[1..].to_string();
-            ctx.register_global_var(g, name);
-            p.push(AsmValue::Directive(Directive::Data));
+            let name = ctx.global_value_data(g).name().clone().unwrap();
+            // an `extern` variable is only referred to, by the name it has in its own file
+            if let Some(name) = name.strip_prefix(EXTERN_PREFIX) {
+                ctx.register_global_var(g, name.to_string());
+                return;
+            }
+            ctx.register_global_var(g, name[1..].to_string());
+            let section = match is_string(ctx, g) {
+                true => Directive::Rodata,
+                false => Directive::Data,
+            };
+            p.push(AsmValue::Directive(section));
+            // a global may hold pointers, which are double words on RV64
+            if ctx.target() == Target::Rv64 && !is_string(ctx, g) {
+                p.directive(Directive::Align(3));
+            }
             g.generate(ctx, p);
         });
 
@@ -32,31 +75,47 @@ impl GenerateAsm for Program {
                 ctx.set_func(f);
                 data.generate(ctx, p);
             });
+
+        // the routines operating on `long long`s are only declared in Koopa IR
+        self.funcs()
+            .values()
+            .filter_map(|data| Routine::from_callee(data.name()))
+            .for_each(|routine| p.push(AsmValue::Verbatim(routine_body(routine))));
     }
 }
 
 impl GenerateAsm for FunctionData {
     fn generate(&self, ctx: &mut Context, p: &mut AsmProgram) {
-        let max_arg_num = self
+        let word = ctx.target().word_size();
+        // intrinsics are expanded inline, so they are not calls
+        let max_stack_args = self
             .dfg()
             .values()
             .values()
             .filter_map(|data| match data.kind() {
-                ValueKind::Call(c) => Some(c.args().len()),
+                ValueKind::Call(c) => {
+                    let callee = ctx.get_func_name(c.callee());
+                    Intrinsic::from_callee(callee).is_none().then(|| {
+                        arg_places(ctx.float_sig(c.callee()), c.args().len(), word)
+                            .iter()
+                            .filter(|place| matches!(place, ArgPlace::Stack(_)))
+                            .count()
+                    })
+                }
                 ValueKind::Store(s)
-                    if matches!(self.dfg().value(s.value()).kind(), ValueKind::ZeroInit(_)) =>
+                    if matches!(ctx.value_kind(s.value()), ValueKind::ZeroInit(_)) =>
                 {
-                    Some(3)
+                    Some(0)
                 }
                 _ => None,
             })
             .max();
 
-        let is_leaf = max_arg_num.is_none();
-        let protect_space = if is_leaf { 0 } else { 4 };
+        let is_leaf = max_stack_args.is_none();
+        let protect_space = if is_leaf { 0 } else { word };
         ctx.cur_func_mut().set_is_leaf(is_leaf);
 
-        let spilled_arg_size = max(max_arg_num.unwrap_or(0) as i32 - 8, 0) * 4;
+        let spilled_arg_size = max_stack_args.unwrap_or(0) as i32 * word;
         let saved_reg_range = ctx.cur_func().saved_regs();
         let base_offset = spilled_arg_size + (saved_reg_range.1 - saved_reg_range.0);
         ctx.cur_func_mut().set_base_offset(base_offset);
@@ -66,12 +125,14 @@ impl GenerateAsm for FunctionData {
             for &val in node.insts().keys() {
                 let data = self.dfg().value(val);
                 if let ValueKind::Alloc(_) = data.kind() {
-                    let size = match data.ty().kind() {
-                        TypeKind::Pointer(base_ty) => base_ty.size() as i32,
+                    let base_ty = match data.ty().kind() {
+                        TypeKind::Pointer(base_ty) => base_ty.clone(),
                         _ => unreachable!(),
                     };
+                    let align = align_of(&base_ty);
+                    off = (off + align - 1) / align * align;
                     ctx.cur_func_mut().spill_to_mem(val, off);
-                    off += size;
+                    off += base_ty.size() as i32;
                 }
             }
         });
@@ -88,18 +149,77 @@ impl GenerateAsm for FunctionData {
         p.prologue(&self.name()[1..], ctx, ctx.cur_func().saved_regs(), is_leaf);
 
         let ss = ctx.cur_func().ss();
-        self.params().iter().enumerate().for_each(|(i, param)| {
-            if i < 8 {
-                p.write_back(ctx, format!("a{}", i).into_id(), *param);
-            } else {
-                p.load(*TMP2, "sp".into_id(), ss + (i as i32 - 8) * 4);
-                p.write_back(ctx, *TMP2, *param);
+        let places = arg_places(
+            ctx.float_sig(ctx.cur_func().id()),
+            self.params().len(),
+            word,
+        );
+        let mut reg_params: Vec<_> = self
+            .params()
+            .iter()
+            .zip(&places)
+            .filter_map(|(&param, place)| match place {
+                ArgPlace::Reg(reg) => Some((*reg, param)),
+                _ => None,
+            })
+            .collect();
+        // the place of a parameter may be the register of another
+        while !reg_params.is_empty() {
+            // a parameter is ready to move if its place is not the register of another one
+            let free =
+                (0..reg_params.len()).find(|&i| match ctx.get_local_place(reg_params[i].1) {
+                    Place::Reg(dst) => reg_params
+                        .iter()
+                        .enumerate()
+                        .all(|(j, (src, _))| j == i || *src != dst),
+                    Place::Mem(_) => true,
+                });
+            match free {
+                Some(i) => {
+                    let (src, param) = reg_params.remove(i);
+                    p.write_back(ctx, src, param);
+                }
+                None => {
+                    // break the cycle through a temporary register
+                    p.mv(*TMP2, reg_params[0].0);
+                    reg_params[0].0 = *TMP2;
+                }
             }
-        });
+        }
+        // the others are not in registers which may be allocated
+        self.params()
+            .iter()
+            .zip(places)
+            .for_each(|(param, place)| match place {
+                ArgPlace::Reg(_) => {}
+                ArgPlace::FloatReg(reg) => {
+                    p.unary(AsmUnaryOp::FmvXW, *TMP2, reg);
+                    p.write_back(ctx, *TMP2, *param);
+                }
+                ArgPlace::Stack(off) => {
+                    p.load(*TMP2, "sp".into_id(), ss + off);
+                    p.write_back(ctx, *TMP2, *param);
+                }
+            });
 
+        // the chains of comparisons of a `switch` are replaced by a single dispatch
+        let switches = SwitchChains::new(self);
         self.layout().bbs().iter().for_each(|(bb, node)| {
-            p.local_symbol(ctx.cur_func().get_bb_name(bb));
-            node.insts().keys().for_each(|inst| inst.generate(ctx, p))
+            if switches.is_absorbed(bb) {
+                return;
+            }
+            let name = ctx.cur_func().get_bb_name(bb).clone();
+            p.local_symbol(&name);
+            match switches.get(bb) {
+                Some(switch) => {
+                    node.insts()
+                        .keys()
+                        .filter(|&&inst| !switch.replaces(inst))
+                        .for_each(|inst| inst.generate(ctx, p));
+                    switch.generate(ctx, p, &name);
+                }
+                None => node.insts().keys().for_each(|inst| inst.generate(ctx, p)),
+            }
         })
     }
 }
@@ -145,7 +265,10 @@ impl GenerateAsm for Store {
         }
         let dst = p.read_value_addr(ctx, t1, self.dest());
         let val = p.read_value(ctx, t2, self.value());
-        p.store(val, dst, 0);
+        match ctx.value_ty(self.value()).is_i32() {
+            true => p.store_word(val, dst, 0),
+            false => p.store(val, dst, 0),
+        }
     }
 }
 
@@ -187,6 +310,12 @@ impl GenerateAsm for Return {
             if ret != a0 {
                 p.mv(a0, ret);
             }
+            if ctx
+                .float_sig(ctx.cur_func().id())
+                .is_some_and(|sig| sig.ret)
+            {
+                p.unary(AsmUnaryOp::FmvWX, "fa0".into_id(), a0);
+            }
         }
         p.epilogue(ctx, ctx.cur_func().saved_regs(), ctx.cur_func().is_leaf());
     }
@@ -213,10 +342,14 @@ impl NonUnitGenerateAsm for Load {
         let t1 = *TMP1;
         let t2 = *TMP2;
         let src = p.read_value_addr(ctx, t1, self.src());
+        let load = match ctx.value_ty(val).is_i32() {
+            true => AsmProgram::load_word,
+            false => AsmProgram::load,
+        };
         match ctx.get_local_place(val) {
-            Place::Reg(dst) => p.load(dst, src, 0),
+            Place::Reg(dst) => load(p, dst, src, 0),
             Place::Mem(_) => {
-                p.load(t2, src, 0);
+                load(p, t2, src, 0);
                 p.write_back(ctx, t2, val);
             }
         }
@@ -252,11 +385,64 @@ impl NonUnitGenerateAsm for Binary {
 impl NonUnitGenerateAsm for GlobalAlloc {
     fn generate(&self, ctx: &mut Context, p: &mut AsmProgram, val: Value) {
         let id = ctx.get_global_var(&val);
-        p.global_symbol(id);
-        self.init().generate(ctx, p)
+        if has_prefix(ctx, val, STATIC_PREFIX) {
+            p.local_symbol(id);
+            return self.init().generate(ctx, p);
+        }
+        if !is_string(ctx, val) {
+            p.global_symbol(id);
+            return self.init().generate(ctx, p);
+        }
+
+        // the words of a string literal are packed into bytes
+        p.local_symbol(id);
+        let mut bytes: Vec<_> = match ctx.global_value_data(self.init()).kind() {
+            ValueKind::Aggregate(chars) => chars
+                .elems()
+                .iter()
+                .map(|&c| match ctx.global_value_data(c).kind() {
+                    ValueKind::Integer(i) => i.value() as u8,
+                    _ => 0,
+                })
+                .collect(),
+            _ => vec![0; ctx.global_value_data(self.init()).ty().size() / 4],
+        };
+        bytes.pop();
+        if bytes.contains(&0) {
+            bytes.push(0);
+            bytes
+                .into_iter()
+                .for_each(|b| p.directive(Directive::Byte(b)));
+        } else {
+            p.directive(Directive::Asciz(bytes));
+        }
     }
 }
 
+/// Alignment of a value of the type on the stack, where a pointer takes a whole register
+fn align_of(ty: &Type) -> i32 {
+    match ty.kind() {
+        TypeKind::Pointer(_) => ty.size() as i32,
+        // the words of a `struct` may hold pointers
+        TypeKind::Array(base_ty, _) => {
+            align_of(base_ty).max(Type::get_pointer(base_ty.clone()).size() as i32)
+        }
+        _ => 4,
+    }
+}
+
+/// Whether the global value is the array of a string literal
+fn is_string(ctx: &Context, val: Value) -> bool {
+    has_prefix(ctx, val, STRING_PREFIX)
+}
+
+fn has_prefix(ctx: &Context, val: Value, prefix: &str) -> bool {
+    let data = ctx.global_value_data(val);
+    data.name()
+        .as_ref()
+        .is_some_and(|name| name.starts_with(prefix))
+}
+
 impl NonUnitGenerateAsm for ZeroInit {
     fn generate(&self, ctx: &mut Context, p: &mut AsmProgram, val: Value) {
         let size = ctx.global_value_data(val).ty().size();
@@ -266,26 +452,81 @@ impl NonUnitGenerateAsm for ZeroInit {
 
 impl NonUnitGenerateAsm for Call {
     fn generate(&self, ctx: &mut Context, p: &mut AsmProgram, val: Value) {
-        self.args().iter().enumerate().for_each(|(i, &arg)| {
-            if i < 8 {
-                let dst = format!("a{}", i).into_id();
-                let reg = p.read_value(ctx, dst, arg);
-                if reg != dst {
-                    p.mv(dst, reg);
+        if let Some(op) = Intrinsic::from_callee(ctx.get_func_name(self.callee())) {
+            // an unsigned division by a power of two is a shift or a mask
+            let imm = match (op, self.args()) {
+                (Intrinsic::UDiv | Intrinsic::URem, &[_, rhs]) => match ctx.value_kind(rhs) {
+                    ValueKind::Integer(imm) => Some(imm.value() as u32),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(imm) = imm {
+                let lhs = p.read_value(ctx, *TMP1, self.args()[0]);
+                let dst = match ctx.get_local_place(val) {
+                    Place::Reg(reg) => reg,
+                    Place::Mem(_) => *TMP2,
+                };
+                match op {
+                    Intrinsic::UDiv => p.divui(dst, lhs, imm),
+                    _ => p.remui(dst, lhs, imm),
                 }
-            } else {
-                let reg = p.read_value(ctx, *TMP2, arg);
-                p.store(reg, "sp".into_id(), (i as i32 - 8) * 4);
+                p.write_back(ctx, dst, val);
+                return;
             }
-        });
+
+            let args = [*TMP1, *TMP2];
+            let args: Vec<_> = self
+                .args()
+                .iter()
+                .zip(args)
+                .map(|(&arg, tmp)| p.read_value(ctx, tmp, arg))
+                .collect();
+            if op == Intrinsic::StoreLong {
+                return p.ir_intrinsic(op, *TMP1, &args);
+            }
+            let dst = match ctx.get_local_place(val) {
+                Place::Reg(reg) => reg,
+                Place::Mem(_) => *TMP2,
+            };
+            p.ir_intrinsic(op, dst, &args);
+            p.write_back(ctx, dst, val);
+            return;
+        }
+
+        let sig = ctx.float_sig(self.callee());
+        let places = arg_places(sig, self.args().len(), ctx.target().word_size());
+        self.args()
+            .iter()
+            .zip(places)
+            .for_each(|(&arg, place)| match place {
+                ArgPlace::Reg(dst) => {
+                    let reg = p.read_value(ctx, dst, arg);
+                    if reg != dst {
+                        p.mv(dst, reg);
+                    }
+                }
+                ArgPlace::FloatReg(dst) => {
+                    let reg = p.read_value(ctx, *TMP2, arg);
+                    p.unary(AsmUnaryOp::FmvWX, dst, reg);
+                }
+                ArgPlace::Stack(off) => {
+                    let reg = p.read_value(ctx, *TMP2, arg);
+                    p.store(reg, "sp".into_id(), off);
+                }
+            });
 
         let callee = &ctx.get_func_name(self.callee())[1..];
         p.call(callee);
 
         // write the return value to pre-allocated space
         if let TypeKind::Function(_, ret_ty) = ctx.func_data(self.callee()).ty().kind() {
-            if ret_ty.is_i32() {
-                p.write_back(ctx, "a0".into_id(), val);
+            if !ret_ty.is_unit() {
+                let a0 = "a0".into_id();
+                if sig.is_some_and(|sig| sig.ret) {
+                    p.unary(AsmUnaryOp::FmvXW, a0, "fa0".into_id());
+                }
+                p.write_back(ctx, a0, val);
             }
         }
     }
@@ -40,10 +40,16 @@ impl LiveRange {
                 for val in node.insts().keys() {
                     self.number_mapping.insert(*val, id);
                     let kind = f.dfg().value(*val).kind();
-                    if matches!(kind, ValueKind::Call(_)) {
-                        self.function_calls.get_mut(fid).unwrap().push(id);
+                    if let ValueKind::Call(call) = kind {
+                        // intrinsics are expanded inline and clobber no allocated registers
+                        if Intrinsic::from_callee(p.func(call.callee()).name()).is_none() {
+                            self.function_calls.get_mut(fid).unwrap().push(id);
+                        }
                     } else if let ValueKind::Store(store) = kind {
-                        if matches!(f.dfg().value(store.value()).kind(), ValueKind::ZeroInit(_)) {
+                        // the stored value may be the address of a global variable
+                        if !store.value().is_global()
+                            && matches!(f.dfg().value(store.value()).kind(), ValueKind::ZeroInit(_))
+                        {
                             self.function_calls.get_mut(fid).unwrap().push(id);
                         }
                     }
@@ -78,12 +84,18 @@ impl LiveRange {
                     }
                     self.find_live_range_of(fid, f, val, val);
                 }
+                // a jump or a branch back to a visited block closes a loop, e.g. a `do-while`
                 let bb_exit = *node.insts().back_key().unwrap();
-                if let ValueKind::Jump(j) = f.dfg().value(bb_exit).kind() {
-                    if !visited.contains(&j.target()) {
+                let targets = match f.dfg().value(bb_exit).kind() {
+                    ValueKind::Jump(j) => vec![j.target()],
+                    ValueKind::Branch(b) => vec![b.true_bb(), b.false_bb()],
+                    _ => vec![],
+                };
+                for target in targets {
+                    if !visited.contains(&target) {
                         continue;
                     }
-                    let loop_begin = first_inst_of_bb(f, j.target());
+                    let loop_begin = first_inst_of_bb(f, target);
                     let loop_begin = *self.number_mapping.get(&loop_begin).unwrap();
                     let loop_end = *self.number_mapping.get(&bb_exit).unwrap();
                     self.update_use_in_loop(fid, loop_begin, loop_end);
@@ -93,11 +105,12 @@ impl LiveRange {
 
         self.ranges
             .values_mut()
-            .for_each(|v| v.sort_by(|a, b| a.0.begin.cmp(&b.0.begin)));
+            .for_each(|v| v.sort_by_key(|a| a.0.begin));
     }
 
     fn update_use_in_loop(&mut self, fid: Function, loop_begin: ID, loop_end: ID) {
-        for (r, _) in self.ranges.get_mut(&fid).unwrap() {
+        // a function may have no values at all, e.g. only an empty infinite loop
+        for (r, _) in self.ranges.entry(fid).or_default() {
             if r.begin < loop_begin && r.end >= loop_begin && r.end < loop_end {
                 r.end = loop_end;
             }
@@ -145,7 +158,7 @@ impl LiveRange {
 
     fn update_range(&mut self, fid: Function, val: Value, used_in: ID) {
         let idx = *self.idx_mapping.get(&val).unwrap();
-        let mut r = &mut self.ranges.get_mut(&fid).unwrap().get_mut(idx).unwrap().0;
+        let r = &mut self.ranges.get_mut(&fid).unwrap().get_mut(idx).unwrap().0;
         if r.begin > used_in {
             r.begin = used_in;
         } else if r.end < used_in {
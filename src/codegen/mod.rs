@@ -3,11 +3,19 @@ mod context;
 mod gen;
 mod live_range;
 mod program;
+mod runtime;
+mod switch;
 mod write;
 
-use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
 
-use crate::irgen::generate_mem_ir;
+use crate::diag::{DiagnosticOptions, SourceFile, Warnings};
+use crate::irgen::{
+    generate_mem_ir, FloatSignature, FloatSignatures, Intrinsic, Routine, EXTERN_PREFIX,
+    STATIC_PREFIX, STRING_PREFIX,
+};
+use crate::opt::OptLevel;
 use anyhow::Result;
 use koopa::ir::{values::*, *};
 
@@ -15,19 +23,48 @@ use context::*;
 use gen::*;
 use live_range::*;
 use program::*;
+use runtime::*;
+use switch::*;
 use write::*;
 
 use self::alloca::RegAllocator;
 
-pub fn generate_code(input: &str, output: &str, opt: bool) -> Result<()> {
-    let program = generate_mem_ir(input, opt)?;
+/// The RISC-V base integer instruction set to generate code for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Rv32,
+    Rv64,
+}
+
+impl Target {
+    /// Size of a register, a pointer and a stack slot in bytes
+    pub fn word_size(self) -> i32 {
+        match self {
+            Self::Rv32 => 4,
+            Self::Rv64 => 8,
+        }
+    }
+}
+
+/// Generate the assembly of the source file for the target into the writer, and return the
+/// warnings
+pub fn generate_code(
+    file: SourceFile,
+    include_dirs: &[PathBuf],
+    diag_opts: &DiagnosticOptions,
+    output: impl Write,
+    opt: OptLevel,
+    target: Target,
+) -> Result<Warnings> {
+    let (program, float_sigs, warnings) =
+        generate_mem_ir(file, include_dirs, diag_opts, opt, target)?;
 
-    let mut ctx = Context::new(&program);
-    let mut asm_program = AsmProgram::new();
+    let mut ctx = Context::new(&program, &float_sigs, target);
+    let mut asm_program = AsmProgram::new(target);
     program.generate(&mut ctx, &mut asm_program);
 
-    let mut writer = AsmWriter::from_path(output);
+    let mut writer = AsmWriter::new(output, target);
     writer.write_program(&asm_program)?;
 
-    Ok(())
+    Ok(warnings)
 }
@@ -5,23 +5,41 @@ use strum_macros::Display;
 #[derive(Debug, Clone)]
 pub enum Directive {
     Data,
+    /// read-only data, i.e. string literals
+    Rodata,
     Text,
     Word(i32),
+    /// the address of a label as a register-sized word, i.e. an entry of a jump table
+    Label(Lable),
+    Byte(u8),
+    /// a null-terminated string
+    Asciz(Vec<u8>),
     Zero(usize),
+    /// align to the power of two
+    Align(u32),
 }
 
 pub struct AsmProgram {
     pub values: Vec<AsmValue>,
+    target: Target,
 }
 
 lazy_static! {
     static ref T0: RegID = "t0".into_id();
+    static ref T1: RegID = "t1".into_id();
     static ref SP: RegID = "sp".into_id();
 }
 
 impl AsmProgram {
-    pub fn new() -> Self {
-        Self { values: Vec::new() }
+    pub fn new(target: Target) -> Self {
+        Self {
+            values: Vec::new(),
+            target,
+        }
+    }
+
+    pub fn target(&self) -> Target {
+        self.target
     }
 
     pub fn push(&mut self, val: AsmValue) {
@@ -50,10 +68,24 @@ impl AsmProgram {
         self.push(AsmValue::BinaryImm(op, dst, lhs, imm));
     }
 
+    /// An operation on `int`s, which only takes the low words of the operands on RV64
+    pub fn int_binary(&mut self, op: AsmBinaryOp, dst: RegID, lhs: RegID, rhs: RegID) {
+        self.binary(op.into_word_type(self.target), dst, lhs, rhs);
+    }
+
+    pub fn int_binary_with_imm(&mut self, op: AsmBinaryOp, dst: RegID, lhs: RegID, imm: i32) {
+        self.binary_with_imm(op.into_word_type(self.target), dst, lhs, imm);
+    }
+
     pub fn load(&mut self, dst: RegID, src: RegID, offset: i32) {
         self.push(AsmValue::Load(dst, src, offset));
     }
 
+    /// Load an `int`, while `load` loads a whole register
+    pub fn load_word(&mut self, dst: RegID, src: RegID, offset: i32) {
+        self.push(AsmValue::LoadWord(dst, src, offset));
+    }
+
     pub fn load_imm(&mut self, dst: RegID, imm: i32) {
         self.push(AsmValue::LoadImm(dst, imm));
     }
@@ -66,6 +98,11 @@ impl AsmProgram {
         self.push(AsmValue::Store(src, dst, offset));
     }
 
+    /// Store an `int`, while `store` stores a whole register
+    pub fn store_word(&mut self, src: RegID, dst: RegID, offset: i32) {
+        self.push(AsmValue::StoreWord(src, dst, offset));
+    }
+
     pub fn local_symbol(&mut self, lable: &str) {
         self.push(AsmValue::LocalSymbol(lable.to_owned()));
     }
@@ -120,6 +157,14 @@ impl AsmProgram {
                 AsmValue::Branch(BranchOp::Bnez, _, _, target),
                 AsmValue::Binary(AsmBinaryOp::Sgt, _, lhs, rhs),
             ) => self.compress_back(AsmValue::Branch(BranchOp::Bgt, *lhs, *rhs, target.clone())),
+            (
+                AsmValue::Branch(BranchOp::Bnez, _, _, target),
+                AsmValue::Binary(AsmBinaryOp::Sltu, _, lhs, rhs),
+            ) => self.compress_back(AsmValue::Branch(BranchOp::Bltu, *lhs, *rhs, target.clone())),
+            (
+                AsmValue::Branch(BranchOp::Beqz, _, _, target),
+                AsmValue::Binary(AsmBinaryOp::Sltu, _, lhs, rhs),
+            ) => self.compress_back(AsmValue::Branch(BranchOp::Bgeu, *lhs, *rhs, target.clone())),
             (
                 AsmValue::Branch(BranchOp::Beqz, lhs, _, target),
                 AsmValue::BinaryImm(AsmBinaryOp::Xori, _, rhs, imm),
@@ -169,9 +214,10 @@ impl AsmProgram {
         self.global_symbol(func_name);
 
         let ss = ctx.cur_func().ss();
+        let word = self.target.word_size();
         self.binary_with_imm(AsmBinaryOp::Addi, *SP, *SP, -ss);
         if !is_leaf {
-            self.store("ra".into_id(), *SP, ss - 4);
+            self.store("ra".into_id(), *SP, ss - word);
         }
 
         let mut off: i32 = saved_regs.0;
@@ -179,22 +225,23 @@ impl AsmProgram {
         while off < saved_regs.1 {
             self.store(format!("s{}", id).into_id(), *SP, off);
             id += 1;
-            off += 4;
+            off += word;
         }
     }
 
     pub fn epilogue(&mut self, ctx: &Context, saved_regs: (i32, i32), is_leaf: bool) {
         let (ra, sp) = ("ra".into_id(), "sp".into_id());
         let ss = ctx.cur_func().ss();
+        let word = self.target.word_size();
         if !is_leaf {
-            self.load(ra, sp, ss - 4);
+            self.load(ra, sp, ss - word);
         }
         let mut off = saved_regs.0;
         let mut id = 0;
         while off < saved_regs.1 {
             self.load(format!("s{}", id).into_id(), *SP, off);
             id += 1;
-            off += 4;
+            off += word;
         }
         self.binary_with_imm(AsmBinaryOp::Addi, sp, sp, ss);
         self.ret();
@@ -219,10 +266,10 @@ impl AsmProgram {
 
     pub fn read_value(&mut self, ctx: &Context, dst: RegID, val: Value) -> RegID {
         let sp = *SP;
+        // a global variable used as a value is its address, e.g. in `store @x, @p`
         if ctx.is_global(val) {
             let name = ctx.get_global_var(&val);
             self.load_address(dst, name);
-            self.load(dst, dst, 0);
             return dst;
         }
         if let ValueKind::Integer(imm) = ctx.value_kind(val) {
@@ -239,43 +286,28 @@ impl AsmProgram {
         }
     }
 
+    /// Remove `mv dst, src` by writing to `dst` in the instruction defining `src`
     pub fn try_remove_redundant_mv(&mut self, dst: RegID, src: RegID) -> bool {
-        let mut iter = self.values.iter_mut().rev();
-        while let Some(val) = iter.next() {
+        for val in self.values.iter_mut().rev() {
             match val {
                 AsmValue::Binary(_, ddst, lhs, rhs) => {
                     if *ddst == src {
                         *ddst = dst;
                         break;
                     }
-                    if *lhs == src || *rhs == src {
-                        return false;
-                    }
-                }
-                AsmValue::BinaryImm(_, ddst, opr, _) => {
-                    if *ddst == src {
-                        *ddst = dst;
-                        break;
-                    }
-                    if *opr == src {
-                        return false;
-                    }
-                }
-                AsmValue::Unary(_, ddst, opr) => {
-                    if *ddst == src {
-                        *ddst = dst;
-                        break;
-                    }
-                    if *opr == src {
+                    if [*lhs, *rhs].contains(&src) || [*ddst, *lhs, *rhs].contains(&dst) {
                         return false;
                     }
                 }
-                AsmValue::Load(ddst, ssrc, _) => {
+                AsmValue::BinaryImm(_, ddst, opr, _)
+                | AsmValue::Unary(_, ddst, opr)
+                | AsmValue::Load(ddst, opr, _)
+                | AsmValue::LoadWord(ddst, opr, _) => {
                     if *ddst == src {
                         *ddst = dst;
                         break;
                     }
-                    if *ssrc == src {
+                    if *opr == src || [*ddst, *opr].contains(&dst) {
                         return false;
                     }
                 }
@@ -283,13 +315,10 @@ impl AsmProgram {
                     *ddst = dst;
                     break;
                 }
-                AsmValue::Store(reg1, reg2, _) => {
-                    if *reg1 == src || *reg2 == src {
-                        return false;
-                    }
-                }
-                AsmValue::Branch(_, reg1, reg2, _) => {
-                    if *reg1 == src || *reg2 == src {
+                AsmValue::Store(reg1, reg2, _)
+                | AsmValue::StoreWord(reg1, reg2, _)
+                | AsmValue::Branch(_, reg1, reg2, _) => {
+                    if [src, dst].contains(reg1) || [src, dst].contains(reg2) {
                         return false;
                     }
                 }
@@ -327,13 +356,17 @@ impl AsmProgram {
 
     pub fn ir_binary(&mut self, op: BinaryOp, dst: RegID, lhs: RegID, rhs: RegID) {
         match op {
-            BinaryOp::Add => self.binary(AsmBinaryOp::Add, dst, lhs, rhs),
-            BinaryOp::Sub => self.binary(AsmBinaryOp::Sub, dst, lhs, rhs),
-            BinaryOp::Mul => self.binary(AsmBinaryOp::Mul, dst, lhs, rhs),
-            BinaryOp::Div => self.binary(AsmBinaryOp::Div, dst, lhs, rhs),
-            BinaryOp::Mod => self.binary(AsmBinaryOp::Rem, dst, lhs, rhs),
+            BinaryOp::Add => self.int_binary(AsmBinaryOp::Add, dst, lhs, rhs),
+            BinaryOp::Sub => self.int_binary(AsmBinaryOp::Sub, dst, lhs, rhs),
+            BinaryOp::Mul => self.int_binary(AsmBinaryOp::Mul, dst, lhs, rhs),
+            BinaryOp::Div => self.int_binary(AsmBinaryOp::Div, dst, lhs, rhs),
+            BinaryOp::Mod => self.int_binary(AsmBinaryOp::Rem, dst, lhs, rhs),
             BinaryOp::And => self.binary(AsmBinaryOp::And, dst, lhs, rhs),
             BinaryOp::Or => self.binary(AsmBinaryOp::Or, dst, lhs, rhs),
+            BinaryOp::Xor => self.binary(AsmBinaryOp::Xor, dst, lhs, rhs),
+            BinaryOp::Shl => self.int_binary(AsmBinaryOp::Sll, dst, lhs, rhs),
+            BinaryOp::Shr => self.int_binary(AsmBinaryOp::Srl, dst, lhs, rhs),
+            BinaryOp::Sar => self.int_binary(AsmBinaryOp::Sra, dst, lhs, rhs),
             BinaryOp::Lt => self.binary(AsmBinaryOp::Slt, dst, lhs, rhs),
             BinaryOp::Gt => self.binary(AsmBinaryOp::Sgt, dst, lhs, rhs),
             BinaryOp::Eq => {
@@ -352,19 +385,22 @@ impl AsmProgram {
                 self.binary(AsmBinaryOp::Slt, dst, lhs, rhs);
                 self.unary(AsmUnaryOp::Seqz, dst, dst);
             }
-            _ => unreachable!(),
         }
     }
 
     pub fn ir_binary_with_imm(&mut self, op: BinaryOp, dst: RegID, lhs: RegID, imm: i32) {
         match op {
-            BinaryOp::Add => self.binary_with_imm(AsmBinaryOp::Addi, dst, lhs, imm),
-            BinaryOp::Sub => self.binary_with_imm(AsmBinaryOp::Addi, dst, lhs, -imm),
+            BinaryOp::Add => self.int_binary_with_imm(AsmBinaryOp::Addi, dst, lhs, imm),
+            BinaryOp::Sub => self.int_binary_with_imm(AsmBinaryOp::Addi, dst, lhs, -imm),
             BinaryOp::Mul => self.muli(dst, lhs, imm),
             BinaryOp::Div => self.divi(dst, lhs, imm),
             BinaryOp::Mod => self.remi(dst, lhs, imm),
             BinaryOp::And => self.binary_with_imm(AsmBinaryOp::Andi, dst, lhs, imm),
             BinaryOp::Or => self.binary_with_imm(AsmBinaryOp::Ori, dst, lhs, imm),
+            BinaryOp::Xor => self.binary_with_imm(AsmBinaryOp::Xori, dst, lhs, imm),
+            BinaryOp::Shl => self.int_binary_with_imm(AsmBinaryOp::Slli, dst, lhs, imm & 31),
+            BinaryOp::Shr => self.int_binary_with_imm(AsmBinaryOp::Srli, dst, lhs, imm & 31),
+            BinaryOp::Sar => self.int_binary_with_imm(AsmBinaryOp::Srai, dst, lhs, imm & 31),
             BinaryOp::Eq => {
                 self.binary_with_imm(AsmBinaryOp::Xori, dst, lhs, imm);
                 self.unary(AsmUnaryOp::Seqz, dst, dst);
@@ -384,6 +420,141 @@ impl AsmProgram {
         }
     }
 
+    /// Expand an intrinsic on operands in integer registers, and put the result to `dst`
+    pub fn ir_intrinsic(&mut self, op: Intrinsic, dst: RegID, args: &[RegID]) {
+        let (ft0, ft1) = ("ft0".into_id(), "ft1".into_id());
+        match op {
+            Intrinsic::UDiv => return self.int_binary(AsmBinaryOp::Divu, dst, args[0], args[1]),
+            Intrinsic::URem => return self.int_binary(AsmBinaryOp::Remu, dst, args[0], args[1]),
+            Intrinsic::ULt => return self.binary(AsmBinaryOp::Sltu, dst, args[0], args[1]),
+            Intrinsic::UGt => return self.binary(AsmBinaryOp::Sltu, dst, args[1], args[0]),
+            Intrinsic::ULe => {
+                self.binary(AsmBinaryOp::Sltu, dst, args[1], args[0]);
+                return self.unary(AsmUnaryOp::Seqz, dst, dst);
+            }
+            Intrinsic::UGe => {
+                self.binary(AsmBinaryOp::Sltu, dst, args[0], args[1]);
+                return self.unary(AsmUnaryOp::Seqz, dst, dst);
+            }
+            // an address is already an integer
+            Intrinsic::PtrToInt if self.target == Target::Rv32 => return self.mv(dst, args[0]),
+            Intrinsic::PtrToInt => {
+                return self.int_binary_with_imm(AsmBinaryOp::Addi, dst, args[0], 0)
+            }
+            Intrinsic::NullPtr => return self.load_imm(dst, 0),
+            Intrinsic::PtrCast => return self.mv(dst, args[0]),
+            Intrinsic::LoadLong => return self.load(dst, args[0], 0),
+            Intrinsic::StoreLong => return self.store(args[0], args[1], 0),
+            Intrinsic::Add64 => return self.binary(AsmBinaryOp::Add, dst, args[0], args[1]),
+            Intrinsic::Sub64 => return self.binary(AsmBinaryOp::Sub, dst, args[0], args[1]),
+            Intrinsic::Mul64 => return self.binary(AsmBinaryOp::Mul, dst, args[0], args[1]),
+            Intrinsic::Div64 => return self.binary(AsmBinaryOp::Div, dst, args[0], args[1]),
+            Intrinsic::Rem64 => return self.binary(AsmBinaryOp::Rem, dst, args[0], args[1]),
+            Intrinsic::And64 => return self.binary(AsmBinaryOp::And, dst, args[0], args[1]),
+            Intrinsic::Or64 => return self.binary(AsmBinaryOp::Or, dst, args[0], args[1]),
+            Intrinsic::Xor64 => return self.binary(AsmBinaryOp::Xor, dst, args[0], args[1]),
+            Intrinsic::Shl64 => return self.binary(AsmBinaryOp::Sll, dst, args[0], args[1]),
+            Intrinsic::Sar64 => return self.binary(AsmBinaryOp::Sra, dst, args[0], args[1]),
+            Intrinsic::Eq64 => {
+                self.binary(AsmBinaryOp::Xor, dst, args[0], args[1]);
+                return self.unary(AsmUnaryOp::Seqz, dst, dst);
+            }
+            Intrinsic::Ne64 => {
+                self.binary(AsmBinaryOp::Xor, dst, args[0], args[1]);
+                return self.unary(AsmUnaryOp::Snez, dst, dst);
+            }
+            Intrinsic::Lt64 => return self.binary(AsmBinaryOp::Slt, dst, args[0], args[1]),
+            Intrinsic::Gt64 => return self.binary(AsmBinaryOp::Slt, dst, args[1], args[0]),
+            Intrinsic::Le64 => {
+                self.binary(AsmBinaryOp::Slt, dst, args[1], args[0]);
+                return self.unary(AsmUnaryOp::Seqz, dst, dst);
+            }
+            Intrinsic::Ge64 => {
+                self.binary(AsmBinaryOp::Slt, dst, args[0], args[1]);
+                return self.unary(AsmUnaryOp::Seqz, dst, dst);
+            }
+            Intrinsic::LToF => {
+                self.unary(AsmUnaryOp::FcvtSL, ft0, args[0]);
+                return self.unary(AsmUnaryOp::FmvXW, dst, ft0);
+            }
+            _ => {}
+        }
+        if let Intrinsic::IToF | Intrinsic::UToF = op {
+            let cvt = match op {
+                Intrinsic::IToF => AsmUnaryOp::FcvtSW,
+                _ => AsmUnaryOp::FcvtSWU,
+            };
+            self.unary(cvt, ft0, args[0]);
+            self.unary(AsmUnaryOp::FmvXW, dst, ft0);
+            return;
+        }
+
+        self.unary(AsmUnaryOp::FmvWX, ft0, args[0]);
+        if let Some(&rhs) = args.get(1) {
+            self.unary(AsmUnaryOp::FmvWX, ft1, rhs);
+        }
+        let arith = |p: &mut Self, op| {
+            p.binary(op, ft0, ft0, ft1);
+            p.unary(AsmUnaryOp::FmvXW, dst, ft0);
+        };
+        match op {
+            Intrinsic::FAdd => arith(self, AsmBinaryOp::FaddS),
+            Intrinsic::FSub => arith(self, AsmBinaryOp::FsubS),
+            Intrinsic::FMul => arith(self, AsmBinaryOp::FmulS),
+            Intrinsic::FDiv => arith(self, AsmBinaryOp::FdivS),
+            Intrinsic::FNeg => {
+                self.unary(AsmUnaryOp::FnegS, ft0, ft0);
+                self.unary(AsmUnaryOp::FmvXW, dst, ft0);
+            }
+            Intrinsic::FEq => self.binary(AsmBinaryOp::FeqS, dst, ft0, ft1),
+            Intrinsic::FNe => {
+                self.binary(AsmBinaryOp::FeqS, dst, ft0, ft1);
+                self.unary(AsmUnaryOp::Seqz, dst, dst);
+            }
+            Intrinsic::FLt => self.binary(AsmBinaryOp::FltS, dst, ft0, ft1),
+            Intrinsic::FLe => self.binary(AsmBinaryOp::FleS, dst, ft0, ft1),
+            Intrinsic::FGt => self.binary(AsmBinaryOp::FltS, dst, ft1, ft0),
+            Intrinsic::FGe => self.binary(AsmBinaryOp::FleS, dst, ft1, ft0),
+            Intrinsic::FToI => self.unary(AsmUnaryOp::FcvtWS, dst, ft0),
+            Intrinsic::FToU => self.unary(AsmUnaryOp::FcvtWUS, dst, ft0),
+            Intrinsic::FToL => self.unary(AsmUnaryOp::FcvtLS, dst, ft0),
+            Intrinsic::FToD => {
+                self.unary(AsmUnaryOp::FcvtDS, ft0, ft0);
+                self.unary(AsmUnaryOp::FmvXD, dst, ft0);
+            }
+            Intrinsic::IToF
+            | Intrinsic::UToF
+            | Intrinsic::PtrToInt
+            | Intrinsic::NullPtr
+            | Intrinsic::PtrCast
+            | Intrinsic::LoadLong
+            | Intrinsic::StoreLong
+            | Intrinsic::Add64
+            | Intrinsic::Sub64
+            | Intrinsic::Mul64
+            | Intrinsic::Div64
+            | Intrinsic::Rem64
+            | Intrinsic::And64
+            | Intrinsic::Or64
+            | Intrinsic::Xor64
+            | Intrinsic::Shl64
+            | Intrinsic::Sar64
+            | Intrinsic::Eq64
+            | Intrinsic::Ne64
+            | Intrinsic::Lt64
+            | Intrinsic::Gt64
+            | Intrinsic::Le64
+            | Intrinsic::Ge64
+            | Intrinsic::LToF
+            | Intrinsic::UDiv
+            | Intrinsic::URem
+            | Intrinsic::ULt
+            | Intrinsic::UGt
+            | Intrinsic::ULe
+            | Intrinsic::UGe => unreachable!(),
+        }
+    }
+
     pub fn write_back(&mut self, ctx: &Context, src: RegID, val: Value) {
         let t0 = *T0;
         let sp = *SP;
@@ -417,38 +588,72 @@ impl AsmProgram {
                 shift += 1;
                 imm >>= 1;
             }
-            self.binary_with_imm(AsmBinaryOp::Slli, dst, opr, shift)
+            self.int_binary_with_imm(AsmBinaryOp::Slli, dst, opr, shift)
         } else {
             self.load_imm(dst, imm);
-            self.binary(AsmBinaryOp::Mul, dst, opr, dst)
+            self.int_binary(AsmBinaryOp::Mul, dst, opr, dst)
         }
     }
 
+    /// Put `opr + (opr < 0 ? 2^shift - 1 : 0)` to a register other than `opr`, so that a
+    /// shift of it rounds towards zero
+    fn round_towards_zero(&mut self, dst: RegID, opr: RegID, shift: i32) -> RegID {
+        let tmp = if dst != opr { dst } else { *T0 };
+        self.int_binary_with_imm(AsmBinaryOp::Srai, tmp, opr, 31);
+        self.int_binary_with_imm(AsmBinaryOp::Srli, tmp, tmp, 32 - shift);
+        self.int_binary(AsmBinaryOp::Add, tmp, opr, tmp);
+        tmp
+    }
+
     pub fn divi(&mut self, dst: RegID, opr: RegID, imm: i32) {
         if imm == 1 {
             if dst != opr {
                 self.unary(AsmUnaryOp::Move, dst, opr);
             }
         } else if imm > 0 && (imm & (imm - 1)) == 0 {
-            let mut shift = 0;
-            let mut imm = imm >> 1;
-            while imm != 0 {
-                shift += 1;
-                imm >>= 1;
-            }
-            self.binary_with_imm(AsmBinaryOp::Srai, dst, opr, shift)
+            let shift = imm.trailing_zeros() as i32;
+            let tmp = self.round_towards_zero(dst, opr, shift);
+            self.int_binary_with_imm(AsmBinaryOp::Srai, dst, tmp, shift)
         } else {
             self.load_imm(dst, imm);
-            self.binary(AsmBinaryOp::Div, dst, opr, dst)
+            self.int_binary(AsmBinaryOp::Div, dst, opr, dst)
         }
     }
 
+    /// The remainder has the sign of the dividend, i.e. `opr - (opr / imm) * imm`
     pub fn remi(&mut self, dst: RegID, opr: RegID, imm: i32) {
-        if imm > 0 && (imm & (imm - 1)) == 0 {
-            self.binary_with_imm(AsmBinaryOp::Andi, dst, opr, imm - 1)
+        if imm == 1 {
+            self.load_imm(dst, 0)
+        } else if imm > 0 && (imm & (imm - 1)) == 0 && imm <= 2048 {
+            let tmp = self.round_towards_zero(dst, opr, imm.trailing_zeros() as i32);
+            self.binary_with_imm(AsmBinaryOp::Andi, tmp, tmp, -imm);
+            self.int_binary(AsmBinaryOp::Sub, dst, opr, tmp)
         } else {
             self.load_imm(dst, imm);
-            self.binary(AsmBinaryOp::Rem, dst, opr, dst)
+            self.int_binary(AsmBinaryOp::Rem, dst, opr, dst)
+        }
+    }
+
+    pub fn divui(&mut self, dst: RegID, opr: RegID, imm: u32) {
+        if imm == 1 {
+            if dst != opr {
+                self.unary(AsmUnaryOp::Move, dst, opr);
+            }
+        } else if imm.is_power_of_two() {
+            let shift = imm.trailing_zeros() as i32;
+            self.int_binary_with_imm(AsmBinaryOp::Srli, dst, opr, shift)
+        } else {
+            self.load_imm(dst, imm as i32);
+            self.int_binary(AsmBinaryOp::Divu, dst, opr, dst)
+        }
+    }
+
+    pub fn remui(&mut self, dst: RegID, opr: RegID, imm: u32) {
+        if imm.is_power_of_two() && imm <= 2048 {
+            self.binary_with_imm(AsmBinaryOp::Andi, dst, opr, imm as i32 - 1)
+        } else {
+            self.load_imm(dst, imm as i32);
+            self.int_binary(AsmBinaryOp::Remu, dst, opr, dst)
         }
     }
 }
@@ -459,16 +664,21 @@ pub enum AsmValue {
     LoadImm(RegID, i32),                       // li dst, imm
     Load(RegID, RegID, i32),                   // load dst, src
     Store(RegID, RegID, i32),                  // store src, dst
+    LoadWord(RegID, RegID, i32),               // lw dst, src
+    StoreWord(RegID, RegID, i32),              // sw src, dst
     Binary(AsmBinaryOp, RegID, RegID, RegID),  // op dst, lhs, rhs
     BinaryImm(AsmBinaryOp, RegID, RegID, i32), // op dst, lhs, imm
     Unary(AsmUnaryOp, RegID, RegID),           // op dst, opr
     Branch(BranchOp, RegID, RegID, Lable),
     Jump(Lable),
+    JumpReg(RegID), // jr src
     Call(Lable),
     Directive(Directive),
     GlobalSymbol(Lable),
     LocalSymbol(Lable),
     Return,
+    /// instructions written as they are, i.e. the body of a runtime routine
+    Verbatim(String),
 }
 
 pub type Lable = String;
@@ -487,6 +697,10 @@ pub enum AsmBinaryOp {
     Div,
     #[strum(serialize = "rem")]
     Rem,
+    #[strum(serialize = "divu")]
+    Divu,
+    #[strum(serialize = "remu")]
+    Remu,
     #[strum(serialize = "and")]
     And,
     #[strum(serialize = "andi")]
@@ -501,14 +715,66 @@ pub enum AsmBinaryOp {
     Slti,
     #[strum(serialize = "sgt")]
     Sgt,
+    #[strum(serialize = "sltu")]
+    Sltu,
     #[strum(serialize = "xor")]
     Xor,
     #[strum(serialize = "xori")]
     Xori,
+    #[strum(serialize = "sll")]
+    Sll,
     #[strum(serialize = "slli")]
     Slli,
+    #[strum(serialize = "srl")]
+    Srl,
+    #[strum(serialize = "srli")]
+    Srli,
+    #[strum(serialize = "sra")]
+    Sra,
     #[strum(serialize = "srai")]
     Srai,
+    #[strum(serialize = "addw")]
+    Addw,
+    #[strum(serialize = "addiw")]
+    Addiw,
+    #[strum(serialize = "subw")]
+    Subw,
+    #[strum(serialize = "mulw")]
+    Mulw,
+    #[strum(serialize = "divw")]
+    Divw,
+    #[strum(serialize = "remw")]
+    Remw,
+    #[strum(serialize = "divuw")]
+    Divuw,
+    #[strum(serialize = "remuw")]
+    Remuw,
+    #[strum(serialize = "sllw")]
+    Sllw,
+    #[strum(serialize = "slliw")]
+    Slliw,
+    #[strum(serialize = "srlw")]
+    Srlw,
+    #[strum(serialize = "srliw")]
+    Srliw,
+    #[strum(serialize = "sraw")]
+    Sraw,
+    #[strum(serialize = "sraiw")]
+    Sraiw,
+    #[strum(serialize = "fadd.s")]
+    FaddS,
+    #[strum(serialize = "fsub.s")]
+    FsubS,
+    #[strum(serialize = "fmul.s")]
+    FmulS,
+    #[strum(serialize = "fdiv.s")]
+    FdivS,
+    #[strum(serialize = "feq.s")]
+    FeqS,
+    #[strum(serialize = "flt.s")]
+    FltS,
+    #[strum(serialize = "fle.s")]
+    FleS,
 }
 
 impl AsmBinaryOp {
@@ -519,9 +785,35 @@ impl AsmBinaryOp {
             Self::Ori => Self::Or,
             Self::Slti => Self::Slt,
             Self::Xori => Self::Xor,
+            Self::Addiw => Self::Addw,
             _ => unreachable!(),
         }
     }
+
+    /// The instruction taking the low words of the operands and sign-extending the result
+    /// on RV64, to keep an `int` in a register as a sign-extended word
+    pub fn into_word_type(self, target: Target) -> Self {
+        if target == Target::Rv32 {
+            return self;
+        }
+        match self {
+            Self::Add => Self::Addw,
+            Self::Addi => Self::Addiw,
+            Self::Sub => Self::Subw,
+            Self::Mul => Self::Mulw,
+            Self::Div => Self::Divw,
+            Self::Rem => Self::Remw,
+            Self::Divu => Self::Divuw,
+            Self::Remu => Self::Remuw,
+            Self::Sll => Self::Sllw,
+            Self::Slli => Self::Slliw,
+            Self::Srl => Self::Srlw,
+            Self::Srli => Self::Srliw,
+            Self::Sra => Self::Sraw,
+            Self::Srai => Self::Sraiw,
+            _ => self,
+        }
+    }
 }
 
 #[derive(Debug, Display, Clone)]
@@ -532,6 +824,33 @@ pub enum AsmUnaryOp {
     Snez,
     #[strum(serialize = "mv")]
     Move,
+    #[strum(serialize = "fneg.s")]
+    FnegS,
+    /// move the bits of an integer register to a floating-point register
+    #[strum(serialize = "fmv.w.x")]
+    FmvWX,
+    /// move the bits of a floating-point register to an integer register
+    #[strum(serialize = "fmv.x.w")]
+    FmvXW,
+    #[strum(serialize = "fcvt.s.w")]
+    FcvtSW,
+    /// always rounds towards zero
+    #[strum(serialize = "fcvt.w.s")]
+    FcvtWS,
+    #[strum(serialize = "fcvt.s.wu")]
+    FcvtSWU,
+    /// always rounds towards zero
+    #[strum(serialize = "fcvt.wu.s")]
+    FcvtWUS,
+    #[strum(serialize = "fcvt.s.l")]
+    FcvtSL,
+    /// always rounds towards zero
+    #[strum(serialize = "fcvt.l.s")]
+    FcvtLS,
+    #[strum(serialize = "fcvt.d.s")]
+    FcvtDS,
+    #[strum(serialize = "fmv.x.d")]
+    FmvXD,
 }
 
 #[derive(Debug, Display, Clone)]
@@ -544,8 +863,12 @@ pub enum BranchOp {
     Blt,
     #[strum(serialize = "bgt")]
     Bgt,
+    #[strum(serialize = "bltu")]
+    Bltu,
     #[strum(serialize = "beq")]
     Beq,
     #[strum(serialize = "bne")]
     Bne,
+    #[strum(serialize = "bgeu")]
+    Bgeu,
 }
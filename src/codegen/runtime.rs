@@ -0,0 +1,244 @@
+use super::*;
+
+/// Load the operands pointed to by `a1` and `a2` to `t1:t0` and `t3:t2`
+const RV32_LOAD: &[&str] = &[
+    "lw t0, 0(a1)",
+    "lw t1, 4(a1)",
+    "lw t2, 0(a2)",
+    "lw t3, 4(a2)",
+];
+
+/// Store `t1:t0` to the place pointed to by `a0`
+const RV32_STORE: &[&str] = &["sw t0, 0(a0)", "sw t1, 4(a0)", "ret"];
+
+/// `(t1:t0 ^ a6) - a6`, i.e. negate it if `a6` is -1
+const RV32_APPLY_SIGN: &[&str] = &[
+    "xor t0, t0, a6",
+    "xor t1, t1, a6",
+    "sltu a5, t0, a6",
+    "sub t0, t0, a6",
+    "sub t1, t1, a6",
+    "sub t1, t1, a5",
+];
+
+/// `|t1:t0| / |t3:t2|` to `t1:t0` and the remainder to `t5:t4`, with the signs in `a6` and `a7`
+const RV32_DIVIDE: &[&str] = &[
+    "srai a6, t1, 31",
+    "srai a7, t3, 31",
+    "bgez t1, {L}_pos_lhs",
+    "snez a5, t0",
+    "neg t0, t0",
+    "neg t1, t1",
+    "sub t1, t1, a5",
+    "{L}_pos_lhs:",
+    "bgez t3, {L}_pos_rhs",
+    "snez a5, t2",
+    "neg t2, t2",
+    "neg t3, t3",
+    "sub t3, t3, a5",
+    "{L}_pos_rhs:",
+    "li t4, 0",
+    "li t5, 0",
+    "li a4, 64",
+    "{L}_loop:",
+    "slli t5, t5, 1",
+    "srli a5, t4, 31",
+    "or t5, t5, a5",
+    "slli t4, t4, 1",
+    "srli a5, t1, 31",
+    "or t4, t4, a5",
+    "slli t1, t1, 1",
+    "srli a5, t0, 31",
+    "or t1, t1, a5",
+    "slli t0, t0, 1",
+    "bltu t5, t3, {L}_next",
+    "bne t5, t3, {L}_sub",
+    "bltu t4, t2, {L}_next",
+    "{L}_sub:",
+    "sltu a5, t4, t2",
+    "sub t4, t4, t2",
+    "sub t5, t5, t3",
+    "sub t5, t5, a5",
+    "ori t0, t0, 1",
+    "{L}_next:",
+    "addi a4, a4, -1",
+    "bnez a4, {L}_loop",
+];
+
+fn rv32_body(routine: Routine) -> Vec<&'static [&'static str]> {
+    match routine {
+        Routine::LAdd => vec![
+            RV32_LOAD,
+            &[
+                "add t0, t0, t2",
+                "sltu t4, t0, t2",
+                "add t1, t1, t3",
+                "add t1, t1, t4",
+            ],
+            RV32_STORE,
+        ],
+        Routine::LSub => vec![
+            RV32_LOAD,
+            &[
+                "sltu t4, t0, t2",
+                "sub t0, t0, t2",
+                "sub t1, t1, t3",
+                "sub t1, t1, t4",
+            ],
+            RV32_STORE,
+        ],
+        Routine::LMul => vec![
+            RV32_LOAD,
+            &[
+                "mulhu t4, t0, t2",
+                "mul t1, t1, t2",
+                "mul t3, t0, t3",
+                "mul t0, t0, t2",
+                "add t1, t1, t4",
+                "add t1, t1, t3",
+            ],
+            RV32_STORE,
+        ],
+        Routine::LDiv => vec![
+            RV32_LOAD,
+            RV32_DIVIDE,
+            &["xor a6, a6, a7"],
+            RV32_APPLY_SIGN,
+            RV32_STORE,
+        ],
+        Routine::LMod => vec![
+            RV32_LOAD,
+            RV32_DIVIDE,
+            &["mv t0, t4", "mv t1, t5"],
+            RV32_APPLY_SIGN,
+            RV32_STORE,
+        ],
+        Routine::LAnd => vec![RV32_LOAD, &["and t0, t0, t2", "and t1, t1, t3"], RV32_STORE],
+        Routine::LOr => vec![RV32_LOAD, &["or t0, t0, t2", "or t1, t1, t3"], RV32_STORE],
+        Routine::LXor => vec![RV32_LOAD, &["xor t0, t0, t2", "xor t1, t1, t3"], RV32_STORE],
+        Routine::LShl => vec![
+            &[
+                "lw t0, 0(a1)",
+                "lw t1, 4(a1)",
+                "andi a2, a2, 63",
+                "li t2, 32",
+                "blt a2, t2, {L}_short",
+                "addi a2, a2, -32",
+                "sll t1, t0, a2",
+                "li t0, 0",
+                "j {L}_done",
+                "{L}_short:",
+                "beqz a2, {L}_done",
+                "sub t2, t2, a2",
+                "srl t3, t0, t2",
+                "sll t1, t1, a2",
+                "or t1, t1, t3",
+                "sll t0, t0, a2",
+                "{L}_done:",
+            ],
+            RV32_STORE,
+        ],
+        Routine::LShr => vec![
+            &[
+                "lw t0, 0(a1)",
+                "lw t1, 4(a1)",
+                "andi a2, a2, 63",
+                "li t2, 32",
+                "blt a2, t2, {L}_short",
+                "addi a2, a2, -32",
+                "sra t0, t1, a2",
+                "srai t1, t1, 31",
+                "j {L}_done",
+                "{L}_short:",
+                "beqz a2, {L}_done",
+                "sub t2, t2, a2",
+                "sll t3, t1, t2",
+                "srl t0, t0, a2",
+                "or t0, t0, t3",
+                "sra t1, t1, a2",
+                "{L}_done:",
+            ],
+            RV32_STORE,
+        ],
+        Routine::LNeg => vec![
+            &[
+                "lw t0, 0(a1)",
+                "lw t1, 4(a1)",
+                "snez t2, t0",
+                "neg t0, t0",
+                "neg t1, t1",
+                "sub t1, t1, t2",
+            ],
+            RV32_STORE,
+        ],
+        Routine::LNot => vec![
+            &["lw t0, 0(a1)", "lw t1, 4(a1)", "not t0, t0", "not t1, t1"],
+            RV32_STORE,
+        ],
+        Routine::LCmp => vec![&[
+            "lw t1, 4(a0)",
+            "lw t3, 4(a1)",
+            "blt t1, t3, {L}_less",
+            "blt t3, t1, {L}_greater",
+            "lw t0, 0(a0)",
+            "lw t2, 0(a1)",
+            "bltu t0, t2, {L}_less",
+            "bltu t2, t0, {L}_greater",
+            "li a0, 0",
+            "ret",
+            "{L}_less:",
+            "li a0, -1",
+            "ret",
+            "{L}_greater:",
+            "li a0, 1",
+            "ret",
+        ]],
+        // 1333788672 is the bits of `2^32`
+        Routine::LToF => vec![&[
+            "lw t0, 0(a0)",
+            "lw t1, 4(a0)",
+            "fcvt.s.w ft0, t1",
+            "li t2, 1333788672",
+            "fmv.w.x ft1, t2",
+            "fmul.s ft0, ft0, ft1",
+            "fcvt.s.wu ft1, t0",
+            "fadd.s ft0, ft0, ft1",
+            "fmv.x.w a0, ft0",
+            "ret",
+        ]],
+        // 796917760 is the bits of `2^-32`
+        Routine::FToL => vec![
+            &[
+                "fmv.w.x ft0, a1",
+                "fabs.s ft0, ft0",
+                "li t2, 796917760",
+                "fmv.w.x ft1, t2",
+                "fmul.s ft1, ft0, ft1",
+                "fcvt.wu.s t1, ft1, rtz",
+                "fcvt.s.wu ft1, t1",
+                "li t2, 1333788672",
+                "fmv.w.x ft2, t2",
+                "fmul.s ft1, ft1, ft2",
+                "fsub.s ft0, ft0, ft1",
+                "fcvt.wu.s t0, ft0, rtz",
+                "srai a6, a1, 31",
+            ],
+            RV32_APPLY_SIGN,
+            RV32_STORE,
+        ],
+    }
+}
+
+/// Assembly of the routine as a local function, for RV32
+pub fn routine_body(routine: Routine) -> String {
+    let name = routine.to_string();
+    let mut body = format!("  .text\n{}:\n", name);
+    for line in rv32_body(routine).concat() {
+        let line = line.replace("{L}", &format!(".L{}", name));
+        match line.ends_with(':') {
+            true => body += &format!("{}\n", line),
+            false => body += &format!("  {}\n", line),
+        }
+    }
+    body
+}
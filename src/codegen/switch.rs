@@ -0,0 +1,269 @@
+use std::collections::{HashMap, HashSet};
+
+use lazy_static_include::lazy_static::lazy_static;
+
+use super::*;
+
+lazy_static! {
+    static ref TMP1: RegID = "t0".into_id();
+    static ref TMP2: RegID = "t1".into_id();
+}
+
+/// Minimum number of cases to lower a chain
+const MIN_CASES: usize = 4;
+
+/// A chain of `br (eq %x, imm)` on one value, as generated for a `switch`
+pub struct SwitchChain {
+    value: Value,
+    /// values and targets of the cases, sorted by value
+    cases: Vec<(i32, Target)>,
+    default: Target,
+    /// the test ending the first block
+    cond: Value,
+    branch: Value,
+}
+
+/// A block to jump to with its arguments
+type Target = (BasicBlock, Vec<Value>);
+
+/// The chains of a function, found by their first blocks
+pub struct SwitchChains {
+    heads: HashMap<BasicBlock, SwitchChain>,
+    absorbed: HashSet<BasicBlock>,
+}
+
+/// The test ending a block: `br (eq value, imm), target, next`
+struct CaseTest {
+    cond: Value,
+    branch: Value,
+    value: Value,
+    imm: i32,
+    target: Target,
+    next: Target,
+}
+
+fn case_test(data: &FunctionData, bb: BasicBlock) -> Option<CaseTest> {
+    let node = data.layout().bbs().node(&bb)?;
+    let branch = *node.insts().back_key()?;
+    let br = match data.dfg().value(branch).kind() {
+        ValueKind::Branch(br) => br,
+        _ => return None,
+    };
+    let cond = data.dfg().value(br.cond());
+    if cond.used_by().len() != 1 {
+        return None;
+    }
+    let (lhs, rhs) = match cond.kind() {
+        ValueKind::Binary(b) if b.op() == BinaryOp::Eq => (b.lhs(), b.rhs()),
+        _ => return None,
+    };
+    let imm = |v: Value| match data.dfg().values().get(&v).map(|d| d.kind()) {
+        Some(ValueKind::Integer(i)) => Some(i.value()),
+        _ => None,
+    };
+    let (value, imm) = match (imm(lhs), imm(rhs)) {
+        (None, Some(i)) => (lhs, i),
+        (Some(i), None) => (rhs, i),
+        _ => return None,
+    };
+    Some(CaseTest {
+        cond: br.cond(),
+        branch,
+        value,
+        imm,
+        target: (br.true_bb(), br.true_args().to_vec()),
+        next: (br.false_bb(), br.false_args().to_vec()),
+    })
+}
+
+impl SwitchChains {
+    pub fn new(data: &FunctionData) -> Self {
+        let tests: HashMap<_, _> = data
+            .layout()
+            .bbs()
+            .keys()
+            .filter_map(|&bb| case_test(data, bb).map(|t| (bb, t)))
+            .collect();
+        let link = |bb: BasicBlock, value: Value| {
+            let bb_data = data.dfg().bb(bb);
+            let test = tests.get(&bb)?;
+            let only_test = data.layout().bbs().node(&bb)?.insts().len() == 2
+                && test.value == value
+                && bb_data.params().is_empty()
+                && bb_data.used_by().len() == 1;
+            only_test.then_some(test)
+        };
+        let is_link = |bb: BasicBlock| {
+            tests
+                .values()
+                .any(|t| t.next.0 == bb && link(bb, t.value).is_some())
+        };
+
+        let mut chains = Self {
+            heads: HashMap::new(),
+            absorbed: HashSet::new(),
+        };
+        for (&head, first) in tests.iter().filter(|(&bb, _)| !is_link(bb)) {
+            let mut cases = vec![(first.imm, first.target.clone())];
+            let mut blocks = vec![];
+            let mut next = first.next.clone();
+            while let Some(test) = link(next.0, first.value) {
+                if next.0 == head || blocks.contains(&next.0) {
+                    break;
+                }
+                blocks.push(next.0);
+                if cases.iter().all(|(imm, _)| *imm != test.imm) {
+                    cases.push((test.imm, test.target.clone()));
+                }
+                next = test.next.clone();
+            }
+            if cases.len() < MIN_CASES {
+                continue;
+            }
+            cases.sort_by_key(|(imm, _)| *imm);
+            chains.absorbed.extend(blocks);
+            chains.heads.insert(
+                head,
+                SwitchChain {
+                    value: first.value,
+                    cases,
+                    default: next,
+                    cond: first.cond,
+                    branch: first.branch,
+                },
+            );
+        }
+        chains
+    }
+
+    pub fn is_absorbed(&self, bb: &BasicBlock) -> bool {
+        self.absorbed.contains(bb)
+    }
+
+    pub fn get(&self, head: &BasicBlock) -> Option<&SwitchChain> {
+        self.heads.get(head)
+    }
+}
+
+impl SwitchChain {
+    /// Whether the instruction of the first block is replaced by the dispatch
+    pub fn replaces(&self, inst: Value) -> bool {
+        inst == self.cond || inst == self.branch
+    }
+
+    /// Jump through a table if the cases are dense enough, or a tree of comparisons
+    pub fn generate(&self, ctx: &Context, p: &mut AsmProgram, head: &str) {
+        // a target with arguments is reached through a stub passing them
+        let mut labels = 0;
+        let mut stubs = vec![];
+        let mut label_of = |(bb, args): &Target| match args.is_empty() {
+            true => ctx.cur_func().get_bb_name(bb).clone(),
+            false => {
+                let stub = format!("{}_{}", head, labels);
+                labels += 1;
+                stubs.push((stub.clone(), *bb, args.clone()));
+                stub
+            }
+        };
+        let cases: Vec<_> = self
+            .cases
+            .iter()
+            .map(|(imm, target)| (*imm, label_of(target)))
+            .collect();
+        let default = label_of(&self.default);
+
+        let value = p.read_value(ctx, *TMP1, self.value);
+        let (min, max) = (cases[0].0, cases[cases.len() - 1].0);
+        let range = max as i64 - min as i64 + 1;
+        if range <= 2 * cases.len() as i64 {
+            jump_table(p, head, value, &cases, &default, range as i32);
+        } else {
+            compare_tree(p, head, value, &cases, &default, &mut labels);
+        }
+
+        for (stub, bb, args) in stubs {
+            p.local_symbol(&stub);
+            let params = ctx.cur_func_data().dfg().bb(bb).params();
+            for (&arg, &param) in args.iter().zip(params) {
+                p.move_local_value(ctx, param, arg);
+            }
+            p.jump(ctx.cur_func().get_bb_name(&bb));
+        }
+    }
+}
+
+fn jump_table(
+    p: &mut AsmProgram,
+    head: &str,
+    value: RegID,
+    cases: &[(i32, String)],
+    default: &str,
+    len: i32,
+) {
+    let table = format!("{}_table", head);
+    let min = cases[0].0;
+
+    // the value may be in TMP1, which is only reused once the index is taken
+    let (index, addr) = (*TMP2, *TMP1);
+    p.int_binary_with_imm(AsmBinaryOp::Addi, index, value, min.wrapping_neg());
+    p.load_imm(addr, len);
+    p.push(AsmValue::Branch(
+        BranchOp::Bgeu,
+        index,
+        addr,
+        default.to_string(),
+    ));
+    let word = p.target().word_size();
+    p.binary_with_imm(
+        AsmBinaryOp::Slli,
+        index,
+        index,
+        word.trailing_zeros() as i32,
+    );
+    p.load_address(addr, &table);
+    p.binary(AsmBinaryOp::Add, addr, addr, index);
+    p.load(addr, addr, 0);
+    p.push(AsmValue::JumpReg(addr));
+
+    p.directive(Directive::Rodata);
+    p.directive(Directive::Align(word.trailing_zeros()));
+    p.local_symbol(&table);
+    let mut cases = cases.iter().peekable();
+    for i in 0..len {
+        let label = match cases.next_if(|(imm, _)| *imm == min.wrapping_add(i)) {
+            Some((_, label)) => label,
+            None => default,
+        };
+        p.directive(Directive::Label(label.to_string()));
+    }
+    p.directive(Directive::Text);
+}
+
+fn compare_tree(
+    p: &mut AsmProgram,
+    head: &str,
+    value: RegID,
+    cases: &[(i32, String)],
+    default: &str,
+    labels: &mut usize,
+) {
+    let t2 = *TMP2;
+    if cases.len() < MIN_CASES {
+        for (imm, label) in cases {
+            p.load_imm(t2, *imm);
+            p.push(AsmValue::Branch(BranchOp::Beq, value, t2, label.clone()));
+        }
+        p.jump(default);
+        return;
+    }
+    let mid = cases.len() / 2;
+    let (imm, label) = &cases[mid];
+    let lower = format!("{}_{}", head, labels);
+    *labels += 1;
+    p.load_imm(t2, *imm);
+    p.push(AsmValue::Branch(BranchOp::Blt, value, t2, lower.clone()));
+    p.push(AsmValue::Branch(BranchOp::Beq, value, t2, label.clone()));
+    compare_tree(p, head, value, &cases[mid + 1..], default, labels);
+    p.local_symbol(&lower);
+    compare_tree(p, head, value, &cases[..mid], default, labels);
+}
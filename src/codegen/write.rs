@@ -1,14 +1,14 @@
-use std::io::BufWriter;
 use std::io::{Result, Write};
 
 use super::program::{AsmProgram, AsmValue};
 use super::*;
 
-pub struct AsmWriter {
-    f: BufWriter<File>,
+pub struct AsmWriter<W: Write> {
+    f: W,
+    target: Target,
 }
 
-impl AsmWriter {
+impl<W: Write> AsmWriter<W> {
     pub fn segment(&mut self, name: &str) -> Result<()> {
         writeln!(self.f, "  .{}", name)
     }
@@ -21,12 +21,12 @@ impl AsmWriter {
         writeln!(self.f, "  {} {}, {}({})", method, rd1, offset, rd2)
     }
 
-    pub fn lw(&mut self, dst: RegID, src: RegID, offset: i32) -> Result<()> {
+    pub fn load(&mut self, method: &str, dst: RegID, src: RegID, offset: i32) -> Result<()> {
         if (-2048..=2047).contains(&offset) {
-            self.mem_access("lw", dst, src, offset)
+            self.mem_access(method, dst, src, offset)
         } else {
             self.binary_with_imm(&AsmBinaryOp::Addi, dst, src, offset)?;
-            self.mem_access("lw", dst, dst, 0)
+            self.mem_access(method, dst, dst, 0)
         }
     }
 
@@ -34,13 +34,13 @@ impl AsmWriter {
         writeln!(self.f, "  la {}, {}", dst, name)
     }
 
-    pub fn sw(&mut self, src: RegID, dst: RegID, offset: i32) -> Result<()> {
+    pub fn store(&mut self, method: &str, src: RegID, dst: RegID, offset: i32) -> Result<()> {
         if (-2048..=2047).contains(&offset) {
-            self.mem_access("sw", src, dst, offset)
+            self.mem_access(method, src, dst, offset)
         } else {
             let tmp = "t0".into_id();
             self.binary_with_imm(&AsmBinaryOp::Addi, tmp, dst, offset)?;
-            self.mem_access("sw", src, tmp, 0)
+            self.mem_access(method, src, tmp, 0)
         }
     }
 
@@ -48,6 +48,10 @@ impl AsmWriter {
         writeln!(self.f, "  j {}", target)
     }
 
+    pub fn jr(&mut self, src: RegID) -> Result<()> {
+        writeln!(self.f, "  jr {}", src)
+    }
+
     pub fn branch(&mut self, op: &BranchOp, lhs: RegID, rhs: RegID, target: &str) -> Result<()> {
         match op {
             BranchOp::Bnez => writeln!(self.f, "  bnez {}, {}", lhs, target),
@@ -59,7 +63,12 @@ impl AsmWriter {
     }
 
     pub fn unary(&mut self, op: &AsmUnaryOp, dst: RegID, opr: RegID) -> Result<()> {
-        writeln!(self.f, "  {} {}, {}", op, dst, opr)
+        match op {
+            AsmUnaryOp::FcvtWS | AsmUnaryOp::FcvtWUS | AsmUnaryOp::FcvtLS => {
+                writeln!(self.f, "  {} {}, {}, rtz", op, dst, opr)
+            }
+            _ => writeln!(self.f, "  {} {}, {}", op, dst, opr),
+        }
     }
 
     pub fn binary(&mut self, op: &AsmBinaryOp, dst: RegID, lhs: RegID, rhs: RegID) -> Result<()> {
@@ -111,27 +120,67 @@ impl AsmWriter {
         writeln!(self.f, "  .word {}", val)
     }
 
-    pub fn from_path(path: &str) -> Self {
-        Self {
-            f: BufWriter::new(File::create(path).unwrap()),
+    /// The address of the label, which takes a whole register
+    pub fn word_label(&mut self, label: &str) -> Result<()> {
+        match self.target {
+            Target::Rv32 => writeln!(self.f, "  .word {}", label),
+            Target::Rv64 => writeln!(self.f, "  .dword {}", label),
         }
     }
 
+    pub fn align(&mut self, power: u32) -> Result<()> {
+        writeln!(self.f, "  .align {}", power)
+    }
+
+    pub fn byte(&mut self, val: u8) -> Result<()> {
+        writeln!(self.f, "  .byte {}", val)
+    }
+
+    /// Write the string with the bytes other than printable characters escaped in octal
+    pub fn asciz(&mut self, s: &[u8]) -> Result<()> {
+        write!(self.f, "  .asciz \"")?;
+        for &c in s {
+            match c {
+                b'"' | b'\\' => write!(self.f, "\\{}", c as char)?,
+                b'\n' => write!(self.f, "\\n")?,
+                b'\t' => write!(self.f, "\\t")?,
+                b' '..=b'~' => write!(self.f, "{}", c as char)?,
+                _ => write!(self.f, "\\{:03o}", c)?,
+            }
+        }
+        writeln!(self.f, "\"")
+    }
+
+    pub fn new(f: W, target: Target) -> Self {
+        Self { f, target }
+    }
+
     fn directive(&mut self, directive: &Directive) -> Result<()> {
         match directive {
             Directive::Data => self.segment("data"),
+            Directive::Rodata => self.segment("section .rodata"),
             Directive::Text => self.segment("text"),
             Directive::Word(val) => self.word(*val),
+            Directive::Label(label) => self.word_label(label),
+            Directive::Byte(val) => self.byte(*val),
+            Directive::Asciz(s) => self.asciz(s),
             Directive::Zero(size) => self.zero(*size),
+            Directive::Align(n) => self.align(*n),
         }
     }
 
     pub fn write_program(&mut self, program: &AsmProgram) -> Result<()> {
+        let (load, store) = match self.target {
+            Target::Rv32 => ("lw", "sw"),
+            Target::Rv64 => ("ld", "sd"),
+        };
         program.values.iter().try_for_each(|value| match value {
             AsmValue::LoadAddress(dst, lable) => self.la(*dst, lable),
             AsmValue::LoadImm(dst, imm) => self.li(*dst, *imm),
-            AsmValue::Load(dst, src, offset) => self.lw(*dst, *src, *offset),
-            AsmValue::Store(src, dst, offset) => self.sw(*src, *dst, *offset),
+            AsmValue::Load(dst, src, offset) => self.load(load, *dst, *src, *offset),
+            AsmValue::Store(src, dst, offset) => self.store(store, *src, *dst, *offset),
+            AsmValue::LoadWord(dst, src, offset) => self.load("lw", *dst, *src, *offset),
+            AsmValue::StoreWord(src, dst, offset) => self.store("sw", *src, *dst, *offset),
             AsmValue::Unary(op, dst, opr) => self.unary(op, *dst, *opr),
             AsmValue::Binary(op, dst, lhs, rhs) => self.binary(op, *dst, *lhs, *rhs),
             AsmValue::BinaryImm(op, dst, lhs, imm) => self.binary_with_imm(op, *dst, *lhs, *imm),
@@ -139,9 +188,11 @@ impl AsmWriter {
             AsmValue::Call(label) => self.call(label),
             AsmValue::Branch(op, lhs, rhs, target) => self.branch(op, *lhs, *rhs, target),
             AsmValue::Jump(target) => self.j(target),
+            AsmValue::JumpReg(src) => self.jr(*src),
             AsmValue::LocalSymbol(label) => self.local_symbol(label),
             AsmValue::GlobalSymbol(label) => self.global_symbol(label),
             AsmValue::Return => self.ret(),
+            AsmValue::Verbatim(text) => write!(self.f, "{}", text),
         })
     }
 }
@@ -0,0 +1,187 @@
+use std::fmt::Write;
+
+use strum_macros::Display;
+
+use super::*;
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    #[strum(serialize = "error")]
+    Error,
+    #[strum(serialize = "warning")]
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+    /// the original file the span is in, or `None` if it is in the preprocessed text
+    pub file: Option<FileId>,
+    /// the kind of the warning, even if it has been turned into an error by `-Werror`
+    pub warning: Option<Warning>,
+    /// the kind of the error, unless it is a warning
+    pub error: Option<ErrorCode>,
+    /// secondary spans in the same file, e.g. the previous declaration of a name
+    pub labels: Vec<Label>,
+    pub fixes: Vec<Fix>,
+}
+
+/// A secondary span of a diagnostic with a note on it
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A suggested edit that replaces the span with the text, e.g. an empty span with `;`
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(error: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+            file: None,
+            warning: None,
+            error: Some(error),
+            labels: vec![],
+            fixes: vec![],
+        }
+    }
+
+    pub fn warning(warning: Warning, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            span: None,
+            file: None,
+            warning: Some(warning),
+            error: None,
+            labels: vec![],
+            fixes: vec![],
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_fix(
+        mut self,
+        span: Span,
+        replacement: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        self.fixes.push(Fix {
+            span,
+            replacement: replacement.into(),
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Name of the kind of the diagnostic, which is that of the warning if it is one
+    pub fn code(&self) -> String {
+        match (self.warning, self.error) {
+            (Some(w), _) => w.to_string(),
+            (None, Some(e)) => e.to_string(),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    /// Locate the span in an original file, e.g. for a directive
+    pub fn in_file(mut self, file: FileId) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// Render the diagnostic in a human-readable form, e.g.
+    ///
+    /// ```text
+    /// error: redefinition of `x`
+    ///  --> main.c:3:9
+    ///   |
+    /// 3 |     int x = 1;
+    ///   |         ^^^^^
+    /// note: previous definition of `x` is here
+    ///  --> main.c:2:9
+    ///   |
+    /// 2 |     int x;
+    ///   |         -
+    /// ```
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let mut out = String::new();
+        write!(out, "{}: {}", self.severity, self.message).unwrap();
+        match (self.severity, self.warning) {
+            (Severity::Warning, Some(w)) => writeln!(out, " [-W{}]", w),
+            (Severity::Error, Some(w)) => writeln!(out, " [-Werror={}]", w),
+            _ => writeln!(out),
+        }
+        .unwrap();
+
+        let (file, span) = match sources.locate(self) {
+            (file, Some(span)) => (file, span),
+            (file, None) => {
+                writeln!(out, " --> {}", file.name()).unwrap();
+                return out;
+            }
+        };
+        render_snippet(&mut out, file, span, '^');
+
+        for label in &self.labels {
+            writeln!(out, "note: {}", label.message).unwrap();
+            if let (file, Some(span)) = sources.locate_span(self.file, Some(label.span)) {
+                render_snippet(&mut out, file, span, '-');
+            }
+        }
+        for fix in &self.fixes {
+            writeln!(out, "help: {}", fix.message).unwrap();
+        }
+
+        out
+    }
+}
+
+/// Render the line where the span starts, underlined up to the end of the line at most
+fn render_snippet(out: &mut String, file: &SourceFile, span: Span, underline: char) {
+    let (line, col) = file.line_col(span.start);
+    let text = file.line(line - 1);
+    let gutter = line.to_string().len();
+
+    let width = text.chars().skip(col - 1).count();
+    let len = file.src()[span.start..]
+        .char_indices()
+        .take_while(|&(i, c)| i < span.end.saturating_sub(span.start) && c != '\n')
+        .count()
+        .clamp(1, width.max(1));
+
+    writeln!(out, "{:gutter$}--> {}:{}:{}", "", file.name(), line, col).unwrap();
+    writeln!(out, "{:gutter$} |", "").unwrap();
+    writeln!(out, "{} | {}", line, text).unwrap();
+    writeln!(
+        out,
+        "{:gutter$} | {:pad$}{}",
+        "",
+        "",
+        underline.to_string().repeat(len),
+        pad = col - 1
+    )
+    .unwrap();
+}
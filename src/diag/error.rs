@@ -0,0 +1,73 @@
+use strum_macros::Display;
+
+/// A kind of error, by which tools tell the errors apart rather than by their messages
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    #[strum(serialize = "syntax")]
+    Syntax,
+    /// an integer literal which does not fit any integer type
+    #[strum(serialize = "literal-range")]
+    LiteralRange,
+    /// a malformed or misplaced preprocessing directive
+    #[strum(serialize = "directive")]
+    Directive,
+    /// a function-like macro invoked with the wrong number of arguments
+    #[strum(serialize = "macro-arguments")]
+    MacroArguments,
+    #[strum(serialize = "include")]
+    Include,
+    #[strum(serialize = "error-directive")]
+    ErrorDirective,
+    #[strum(serialize = "undeclared")]
+    Undeclared,
+    /// a name, type or member declared twice in the same scope
+    #[strum(serialize = "redefinition")]
+    Redefinition,
+    #[strum(serialize = "conflicting-types")]
+    ConflictingTypes,
+    /// a variable, member or parameter of a type without a size
+    #[strum(serialize = "incomplete-type")]
+    IncompleteType,
+    /// a type C does not allow, e.g. a function returning an array
+    #[strum(serialize = "invalid-type")]
+    InvalidType,
+    /// an array size which is negative or not an integer
+    #[strum(serialize = "array-size")]
+    ArraySize,
+    #[strum(serialize = "not-constant")]
+    NotConstant,
+    /// a constant index past the bounds of a `const` array
+    #[strum(serialize = "index-out-of-bounds")]
+    IndexOutOfBounds,
+    /// an initializer which does not match the declaration
+    #[strum(serialize = "invalid-initializer")]
+    InvalidInitializer,
+    /// an assignment to a constant or an rvalue
+    #[strum(serialize = "not-assignable")]
+    NotAssignable,
+    /// an operand of a type the operation does not accept
+    #[strum(serialize = "type-mismatch")]
+    TypeMismatch,
+    #[strum(serialize = "argument-count")]
+    ArgumentCount,
+    /// a `return` whose value does not match the function
+    #[strum(serialize = "return-value")]
+    ReturnValue,
+    /// a `break`, `continue` or label outside the statement it belongs to
+    #[strum(serialize = "misplaced-statement")]
+    MisplacedStatement,
+    #[strum(serialize = "duplicate-case")]
+    DuplicateCase,
+    #[strum(serialize = "no-member")]
+    NoMember,
+    #[strum(serialize = "missing-main")]
+    MissingMain,
+    #[strum(serialize = "missing-parameter-name")]
+    MissingParameterName,
+    /// a name the compiler keeps for the symbols it generates
+    #[strum(serialize = "reserved-name")]
+    ReservedName,
+    /// a feature of C the compiler does not implement
+    #[strum(serialize = "unsupported")]
+    Unsupported,
+}
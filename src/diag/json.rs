@@ -0,0 +1,54 @@
+use serde_json::{json, Value};
+
+use super::*;
+
+impl Diagnostic {
+    /// The diagnostic as a JSON object, with the spans located in the original files
+    pub fn to_json(&self, sources: &SourceMap) -> Value {
+        let span = |span: Span| match sources.locate_span(self.file, Some(span)) {
+            (file, Some(span)) => span_to_json(file, span),
+            (_, None) => Value::Null,
+        };
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|l| json!({ "message": l.message, "span": span(l.span) }))
+            .collect();
+        let fixes: Vec<_> = self
+            .fixes
+            .iter()
+            .map(|f| {
+                json!({
+                    "message": f.message,
+                    "span": span(f.span),
+                    "replacement": f.replacement,
+                })
+            })
+            .collect();
+        let (file, primary) = sources.locate(self);
+        json!({
+            "severity": self.severity.to_string(),
+            "code": self.code(),
+            "message": self.message,
+            "file": file.name(),
+            "span": primary.map_or(Value::Null, |s| span_to_json(file, s)),
+            "labels": labels,
+            "fixes": fixes,
+        })
+    }
+}
+
+/// Position of a span in a file, with one-based lines and columns counted in characters
+fn span_to_json(file: &SourceFile, span: Span) -> Value {
+    let (line_start, column_start) = file.line_col(span.start);
+    let (line_end, column_end) = file.line_col(span.end);
+    json!({
+        "file": file.name(),
+        "byte_start": span.start,
+        "byte_end": span.end,
+        "line_start": line_start,
+        "column_start": column_start,
+        "line_end": line_end,
+        "column_end": column_end,
+    })
+}
@@ -0,0 +1,15 @@
+mod diagnostic;
+mod error;
+mod json;
+mod parse;
+mod sink;
+mod source_map;
+mod span;
+mod warning;
+
+pub use diagnostic::*;
+pub use error::*;
+pub use sink::*;
+pub use source_map::*;
+pub use span::*;
+pub use warning::*;
@@ -0,0 +1,116 @@
+use lalrpop_util::lexer::Token;
+use lalrpop_util::ParseError;
+
+use super::*;
+
+impl Diagnostic {
+    /// Diagnostic of a parse error, quoting the tokens as written rather than as marked
+    pub fn from_parse_error(err: ParseError<usize, Token<'_>, Diagnostic>, src: &str) -> Self {
+        match err {
+            ParseError::InvalidToken { location } => {
+                Self::error(ErrorCode::Syntax, "invalid token")
+                    .with_span(Span::new(location, location + 1))
+            }
+            ParseError::UnrecognizedEOF { location, expected } => {
+                Self::error(ErrorCode::Syntax, unexpected(&expected, "end of file"))
+                    .with_span(Span::new(location, location))
+                    .with_semicolon_fix(&expected, src, location)
+            }
+            ParseError::UnrecognizedToken {
+                token: (lo, _, hi),
+                expected,
+            } => Self::error(
+                ErrorCode::Syntax,
+                unexpected(&expected, &format!("`{}`", &src[lo..hi])),
+            )
+            .with_span(Span::new(lo, hi))
+            .with_semicolon_fix(&expected, src, lo),
+            ParseError::ExtraToken { token: (lo, _, hi) } => Self::error(
+                ErrorCode::Syntax,
+                format!("unexpected token `{}`", &src[lo..hi]),
+            )
+            .with_span(Span::new(lo, hi)),
+            ParseError::User { error } => error,
+        }
+    }
+
+    /// Suggest inserting a `;` after the token before the unexpected one, if one is expected
+    fn with_semicolon_fix(self, expected: &[String], src: &str, location: usize) -> Self {
+        if !expected.iter().any(|t| t == r#"";""#) {
+            return self;
+        }
+        let end = src[..location].trim_end().len();
+        self.with_fix(Span::new(end, end), ";", "insert `;`")
+    }
+}
+
+/// Tokens which may start an expression
+const EXPR_START: &[&str] = &[
+    "`!`",
+    "`(`",
+    "`++`",
+    "`--`",
+    "`+`",
+    "`-`",
+    "`~`",
+    "`*`",
+    "`&`",
+    "identifier",
+    "integer literal",
+    "floating-point literal",
+    "character literal",
+    "string literal",
+];
+
+/// Tokens which may follow an operand
+const BINARY_OP: &[&str] = &[
+    "`*`", "`/`", "`%`", "`+`", "`-`", "`<`", "`<=`", "`>`", "`>=`", "`==`", "`!=`", "`&&`",
+    "`||`", "`&`", "`|`", "`^`", "`<<`", "`>>`", "`+=`", "`-=`", "`*=`", "`/=`", "`%=`", "`&=`",
+    "`|=`", "`^=`", "`<<=`", "`>>=`", "`++`", "`--`", "`?`", "`.`", "`->`",
+];
+
+/// Name of a terminal as reported by the parser, e.g. `";"` or `r#"[1-9][0-9]*"#`
+fn describe_terminal(terminal: &str) -> String {
+    if terminal.starts_with("r#") {
+        match terminal {
+            r##"r#"[_a-zA-Z][_a-zA-Z0-9]*"#"## => "identifier".to_string(),
+            _ if terminal.starts_with(r#"r#"\\$"#) => "type name".to_string(),
+            _ if terminal.starts_with("r#\"'") => "character literal".to_string(),
+            _ if terminal.starts_with("r#\"\"") => "string literal".to_string(),
+            _ if terminal.contains(r"\.") => "floating-point literal".to_string(),
+            _ => "integer literal".to_string(),
+        }
+    } else {
+        format!("`{}`", terminal.trim_matches('"'))
+    }
+}
+
+/// Message for an unexpected token, e.g. "expected one of `;` or `,`, found `}`"
+fn unexpected(expected: &[String], found: &str) -> String {
+    let mut names: Vec<String> = vec![];
+    for name in expected.iter().map(|t| describe_terminal(t)) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    // summarize the long lists of tokens around expressions
+    if names.iter().any(|n| n == "integer literal") {
+        names.retain(|n| !EXPR_START.contains(&n.as_str()));
+        names.push("an expression".to_string());
+    }
+    if names.iter().any(|n| n == "`/`") {
+        names.retain(|n| !BINARY_OP.contains(&n.as_str()));
+        names.push("an operator".to_string());
+    }
+
+    match names.as_slice() {
+        [] => format!("unexpected {}", found),
+        [name] => format!("expected {}, found {}", name, found),
+        [init @ .., last] => format!(
+            "expected one of {} or {}, found {}",
+            init.join(", "),
+            last,
+            found
+        ),
+    }
+}
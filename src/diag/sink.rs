@@ -0,0 +1,154 @@
+use std::fmt::Display;
+
+use strum_macros::EnumString;
+
+use super::*;
+
+/// How the diagnostics are printed, as given by `--diagnostic-format=<format>`
+#[derive(Debug, EnumString, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiagnosticFormat {
+    /// the rendered snippets of the source
+    #[default]
+    #[strum(serialize = "human")]
+    Human,
+    /// a JSON object on a line for each diagnostic
+    #[strum(serialize = "json")]
+    Json,
+}
+
+/// Which diagnostics are reported and how they are printed
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticOptions {
+    pub warnings: WarningOptions,
+    pub format: DiagnosticFormat,
+}
+
+impl DiagnosticOptions {
+    /// Fail with the diagnostics if there are errors, or return the warnings left otherwise
+    pub fn check(
+        &self,
+        sources: SourceMap,
+        mut diags: Diagnostics,
+    ) -> Result<Warnings, CompileError> {
+        self.warnings.apply(&mut diags);
+        if diags.has_errors() {
+            return Err(CompileError { sources, diags });
+        }
+        Ok(Warnings { sources, diags })
+    }
+}
+
+/// Diagnostics collected by a compiler pass
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    diags: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push(&mut self, diag: Diagnostic) {
+        self.diags.push(diag);
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.diags.extend(other.diags);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diags.iter().any(|d| d.severity == Severity::Error)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.diags
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diags.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diags.iter()
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&Diagnostic) -> bool) {
+        self.diags.retain(f);
+    }
+
+    pub fn for_each_mut(&mut self, f: impl FnMut(&mut Diagnostic)) {
+        self.diags.iter_mut().for_each(f);
+    }
+
+    /// Render the diagnostics one after another, e.g. the warnings of a file that compiled
+    pub fn render(&self, sources: &SourceMap) -> String {
+        self.iter()
+            .map(|diag| format!("{}\n", diag.render(sources)))
+            .collect()
+    }
+
+    pub fn emit(&self, sources: &SourceMap, format: DiagnosticFormat) -> String {
+        match format {
+            DiagnosticFormat::Human => self.render(sources),
+            DiagnosticFormat::Json => self
+                .iter()
+                .map(|diag| format!("{}\n", diag.to_json(sources)))
+                .collect(),
+        }
+    }
+}
+
+/// The warnings of a source file that compiled, which are left to the caller to print
+#[derive(Debug)]
+pub struct Warnings {
+    pub sources: SourceMap,
+    pub diags: Diagnostics,
+}
+
+impl Warnings {
+    pub fn emit(&self, format: DiagnosticFormat) -> String {
+        self.diags.emit(&self.sources, format)
+    }
+}
+
+/// All the diagnostics of a source file that failed to compile
+#[derive(Debug)]
+pub struct CompileError {
+    pub sources: SourceMap,
+    pub diags: Diagnostics,
+}
+
+impl CompileError {
+    /// The diagnostics in the given format, followed by a summary if they are for humans
+    pub fn emit(&self, format: DiagnosticFormat) -> String {
+        match format {
+            DiagnosticFormat::Human => format!("{}\n", self),
+            DiagnosticFormat::Json => self.diags.emit(&self.sources, format),
+        }
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diags.render(&self.sources))?;
+        match self.diags.error_count() {
+            1 => write!(
+                f,
+                "could not compile `{}` due to previous error",
+                self.sources.main_file().name()
+            ),
+            n => write!(
+                f,
+                "could not compile `{}` due to {} previous errors",
+                self.sources.main_file().name(),
+                n
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
@@ -0,0 +1,147 @@
+use super::*;
+
+pub type FileId = usize;
+
+/// Where a piece of a preprocessed line comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    /// byte offset of the piece in the preprocessed line
+    pub out: usize,
+    /// zero-based line and byte column in the original file
+    pub line: usize,
+    pub col: usize,
+    /// whether the piece is the expansion of the macro invoked there
+    pub verbatim: bool,
+}
+
+/// Origin of a preprocessed line, whose pieces may come from several lines of the same file
+#[derive(Debug)]
+struct LineOrigin {
+    file: FileId,
+    line: usize,
+    segments: Vec<Segment>,
+}
+
+/// The preprocessed text of a program, with the line map back to its original files
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    /// the original files, starting with the main file
+    files: Vec<SourceFile>,
+    text: String,
+    line_starts: Vec<usize>,
+    origins: Vec<LineOrigin>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add_file(&mut self, file: SourceFile) -> FileId {
+        self.files.push(file);
+        self.files.len() - 1
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id]
+    }
+
+    pub fn main_file(&self) -> &SourceFile {
+        &self.files[0]
+    }
+
+    pub fn into_main_file(mut self) -> SourceFile {
+        self.files.swap_remove(0)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Append a preprocessed line coming from the zero-based line of the file
+    pub fn push_line(&mut self, text: &str, file: FileId, line: usize, segments: Vec<Segment>) {
+        self.line_starts.push(self.text.len());
+        self.text += text;
+        self.text.push('\n');
+        self.origins.push(LineOrigin {
+            file,
+            line,
+            segments,
+        });
+    }
+
+    /// Line of the preprocessed text the byte offset is in, and the column of the offset
+    fn origin(&self, offset: usize) -> Option<(&LineOrigin, usize)> {
+        let index = match self.line_starts.binary_search(&offset) {
+            Ok(index) => index,
+            Err(next) => next.saturating_sub(1),
+        };
+        let origin = self.origins.get(index)?;
+        Some((origin, offset - self.line_starts[index]))
+    }
+
+    /// Original file and byte offset of an offset in the preprocessed text, where an offset
+    /// in a macro expansion is located at the invocation
+    pub fn resolve(&self, offset: usize) -> (FileId, usize) {
+        let Some((origin, col)) = self.origin(offset) else {
+            return (0, self.files[0].src().len());
+        };
+        let file = &self.files[origin.file];
+        let (line, col) = match origin.segments.iter().rev().find(|s| s.out <= col) {
+            Some(s) if s.verbatim => (s.line, s.col + col - s.out),
+            Some(s) => (s.line, s.col),
+            None => (origin.line, 0),
+        };
+        let offset = (file.line_start(line) + col).min(file.src().len());
+        (origin.file, offset)
+    }
+
+    /// Original file and byte offset of the end of the macro invocation the offset is in
+    fn invocation_end(&self, offset: usize) -> Option<(FileId, usize)> {
+        let (origin, col) = self.origin(offset)?;
+        let index = origin.segments.iter().rposition(|s| s.out <= col)?;
+        if origin.segments[index].verbatim {
+            return None;
+        }
+        let file = &self.files[origin.file];
+        let end = match origin.segments.get(index + 1) {
+            Some(next) => file.line_start(next.line) + next.col,
+            None => {
+                let line = origin.segments[index].line;
+                file.line_start(line) + file.line(line).trim_end().len()
+            }
+        };
+        Some((origin.file, end.min(file.src().len())))
+    }
+
+    /// Original file of a diagnostic, and its span in the file if any
+    pub fn locate(&self, diag: &Diagnostic) -> (&SourceFile, Option<Span>) {
+        self.locate_span(diag.file, diag.span)
+    }
+
+    /// Original file of a span in the given file or else in the preprocessed text
+    pub fn locate_span(
+        &self,
+        file: Option<FileId>,
+        span: Option<Span>,
+    ) -> (&SourceFile, Option<Span>) {
+        match (file, span) {
+            (Some(file), span) => (&self.files[file], span),
+            (None, None) => (self.main_file(), None),
+            (None, Some(span)) => {
+                let (file, start) = self.resolve(span.start);
+                // a span ending in the expansion of a macro covers the rest of the invocation
+                let end = match span.end > span.start {
+                    true => self.invocation_end(span.end - 1),
+                    false => None,
+                };
+                // a span across files or macros is cut at its start
+                let end = match end.unwrap_or_else(|| self.resolve(span.end)) {
+                    (end_file, end) if end_file == file && end >= start => end,
+                    _ => start,
+                };
+                (&self.files[file], Some(Span::new(start, end)))
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+/// A half-open range of byte offsets into the source file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    name: String,
+    src: String,
+    /// byte offset of the first character of each line
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: String, src: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            name,
+            src,
+            line_starts,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// Byte offset of the first character of the zero-based line
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts[line]
+    }
+
+    /// Zero-based line index of the byte offset
+    pub fn line_index(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        }
+    }
+
+    /// One-based line and column of the byte offset
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_index(offset);
+        let col = self.src[self.line_starts[line]..offset].chars().count();
+        (line + 1, col + 1)
+    }
+
+    /// Text of the zero-based line, without the trailing newline
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(self.src.len());
+        self.src[start..end].trim_end_matches(['\n', '\r'])
+    }
+}
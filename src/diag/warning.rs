@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+
+use strum_macros::{Display, EnumString};
+
+use super::*;
+
+/// A kind of warning, which is enabled by `-W<name>` and disabled by `-Wno-<name>`
+#[derive(Debug, Display, EnumString, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Warning {
+    /// a local variable that is never used
+    #[strum(serialize = "unused-variable")]
+    UnusedVariable,
+    /// control reaching the end of a non-void function
+    #[strum(serialize = "return-type")]
+    ReturnType,
+    /// a statement that can never be executed
+    #[strum(serialize = "unreachable-code")]
+    UnreachableCode,
+    /// a declaration hiding another one in an enclosing scope
+    #[strum(serialize = "shadow")]
+    Shadow,
+    /// a division or remainder by a constant zero
+    #[strum(serialize = "div-by-zero")]
+    DivByZero,
+    /// an operation of signed constants whose result does not fit its type
+    #[strum(serialize = "overflow")]
+    Overflow,
+}
+
+impl Warning {
+    /// The warnings enabled by `-Wall`
+    const ALL: &'static [Warning] = &[Self::UnusedVariable, Self::ReturnType];
+}
+
+/// Which warnings are reported, and whether they are errors
+#[derive(Debug, Clone)]
+pub struct WarningOptions {
+    enabled: HashSet<Warning>,
+    /// `-Werror`
+    werror: bool,
+}
+
+impl Default for WarningOptions {
+    /// Only `-Wreturn-type`, `-Wdiv-by-zero` and `-Woverflow` are enabled by default, as in GCC
+    fn default() -> Self {
+        Self {
+            enabled: HashSet::from([Warning::ReturnType, Warning::DivByZero, Warning::Overflow]),
+            werror: false,
+        }
+    }
+}
+
+impl WarningOptions {
+    /// Apply an option following `-W`, or return `false` if it is unknown
+    pub fn parse(&mut self, option: &str) -> bool {
+        match option {
+            "all" => self.enabled.extend(Warning::ALL),
+            "error" => self.werror = true,
+            "no-error" => self.werror = false,
+            _ => match option.strip_prefix("no-") {
+                Some(name) => match name.parse() {
+                    Ok(warning) => {
+                        self.enabled.remove(&warning);
+                    }
+                    Err(_) => return false,
+                },
+                None => match option.parse() {
+                    Ok(warning) => {
+                        self.enabled.insert(warning);
+                    }
+                    Err(_) => return false,
+                },
+            },
+        }
+        true
+    }
+
+    /// Drop the disabled warnings, and turn the rest into errors under `-Werror`
+    pub fn apply(&self, diags: &mut Diagnostics) {
+        diags.retain(|d| d.warning.is_none_or(|w| self.enabled.contains(&w)));
+        if self.werror {
+            diags.for_each_mut(|d| d.severity = Severity::Error);
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{ast::*, sema::ty::TypeKind};
+use crate::{
+    ast::*,
+    sema::ty::{Type, TypeKind},
+};
 use koopa::ir::builder_traits::{GlobalInstBuilder, LocalInstBuilder, ValueBuilder};
 use smallvec::SmallVec;
 
@@ -15,10 +18,44 @@ impl<'i> GenerateIR<'i> for CompUnit {
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
         recorder.install_lib();
+        for item in &self.items {
+            if let GlobalItem::Decl(Decl::VarDecl(decls)) = item {
+                let defs = decls.iter().filter(|d| d.storage != Storage::Extern);
+                defs.for_each(|d| recorder.insert_var_def(d));
+            }
+        }
 
-        self.items
-            .iter()
-            .try_for_each(|item| item.generate_ir(recorder))
+        for item in &self.items {
+            match item {
+                // a prototype refers to the definition later in the file, if there is one
+                GlobalItem::Func(f) if f.block.is_none() => match self.find_func_def(&f.ident) {
+                    Some(def) => {
+                        recorder.new_func(def);
+                    }
+                    None => recorder.declare_func(&f.ident),
+                },
+                // likewise an `extern` declaration
+                GlobalItem::Decl(Decl::VarDecl(decls)) => decls.iter().try_for_each(|d| {
+                    match d.storage {
+                        Storage::Extern => recorder.var_def(&d.lval.ident).unwrap_or(d),
+                        _ => d,
+                    }
+                    .generate_ir(recorder)
+                })?,
+                _ => item.generate_ir(recorder)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CompUnit {
+    fn find_func_def(&self, ident: &str) -> Option<&FuncDef> {
+        self.items.iter().find_map(|item| match item {
+            GlobalItem::Func(f) if f.ident == ident && f.block.is_some() => Some(f),
+            _ => None,
+        })
     }
 }
 
@@ -29,6 +66,8 @@ impl<'i> GenerateIR<'i> for GlobalItem {
         match self {
             GlobalItem::Decl(i) => i.generate_ir(recorder),
             GlobalItem::Func(i) => i.generate_ir(recorder),
+            // a `struct` only has effects on the types
+            GlobalItem::Struct(_) => Ok(()),
         }
     }
 }
@@ -46,26 +85,45 @@ impl<'i> GenerateIR<'i> for FuncDef {
 
         let param_values: SmallVec<[_; 6]> = recorder.get_func_data().params().into();
         let ty = recorder.get_ty(&self.ident).clone();
-        let (ret_ty, param_tys) = if let TypeKind::Func(ret_ty, param_tys) = ty.kind() {
+        let (ret_ty, param_tys) = if let TypeKind::Func(ret_ty, param_tys, _) = ty.kind() {
             (ret_ty, param_tys)
         } else {
             unreachable!()
         };
 
-        (0..param_values.len()).for_each(|i| {
-            let ident = &self.params[i].ident;
+        (0..self.params.len()).for_each(|i| {
+            let ident = self.params[i].ident.as_ref().unwrap();
             let ty = param_tys[i].get_ir_ty();
             let value = param_values[i];
             let alloc = local_alloc(recorder, ty, Some(format!("@{}", ident)));
-            let store = recorder.new_value().store(value, alloc);
-            recorder.push_inst(store);
+            if param_tys[i].is_by_ref() {
+                // the callee works on its own copy of a `struct` or `long long` argument
+                let dst = into_ptr(recorder, alloc);
+                match param_tys[i].is_passed_by_ref() {
+                    true => copy_value(recorder, dst, value, &param_tys[i]),
+                    false => store_long(recorder, value, dst),
+                }
+            } else {
+                let store = recorder.new_value().store(value, alloc);
+                recorder.push_inst(store);
+            }
             recorder.insert_value(ident, alloc);
         });
 
-        // allocate the return value
-        if !matches!(self.ret_kind, ExprKind::Void) {
+        // allocate the return value, or write it through the pointer after the parameters
+        if ret_ty.is_passed_by_ref() {
+            recorder
+                .func_mut()
+                .set_ret_val(*param_values.last().unwrap());
+        } else if ret_ty.is_by_ref() {
+            let ret_val = long_alloc(recorder);
+            recorder.func_mut().set_ret_val(ret_val);
+        } else if !matches!(self.ret_kind, BType::Void) {
             let ret_val = local_alloc(recorder, ret_ty.get_ir_ty(), Some("%ret".to_owned()));
-            let init = recorder.new_value().integer(0);
+            let init = match ret_ty.kind() {
+                TypeKind::Pointer(_) => null_ptr(recorder, ret_ty),
+                _ => recorder.new_value().integer(0),
+            };
             let store = recorder.new_value().store(init, ret_val);
             recorder.push_inst(store);
             recorder.func_mut().set_ret_val(ret_val);
@@ -75,7 +133,7 @@ impl<'i> GenerateIR<'i> for FuncDef {
         let main_body = recorder.new_anonymous_bb();
         recorder.push_bb(main_body);
         // generate IR for the main body block
-        self.block.generate_ir(recorder)?;
+        self.block.as_ref().unwrap().generate_ir(recorder)?;
 
         // finishing off the function
         let entry = recorder.func().get_entry_bb();
@@ -90,9 +148,14 @@ impl<'i> GenerateIR<'i> for FuncDef {
         recorder.push_bb(end_bb);
 
         // load the return value and return
-        if matches!(self.ret_kind, ExprKind::Void) {
+        if matches!(self.ret_kind, BType::Void) || ret_ty.is_passed_by_ref() {
             let ret = recorder.new_value().ret(None);
             recorder.push_inst(ret);
+        } else if ret_ty.is_by_ref() {
+            let ret_val = recorder.func().get_ret_val().unwrap();
+            let ld = load_long(recorder, ret_val);
+            let ret = recorder.new_value().ret(Some(ld));
+            recorder.push_inst(ret);
         } else {
             let ret_val = recorder.func().get_ret_val().unwrap();
             let ld = recorder.new_value().load(ret_val);
@@ -126,6 +189,8 @@ impl<'i> GenerateIR<'i> for Decl {
         match self {
             Decl::ConstDecl(decls) => decls.iter().try_for_each(|d| d.generate_ir(recorder)),
             Decl::VarDecl(decls) => decls.iter().try_for_each(|d| d.generate_ir(recorder)),
+            // enumerators have been folded into their uses, like scalar constants
+            Decl::Typedef(_) | Decl::Enum(_) => Ok(()),
         }
     }
 }
@@ -135,33 +200,22 @@ impl<'i> GenerateIR<'i> for VarDecl {
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
         let id = &self.lval.ident;
-        let ty = recorder.get_ty(id).clone();
+        if self.storage == Storage::Extern && !recorder.is_global() {
+            // refers to the global variable, which may be defined later in the file
+            let def = recorder.var_def(id).unwrap_or(self);
+            return def.generate_global(recorder);
+        }
+        if recorder.is_global() || self.storage == Storage::Static {
+            return self.generate_global(recorder);
+        }
 
-        if recorder.is_global() {
-            let init_val = match &ty.kind() {
-                TypeKind::Integer => match &self.init {
-                    Some(InitVal::Expr(e)) => recorder.new_global_value().integer(e.get_i32()),
-                    None => recorder.new_global_value().zero_init(IrType::get_i32()),
-                    _ => unreachable!(),
-                },
-                TypeKind::Array(_, _) => match &self.init {
-                    Some(init) => {
-                        let elems = eval_array(init, &ty);
-                        init_global_array(recorder, &ty, &elems)
-                    }
-                    None => recorder.new_global_value().zero_init(ty.get_ir_ty()),
-                },
-                _ => unreachable!(),
-            };
-            let alloc = recorder.new_global_value().global_alloc(init_val);
-            recorder.set_global_value_name(format!("@{}", &id), alloc);
-            recorder.insert_value(id, alloc);
-        } else {
-            let val = local_alloc(recorder, ty.get_ir_ty(), Some(format!("@{}", &id)));
-            recorder.insert_value(id, val);
+        let ty = recorder.get_ty(id).clone();
+        let val = local_alloc(recorder, ty.get_ir_ty(), Some(format!("@{}", &id)));
+        recorder.insert_value(id, val);
 
-            match &ty.kind() {
-                TypeKind::Integer => match &self.init {
+        match &ty.kind() {
+            TypeKind::Integer | TypeKind::Unsigned | TypeKind::Float | TypeKind::Pointer(_) => {
+                match &self.init {
                     Some(InitVal::Expr(e)) => {
                         let init_val = e.generate_ir(recorder)?;
                         let store = recorder.new_value().store(init_val, val);
@@ -169,27 +223,78 @@ impl<'i> GenerateIR<'i> for VarDecl {
                     }
                     None => {}
                     _ => unreachable!(),
-                },
-                TypeKind::Array(_, _) => match &self.init {
-                    Some(init) => {
-                        let elems = eval_array(init, &ty);
-                        init_array(recorder, val, &ty, &elems);
-                    }
-                    None => {}
-                },
-                _ => unreachable!(),
+                }
+            }
+            TypeKind::Array(_, _) | TypeKind::Struct(_, _) | TypeKind::LongLong => {
+                if let Some(init) = &self.init {
+                    init_aggregate(recorder, val, &ty, init);
+                }
             }
+            _ => unreachable!(),
         }
 
         Ok(())
     }
 }
 
+impl VarDecl {
+    fn generate_global<'i>(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<()> {
+        let id = &self.lval.ident;
+        // already generated for an earlier `extern` declaration
+        if recorder.has_value(id) {
+            return Ok(());
+        }
+        let ty = recorder.get_ty(id).clone();
+        let init_val = match &ty.kind() {
+            _ if self.storage == Storage::Extern => {
+                recorder.new_global_value().zero_init(ty.get_ir_ty())
+            }
+            TypeKind::Integer | TypeKind::Unsigned | TypeKind::Float => match &self.init {
+                Some(InitVal::Expr(e)) => {
+                    let init = e.get_const().to_bits(&ty);
+                    recorder.new_global_value().integer(init)
+                }
+                None => recorder.new_global_value().zero_init(IrType::get_i32()),
+                _ => unreachable!(),
+            },
+            TypeKind::LongLong => match &self.init {
+                Some(InitVal::Expr(e)) => {
+                    let words = e.get_const().to_words(&ty);
+                    init_global_array(recorder, &ty, &words)
+                }
+                None => recorder.new_global_value().zero_init(ty.get_ir_ty()),
+                _ => unreachable!(),
+            },
+            // the initializer of a global pointer can only be a null pointer
+            TypeKind::Pointer(_) => recorder.new_global_value().zero_init(ty.get_ir_ty()),
+            TypeKind::Array(_, _) | TypeKind::Struct(_, _) => match &self.init {
+                Some(init) => {
+                    let elems = eval_array(init, &ty);
+                    init_global_array(recorder, &ty, &elems)
+                }
+                None => recorder.new_global_value().zero_init(ty.get_ir_ty()),
+            },
+            _ => unreachable!(),
+        };
+        let alloc = recorder.new_global_value().global_alloc(init_val);
+        let prefix = match self.storage {
+            Storage::Auto => "@",
+            Storage::Static => STATIC_PREFIX,
+            Storage::Extern => EXTERN_PREFIX,
+        };
+        recorder.set_global_value_name(format!("{}{}", prefix, &id), alloc);
+        recorder.insert_value(id, alloc);
+
+        Ok(())
+    }
+}
+
 impl<'i> GenerateIR<'i> for ConstDecl {
     type Out = ();
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
-        if matches!(self.kind, ExprKind::Int) {
+        // scalar constants have been folded into their uses
+        if self.is_scalar() {
             return Ok(());
         }
 
@@ -197,12 +302,8 @@ impl<'i> GenerateIR<'i> for ConstDecl {
         let ty = recorder.get_ty(id).clone();
 
         if recorder.is_global() {
-            let init_val = if matches!(ty.kind(), TypeKind::Array(_, _)) {
-                let elems = eval_array(&self.init, &ty);
-                init_global_array(recorder, &ty, &elems)
-            } else {
-                unreachable!()
-            };
+            let elems = eval_array(&self.init, &ty);
+            let init_val = init_global_array(recorder, &ty, &elems);
 
             let alloc = recorder.new_global_value().global_alloc(init_val);
             recorder.set_global_value_name(format!("@{}", &id), alloc);
@@ -211,33 +312,59 @@ impl<'i> GenerateIR<'i> for ConstDecl {
             let val = local_alloc(recorder, ty.get_ir_ty(), Some(format!("@{}", &id)));
             recorder.insert_value(id, val);
 
-            if matches!(ty.kind(), TypeKind::Array(_, _)) {
-                let elems = eval_array(&self.init, &ty);
-                init_array(recorder, val, &ty, &elems);
-            } else {
-                unreachable!()
-            }
+            init_aggregate(recorder, val, &ty, &self.init);
         }
 
         Ok(())
     }
 }
 
+/// Initialize a local array, `struct` or `long long`
+fn init_aggregate<'i>(
+    recorder: &mut ProgramRecorder<'i>,
+    dst: Value,
+    ty: &Type,
+    init: &'i InitVal,
+) {
+    match init {
+        InitVal::Expr(e) => {
+            let src = e.generate_ir(recorder).unwrap();
+            let dst = into_ptr(recorder, dst);
+            copy_value(recorder, dst, src, ty);
+        }
+        _ => {
+            let elems = eval_array(init, ty);
+            init_array(recorder, dst, ty, &elems);
+        }
+    }
+}
+
 impl<'i> GenerateIR<'i> for Stmt {
     type Out = ();
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
-        match self {
-            Self::Assign(s) => s.generate_ir(recorder),
-            Self::Block(s) => s.generate_ir(recorder),
-            Self::Expr(s) => s
+        match &self.kind {
+            StmtKind::Assign(s) => s.generate_ir(recorder),
+            StmtKind::Block(s) => s.generate_ir(recorder),
+            StmtKind::Expr(s) => s
                 .as_ref()
                 .map_or(Ok(()), |exp| exp.generate_ir(recorder).map(|_| ())),
-            Self::Return(s) => s.generate_ir(recorder),
-            Self::Branch(s) => s.generate_ir(recorder),
-            Self::While(s) => s.generate_ir(recorder),
-            Self::Break(s) => s.generate_ir(recorder),
-            Self::Continue(s) => s.generate_ir(recorder),
+            StmtKind::Return(s) => s.generate_ir(recorder),
+            StmtKind::Branch(s) => s.generate_ir(recorder),
+            StmtKind::While(s) => s.generate_ir(recorder),
+            StmtKind::DoWhile(s) => s.generate_ir(recorder),
+            StmtKind::For(s) => s.generate_ir(recorder),
+            StmtKind::Switch(s) => s.generate_ir(recorder),
+            // control falls through to the label
+            StmtKind::Case(_) | StmtKind::Default => {
+                let label = recorder.next_switch_label();
+                let jump = recorder.new_value().jump(label);
+                recorder.push_inst(jump);
+                recorder.push_bb(label);
+                Ok(())
+            }
+            StmtKind::Break(s) => s.generate_ir(recorder),
+            StmtKind::Continue(s) => s.generate_ir(recorder),
         }
     }
 }
@@ -246,7 +373,14 @@ impl<'i> GenerateIR<'i> for Assign {
     type Out = ();
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
-        let dst = get_lval_ptr(recorder, &self.lval);
+        if self.val.ty().is_by_ref() {
+            let dst = get_aggregate_ptr(recorder, &self.lval);
+            let src = self.val.generate_ir(recorder)?;
+            copy_value(recorder, dst, src, self.val.ty());
+            return Ok(());
+        }
+
+        let dst = get_place_ptr(recorder, &self.lval);
         let value = self.val.generate_ir(recorder)?;
         let store = recorder.new_value().store(value, dst);
         recorder.push_inst(store);
@@ -324,16 +458,158 @@ impl<'i> GenerateIR<'i> for While {
     }
 }
 
+impl<'i> GenerateIR<'i> for DoWhile {
+    type Out = ();
+
+    fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
+        let loop_body = recorder.new_anonymous_bb();
+        let loop_cond = recorder.new_anonymous_bb();
+        let loop_exit = recorder.new_anonymous_bb();
+
+        // record the loop information
+        recorder.enter_loop(loop_cond, loop_exit);
+
+        // jump to the loop body
+        let jump = recorder.new_value().jump(loop_body);
+        recorder.push_inst(jump);
+
+        // enter the loop body block
+        recorder.push_bb(loop_body);
+        self.stmt.generate_ir(recorder)?;
+
+        let jump = recorder.new_value().jump(loop_cond);
+        recorder.push_inst(jump);
+
+        // check the loop condition, and jump back to the loop body
+        recorder.push_bb(loop_cond);
+        short_circuit(recorder, &self.cond, loop_body, loop_exit)?;
+
+        // enter the exit of loop
+        recorder.push_bb(loop_exit);
+        recorder.exit_loop();
+
+        Ok(())
+    }
+}
+
+impl<'i> GenerateIR<'i> for For {
+    type Out = ();
+
+    fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
+        match self.init.as_deref() {
+            Some(BlockItem::Decl(decl)) => decl.generate_ir(recorder)?,
+            Some(BlockItem::Stmt(stmt)) => stmt.generate_ir(recorder)?,
+            None => {}
+        }
+
+        let loop_entry = recorder.new_anonymous_bb();
+        let loop_body = recorder.new_anonymous_bb();
+        let loop_step = recorder.new_anonymous_bb();
+        let loop_exit = recorder.new_anonymous_bb();
+
+        // record the loop information
+        recorder.enter_loop(loop_step, loop_exit);
+
+        // jump to the loop entry
+        let jump = recorder.new_value().jump(loop_entry);
+        recorder.push_inst(jump);
+
+        // check the loop condition, if any
+        recorder.push_bb(loop_entry);
+        match &self.cond {
+            Some(cond) => short_circuit(recorder, cond, loop_body, loop_exit)?,
+            None => {
+                let jump = recorder.new_value().jump(loop_body);
+                recorder.push_inst(jump);
+            }
+        }
+
+        // enter the loop body block
+        recorder.push_bb(loop_body);
+        self.stmt.generate_ir(recorder)?;
+
+        let jump = recorder.new_value().jump(loop_step);
+        recorder.push_inst(jump);
+
+        // run the step, and jump back to the loop entry
+        recorder.push_bb(loop_step);
+        if let Some(step) = &self.step {
+            step.generate_ir(recorder)?;
+        }
+        let jump = recorder.new_value().jump(loop_entry);
+        recorder.push_inst(jump);
+
+        // enter the exit of loop
+        recorder.push_bb(loop_exit);
+        recorder.exit_loop();
+
+        Ok(())
+    }
+}
+
+impl<'i> GenerateIR<'i> for Switch {
+    type Out = ();
+
+    fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
+        let cond = self.cond.generate_ir(recorder)?;
+        let labels = self.labels();
+        let label_bbs: Vec<_> = labels.iter().map(|_| recorder.new_anonymous_bb()).collect();
+        let switch_exit = recorder.new_anonymous_bb();
+        let default = labels
+            .iter()
+            .position(Option::is_none)
+            .map_or(switch_exit, |i| label_bbs[i]);
+
+        if matches!(self.cond.ty().kind(), TypeKind::LongLong) {
+            let mut cases: Vec<_> = labels
+                .iter()
+                .zip(&label_bbs)
+                .filter_map(|(label, &label_bb)| Some(((*label)?, label_bb)))
+                .collect();
+            cases.sort_unstable_by_key(|&(value, _)| value);
+            long_compare_tree(recorder, cond, &cases, default);
+        } else {
+            // the backend turns a long chain of comparisons into a jump table or a binary search
+            for (label, &label_bb) in labels.iter().zip(&label_bbs) {
+                if let Some(value) = *label {
+                    let value = recorder.new_value().integer(value as i32);
+                    let eq = binary(recorder, IrBinaryOp::Eq, cond, value);
+                    let next_bb = recorder.new_anonymous_bb();
+                    let br = recorder.new_value().branch(eq, label_bb, next_bb);
+                    recorder.push_inst(br);
+                    recorder.push_bb(next_bb);
+                }
+            }
+            let jump = recorder.new_value().jump(default);
+            recorder.push_inst(jump);
+        }
+
+        // the body is only entered through the labels
+        recorder.enter_switch(switch_exit, label_bbs);
+        let body_bb = recorder.new_anonymous_bb();
+        recorder.push_bb(body_bb);
+        self.stmt.generate_ir(recorder)?;
+
+        let jump = recorder.new_value().jump(switch_exit);
+        recorder.push_inst(jump);
+
+        recorder.push_bb(switch_exit);
+        recorder.exit_switch();
+
+        Ok(())
+    }
+}
+
 impl<'i> GenerateIR<'i> for Break {
     type Out = ();
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
-        if !recorder.inside_loop() {
-            bail!("break outside of loop");
+        if !recorder.inside_breakable() {
+            bail!("break outside of loop or switch");
         }
 
-        let loop_exit = recorder.loop_exit();
-        let jump = recorder.new_value().jump(loop_exit);
+        let exit = recorder.break_target();
+        let jump = recorder.new_value().jump(exit);
         recorder.push_inst(jump);
 
         let next_bb = recorder.new_anonymous_bb();
@@ -351,9 +627,9 @@ impl<'i> GenerateIR<'i> for Continue {
             bail!("continue outside of loop");
         }
 
-        // instantly jump to the loop entry
-        let loop_entry = recorder.loop_entry();
-        let jump = recorder.new_value().jump(loop_entry);
+        // instantly jump to the condition or the step of the loop
+        let loop_continue = recorder.loop_continue();
+        let jump = recorder.new_value().jump(loop_continue);
         recorder.push_inst(jump);
 
         // enter the next block (unreachable)
@@ -369,10 +645,14 @@ impl<'i> GenerateIR<'i> for Return {
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
         if let Some(ret_val) = &self.ret_val {
-            let ret_val = ret_val.generate_ir(recorder)?;
+            let src = ret_val.generate_ir(recorder)?;
             let dst = recorder.func().get_ret_val().unwrap();
-            let st = recorder.new_value().store(ret_val, dst);
-            recorder.push_inst(st);
+            if ret_val.ty().is_by_ref() {
+                copy_value(recorder, dst, src, ret_val.ty());
+            } else {
+                let st = recorder.new_value().store(src, dst);
+                recorder.push_inst(st);
+            }
         }
         let end_bb = recorder.func().get_end_bb();
         let jump = recorder.new_value().jump(end_bb);
@@ -389,12 +669,54 @@ impl<'i> GenerateIR<'i> for Expr {
     type Out = Value;
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
-        Ok(match self {
-            Expr::Integer(i) => recorder.new_value().integer(*i),
-            Expr::Unary(uxp) => uxp.generate_ir(recorder)?,
-            Expr::Binary(bxp) => bxp.generate_ir(recorder)?,
-            Expr::LVal(lval) => load_lval(recorder, lval),
-            Expr::Error => panic!("expected an expression"),
+        Ok(match &self.kind {
+            ExprKind::Integer(i) => recorder.new_value().integer(*i),
+            ExprKind::Unsigned(u) => recorder.new_value().integer(*u as i32),
+            ExprKind::Long(l) => long_const(recorder, *l),
+            ExprKind::Float(f) => recorder.new_value().integer(f.to_bits() as i32),
+            ExprKind::Unary(uxp) => uxp.generate_ir(recorder)?,
+            ExprKind::Binary(bxp) => bxp.generate_ir(recorder)?,
+            // a `struct` or a `long long` is referred to by the pointer to its words
+            ExprKind::LVal(_) | ExprKind::Deref(_) | ExprKind::Member(..)
+                if self.ty().is_by_ref() =>
+            {
+                get_aggregate_ptr(recorder, self)
+            }
+            ExprKind::LVal(lval) => load_lval(recorder, lval),
+            ExprKind::Member(..) => {
+                let ptr = get_place_ptr(recorder, self);
+                let ld = recorder.new_value().load(ptr);
+                recorder.push_inst(ld);
+                ld
+            }
+            ExprKind::Deref(opr) => {
+                let ptr = opr.generate_ir(recorder)?;
+                let ld = recorder.new_value().load(ptr);
+                recorder.push_inst(ld);
+                ld
+            }
+            ExprKind::AddrOf(opr) => get_place_ptr(recorder, opr),
+            ExprKind::String(_) => get_aggregate_ptr(recorder, self),
+            ExprKind::CompoundAssign(c) => compound_assign(recorder, c, self.ty())?,
+            ExprKind::Conditional(c) => c.generate_ir(recorder)?,
+            ExprKind::Cast(e) => match (e.ty().kind(), self.ty().kind()) {
+                // an array is converted to the pointer to its first element
+                (TypeKind::Array(_, _), _) => get_aggregate_ptr(recorder, e),
+                // only a null pointer constant is converted to a pointer
+                (_, TypeKind::Pointer(_)) => null_ptr(recorder, self.ty()),
+                (TypeKind::Pointer(_), _) => {
+                    let opr = e.generate_ir(recorder)?;
+                    let func = recorder.pointer_intrinsic(Intrinsic::PtrToInt, e.ty().get_ir_ty());
+                    let call = recorder.new_value().call(func, vec![opr]);
+                    recorder.push_inst(call);
+                    call
+                }
+                _ => {
+                    let opr = e.generate_ir(recorder)?;
+                    convert(recorder, opr, e.ty(), self.ty())
+                }
+            },
+            ExprKind::Error => panic!("expected an expression"),
         })
     }
 }
@@ -416,11 +738,64 @@ impl<'i> GenerateIR<'i> for BinaryExpr {
             let lhs = self.lhs.generate_ir(recorder)?;
             let rhs = self.rhs.generate_ir(recorder)?;
 
-            Ok(binary(recorder, self.op.into(), lhs, rhs))
+            match (self.lhs.ty().kind(), self.rhs.ty().kind()) {
+                (TypeKind::Pointer(base_ty), TypeKind::Pointer(_)) => {
+                    return Ok(pointer_diff(recorder, base_ty, lhs, rhs));
+                }
+                (TypeKind::Pointer(_), _) => return Ok(offset_ptr(recorder, self.op, lhs, rhs)),
+                (_, TypeKind::Pointer(_)) => return Ok(offset_ptr(recorder, self.op, rhs, lhs)),
+                _ => {}
+            }
+            Ok(arith_binary(recorder, self.op, self.lhs.ty(), lhs, rhs))
         }
     }
 }
 
+impl<'i> GenerateIR<'i> for Conditional {
+    type Out = Value;
+
+    fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
+        // a constant condition was folded by the evaluator
+        match self.cond.kind {
+            ExprKind::Integer(i) if i != 0 => return self.true_val.generate_ir(recorder),
+            ExprKind::Integer(_) => return self.false_val.generate_ir(recorder),
+            _ => {}
+        }
+
+        let ty = self.true_val.ty();
+        let result = match ty.is_by_ref() {
+            true => long_alloc(recorder),
+            false => local_alloc(recorder, ty.get_ir_ty(), None),
+        };
+        let true_bb = recorder.new_anonymous_bb();
+        let false_bb = recorder.new_anonymous_bb();
+        let end_bb = recorder.new_anonymous_bb();
+        short_circuit(recorder, &self.cond, true_bb, false_bb)?;
+
+        for (bb, val) in [(true_bb, &self.true_val), (false_bb, &self.false_val)] {
+            recorder.push_bb(bb);
+            let val = val.generate_ir(recorder)?;
+            if ty.is_by_ref() {
+                copy_value(recorder, result, val, ty);
+            } else {
+                let st = recorder.new_value().store(val, result);
+                recorder.push_inst(st);
+            }
+            let jump = recorder.new_value().jump(end_bb);
+            recorder.push_inst(jump);
+        }
+
+        recorder.push_bb(end_bb);
+        if ty.is_by_ref() {
+            return Ok(result);
+        }
+        let ld = recorder.new_value().load(result);
+        recorder.push_inst(ld);
+
+        Ok(ld)
+    }
+}
+
 impl<'i> GenerateIR<'i> for UnaryExpr {
     type Out = Value;
 
@@ -428,10 +803,16 @@ impl<'i> GenerateIR<'i> for UnaryExpr {
         match self {
             Self::Unary(op, exp) => {
                 let opr = exp.generate_ir(recorder)?;
-                let val = match op {
-                    UnaryOp::Nop => opr,
-                    UnaryOp::Neg => negative(recorder, opr),
-                    UnaryOp::Not => logical_not(recorder, opr),
+                let val = match (op, exp.ty().kind()) {
+                    (UnaryOp::Nop, _) => opr,
+                    (UnaryOp::Neg, TypeKind::Float) => {
+                        call_intrinsic(recorder, Intrinsic::FNeg, vec![opr])
+                    }
+                    (UnaryOp::Neg, TypeKind::LongLong) => long_negative(recorder, opr),
+                    (UnaryOp::Neg, _) => negative(recorder, opr),
+                    (UnaryOp::Not, _) => logical_not(recorder, opr),
+                    (UnaryOp::BitNot, TypeKind::LongLong) => long_bitwise_not(recorder, opr),
+                    (UnaryOp::BitNot, _) => bitwise_not(recorder, opr),
                 };
                 Ok(val)
             }
@@ -445,45 +826,170 @@ impl<'i> GenerateIR<'i> for Call {
 
     fn generate_ir(&'i self, recorder: &mut ProgramRecorder<'i>) -> Result<Self::Out> {
         let func = recorder.get_func_id(&self.ident);
-        let ty = recorder.get_ty(&self.ident).clone();
-        let param_tys = if let TypeKind::Func(_, param_tys) = ty.kind() {
-            param_tys
-        } else {
-            unreachable!()
+        let (ret_ty, param_tys) = match recorder.get_ty(&self.ident).kind() {
+            TypeKind::Func(ret_ty, param_tys, _) => (ret_ty.clone(), param_tys.clone()),
+            _ => unreachable!(),
         };
-
-        let arg_values: Vec<_> = self
-            .args
+        // the `float` parameters in fa0-fa7 do not count in the positions of the variable ones
+        let float_regs = param_tys
             .iter()
-            .zip(param_tys.iter())
-            .map(|(arg, ty)| match (ty.kind(), arg) {
-                (TypeKind::Pointer(_), Expr::LVal(lval)) => {
-                    let mut val = get_lval_ptr(recorder, lval);
-                    if matches!(recorder.get_ty(&lval.ident).kind(), TypeKind::Array(_, _))
-                        || !lval.dims.is_empty()
-                    {
-                        // convert an array to the pointer of its first elements
-                        val = into_ptr(recorder, val);
-                    }
-                    val
+            .filter(|ty| matches!(ty.kind(), TypeKind::Float))
+            .count()
+            .min(8);
+
+        // a `struct` or a `long long` is passed by the pointer to its words, unless the
+        // `long long` fits in a register
+        let mut arg_values = vec![];
+        for (i, arg) in self.args.iter().enumerate() {
+            let val = arg.generate_ir(recorder)?;
+            if let Some(ty) = param_tys.get(i) {
+                match ty.is_by_ref() && !ty.is_passed_by_ref() {
+                    true => arg_values.push(load_long(recorder, val)),
+                    false => arg_values.push(val),
                 }
-                _ => arg.generate_ir(recorder).unwrap(),
-            })
-            .collect();
+                continue;
+            }
+            if !matches!(arg.ty().kind(), TypeKind::Float | TypeKind::LongLong) {
+                arg_values.push(val);
+                continue;
+            }
+            // a variable `float` argument is promoted to `double`, passed like a `long long`
+            let is_float = matches!(arg.ty().kind(), TypeKind::Float);
+            if recorder.target() == Target::Rv64 {
+                let val = match is_float {
+                    true => call_intrinsic(recorder, Intrinsic::FToD, vec![val]),
+                    false => load_long(recorder, val),
+                };
+                arg_values.push(val);
+                continue;
+            }
+            if (arg_values.len() - float_regs) % 2 == 1 {
+                arg_values.push(recorder.new_value().integer(0));
+            }
+            let (lo, hi) = match is_float {
+                true => float_to_double(recorder, val),
+                false => load_words(recorder, val),
+            };
+            arg_values.extend([lo, hi]);
+        }
+        // a `long long` is returned through the pointer after the arguments, or in a register
+        let ret_ptr = ret_ty.is_passed_by_ref().then(|| long_alloc(recorder));
+        arg_values.extend(ret_ptr);
         let call = recorder.new_value().call(func, arg_values);
         recorder.push_inst(call);
+        if ret_ty.is_by_ref() && !ret_ty.is_passed_by_ref() {
+            let dst = long_alloc(recorder);
+            store_long(recorder, call, dst);
+            return Ok(dst);
+        }
 
-        Ok(call)
+        Ok(ret_ptr.unwrap_or(call))
     }
 }
 
+/// Generate `lval op= val` of type `ty`, where the address of `lval` is computed only once
+fn compound_assign<'i>(
+    recorder: &mut ProgramRecorder<'i>,
+    c: &'i CompoundAssign,
+    ty: &Type,
+) -> Result<Value> {
+    // the old value of a `long long` is copied, as the place is written afterwards
+    let (ptr, old) = if ty.is_by_ref() {
+        let ptr = get_aggregate_ptr(recorder, &c.lval);
+        let old = if c.postfix {
+            long_copy(recorder, ptr)
+        } else {
+            ptr
+        };
+        (ptr, old)
+    } else {
+        let ptr = get_place_ptr(recorder, &c.lval);
+        let old = recorder.new_value().load(ptr);
+        recorder.push_inst(old);
+        (ptr, old)
+    };
+    let val = c.val.generate_ir(recorder)?;
+
+    if let TypeKind::Pointer(_) = ty.kind() {
+        let new = offset_ptr(recorder, c.op, old, val);
+        let store = recorder.new_value().store(new, ptr);
+        recorder.push_inst(store);
+        return Ok(if c.postfix { old } else { new });
+    }
+
+    // the value is converted to the common type of both sides, e.g. `float` if the other side
+    // is a `float`, and back afterwards, while a shift is done in the type of the value
+    let op_ty = match c.op {
+        BinaryOp::Shl | BinaryOp::Shr => ty,
+        _ => c.val.ty(),
+    };
+    let lhs = convert(recorder, old, ty, op_ty);
+    let new = arith_binary(recorder, c.op, op_ty, lhs, val);
+    let new = convert(recorder, new, op_ty, ty);
+
+    if ty.is_by_ref() {
+        copy_value(recorder, ptr, new, ty);
+    } else {
+        let store = recorder.new_value().store(new, ptr);
+        recorder.push_inst(store);
+    }
+
+    Ok(if c.postfix { old } else { new })
+}
+
+/// `lhs op rhs` on arithmetic values, where the left operand is of type `ty`
+fn arith_binary(
+    recorder: &mut ProgramRecorder,
+    op: BinaryOp,
+    ty: &Type,
+    lhs: Value,
+    rhs: Value,
+) -> Value {
+    match ty.kind() {
+        TypeKind::Float => call_intrinsic(recorder, op.into(), vec![lhs, rhs]),
+        TypeKind::LongLong => long_binary(recorder, op, lhs, rhs),
+        TypeKind::Unsigned => match (op, Intrinsic::unsigned(op)) {
+            (_, Some(intrinsic)) => call_intrinsic(recorder, intrinsic, vec![lhs, rhs]),
+            // a right shift fills in zeros
+            (BinaryOp::Shr, None) => binary(recorder, IrBinaryOp::Shr, lhs, rhs),
+            (_, None) => binary(recorder, op.into(), lhs, rhs),
+        },
+        _ => binary(recorder, op.into(), lhs, rhs),
+    }
+}
+
+/// Offset a pointer by `±offset` elements
+fn offset_ptr(recorder: &mut ProgramRecorder, op: BinaryOp, ptr: Value, offset: Value) -> Value {
+    let offset = match op {
+        BinaryOp::Sub => negative(recorder, offset),
+        _ => offset,
+    };
+    get_ptr(recorder, ptr, offset)
+}
+
+/// Number of elements of type `base_ty` between the pointers
+fn pointer_diff(recorder: &mut ProgramRecorder, base_ty: &Type, lhs: Value, rhs: Value) -> Value {
+    let ptr_ty = Type::get_pointer(base_ty.clone()).get_ir_ty();
+    let func = recorder.pointer_intrinsic(Intrinsic::PtrToInt, ptr_ty);
+    let [lhs, rhs] = [lhs, rhs].map(|ptr| {
+        let addr = recorder.new_value().call(func, vec![ptr]);
+        recorder.push_inst(addr);
+        addr
+    });
+    let diff = binary(recorder, IrBinaryOp::Sub, lhs, rhs);
+    let size = recorder
+        .new_value()
+        .integer(base_ty.get_ir_ty().size() as i32);
+    binary(recorder, IrBinaryOp::Div, diff, size)
+}
+
 fn short_circuit<'i>(
     recorder: &mut ProgramRecorder<'i>,
     cond: &'i Expr,
     true_bb: BasicBlock,
     false_bb: BasicBlock,
 ) -> Result<()> {
-    let cond = if let Expr::Binary(bxp) = cond {
+    let cond = if let ExprKind::Binary(bxp) = &cond.kind {
         if !matches!(bxp.op, BinaryOp::And | BinaryOp::Or) {
             let result = cond.generate_ir(recorder)?;
             let br = recorder.new_value().branch(result, true_bb, false_bb);
@@ -569,6 +1075,11 @@ impl From<BinaryOp> for IrBinaryOp {
             BinaryOp::Mul => IrBinaryOp::Mul,
             BinaryOp::Div => IrBinaryOp::Div,
             BinaryOp::Mod => IrBinaryOp::Mod,
+            BinaryOp::BitAnd => IrBinaryOp::And,
+            BinaryOp::BitOr => IrBinaryOp::Or,
+            BinaryOp::BitXor => IrBinaryOp::Xor,
+            BinaryOp::Shl => IrBinaryOp::Shl,
+            BinaryOp::Shr => IrBinaryOp::Sar,
             BinaryOp::Eq => IrBinaryOp::Eq,
             BinaryOp::Neq => IrBinaryOp::NotEq,
             BinaryOp::Lt => IrBinaryOp::Lt,
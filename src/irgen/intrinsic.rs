@@ -0,0 +1,195 @@
+use strum_macros::{Display, EnumString};
+
+use crate::ast::BinaryOp;
+
+/// Operations Koopa IR has no instructions for, e.g. on `float`s, which are kept as their bits
+/// in `i32`s. Each is a call to one of these functions, which the backend expands inline.
+#[derive(Debug, Display, EnumString, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Intrinsic {
+    #[strum(serialize = "__sysy_fadd")]
+    FAdd,
+    #[strum(serialize = "__sysy_fsub")]
+    FSub,
+    #[strum(serialize = "__sysy_fmul")]
+    FMul,
+    #[strum(serialize = "__sysy_fdiv")]
+    FDiv,
+    #[strum(serialize = "__sysy_fneg")]
+    FNeg,
+    #[strum(serialize = "__sysy_feq")]
+    FEq,
+    #[strum(serialize = "__sysy_fne")]
+    FNe,
+    #[strum(serialize = "__sysy_flt")]
+    FLt,
+    #[strum(serialize = "__sysy_fle")]
+    FLe,
+    #[strum(serialize = "__sysy_fgt")]
+    FGt,
+    #[strum(serialize = "__sysy_fge")]
+    FGe,
+    /// `int` to `float`
+    #[strum(serialize = "__sysy_itof")]
+    IToF,
+    /// `float` to `int`, rounding towards zero
+    #[strum(serialize = "__sysy_ftoi")]
+    FToI,
+    /// pointer to its address
+    #[strum(serialize = "__sysy_ptrtoint")]
+    PtrToInt,
+    /// the null pointer
+    #[strum(serialize = "__sysy_null")]
+    NullPtr,
+    /// pointer to the words of a member to a pointer of the type of the member
+    #[strum(serialize = "__sysy_ptrcast")]
+    PtrCast,
+    /// `float` to `double` in a whole register, on RV64 only
+    #[strum(serialize = "__sysy_ftod")]
+    FToD,
+    /// `long long` pointed to into a whole register, on RV64 only like the rest below
+    #[strum(serialize = "__sysy_lload")]
+    LoadLong,
+    /// whole register to the `long long` pointed to
+    #[strum(serialize = "__sysy_lstore")]
+    StoreLong,
+    #[strum(serialize = "__sysy_add64")]
+    Add64,
+    #[strum(serialize = "__sysy_sub64")]
+    Sub64,
+    #[strum(serialize = "__sysy_mul64")]
+    Mul64,
+    #[strum(serialize = "__sysy_div64")]
+    Div64,
+    #[strum(serialize = "__sysy_rem64")]
+    Rem64,
+    #[strum(serialize = "__sysy_and64")]
+    And64,
+    #[strum(serialize = "__sysy_or64")]
+    Or64,
+    #[strum(serialize = "__sysy_xor64")]
+    Xor64,
+    #[strum(serialize = "__sysy_shl64")]
+    Shl64,
+    #[strum(serialize = "__sysy_sar64")]
+    Sar64,
+    #[strum(serialize = "__sysy_eq64")]
+    Eq64,
+    #[strum(serialize = "__sysy_ne64")]
+    Ne64,
+    #[strum(serialize = "__sysy_lt64")]
+    Lt64,
+    #[strum(serialize = "__sysy_gt64")]
+    Gt64,
+    #[strum(serialize = "__sysy_le64")]
+    Le64,
+    #[strum(serialize = "__sysy_ge64")]
+    Ge64,
+    /// `long long` to the bits of a `float`
+    #[strum(serialize = "__sysy_ltof64")]
+    LToF,
+    /// bits of a `float` to `long long`, rounding towards zero
+    #[strum(serialize = "__sysy_ftol64")]
+    FToL,
+    #[strum(serialize = "__sysy_udiv")]
+    UDiv,
+    #[strum(serialize = "__sysy_urem")]
+    URem,
+    #[strum(serialize = "__sysy_ult")]
+    ULt,
+    #[strum(serialize = "__sysy_ugt")]
+    UGt,
+    #[strum(serialize = "__sysy_ule")]
+    ULe,
+    #[strum(serialize = "__sysy_uge")]
+    UGe,
+    /// `unsigned int` to `float`
+    #[strum(serialize = "__sysy_utof")]
+    UToF,
+    /// `float` to `unsigned int`, rounding towards zero
+    #[strum(serialize = "__sysy_ftou")]
+    FToU,
+}
+
+impl Intrinsic {
+    /// Recognize an intrinsic from the name of the callee, e.g. `@__sysy_ptrtoint_1`
+    pub fn from_callee(name: &str) -> Option<Self> {
+        let name = name.strip_prefix('@')?;
+        name.parse().ok().or_else(|| {
+            let (name, suffix) = name.rsplit_once('_')?;
+            suffix.parse::<usize>().ok()?;
+            name.parse().ok()
+        })
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Self::NullPtr => 0,
+            Self::FNeg
+            | Self::IToF
+            | Self::FToI
+            | Self::UToF
+            | Self::FToU
+            | Self::PtrToInt
+            | Self::PtrCast
+            | Self::FToD
+            | Self::LoadLong
+            | Self::LToF
+            | Self::FToL => 1,
+            _ => 2,
+        }
+    }
+
+    /// The operation on `unsigned int`s which differs from the signed instruction in Koopa IR
+    pub fn unsigned(op: BinaryOp) -> Option<Self> {
+        match op {
+            BinaryOp::Div => Some(Self::UDiv),
+            BinaryOp::Mod => Some(Self::URem),
+            BinaryOp::Lt => Some(Self::ULt),
+            BinaryOp::Gt => Some(Self::UGt),
+            BinaryOp::Le => Some(Self::ULe),
+            BinaryOp::Ge => Some(Self::UGe),
+            _ => None,
+        }
+    }
+
+    /// The operation on `long long`s in registers
+    pub fn long(op: BinaryOp) -> Self {
+        match op {
+            BinaryOp::Add => Self::Add64,
+            BinaryOp::Sub => Self::Sub64,
+            BinaryOp::Mul => Self::Mul64,
+            BinaryOp::Div => Self::Div64,
+            BinaryOp::Mod => Self::Rem64,
+            BinaryOp::BitAnd => Self::And64,
+            BinaryOp::BitOr => Self::Or64,
+            BinaryOp::BitXor => Self::Xor64,
+            BinaryOp::Shl => Self::Shl64,
+            BinaryOp::Shr => Self::Sar64,
+            BinaryOp::Eq => Self::Eq64,
+            BinaryOp::Neq => Self::Ne64,
+            BinaryOp::Lt => Self::Lt64,
+            BinaryOp::Gt => Self::Gt64,
+            BinaryOp::Le => Self::Le64,
+            BinaryOp::Ge => Self::Ge64,
+            BinaryOp::And | BinaryOp::Or => unreachable!(),
+        }
+    }
+}
+
+impl From<BinaryOp> for Intrinsic {
+    fn from(value: BinaryOp) -> Self {
+        match value {
+            BinaryOp::Add => Self::FAdd,
+            BinaryOp::Sub => Self::FSub,
+            BinaryOp::Mul => Self::FMul,
+            BinaryOp::Div => Self::FDiv,
+            BinaryOp::Eq => Self::FEq,
+            BinaryOp::Neq => Self::FNe,
+            BinaryOp::Lt => Self::FLt,
+            BinaryOp::Le => Self::FLe,
+            BinaryOp::Gt => Self::FGt,
+            BinaryOp::Ge => Self::FGe,
+            _ => unreachable!(),
+        }
+    }
+}
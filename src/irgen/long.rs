@@ -0,0 +1,222 @@
+use koopa::ir::builder_traits::{LocalInstBuilder, ValueBuilder};
+
+use super::*;
+use crate::ast::BinaryOp;
+use crate::sema::ty::{Type, TypeKind};
+
+/// Pointer to the low word of a new `long long` on the stack
+pub fn long_alloc(recorder: &mut ProgramRecorder) -> Value {
+    let alloc = local_alloc(recorder, Type::get_long_long().get_ir_ty(), None);
+    into_ptr(recorder, alloc)
+}
+
+/// A `long long` constant, written into a new place on the stack
+pub fn long_const(recorder: &mut ProgramRecorder, value: i64) -> Value {
+    let dst = long_alloc(recorder);
+    let lo = recorder.new_value().integer(value as i32);
+    let hi = recorder.new_value().integer((value >> 32) as i32);
+    store_words(recorder, dst, lo, hi);
+    dst
+}
+
+fn store_words(recorder: &mut ProgramRecorder, dst: Value, lo: Value, hi: Value) {
+    let st = recorder.new_value().store(lo, dst);
+    recorder.push_inst(st);
+    let one = recorder.new_value().integer(1);
+    let hi_ptr = get_ptr(recorder, dst, one);
+    let st = recorder.new_value().store(hi, hi_ptr);
+    recorder.push_inst(st);
+}
+
+/// Low and high words of the `long long` pointed to
+pub fn load_words(recorder: &mut ProgramRecorder, src: Value) -> (Value, Value) {
+    let lo = recorder.new_value().load(src);
+    recorder.push_inst(lo);
+    let one = recorder.new_value().integer(1);
+    let hi_ptr = get_ptr(recorder, src, one);
+    let hi = recorder.new_value().load(hi_ptr);
+    recorder.push_inst(hi);
+    (lo, hi)
+}
+
+/// The `long long` pointed to in a whole register, which is only possible on RV64
+pub fn load_long(recorder: &mut ProgramRecorder, src: Value) -> Value {
+    let ptr_ty = IrType::get_pointer(IrType::get_i32());
+    let func = recorder.pointer_intrinsic(Intrinsic::LoadLong, ptr_ty);
+    let call = recorder.new_value().call(func, vec![src]);
+    recorder.push_inst(call);
+    call
+}
+
+/// Write a whole register to the `long long` pointed to
+pub fn store_long(recorder: &mut ProgramRecorder, val: Value, dst: Value) {
+    let ptr_ty = IrType::get_pointer(IrType::get_i32());
+    let func = recorder.pointer_intrinsic(Intrinsic::StoreLong, ptr_ty);
+    let call = recorder.new_value().call(func, vec![val, dst]);
+    recorder.push_inst(call);
+}
+
+/// Write a whole register into a new place, which is returned
+fn long_from_reg(recorder: &mut ProgramRecorder, val: Value) -> Value {
+    let dst = long_alloc(recorder);
+    store_long(recorder, val, dst);
+    dst
+}
+
+pub fn call_routine(recorder: &mut ProgramRecorder, routine: Routine, args: Vec<Value>) -> Value {
+    let func = recorder.routine(routine);
+    let call = recorder.new_value().call(func, args);
+    recorder.push_inst(call);
+
+    call
+}
+
+/// Call a routine writing a `long long` into a new place, which is returned
+fn long_routine(recorder: &mut ProgramRecorder, routine: Routine, args: &[Value]) -> Value {
+    let dst = long_alloc(recorder);
+    let args = std::iter::once(dst).chain(args.iter().copied()).collect();
+    call_routine(recorder, routine, args);
+    dst
+}
+
+/// `lhs op rhs` on `long long`s, in registers on RV64 and by a routine on RV32
+pub fn long_binary(recorder: &mut ProgramRecorder, op: BinaryOp, lhs: Value, rhs: Value) -> Value {
+    if recorder.target() == Target::Rv64 {
+        let lhs = load_long(recorder, lhs);
+        let rhs = match op {
+            BinaryOp::Shl | BinaryOp::Shr => rhs,
+            _ => load_long(recorder, rhs),
+        };
+        let val = call_intrinsic(recorder, Intrinsic::long(op), vec![lhs, rhs]);
+        return match op.is_logical() {
+            true => val,
+            false => long_from_reg(recorder, val),
+        };
+    }
+    match Routine::from(op) {
+        Routine::LCmp => {
+            let cmp = call_routine(recorder, Routine::LCmp, vec![lhs, rhs]);
+            let zero = recorder.new_value().integer(0);
+            binary(recorder, op.into(), cmp, zero)
+        }
+        routine => long_routine(recorder, routine, &[lhs, rhs]),
+    }
+}
+
+/// Jump to the block of the label equal to the `long long` pointed to by `cond`, or to
+/// `default`, by a binary search of the sorted labels
+pub fn long_compare_tree(
+    recorder: &mut ProgramRecorder,
+    cond: Value,
+    cases: &[(i64, BasicBlock)],
+    default: BasicBlock,
+) {
+    if cases.len() <= 3 {
+        for &(value, label_bb) in cases {
+            let value = long_const(recorder, value);
+            let eq = long_binary(recorder, BinaryOp::Eq, cond, value);
+            let next_bb = recorder.new_anonymous_bb();
+            let br = recorder.new_value().branch(eq, label_bb, next_bb);
+            recorder.push_inst(br);
+            recorder.push_bb(next_bb);
+        }
+        let jump = recorder.new_value().jump(default);
+        recorder.push_inst(jump);
+        return;
+    }
+
+    let mid = cases.len() / 2;
+    let value = long_const(recorder, cases[mid].0);
+    let lt = long_binary(recorder, BinaryOp::Lt, cond, value);
+    let lower_bb = recorder.new_anonymous_bb();
+    let upper_bb = recorder.new_anonymous_bb();
+    let br = recorder.new_value().branch(lt, lower_bb, upper_bb);
+    recorder.push_inst(br);
+    recorder.push_bb(lower_bb);
+    long_compare_tree(recorder, cond, &cases[..mid], default);
+    recorder.push_bb(upper_bb);
+    long_compare_tree(recorder, cond, &cases[mid..], default);
+}
+
+pub fn long_negative(recorder: &mut ProgramRecorder, opr: Value) -> Value {
+    if recorder.target() == Target::Rv32 {
+        return long_routine(recorder, Routine::LNeg, &[opr]);
+    }
+    let zero = recorder.new_value().integer(0);
+    let opr = load_long(recorder, opr);
+    let val = call_intrinsic(recorder, Intrinsic::Sub64, vec![zero, opr]);
+    long_from_reg(recorder, val)
+}
+
+pub fn long_bitwise_not(recorder: &mut ProgramRecorder, opr: Value) -> Value {
+    if recorder.target() == Target::Rv32 {
+        return long_routine(recorder, Routine::LNot, &[opr]);
+    }
+    let ones = recorder.new_value().integer(-1);
+    let opr = load_long(recorder, opr);
+    let val = call_intrinsic(recorder, Intrinsic::Xor64, vec![opr, ones]);
+    long_from_reg(recorder, val)
+}
+
+pub fn convert(recorder: &mut ProgramRecorder, val: Value, from: &Type, to: &Type) -> Value {
+    match (from.kind(), to.kind()) {
+        _ if from == to => val,
+        // the bits are kept
+        (TypeKind::Integer, TypeKind::Unsigned) | (TypeKind::Unsigned, TypeKind::Integer) => val,
+        (TypeKind::Integer, TypeKind::Float) => {
+            call_intrinsic(recorder, Intrinsic::IToF, vec![val])
+        }
+        (TypeKind::Float, TypeKind::Integer) => {
+            call_intrinsic(recorder, Intrinsic::FToI, vec![val])
+        }
+        (TypeKind::Unsigned, TypeKind::Float) => {
+            call_intrinsic(recorder, Intrinsic::UToF, vec![val])
+        }
+        (TypeKind::Float, TypeKind::Unsigned) => {
+            call_intrinsic(recorder, Intrinsic::FToU, vec![val])
+        }
+        // an `int` in a register is sign-extended on RV64
+        (TypeKind::Integer, TypeKind::LongLong) if recorder.target() == Target::Rv64 => {
+            long_from_reg(recorder, val)
+        }
+        // the high word is filled with the sign bit
+        (TypeKind::Integer, TypeKind::LongLong) => {
+            let dst = long_alloc(recorder);
+            let shift = recorder.new_value().integer(31);
+            let hi = binary(recorder, IrBinaryOp::Sar, val, shift);
+            store_words(recorder, dst, val, hi);
+            dst
+        }
+        // the high word is zero
+        (TypeKind::Unsigned, TypeKind::LongLong) => {
+            let dst = long_alloc(recorder);
+            let hi = recorder.new_value().integer(0);
+            store_words(recorder, dst, val, hi);
+            dst
+        }
+        // the high word is truncated
+        (TypeKind::LongLong, TypeKind::Integer | TypeKind::Unsigned) => {
+            let ld = recorder.new_value().load(val);
+            recorder.push_inst(ld);
+            ld
+        }
+        (TypeKind::LongLong, TypeKind::Float) if recorder.target() == Target::Rv64 => {
+            let val = load_long(recorder, val);
+            call_intrinsic(recorder, Intrinsic::LToF, vec![val])
+        }
+        (TypeKind::Float, TypeKind::LongLong) if recorder.target() == Target::Rv64 => {
+            let val = call_intrinsic(recorder, Intrinsic::FToL, vec![val]);
+            long_from_reg(recorder, val)
+        }
+        (TypeKind::LongLong, TypeKind::Float) => call_routine(recorder, Routine::LToF, vec![val]),
+        (TypeKind::Float, TypeKind::LongLong) => long_routine(recorder, Routine::FToL, &[val]),
+        _ => unreachable!(),
+    }
+}
+
+/// Copy a `long long` into a new place, where the value pointed to may be changed later
+pub fn long_copy(recorder: &mut ProgramRecorder, src: Value) -> Value {
+    let dst = long_alloc(recorder);
+    copy_value(recorder, dst, src, &Type::get_long_long());
+    dst
+}
@@ -1,13 +1,18 @@
+pub(crate) mod intrinsic;
 pub(crate) mod record;
+pub(crate) mod routine;
+pub(crate) mod utils;
 
 mod gen;
-mod utils;
+mod long;
 
+pub(crate) use intrinsic::*;
 pub(crate) use record::*;
+pub(crate) use routine::*;
 
-use std::fs::read_to_string;
-use std::fs::File;
-use std::io::BufWriter;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
 
 use anyhow::*;
 use koopa::back::KoopaGenerator;
@@ -15,41 +20,98 @@ use koopa::ir::BinaryOp as IrBinaryOp;
 use koopa::ir::Type as IrType;
 use koopa::ir::{BasicBlock, Function, FunctionData, Program, Value};
 
-use crate::opt::optimize;
+use crate::ast::{CompUnit, GlobalItem};
+use crate::codegen::Target;
+use crate::diag::{Diagnostic, DiagnosticOptions, Diagnostics, ErrorCode, SourceFile, Warnings};
+use crate::opt::{optimize, OptLevel};
+use crate::sema::ty::{Type, TypeKind};
 use crate::sema::*;
-use crate::sysy;
 use gen::*;
+use long::*;
 use utils::*;
 
-pub fn generate_mem_ir(input: &str, opt: bool) -> Result<Program> {
-    let input = read_to_string(input)?;
-    let mut ast = sysy::CompUnitParser::new().parse(&input).unwrap();
-
-    let mut name_manager = NameManager::new();
-    ast.accept(&mut name_manager);
-
-    let mut evaluator = Evaluator::new();
-    ast.accept(&mut evaluator);
-
-    let mut symbols = SymbolTable::new();
-    ast.accept(&mut symbols);
+/// Generate the IR of the source file for the given target, along with the signatures of
+/// functions that pass `float`s and the warnings
+pub fn generate_mem_ir(
+    file: SourceFile,
+    include_dirs: &[PathBuf],
+    diag_opts: &DiagnosticOptions,
+    opt: OptLevel,
+    target: Target,
+) -> Result<(Program, FloatSignatures, Warnings)> {
+    // the layout of a `struct` holding pointers depends on the target
+    IrType::set_ptr_size(target.word_size() as usize);
+    let Analysis {
+        sources,
+        mut diags,
+        program,
+        ..
+    } = analyze(file, include_dirs);
+    if let (Target::Rv32, Some((ast, symbols))) = (target, &program) {
+        diags.extend(check_extern_longs(ast, symbols));
+    }
+    // the program is only left out on an error
+    let warnings = diag_opts.check(sources, diags)?;
+    let (ast, symbols) = program.expect("a program without errors has been checked");
 
     let mut program = Program::new();
-    let mut recorder = ProgramRecorder::new(&mut program, &symbols);
+    let mut recorder = ProgramRecorder::new(&mut program, &symbols, target);
     ast.generate_ir(&mut recorder)?;
+    let float_sigs = recorder.take_float_sigs();
 
-    if opt {
-        optimize(&mut program);
-    }
+    optimize(&mut program, opt);
+    Ok((program, float_sigs, warnings))
+}
 
-    Ok(program)
+/// Report the functions defined in other files which take or return `long long`s, which are
+/// passed through pointers on RV32 rather than in pairs of registers as the calling convention
+/// requires
+fn check_extern_longs(ast: &CompUnit, symbols: &SymbolTable) -> Diagnostics {
+    let mut defined: HashSet<_> = ast
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            GlobalItem::Func(f) if f.block.is_some() => Some(f.ident.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut diags = Diagnostics::new();
+    for item in &ast.items {
+        let GlobalItem::Func(f) = item else {
+            continue;
+        };
+        let Some(TypeKind::Func(ret_ty, param_tys, _)) = symbols.data.get(&f.ident).map(Type::kind)
+        else {
+            continue;
+        };
+        let is_long = |ty: &Type| matches!(ty.kind(), TypeKind::LongLong);
+        if (is_long(ret_ty) || param_tys.iter().any(is_long)) && defined.insert(&f.ident) {
+            diags.push(
+                Diagnostic::error(
+                    ErrorCode::Unsupported,
+                    format!(
+                        "`{}` is defined in another file, which cannot pass `long long` on RV32",
+                        f.ident
+                    ),
+                )
+                .with_span(f.span),
+            );
+        }
+    }
+    diags
 }
 
-pub fn generate_ir(input: &str, output: &str, opt: bool) -> Result<()> {
-    let program = generate_mem_ir(input, opt)?;
-    let output = File::create(output).unwrap();
-    let mut gen = KoopaGenerator::new(BufWriter::new(output));
+/// Generate the text of the IR of the source file into the writer, and return the warnings
+pub fn generate_ir(
+    file: SourceFile,
+    include_dirs: &[PathBuf],
+    diag_opts: &DiagnosticOptions,
+    output: impl Write,
+    opt: OptLevel,
+) -> Result<Warnings> {
+    let (program, _, warnings) = generate_mem_ir(file, include_dirs, diag_opts, opt, Target::Rv32)?;
+    let mut gen = KoopaGenerator::new(output);
     gen.generate_on(&program)?;
 
-    Ok(())
+    Ok(warnings)
 }
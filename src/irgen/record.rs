@@ -7,7 +7,7 @@ use koopa::ir::Type as IrType;
 use smallvec::SmallVec;
 
 use super::*;
-use crate::ast::FuncDef;
+use crate::ast::{FuncDef, VarDecl};
 use crate::sema::symbol::SymbolTable;
 use crate::sema::ty::{Type, TypeKind};
 
@@ -16,8 +16,55 @@ pub struct ProgramRecorder<'i> {
     symbols: &'i SymbolTable,
     values: HashMap<&'i str, Value>,
     funcs: HashMap<&'i str, Function>,
+    /// definitions of the global variables, which the `extern` declarations refer to
+    var_defs: HashMap<&'i str, &'i VarDecl>,
+    intrinsics: HashMap<(Intrinsic, IrType), Function>,
+    routines: HashMap<Routine, Function>,
+    /// global arrays of the string literals, shared by the equal ones
+    strings: HashMap<&'i [u8], Value>,
+    float_sigs: FloatSignatures,
     cur_func: Option<FunctionInfo>,
-    loops: SmallVec<[LoopInfo; 4]>,
+    breakables: SmallVec<[Breakable; 4]>,
+    /// blocks of the labels of the enclosing `switch`es which are not generated yet
+    switch_labels: Vec<vec::IntoIter<BasicBlock>>,
+    /// whose calling convention the variable arguments follow
+    target: Target,
+}
+
+/// Which parameters and return value of a function are `float`s, which are `i32`s in Koopa IR
+#[derive(Debug, Clone, Default)]
+pub struct FloatSignature {
+    pub params: Vec<bool>,
+    pub ret: bool,
+}
+
+pub type FloatSignatures = HashMap<Function, FloatSignature>;
+
+/// Prefix of the global arrays of string literals, which are emitted as bytes
+pub const STRING_PREFIX: &str = "@__sysy_str_";
+
+/// Prefix of the names of the `static` variables, which are local to the file
+pub const STATIC_PREFIX: &str = "@__sysy_static_";
+
+/// Prefix of the `extern` variables defined in other files, which are referred to by the
+/// names after it, as Koopa IR cannot declare a global without allocating it
+pub const EXTERN_PREFIX: &str = "@__sysy_extern_";
+
+impl FloatSignature {
+    fn from_ty(ty: &Type) -> Self {
+        match ty.kind() {
+            TypeKind::Func(ret_ty, param_tys, _) => Self {
+                // including the pointer to the returned `long long`, if any
+                params: param_tys
+                    .iter()
+                    .map(|t| matches!(t.kind(), TypeKind::Float))
+                    .chain(ret_ty.is_passed_by_ref().then_some(false))
+                    .collect(),
+                ret: matches!(ret_ty.kind(), TypeKind::Float),
+            },
+            _ => unreachable!(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -29,21 +76,32 @@ pub struct FunctionInfo {
     ret_val: Option<Value>,
 }
 
+/// A loop or a `switch`, which is left by `break`
 #[derive(Debug)]
-pub struct LoopInfo {
-    entry: BasicBlock,
+pub struct Breakable {
+    /// target of `continue`: the condition of a `while` or `do-while` loop, or the step of a
+    /// `for` loop, and `None` for a `switch`
+    cont: Option<BasicBlock>,
+    /// target of `break`
     exit: BasicBlock,
 }
 
 impl<'i> ProgramRecorder<'i> {
-    pub fn new(program: &'i mut Program, symbols: &'i SymbolTable) -> Self {
+    pub fn new(program: &'i mut Program, symbols: &'i SymbolTable, target: Target) -> Self {
         Self {
             program,
             symbols,
             values: HashMap::new(),
             funcs: HashMap::new(),
+            var_defs: HashMap::new(),
+            intrinsics: HashMap::new(),
+            routines: HashMap::new(),
+            strings: HashMap::new(),
+            float_sigs: HashMap::new(),
             cur_func: None,
-            loops: SmallVec::new(),
+            breakables: SmallVec::new(),
+            switch_labels: vec![],
+            target,
         }
     }
 
@@ -51,14 +109,27 @@ impl<'i> ProgramRecorder<'i> {
         self.values.insert(name, val);
     }
 
-    pub fn enter_func(&mut self, func_def: &'i FuncDef) {
-        let (ret_ty, param_tys) =
-            if let TypeKind::Func(ret_ty, param_tys) = self.get_ty(&func_def.ident).kind() {
-                (ret_ty, param_tys)
-            } else {
-                unreachable!()
-            };
-        let param_ir_tys: Vec<_> = param_tys.iter().map(|t| t.get_ir_ty()).collect();
+    pub fn insert_var_def(&mut self, var_def: &'i VarDecl) {
+        self.var_defs.insert(&var_def.lval.ident, var_def);
+    }
+
+    pub fn var_def(&self, name: &str) -> Option<&'i VarDecl> {
+        self.var_defs.get(name).copied()
+    }
+
+    /// Create the function of a definition, which may be called before its body is generated
+    pub fn new_func(&mut self, func_def: &'i FuncDef) -> Function {
+        if let Some(&func) = self.funcs.get(func_def.ident.as_str()) {
+            return func;
+        }
+        let ty = self.get_ty(&func_def.ident);
+        let (ret_ty, param_tys) = if let TypeKind::Func(ret_ty, param_tys, _) = ty.kind() {
+            (ret_ty, param_tys)
+        } else {
+            unreachable!()
+        };
+        let float_sig = FloatSignature::from_ty(ty);
+        let (param_ir_tys, ret_ir_ty) = Type::get_func_ir_tys(ret_ty, param_tys);
         let params: Vec<_> = vec![None; param_ir_tys.len()]
             .into_iter()
             .zip(param_ir_tys)
@@ -66,8 +137,15 @@ impl<'i> ProgramRecorder<'i> {
         let func = self.program.new_func(FunctionData::with_param_names(
             format!("@{}", &func_def.ident),
             params,
-            ret_ty.get_ir_ty(),
+            ret_ir_ty,
         ));
+        self.funcs.insert(&func_def.ident, func);
+        self.float_sigs.insert(func, float_sig);
+        func
+    }
+
+    pub fn enter_func(&mut self, func_def: &'i FuncDef) {
+        let func = self.new_func(func_def);
         let builder = self.program.func_mut(func).dfg_mut();
         let entry_bb = builder.new_bb().basic_block(Some("%entry".to_owned()));
         let end_bb = builder.new_bb().basic_block(Some("%end".to_owned()));
@@ -78,20 +156,95 @@ impl<'i> ProgramRecorder<'i> {
             cur_bb: entry_bb,
             ret_val: None,
         });
-        self.funcs.insert(&func_def.ident, func);
     }
 
     pub fn exit_func(&mut self) {
         self.cur_func = None;
     }
 
-    fn declare_func(&mut self, name: &'i str, params_ty: Vec<IrType>, ret_ty: IrType) {
+    /// Declare an external function, e.g. a runtime function
+    pub fn declare_func(&mut self, name: &'i str) {
+        if self.funcs.contains_key(name) {
+            return;
+        }
+        let ty = self.get_ty(name);
+        let (ret_ty, param_tys) = if let TypeKind::Func(ret_ty, param_tys, _) = ty.kind() {
+            (ret_ty, param_tys)
+        } else {
+            unreachable!()
+        };
+        let float_sig = FloatSignature::from_ty(ty);
+        let (param_ir_tys, ret_ir_ty) = Type::get_func_ir_tys(ret_ty, param_tys);
         let func_id = self.program.new_func(FunctionData::new_decl(
             format!("@{}", name),
-            params_ty,
-            ret_ty,
+            param_ir_tys,
+            ret_ir_ty,
         ));
         self.funcs.insert(name, func_id);
+        self.float_sigs.insert(func_id, float_sig);
+    }
+
+    pub fn intrinsic(&mut self, op: Intrinsic) -> Function {
+        self.pointer_intrinsic(op, IrType::get_i32())
+    }
+
+    /// Get the function of a pointer intrinsic, declared once for each pointer type
+    pub fn pointer_intrinsic(&mut self, op: Intrinsic, ty: IrType) -> Function {
+        if let Some(func) = self.intrinsics.get(&(op, ty.clone())) {
+            return *func;
+        }
+        let (param_tys, ret_ty) = match op {
+            Intrinsic::PtrToInt | Intrinsic::LoadLong => (vec![ty.clone()], IrType::get_i32()),
+            Intrinsic::StoreLong => (vec![IrType::get_i32(), ty.clone()], IrType::get_unit()),
+            Intrinsic::NullPtr => (vec![], ty.clone()),
+            Intrinsic::PtrCast => (vec![IrType::get_pointer(IrType::get_i32())], ty.clone()),
+            _ => (vec![IrType::get_i32(); op.arity()], IrType::get_i32()),
+        };
+        let name = match self.intrinsics.keys().filter(|(o, _)| *o == op).count() {
+            0 => format!("@{}", op),
+            n => format!("@{}_{}", op, n),
+        };
+        let func = self
+            .program
+            .new_func(FunctionData::new_decl(name, param_tys, ret_ty));
+        self.intrinsics.insert((op, ty), func);
+        func
+    }
+
+    /// Get the function of a `long long` routine, which is declared on its first use
+    pub fn routine(&mut self, routine: Routine) -> Function {
+        if let Some(&func) = self.routines.get(&routine) {
+            return func;
+        }
+        let (param_tys, ret_ty) = routine.signature();
+        let func = self.program.new_func(FunctionData::new_decl(
+            format!("@{}", routine),
+            param_tys,
+            ret_ty,
+        ));
+        self.routines.insert(routine, func);
+        func
+    }
+
+    pub fn string(&mut self, s: &'i [u8]) -> Value {
+        if let Some(&val) = self.strings.get(s) {
+            return val;
+        }
+        let ty = Type::get_array(Type::get_char(), s.len() + 1);
+        let chars: Vec<_> = s.iter().map(|&c| c as i32).collect();
+        let init_val = init_global_array(self, &ty, &chars);
+        let alloc = self.new_global_value().global_alloc(init_val);
+        self.set_global_value_name(format!("{}{}", STRING_PREFIX, self.strings.len()), alloc);
+        self.strings.insert(s, alloc);
+        alloc
+    }
+
+    pub fn target(&self) -> Target {
+        self.target
+    }
+
+    pub fn take_float_sigs(&mut self) -> FloatSignatures {
+        std::mem::take(&mut self.float_sigs)
     }
 }
 
@@ -120,6 +273,10 @@ impl ProgramRecorder<'_> {
         self.symbols.get(name)
     }
 
+    pub fn has_value(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
     pub fn get_value(&self, name: &str) -> Value {
         match self.values.get(name) {
             Some(value) => *value,
@@ -131,24 +288,47 @@ impl ProgramRecorder<'_> {
         self.program.func(self.cur_func_id()).dfg().value(val)
     }
 
-    pub fn enter_loop(&mut self, entry: BasicBlock, exit: BasicBlock) {
-        self.loops.push(LoopInfo { entry, exit });
+    pub fn enter_loop(&mut self, cont: BasicBlock, exit: BasicBlock) {
+        let cont = Some(cont);
+        self.breakables.push(Breakable { cont, exit });
     }
 
     pub fn exit_loop(&mut self) {
-        self.loops.pop();
+        self.breakables.pop();
+    }
+
+    /// Enter the body of a `switch`, whose labels are given in order
+    pub fn enter_switch(&mut self, exit: BasicBlock, labels: Vec<BasicBlock>) {
+        self.breakables.push(Breakable { cont: None, exit });
+        self.switch_labels.push(labels.into_iter());
+    }
+
+    pub fn exit_switch(&mut self) {
+        self.breakables.pop();
+        self.switch_labels.pop();
+    }
+
+    /// Block of the next label of the innermost `switch`
+    pub fn next_switch_label(&mut self) -> BasicBlock {
+        self.switch_labels.last_mut().unwrap().next().unwrap()
     }
 
     pub fn inside_loop(&self) -> bool {
-        !self.loops.is_empty()
+        self.breakables.iter().any(|b| b.cont.is_some())
+    }
+
+    pub fn inside_breakable(&self) -> bool {
+        !self.breakables.is_empty()
     }
 
-    pub fn loop_entry(&self) -> BasicBlock {
-        self.loops.last().unwrap().entry
+    /// Target of `continue` in the innermost loop
+    pub fn loop_continue(&self) -> BasicBlock {
+        self.breakables.iter().rev().find_map(|b| b.cont).unwrap()
     }
 
-    pub fn loop_exit(&self) -> BasicBlock {
-        self.loops.last().unwrap().exit
+    /// Target of `break` in the innermost loop or `switch`
+    pub fn break_target(&self) -> BasicBlock {
+        self.breakables.last().unwrap().exit
     }
 
     pub fn new_value(&mut self) -> LocalBuilder<'_> {
@@ -228,22 +408,19 @@ impl ProgramRecorder<'_> {
     }
 
     pub fn install_lib(&mut self) {
-        self.declare_func("getint", vec![], IrType::get_i32());
-        self.declare_func("getch", vec![], IrType::get_i32());
-        self.declare_func(
-            "getarray",
-            vec![IrType::get_pointer(IrType::get_i32())],
-            IrType::get_i32(),
-        );
-        self.declare_func("putint", vec![IrType::get_i32()], IrType::get_unit());
-        self.declare_func("putch", vec![IrType::get_i32()], IrType::get_unit());
-        self.declare_func(
-            "putarray",
-            vec![IrType::get_i32(), IrType::get_pointer(IrType::get_i32())],
-            IrType::get_unit(),
-        );
-        self.declare_func("starttime", vec![], IrType::get_unit());
-        self.declare_func("stoptime", vec![], IrType::get_unit());
+        self.declare_func("getint");
+        self.declare_func("getch");
+        self.declare_func("getarray");
+        self.declare_func("putint");
+        self.declare_func("putch");
+        self.declare_func("putarray");
+        self.declare_func("getfloat");
+        self.declare_func("getfarray");
+        self.declare_func("putfloat");
+        self.declare_func("putfarray");
+        self.declare_func("putf");
+        self.declare_func("_sysy_starttime");
+        self.declare_func("_sysy_stoptime");
     }
 }
 
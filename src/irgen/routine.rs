@@ -0,0 +1,85 @@
+use koopa::ir::Type as IrType;
+use strum_macros::{Display, EnumString};
+
+use crate::ast::BinaryOp;
+
+/// Operations on `long long`s. Koopa IR has no 64-bit type, so a `long long` is kept in
+/// memory as its low and high words, and referred to by the pointer to the low one. Each
+/// operation on it is a call to one of these functions on RV32, whose bodies the backend emits
+/// into the program. A `long long` result is written through the first argument.
+#[derive(Debug, Display, EnumString, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Routine {
+    #[strum(serialize = "__sysy_ladd")]
+    LAdd,
+    #[strum(serialize = "__sysy_lsub")]
+    LSub,
+    #[strum(serialize = "__sysy_lmul")]
+    LMul,
+    #[strum(serialize = "__sysy_ldiv")]
+    LDiv,
+    #[strum(serialize = "__sysy_lmod")]
+    LMod,
+    #[strum(serialize = "__sysy_land")]
+    LAnd,
+    #[strum(serialize = "__sysy_lor")]
+    LOr,
+    #[strum(serialize = "__sysy_lxor")]
+    LXor,
+    /// shift left by an `int`
+    #[strum(serialize = "__sysy_lshl")]
+    LShl,
+    /// arithmetic shift right by an `int`
+    #[strum(serialize = "__sysy_lshr")]
+    LShr,
+    #[strum(serialize = "__sysy_lneg")]
+    LNeg,
+    #[strum(serialize = "__sysy_lnot")]
+    LNot,
+    /// `-1`, `0` or `1` as the first operand is less than, equal to or greater than the second
+    #[strum(serialize = "__sysy_lcmp")]
+    LCmp,
+    /// `long long` to the bits of a `float`
+    #[strum(serialize = "__sysy_ltof")]
+    LToF,
+    /// bits of a `float` to `long long`, rounding towards zero
+    #[strum(serialize = "__sysy_ftol")]
+    FToL,
+}
+
+impl Routine {
+    pub fn from_callee(name: &str) -> Option<Self> {
+        name.strip_prefix('@')?.parse().ok()
+    }
+
+    /// Types of the parameters and the return value in Koopa IR
+    pub fn signature(self) -> (Vec<IrType>, IrType) {
+        let (ptr, int) = (IrType::get_pointer(IrType::get_i32()), IrType::get_i32());
+        match self {
+            Self::LShl | Self::LShr => (vec![ptr.clone(), ptr, int], IrType::get_unit()),
+            Self::LNeg | Self::LNot => (vec![ptr.clone(), ptr], IrType::get_unit()),
+            Self::LCmp => (vec![ptr.clone(), ptr], int),
+            Self::LToF => (vec![ptr], int),
+            Self::FToL => (vec![ptr, int], IrType::get_unit()),
+            _ => (vec![ptr.clone(), ptr.clone(), ptr], IrType::get_unit()),
+        }
+    }
+}
+
+impl From<BinaryOp> for Routine {
+    fn from(value: BinaryOp) -> Self {
+        match value {
+            BinaryOp::Add => Self::LAdd,
+            BinaryOp::Sub => Self::LSub,
+            BinaryOp::Mul => Self::LMul,
+            BinaryOp::Div => Self::LDiv,
+            BinaryOp::Mod => Self::LMod,
+            BinaryOp::BitAnd => Self::LAnd,
+            BinaryOp::BitOr => Self::LOr,
+            BinaryOp::BitXor => Self::LXor,
+            BinaryOp::Shl => Self::LShl,
+            BinaryOp::Shr => Self::LShr,
+            // a comparison is done on the result of `LCmp`
+            _ => Self::LCmp,
+        }
+    }
+}
@@ -3,10 +3,17 @@ use koopa::ir::ValueKind;
 use smallvec::SmallVec;
 
 use super::*;
-use crate::ast::{InitVal, LVal};
+use crate::ast::{Expr, ExprKind, InitVal, LVal, Member};
 use crate::sema::ty::{DimTy, Type, TypeKind};
 
 pub fn eval_array(init: &InitVal, ty: &Type) -> Vec<i32> {
+    if matches!(
+        ty.elem_ty().kind(),
+        TypeKind::Struct(_, _) | TypeKind::LongLong
+    ) {
+        return eval_aggregate(init, ty);
+    }
+
     let mut elems = Vec::new();
     let mut dims: DimTy = SmallVec::new();
     ty.get_dims(&mut dims);
@@ -21,37 +28,98 @@ pub fn eval_array(init: &InitVal, ty: &Type) -> Vec<i32> {
         })
         .collect();
 
-    fn fill_array(init: &[InitVal], bds: &[usize], pos: usize, elems: &mut Vec<i32>) -> usize {
-        let (idx, stride) = bds
-            .iter()
-            .rev()
-            .enumerate()
-            .find(|&(_, d)| pos % d == 0)
-            .expect("invalid initializer");
+    /// Fill the object at `pos`, where `bds` are the sizes of its subarrays
+    fn fill_array(
+        init: &[InitVal],
+        bds: &[usize],
+        pos: usize,
+        elem_ty: &Type,
+        elems: &mut Vec<i32>,
+    ) {
         let mut pos = pos;
-        let next_pos = pos + stride;
-        let next_bd = bds.len() - idx - 1;
-
         for e in init {
             match e {
                 InitVal::Expr(e) => {
                     if pos > elems.len() {
                         elems.resize_with(pos, Default::default);
                     }
-                    elems.push(e.get_i32());
+                    elems.push(e.get_const().to_bits(elem_ty));
                     pos += 1;
                 }
-                InitVal::List(list) => {
-                    pos = fill_array(list, &bds[0..next_bd], pos, elems);
-                }
+                InitVal::List(list) => match bds.iter().rposition(|d| pos.is_multiple_of(*d)) {
+                    Some(idx) => {
+                        fill_array(list, &bds[..idx], pos, elem_ty, elems);
+                        pos += bds[idx];
+                    }
+                    // braces around a scalar
+                    None => {
+                        fill_array(list, &[], pos, elem_ty, elems);
+                        pos += 1;
+                    }
+                },
             };
         }
+    }
+
+    if let InitVal::List(list) = init {
+        fill_array(
+            list,
+            &boundaries[..boundaries.len() - 1],
+            0,
+            ty.elem_ty(),
+            &mut elems,
+        );
+    } else {
+        panic!("incompatible initializer type")
+    }
+
+    elems
+}
+
+/// Evaluate the initializer of an aggregate containing `struct`s or `long long`s
+fn eval_aggregate(init: &InitVal, ty: &Type) -> Vec<i32> {
+    fn fill(init: &[InitVal], next: &mut usize, ty: &Type, pos: usize, elems: &mut [i32]) {
+        let subobjects: Vec<(Type, usize)> = match ty.kind() {
+            TypeKind::Array(base_ty, len) => (0..*len)
+                .map(|i| (base_ty.clone(), pos + i * base_ty.size()))
+                .collect(),
+            TypeKind::Struct(_, _) => ty
+                .fields()
+                .unwrap()
+                .iter()
+                .map(|f| (f.ty.clone(), pos + f.offset))
+                .collect(),
+            _ => {
+                match &init[*next] {
+                    InitVal::Expr(e) => {
+                        let words = e.get_const().to_words(ty);
+                        elems[pos..pos + words.len()].copy_from_slice(&words);
+                    }
+                    InitVal::List(list) => fill(list, &mut 0, ty, pos, elems),
+                }
+                *next += 1;
+                return;
+            }
+        };
 
-        next_pos
+        for (ty, pos) in subobjects {
+            if *next >= init.len() {
+                break;
+            }
+            match &init[*next] {
+                InitVal::List(list) if !list.is_empty() => {
+                    fill(list, &mut 0, &ty, pos, elems);
+                    *next += 1;
+                }
+                InitVal::List(_) => *next += 1,
+                InitVal::Expr(_) => fill(init, next, &ty, pos, elems),
+            }
+        }
     }
 
+    let mut elems = vec![0; ty.size()];
     if let InitVal::List(list) = init {
-        fill_array(list, &boundaries, 0, &mut elems);
+        fill(list, &mut 0, ty, 0, &mut elems);
     } else {
         panic!("incompatible initializer type")
     }
@@ -72,7 +140,7 @@ pub fn init_array(recorder: &mut ProgramRecorder, dst: Value, ty: &Type, init: &
         pos: usize,
     ) {
         match ty.kind() {
-            TypeKind::Integer => {
+            TypeKind::Integer | TypeKind::Unsigned | TypeKind::Float => {
                 if *init.get(pos).unwrap_or(&0) == 0 {
                     return;
                 }
@@ -92,6 +160,16 @@ pub fn init_array(recorder: &mut ProgramRecorder, dst: Value, ty: &Type, init: &
                     init_array_recur(recorder, dst, base_ty, init, next_pos);
                 }
             }
+            TypeKind::Struct(_, _) | TypeKind::LongLong => {
+                for i in 0..ty.size() {
+                    if *init.get(pos + i).unwrap_or(&0) == 0 {
+                        continue;
+                    }
+                    let index = recorder.new_value().integer(i as i32);
+                    let dst = get_elem_ptr(recorder, dst, &[index]);
+                    init_array_recur(recorder, dst, &Type::get_int(), init, pos + i);
+                }
+            }
             _ => unreachable!(),
         }
     }
@@ -106,38 +184,26 @@ pub fn init_global_array(recorder: &mut ProgramRecorder, ty: &Type, init: &[i32]
         init: &[i32],
         pos: usize,
     ) -> Value {
-        if pos >= init.len() {
-            return recorder.new_global_value().zero_init(ty.get_ir_ty());
-        }
-        if let TypeKind::Array(base_ty, len) = ty.kind() {
-            match base_ty.kind() {
-                TypeKind::Integer => {
-                    let elems: Vec<_> = (0..*len)
-                        .map(|i| {
-                            recorder
-                                .new_global_value()
-                                .integer(*init.get(pos + i).unwrap_or(&0))
-                        })
-                        .collect();
-                    recorder.new_global_value().aggregate(elems)
-                }
-                TypeKind::Array(_, _) => {
-                    let elems: Vec<_> = (0..*len)
-                        .map(|i| {
-                            init_global_array_recur(
-                                recorder,
-                                base_ty,
-                                init,
-                                pos + i * base_ty.size(),
-                            )
-                        })
-                        .collect();
-                    recorder.new_global_value().aggregate(elems)
-                }
-                _ => unreachable!(),
+        match ty.kind() {
+            TypeKind::Integer | TypeKind::Unsigned | TypeKind::Float | TypeKind::Char => recorder
+                .new_global_value()
+                .integer(*init.get(pos).unwrap_or(&0)),
+            _ if pos >= init.len() => recorder.new_global_value().zero_init(ty.get_ir_ty()),
+            TypeKind::Array(base_ty, len) => {
+                let elems: Vec<_> = (0..*len)
+                    .map(|i| {
+                        init_global_array_recur(recorder, base_ty, init, pos + i * base_ty.size())
+                    })
+                    .collect();
+                recorder.new_global_value().aggregate(elems)
+            }
+            TypeKind::Struct(_, _) | TypeKind::LongLong => {
+                let elems: Vec<_> = (0..ty.size())
+                    .map(|i| init_global_array_recur(recorder, &Type::get_int(), init, pos + i))
+                    .collect();
+                recorder.new_global_value().aggregate(elems)
             }
-        } else {
-            unreachable!()
+            _ => unreachable!(),
         }
     }
 
@@ -163,26 +229,207 @@ pub fn get_elem_ptr(recorder: &mut ProgramRecorder, src: Value, dims: &[Value])
 }
 
 pub fn get_lval_ptr<'i>(recorder: &mut ProgramRecorder<'i>, lval: &'i LVal) -> Value {
-    let src = recorder.get_value(&lval.ident);
+    lval_ptr(recorder, lval).0
+}
+
+/// Pointer to the place designated by `e`, i.e. an `LVal` or a dereference
+pub fn get_place_ptr<'i>(recorder: &mut ProgramRecorder<'i>, e: &'i Expr) -> Value {
+    match place_ptr(recorder, e) {
+        (ptr, true) => cast_ptr(recorder, ptr, e.ty()),
+        (ptr, _) => ptr,
+    }
+}
+
+/// Pointer to the first element of an array, or to the first word of a `struct`
+pub fn get_aggregate_ptr<'i>(recorder: &mut ProgramRecorder<'i>, e: &'i Expr) -> Value {
+    match (place_ptr(recorder, e), e.ty().kind()) {
+        ((ptr, true), TypeKind::Array(base_ty, _)) => cast_ptr(recorder, ptr, base_ty),
+        ((ptr, true), _) => ptr,
+        ((ptr, false), _) => into_ptr(recorder, ptr),
+    }
+}
+
+/// Cast the `*i32` to a member to the pointer of its type
+fn cast_ptr(recorder: &mut ProgramRecorder, ptr: Value, ty: &Type) -> Value {
+    let ir_ty = ty.get_ir_ty();
+    if ir_ty == IrType::get_i32() {
+        return ptr;
+    }
+    let func = recorder.pointer_intrinsic(Intrinsic::PtrCast, IrType::get_pointer(ir_ty));
+    let call = recorder.new_value().call(func, vec![ptr]);
+    recorder.push_inst(call);
+
+    call
+}
+
+fn place_ptr<'i>(recorder: &mut ProgramRecorder<'i>, e: &'i Expr) -> (Value, bool) {
+    match &e.kind {
+        ExprKind::LVal(lval) => lval_ptr(recorder, lval),
+        ExprKind::Member(base, member) => member_ptr(recorder, base, member),
+        ExprKind::Deref(opr) => (opr.generate_ir(recorder).unwrap(), false),
+        ExprKind::String(s) => (recorder.string(s), false),
+        _ => unreachable!(),
+    }
+}
+
+/// Pointer to the value referred to by `lval`, and whether it is a `*i32` into a `struct`
+fn lval_ptr<'i>(recorder: &mut ProgramRecorder<'i>, lval: &'i LVal) -> (Value, bool) {
     let dims: SmallVec<[Value; 4]> = lval
         .dims
         .iter()
         .map(|e| e.generate_ir(recorder).unwrap())
         .collect();
-    match recorder.get_ty(&lval.ident).kind() {
-        TypeKind::Integer => src,
-        TypeKind::Array(_, _) => get_elem_ptr(recorder, src, &dims),
-        TypeKind::Pointer(_) => {
-            let mut ptr = recorder.new_value().load(src);
-            recorder.push_inst(ptr);
-            if !dims.is_empty() {
-                let get_ptr = recorder.new_value().get_ptr(ptr, dims[0]);
-                recorder.push_inst(get_ptr);
-                ptr = get_elem_ptr(recorder, get_ptr, &dims[1..]);
+    let mut ty = recorder.get_ty(&lval.ident).clone();
+    let mut ptr = recorder.get_value(&lval.ident);
+    for idx in dims {
+        ty = match ty.kind() {
+            TypeKind::Array(base_ty, _) => {
+                ptr = get_elem_ptr(recorder, ptr, &[idx]);
+                base_ty.clone()
             }
-            ptr
+            TypeKind::Pointer(base_ty) => {
+                let ld = recorder.new_value().load(ptr);
+                recorder.push_inst(ld);
+                ptr = get_ptr(recorder, ld, idx);
+                base_ty.clone()
+            }
+            _ => unreachable!(),
+        };
+    }
+    if lval.members.is_empty() {
+        return (ptr, false);
+    }
+
+    let mut flat = false;
+    for member in &lval.members {
+        if member.arrow {
+            ptr = load_ptr(recorder, ptr, &ty, flat);
+            flat = false;
+            ty = match ty.kind() {
+                TypeKind::Pointer(base_ty) => base_ty.clone(),
+                _ => unreachable!(),
+            };
         }
-        _ => unreachable!(),
+        (ptr, ty, flat) = field_ptr(recorder, ptr, &ty, flat, member);
+    }
+    flat_ptr(recorder, ptr, &ty, flat)
+}
+
+/// Pointer to the member accessed on `base`, and whether it is a `*i32` into a `struct`
+fn member_ptr<'i>(
+    recorder: &mut ProgramRecorder<'i>,
+    base: &'i Expr,
+    member: &'i Member,
+) -> (Value, bool) {
+    let (ptr, ty, flat) = match base.ty().kind() {
+        TypeKind::Pointer(base_ty) if member.arrow => {
+            (base.generate_ir(recorder).unwrap(), base_ty.clone(), false)
+        }
+        _ => {
+            let (ptr, flat) = place_ptr(recorder, base);
+            (ptr, base.ty().clone(), flat)
+        }
+    };
+    let (ptr, ty, flat) = field_ptr(recorder, ptr, &ty, flat, member);
+    flat_ptr(recorder, ptr, &ty, flat)
+}
+
+/// Pointer to the member of the `struct` of the type at `ptr`, and the type of the member
+fn field_ptr<'i>(
+    recorder: &mut ProgramRecorder<'i>,
+    mut ptr: Value,
+    ty: &Type,
+    mut flat: bool,
+    member: &'i Member,
+) -> (Value, Type, bool) {
+    if !flat {
+        ptr = into_ptr(recorder, ptr);
+        flat = true;
+    }
+    let field = ty.field(&member.ident).unwrap();
+    if field.offset != 0 {
+        let offset = recorder.new_value().integer(field.offset as i32);
+        ptr = get_ptr(recorder, ptr, offset);
+    }
+    let mut ty = field.ty.clone();
+
+    for dim in &member.dims {
+        let mut offset = dim.generate_ir(recorder).unwrap();
+        ty = match ty.kind() {
+            TypeKind::Array(base_ty, _) if flat => {
+                if base_ty.size() != 1 {
+                    let size = recorder.new_value().integer(base_ty.size() as i32);
+                    offset = binary(recorder, IrBinaryOp::Mul, offset, size);
+                }
+                ptr = get_ptr(recorder, ptr, offset);
+                base_ty.clone()
+            }
+            TypeKind::Array(base_ty, _) => {
+                ptr = get_elem_ptr(recorder, ptr, &[offset]);
+                base_ty.clone()
+            }
+            TypeKind::Pointer(base_ty) => {
+                let ld = load_ptr(recorder, ptr, &ty, flat);
+                ptr = get_ptr(recorder, ld, offset);
+                flat = false;
+                base_ty.clone()
+            }
+            _ => unreachable!(),
+        };
+    }
+    (ptr, ty, flat)
+}
+
+/// A pointer stored in a `struct` is cast back to its type
+fn flat_ptr(recorder: &mut ProgramRecorder, ptr: Value, ty: &Type, flat: bool) -> (Value, bool) {
+    match ty.kind() {
+        TypeKind::Pointer(_) if flat => (cast_ptr(recorder, ptr, ty), false),
+        _ => (ptr, flat),
+    }
+}
+
+fn load_ptr(recorder: &mut ProgramRecorder, ptr: Value, ty: &Type, flat: bool) -> Value {
+    let ptr = match flat {
+        true => cast_ptr(recorder, ptr, ty),
+        false => ptr,
+    };
+    let ld = recorder.new_value().load(ptr);
+    recorder.push_inst(ld);
+
+    ld
+}
+
+pub fn get_ptr(recorder: &mut ProgramRecorder, src: Value, offset: Value) -> Value {
+    let ptr = recorder.new_value().get_ptr(src, offset);
+    recorder.push_inst(ptr);
+
+    ptr
+}
+
+/// Copy a `struct` or a `long long` between the places pointed to by `dst` and `src`
+pub fn copy_value(recorder: &mut ProgramRecorder, dst: Value, src: Value, ty: &Type) {
+    match ty.kind() {
+        TypeKind::LongLong if recorder.target() == Target::Rv64 => {
+            let val = load_long(recorder, src);
+            store_long(recorder, val, dst);
+        }
+        _ => copy_struct(recorder, dst, src, ty.size()),
+    }
+}
+
+/// Copy `size` words between the `struct`s pointed to by `dst` and `src`
+pub fn copy_struct(recorder: &mut ProgramRecorder, dst: Value, src: Value, size: usize) {
+    for i in 0..size {
+        let (mut src, mut dst) = (src, dst);
+        if i != 0 {
+            let offset = recorder.new_value().integer(i as i32);
+            src = get_ptr(recorder, src, offset);
+            dst = get_ptr(recorder, dst, offset);
+        }
+        let ld = recorder.new_value().load(src);
+        recorder.push_inst(ld);
+        let st = recorder.new_value().store(ld, dst);
+        recorder.push_inst(st);
     }
 }
 
@@ -212,6 +459,36 @@ pub fn binary(recorder: &mut ProgramRecorder, op: IrBinaryOp, lhs: Value, rhs: V
     by
 }
 
+/// Widen the bits of a `float` to the words of a `double`, flushing denormals to zero
+pub fn float_to_double(recorder: &mut ProgramRecorder, bits: Value) -> (Value, Value) {
+    let mut int = |i: i32| recorder.new_value().integer(i);
+    let (sign_mask, exp_shift, exp_mask, man_mask) =
+        (int(i32::MIN), int(23), int(255), int(0x7fffff));
+    let (zero, bias, hi_shift, lo_shift, man_shift) = (int(0), int(896), int(20), int(29), int(3));
+
+    let sign = binary(recorder, IrBinaryOp::And, bits, sign_mask);
+    let exp = binary(recorder, IrBinaryOp::Shr, bits, exp_shift);
+    let exp = binary(recorder, IrBinaryOp::And, exp, exp_mask);
+    let man = binary(recorder, IrBinaryOp::And, bits, man_mask);
+    // zeros and denormals become zeros
+    let is_normal = binary(recorder, IrBinaryOp::NotEq, exp, zero);
+    let normal_mask = binary(recorder, IrBinaryOp::Sub, zero, is_normal);
+    // the exponent is rebiased from 127 to 1023, and stays all ones for infinities and NaNs
+    let is_special = binary(recorder, IrBinaryOp::Eq, exp, exp_mask);
+    let special_bias = binary(recorder, IrBinaryOp::Mul, is_special, bias);
+    let exp = binary(recorder, IrBinaryOp::Add, exp, bias);
+    let exp = binary(recorder, IrBinaryOp::Add, exp, special_bias);
+    let exp = binary(recorder, IrBinaryOp::And, exp, normal_mask);
+    let man = binary(recorder, IrBinaryOp::And, man, normal_mask);
+
+    let exp = binary(recorder, IrBinaryOp::Shl, exp, hi_shift);
+    let hi = binary(recorder, IrBinaryOp::Or, sign, exp);
+    let man_hi = binary(recorder, IrBinaryOp::Shr, man, man_shift);
+    let hi = binary(recorder, IrBinaryOp::Or, hi, man_hi);
+    let lo = binary(recorder, IrBinaryOp::Shl, man, lo_shift);
+    (lo, hi)
+}
+
 pub fn negative(recorder: &mut ProgramRecorder, opr: Value) -> Value {
     let zero = recorder.new_value().integer(0);
     let neg = recorder.new_value().binary(IrBinaryOp::Sub, zero, opr);
@@ -220,6 +497,14 @@ pub fn negative(recorder: &mut ProgramRecorder, opr: Value) -> Value {
     neg
 }
 
+pub fn bitwise_not(recorder: &mut ProgramRecorder, opr: Value) -> Value {
+    let ones = recorder.new_value().integer(-1);
+    let not = recorder.new_value().binary(IrBinaryOp::Xor, opr, ones);
+    recorder.push_inst(not);
+
+    not
+}
+
 pub fn logical_not(recorder: &mut ProgramRecorder, opr: Value) -> Value {
     let val = if let ValueKind::Binary(b) = recorder.get_value_data(opr).kind().clone() {
         match b.op() {
@@ -231,7 +516,7 @@ pub fn logical_not(recorder: &mut ProgramRecorder, opr: Value) -> Value {
             IrBinaryOp::NotEq => {
                 recorder
                     .replace_value_with(opr)
-                    .binary(IrBinaryOp::NotEq, b.lhs(), b.rhs())
+                    .binary(IrBinaryOp::Eq, b.lhs(), b.rhs())
             }
             IrBinaryOp::Lt => {
                 recorder
@@ -293,3 +578,19 @@ pub fn value_checked(recorder: &mut ProgramRecorder, val: Value) -> Value {
 
     checked
 }
+
+pub fn null_ptr(recorder: &mut ProgramRecorder, ty: &Type) -> Value {
+    let func = recorder.pointer_intrinsic(Intrinsic::NullPtr, ty.get_ir_ty());
+    let call = recorder.new_value().call(func, vec![]);
+    recorder.push_inst(call);
+
+    call
+}
+
+pub fn call_intrinsic(recorder: &mut ProgramRecorder, op: Intrinsic, args: Vec<Value>) -> Value {
+    let func = recorder.intrinsic(op);
+    let call = recorder.new_value().call(func, args);
+    recorder.push_inst(call);
+
+    call
+}
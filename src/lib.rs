@@ -0,0 +1,16 @@
+use lalrpop_util::lalrpop_mod;
+
+pub mod ast;
+pub mod codegen;
+pub mod diag;
+pub mod irgen;
+mod opt;
+pub mod preprocess;
+pub mod sema;
+
+pub use opt::OptLevel;
+
+lalrpop_mod!(
+    #[allow(clippy::all)]
+    sysy
+);
@@ -1,31 +1,303 @@
 use std::env::args;
+use std::ffi::OsString;
+use std::fs::{read_to_string, write};
+use std::io::{stdin, stdout, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-use anyhow::{bail, Result};
-use lalrpop_util::lalrpop_mod;
+use anyhow::{Context, Result};
+use strum_macros::EnumString;
 
-use codegen::generate_code;
-use irgen::generate_ir;
+use rcompiler::codegen::{generate_code, Target};
+use rcompiler::diag::{CompileError, DiagnosticFormat, DiagnosticOptions, SourceFile};
+use rcompiler::irgen::generate_ir;
+use rcompiler::preprocess::{lex, preprocess};
+use rcompiler::sema::parse;
+use rcompiler::OptLevel;
 
-mod ast;
-mod codegen;
-mod irgen;
-mod opt;
-mod sema;
+const USAGE: &str = "\
+Usage: rcompiler [options] <file>...
 
-lalrpop_mod!(sysy);
+Compile SysY source files into RISC-V assembly or Koopa IR. An input of `-` is read from the
+standard input, and an output of `-` is written to the standard output.
 
-fn main() -> Result<()> {
-    let mut args = args();
-    let mode = args.nth(1).unwrap();
-    let input = args.next().unwrap();
-    let output = args.nth(1).unwrap();
+Options:
+  -o <file>                     Write the output to <file>, with one input only
+  -S                            Emit RISC-V assembly, which is the default
+  --emit=<kind>                 Emit `tokens`, `ast`, `koopa` or `riscv`
+  -O0, -O1, -O2                 Set the optimization level, which is -O0 by default
+  -O                            Same as -O1
+  --target=<target>             Generate code for `riscv32`, the default, or `riscv64`
+  -I <dir>                      Also look for included headers in <dir>
+  -W<warning>, -Wno-<warning>   Enable or disable a warning, e.g. -Wshadow, or -Wall
+  -Werror                       Turn the warnings into errors
+  --diagnostic-format=<format>  Print the diagnostics as `human` or `json`
+  -h, --help                    Print this help
+  -V, --version                 Print the version
 
-    match mode.as_str() {
-        "-koopa" => generate_ir(&input, &output, false)?,
-        "-riscv" => generate_code(&input, &output, false)?,
-        "-perf" => generate_code(&input, &output, true)?,
-        _ => bail!("invalid mode: {}", mode.as_str()),
+The output of an input `dir/a.c` is written to `a.s` or `a.koopa` without `-o`, while the
+tokens and the AST are written to the standard output, as is anything compiled from the
+standard input.
+
+The modes `-koopa`, `-riscv`, `-perf`, `-riscv64` and `-perf64` are also accepted, where
+`-perf` is `-S -O2`.
+
+Exit status: 0 on success, 1 if an input fails to compile, and 2 on a bad command line.
+";
+
+/// The name of the standard input or output on the command line
+const STDIO: &str = "-";
+
+/// The name of the standard input in the diagnostics
+const STDIN_NAME: &str = "<stdin>";
+
+/// What is written for each input, as given by `--emit=<kind>`
+#[derive(Debug, EnumString, Clone, Copy, PartialEq, Eq)]
+enum Emit {
+    /// the tokens of the preprocessed text, with where they come from
+    #[strum(serialize = "tokens")]
+    Tokens,
+    /// the syntax tree as parsed
+    #[strum(serialize = "ast")]
+    Ast,
+    #[strum(serialize = "koopa")]
+    Koopa,
+    #[strum(serialize = "riscv")]
+    Riscv,
+}
+
+impl Emit {
+    /// Extension of the output file without `-o`, or `None` for the standard output
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Tokens | Self::Ast => None,
+            Self::Koopa => Some("koopa"),
+            Self::Riscv => Some("s"),
+        }
+    }
+}
+
+/// What the command line asks for
+enum Command {
+    Compile(Options),
+    Help,
+    Version,
+}
+
+struct Options {
+    inputs: Vec<String>,
+    output: Option<String>,
+    emit: Emit,
+    opt: OptLevel,
+    target: Target,
+    include_dirs: Vec<PathBuf>,
+    diag_opts: DiagnosticOptions,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(args().skip(1)) {
+        Ok(Command::Compile(options)) => options,
+        Ok(Command::Help) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("rcompiler {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            eprintln!("run `rcompiler --help` for the usage");
+            return ExitCode::from(2);
+        }
     };
 
+    // an input that fails does not stop the rest from being compiled
+    let mut failed = false;
+    for input in &options.inputs {
+        if let Err(err) = compile(input, &options) {
+            match err.downcast_ref::<CompileError>() {
+                Some(err) => eprint!("{}", err.emit(options.diag_opts.format)),
+                None => eprintln!("error: {:#}", err),
+            }
+            failed = true;
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Parse the arguments after the name of the program, or describe what is wrong with them
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options {
+        inputs: vec![],
+        output: None,
+        emit: Emit::Riscv,
+        opt: OptLevel::O0,
+        target: Target::Rv32,
+        include_dirs: vec![],
+        diag_opts: DiagnosticOptions::default(),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-S" | "-riscv" => options.emit = Emit::Riscv,
+            "-koopa" => options.emit = Emit::Koopa,
+            "-perf" => (options.emit, options.opt) = (Emit::Riscv, OptLevel::O2),
+            "-riscv64" => (options.emit, options.target) = (Emit::Riscv, Target::Rv64),
+            "-perf64" => {
+                (options.emit, options.opt, options.target) =
+                    (Emit::Riscv, OptLevel::O2, Target::Rv64)
+            }
+            "-O" | "-O1" => options.opt = OptLevel::O1,
+            "-O0" => options.opt = OptLevel::O0,
+            "-O2" => options.opt = OptLevel::O2,
+            "-o" | "-I" => {
+                let Some(value) = args.next() else {
+                    return Err(format!("missing argument after `{}`", arg));
+                };
+                if arg == "-o" {
+                    options.output = Some(value);
+                } else {
+                    options.include_dirs.push(PathBuf::from(value));
+                }
+            }
+            // everything after `--` is an input
+            "--" => options.inputs.extend(args.by_ref()),
+            STDIO => options.inputs.push(arg),
+            _ => {
+                if let Some(output) = arg.strip_prefix("-o") {
+                    options.output = Some(output.to_owned());
+                } else if let Some(dir) = arg.strip_prefix("-I") {
+                    options.include_dirs.push(PathBuf::from(dir));
+                } else if arg.starts_with("-O") {
+                    return Err(format!(
+                        "unknown optimization level `{}`, expected -O0, -O1 or -O2",
+                        arg
+                    ));
+                } else if let Some(option) = arg.strip_prefix("-W") {
+                    if !options.diag_opts.warnings.parse(option) {
+                        return Err(format!("unknown warning option `{}`", arg));
+                    }
+                } else if let Some(emit) = arg.strip_prefix("--emit=") {
+                    options.emit = emit.parse().map_err(|_| {
+                        format!(
+                            "unknown kind of output `{}`, expected tokens, ast, koopa or riscv",
+                            emit
+                        )
+                    })?;
+                } else if let Some(target) = arg.strip_prefix("--target=") {
+                    options.target = match target {
+                        "riscv32" => Target::Rv32,
+                        "riscv64" => Target::Rv64,
+                        _ => {
+                            return Err(format!(
+                                "unknown target `{}`, expected riscv32 or riscv64",
+                                target
+                            ))
+                        }
+                    };
+                } else if let Some(format) = arg.strip_prefix("--diagnostic-format=") {
+                    options.diag_opts.format =
+                        format.parse::<DiagnosticFormat>().map_err(|_| {
+                            format!(
+                                "unknown diagnostic format `{}`, expected human or json",
+                                format
+                            )
+                        })?;
+                } else if arg.starts_with('-') {
+                    return Err(format!("unknown option `{}`", arg));
+                } else {
+                    options.inputs.push(arg);
+                }
+            }
+        }
+    }
+
+    match options.inputs.len() {
+        0 => return Err("no input files".to_string()),
+        1 => {}
+        _ if options.output.is_some() => {
+            return Err("`-o` cannot be given with more than one input".to_string())
+        }
+        _ => {}
+    }
+    if options.inputs.iter().filter(|i| *i == STDIO).count() > 1 {
+        return Err("the standard input can only be read once".to_string());
+    }
+    Ok(Command::Compile(options))
+}
+
+/// Compile the input, and write the output only if it succeeds
+fn compile(input: &str, options: &Options) -> Result<()> {
+    let file = if input == STDIO {
+        let mut src = String::new();
+        stdin()
+            .read_to_string(&mut src)
+            .context("cannot read the standard input")?;
+        SourceFile::new(STDIN_NAME.to_owned(), src)
+    } else {
+        let src = read_to_string(input).with_context(|| format!("cannot read `{}`", input))?;
+        SourceFile::new(input.to_owned(), src)
+    };
+
+    let (dirs, diag_opts) = (&options.include_dirs, &options.diag_opts);
+    let mut out = vec![];
+    let warnings = match options.emit {
+        Emit::Tokens => {
+            let (sources, diags) = preprocess(file, dirs);
+            let warnings = diag_opts.check(sources, diags)?;
+            let sources = &warnings.sources;
+            for (kind, span) in lex(sources.text()) {
+                let text = &sources.text()[span.start..span.end];
+                let (file, span) = sources.locate_span(None, Some(span));
+                let (line, col) = file.line_col(span.unwrap_or_default().start);
+                writeln!(out, "{}:{}:{}: {} {}", file.name(), line, col, kind, text)?;
+            }
+            warnings
+        }
+        Emit::Ast => {
+            let (sources, diags, ast) = parse(file, dirs);
+            let warnings = diag_opts.check(sources, diags)?;
+            let ast = ast.expect("a program without errors has been parsed");
+            writeln!(out, "{:#?}", ast)?;
+            warnings
+        }
+        Emit::Koopa => generate_ir(file, dirs, diag_opts, &mut out, options.opt)?,
+        Emit::Riscv => generate_code(file, dirs, diag_opts, &mut out, options.opt, options.target)?,
+    };
+    eprint!("{}", warnings.emit(diag_opts.format));
+
+    match output_path(input, options) {
+        Some(path) => {
+            write(&path, out).with_context(|| format!("cannot write `{}`", path.display()))?
+        }
+        None => stdout()
+            .write_all(&out)
+            .context("cannot write to the standard output")?,
+    }
     Ok(())
 }
+
+/// Where the output of the input goes, or `None` for the standard output
+fn output_path(input: &str, options: &Options) -> Option<PathBuf> {
+    match &options.output {
+        Some(output) if output == STDIO => None,
+        Some(output) => Some(PathBuf::from(output)),
+        None if input == STDIO => None,
+        // next to where the compiler is run, e.g. `a.s` for `dir/a.c`
+        None => {
+            let extension = options.emit.extension()?;
+            let mut name = PathBuf::from(input)
+                .file_stem()
+                .map_or_else(OsString::new, OsString::from);
+            name.push(".");
+            name.push(extension);
+            Some(PathBuf::from(name))
+        }
+    }
+}
@@ -22,29 +22,22 @@ impl RemoveCommonExpression {
                 let mut same = val;
                 for &expr in &visited_values {
                     same = expr;
-                    match (value_kind(f, expr), value_kind(f, val)) {
+                    found_common_expr = match (value_kind(f, expr), value_kind(f, val)) {
                         (ValueKind::GetElemPtr(lhs), ValueKind::GetElemPtr(rhs)) => {
-                            if value_eq(f, lhs.index(), rhs.index()) && lhs.src() == rhs.src() {
-                                found_common_expr = true;
-                                break;
-                            }
+                            value_eq(f, lhs.index(), rhs.index()) && lhs.src() == rhs.src()
                         }
                         (ValueKind::GetPtr(lhs), ValueKind::GetPtr(rhs)) => {
-                            if value_eq(f, lhs.index(), rhs.index()) && lhs.src() == rhs.src() {
-                                found_common_expr = true;
-                                break;
-                            }
+                            value_eq(f, lhs.index(), rhs.index()) && lhs.src() == rhs.src()
                         }
                         (ValueKind::Binary(lhs), ValueKind::Binary(rhs)) => {
-                            if lhs.op() == rhs.op()
+                            lhs.op() == rhs.op()
                                 && lhs.lhs() == rhs.lhs()
                                 && value_eq(f, lhs.rhs(), rhs.rhs())
-                            {
-                                found_common_expr = true;
-                                break;
-                            }
                         }
-                        _ => {}
+                        _ => false,
+                    };
+                    if found_common_expr {
+                        break;
                     }
                 }
                 if found_common_expr {
@@ -25,7 +25,6 @@ impl RemoveEmptyBB {
             if let ValueKind::Jump(j) = f.dfg().value(val).kind() {
                 if params.is_empty() {
                     empty_bbs.push((*bb, val));
-                    changed = true;
                     continue;
                 }
                 if params.len() != j.args().len() {
@@ -37,12 +36,15 @@ impl RemoveEmptyBB {
                     }
                 }
                 empty_bbs.push((*bb, val));
-                changed = true;
             }
         }
 
         for &(bb, val) in &empty_bbs {
             if let ValueKind::Jump(j) = f.dfg().value(val).kind().clone() {
+                // an empty infinite loop, e.g. `while (1) {}`, ends in a bb jumping to itself
+                if j.target() == bb || self.merges_branch(f, bb, j.target()) {
+                    continue;
+                }
                 let extra_args = if f.dfg().bb(bb).params().is_empty() {
                     j.args()
                 } else {
@@ -52,12 +54,29 @@ impl RemoveEmptyBB {
                 f.dfg_mut().remove_value(val);
                 f.dfg_mut().remove_bb(bb);
                 f.layout_mut().bbs_mut().remove(&bb);
+                changed = true;
             }
         }
 
         changed
     }
 
+    /// Whether removing `bb` would make a branch jump to `target` on both arms, which
+    /// can't pass different arguments to `target`, e.g. for `c ? 1 : 0`
+    fn merges_branch(&self, f: &FunctionData, bb: BasicBlock, target: BasicBlock) -> bool {
+        f.dfg()
+            .bb(bb)
+            .used_by()
+            .iter()
+            .any(|&user| match value_kind(f, user) {
+                ValueKind::Branch(br) => {
+                    (br.true_bb() == bb && br.false_bb() == target)
+                        || (br.false_bb() == bb && br.true_bb() == target)
+                }
+                _ => false,
+            })
+    }
+
     fn try_coalesce_entry(&self, f: &mut FunctionData) {
         let entry_bb = f.layout().entry_bb().unwrap();
         let node = f.layout().bbs().node(&entry_bb).unwrap();
@@ -90,23 +109,24 @@ impl RemoveEmptyBB {
     ) {
         for user in f.dfg().bb(bb).used_by().clone() {
             let mut data = f.dfg().value(user).clone();
-            let pred_args = match data.kind_mut() {
+            match data.kind_mut() {
                 ValueKind::Jump(j) => {
                     *j.target_mut() = next_bb;
-                    j.args_mut()
+                    j.args_mut().extend(args);
                 }
+                // both targets may be the empty bb, e.g. after the arms of `c ? 1 : 0` are removed
                 ValueKind::Branch(br) => {
                     if br.true_bb() == bb {
                         *br.true_bb_mut() = next_bb;
-                        br.true_args_mut()
-                    } else {
+                        br.true_args_mut().extend(args);
+                    }
+                    if br.false_bb() == bb {
                         *br.false_bb_mut() = next_bb;
-                        br.false_args_mut()
+                        br.false_args_mut().extend(args);
                     }
                 }
                 _ => unreachable!(),
-            };
-            pred_args.extend(args);
+            }
             f.dfg_mut().replace_value_with(user).raw(data);
         }
     }
@@ -17,15 +17,32 @@ use trivial_arg::*;
 use unreachable::RemoveUnreachable;
 use utils::*;
 
-pub fn optimize(p: &mut Program) {
+/// How much the IR is optimized, as given by `-O<level>`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    /// the IR as generated
+    #[default]
+    O0,
+    /// promotion to SSA form and constant propagation
+    O1,
+    /// also common subexpression elimination and the removal of trivial block arguments
+    O2,
+}
+
+pub fn optimize(p: &mut Program, level: OptLevel) {
+    if level == OptLevel::O0 {
+        return;
+    }
     let mut pass_runner = PassRunner::new();
     pass_runner.register_pass(Pass(Box::new(RemoveUnreachable)));
     pass_runner.register_pass(Pass(Box::new(SsaBuilder::new())));
     pass_runner.register_pass(Pass(Box::new(Sccp::new())));
     pass_runner.register_pass(Pass(Box::new(RemoveUnreachable)));
     pass_runner.register_pass(Pass(Box::new(RemoveEmptyBB)));
-    pass_runner.register_pass(Pass(Box::new(RemoveCommonExpression)));
-    pass_runner.register_pass(Pass(Box::new(RemoveTrivialArgs)));
-    pass_runner.register_pass(Pass(Box::new(RemoveEmptyBB)));
+    if level >= OptLevel::O2 {
+        pass_runner.register_pass(Pass(Box::new(RemoveCommonExpression)));
+        pass_runner.register_pass(Pass(Box::new(RemoveTrivialArgs)));
+        pass_runner.register_pass(Pass(Box::new(RemoveEmptyBB)));
+    }
     pass_runner.run_passes(p);
 }
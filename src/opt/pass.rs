@@ -1,9 +1,5 @@
 use koopa::ir::*;
 
-pub trait ProgramPass {
-    fn run_on(&mut self, p: &mut Program);
-}
-
 pub trait FunctionPass {
     fn run_on(&mut self, f: &mut FunctionData);
 }
@@ -114,19 +114,12 @@ impl Sccp {
                 self.visit_expr(f, val);
             }
         }
-        let out_edges = self.outcoming_edges.get(&entry_bb);
-        if out_edges.is_none() {
-            return;
-        }
-        let out_edges = out_edges.unwrap();
-        if out_edges.len() == 1 {
-            self.flow_worklist.push(out_edges[0]);
-        }
+        self.visit_exit(f, entry_bb);
     }
 
     fn visit_flow_edge(&mut self, f: &FunctionData) {
         let id = self.flow_worklist.pop().unwrap();
-        let mut edge = self.edges.get_mut(id).unwrap();
+        let edge = self.edges.get_mut(id).unwrap();
         if edge.executable {
             return;
         }
@@ -149,13 +142,37 @@ impl Sccp {
             }
         }
 
-        let out_edges = self.outcoming_edges.get(&bb);
-        if out_edges.is_none() {
-            return;
-        }
-        let out_edges = out_edges.unwrap();
+        self.visit_exit(f, bb);
+    }
+
+    /// Mark the outgoing edges of a block as executable, resolving a branch on a value which
+    /// never changes, e.g. the result of a call
+    fn visit_exit(&mut self, f: &FunctionData, bb: BasicBlock) {
+        let out_edges = match self.outcoming_edges.get(&bb) {
+            Some(out_edges) => out_edges,
+            None => return,
+        };
         if out_edges.len() == 1 {
             self.flow_worklist.push(out_edges[0]);
+            return;
+        }
+
+        if let ValueKind::Branch(br) = value_kind(f, last_inst_of_bb(f, bb)) {
+            if self.is_expr(f, br.cond()) {
+                return;
+            }
+            match self.value_to_type(f, br.cond()) {
+                CellType::Constant(i) if i != 0 => {
+                    let id = self.get_edge_id(bb, br.true_bb());
+                    self.flow_worklist.push(id);
+                }
+                CellType::Constant(_) => {
+                    let id = self.get_edge_id(bb, br.false_bb());
+                    self.flow_worklist.push(id);
+                }
+                CellType::Bottom => self.flow_worklist.extend(out_edges.clone()),
+                CellType::Top => {}
+            }
         }
     }
 
@@ -477,35 +494,26 @@ impl Sccp {
     fn evaluate(&mut self, op: BinaryOp, lhs_ty: CellType, rhs_ty: CellType) -> CellType {
         match (lhs_ty, rhs_ty) {
             (CellType::Constant(lhs), CellType::Constant(rhs)) => {
-                let lhs = lhs;
-                let rhs = rhs;
+                // the arithmetic wraps around, while a division by zero is left to run time
                 let result = match op {
-                    BinaryOp::Add => lhs + rhs,
-                    BinaryOp::Sub => lhs - rhs,
-                    BinaryOp::Mul => lhs * rhs,
-                    BinaryOp::And => (lhs != 0 && rhs != 0) as i32,
-                    BinaryOp::Or => (lhs != 0 || rhs != 0) as i32,
+                    BinaryOp::Add => lhs.wrapping_add(rhs),
+                    BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                    BinaryOp::Mul => lhs.wrapping_mul(rhs),
+                    BinaryOp::And => lhs & rhs,
+                    BinaryOp::Or => lhs | rhs,
+                    BinaryOp::Xor => lhs ^ rhs,
+                    BinaryOp::Shl => lhs.wrapping_shl(rhs as u32),
+                    BinaryOp::Sar => lhs.wrapping_shr(rhs as u32),
+                    BinaryOp::Shr => (lhs as u32).wrapping_shr(rhs as u32) as i32,
                     BinaryOp::Eq => (lhs == rhs) as i32,
                     BinaryOp::NotEq => (lhs != rhs) as i32,
                     BinaryOp::Lt => (lhs < rhs) as i32,
                     BinaryOp::Le => (lhs <= rhs) as i32,
                     BinaryOp::Gt => (lhs > rhs) as i32,
                     BinaryOp::Ge => (lhs >= rhs) as i32,
-                    BinaryOp::Div => {
-                        if rhs != 0 {
-                            lhs / rhs
-                        } else {
-                            panic!("attempt to divide an integer by zero");
-                        }
-                    }
-                    BinaryOp::Mod => {
-                        if rhs != 0 {
-                            lhs % rhs
-                        } else {
-                            panic!("attempt to calculate the remainder of an integer with a divisor of zero");
-                        }
-                    }
-                    _ => unimplemented!(),
+                    BinaryOp::Div | BinaryOp::Mod if rhs == 0 => return CellType::Bottom,
+                    BinaryOp::Div => lhs.wrapping_div(rhs),
+                    BinaryOp::Mod => lhs.wrapping_rem(rhs),
                 };
 
                 CellType::Constant(result)
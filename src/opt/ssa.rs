@@ -59,8 +59,10 @@ impl SsaBuilder {
                     ValueKind::Alloc(_) => {
                         // record local variables
                         if let TypeKind::Pointer(base_ty) = val_data.ty().kind() {
-                            // only deal with integer type
-                            if matches!(base_ty.kind(), TypeKind::Int32 | TypeKind::Pointer(_)) {
+                            // only deal with integer type, whose address is not taken
+                            if matches!(base_ty.kind(), TypeKind::Int32 | TypeKind::Pointer(_))
+                                && Self::is_promotable(f, val)
+                            {
                                 self.defs.insert(val, HashMap::new());
                             }
                         } else {
@@ -71,12 +73,12 @@ impl SsaBuilder {
                         if !self.defs.contains_key(&s.dest()) {
                             continue;
                         }
-                        let mut def = Def::Assign(s.value());
-                        if let ValueKind::Load(l) = value_kind(f, s.value()) {
-                            if self.defs.contains_key(&l.src()) {
-                                def = self.read_variable(f, l.src(), bb);
-                            }
-                        }
+                        // a stored load takes the definition seen by the load, which may have
+                        // been overwritten since, e.g. `x = y++`
+                        let def = match self.replace_with.get(&s.value()) {
+                            Some(&(_, def)) => def,
+                            None => Def::Assign(s.value()),
+                        };
                         self.defs.get_mut(&s.dest()).unwrap().insert(bb, def);
                     }
                     ValueKind::Load(l) => {
@@ -94,6 +96,19 @@ impl SsaBuilder {
         }
     }
 
+    /// Whether the local variable is only loaded and stored, i.e. not taken by `&x`
+    fn is_promotable(f: &FunctionData, alloc: Value) -> bool {
+        f.dfg()
+            .value(alloc)
+            .used_by()
+            .iter()
+            .all(|&user| match value_kind(f, user) {
+                ValueKind::Load(_) => true,
+                ValueKind::Store(s) => s.dest() == alloc,
+                _ => false,
+            })
+    }
+
     fn update_cfg(&mut self, f: &mut FunctionData) {
         if !self.cfg.is_empty() {
             self.cfg.clear();
@@ -119,10 +134,17 @@ impl SsaBuilder {
                 continue;
             }
 
+            let param_tys = var
+                .iter()
+                .map(|&v| match f.dfg().value(v).ty().kind() {
+                    TypeKind::Pointer(base_ty) => base_ty.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
             let bb_with_param = f
                 .dfg_mut()
                 .new_bb()
-                .basic_block_with_params(None, vec![Type::get_i32(); var.len()]);
+                .basic_block_with_params(None, param_tys);
 
             // replace the old bb with the new bb
             replace_bb_with(f, *bb, bb_with_param);
@@ -172,7 +194,7 @@ impl SsaBuilder {
                 }
                 // arg is the def of variable
                 let mut args: SmallVec<[Value; 6]> = SmallVec::new();
-                for (_, v) in var.iter().enumerate() {
+                for v in var.iter() {
                     args.push(match self.read_variable(f, *v, pred) {
                         Def::Assign(val) => val,
                         Def::Argument(variable) => self.read_argument_value(f, variable, pred),
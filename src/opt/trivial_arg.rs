@@ -1,3 +1,5 @@
+use std::cmp::Reverse;
+
 use koopa::ir::{builder_traits::ValueBuilder, BasicBlock, FunctionData, Value, ValueKind};
 
 use super::*;
@@ -20,7 +22,7 @@ impl RemoveTrivialArgs {
                 }
             }
         }
-        unused_args.sort_by(|a, b| b.1.cmp(&a.1));
+        unused_args.sort_by_key(|&(_, idx)| Reverse(idx));
 
         for &(bb, idx) in &unused_args {
             self.remove_arg(f, bb, idx);
@@ -0,0 +1,276 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::token::*;
+
+#[derive(Debug, Clone)]
+pub enum Macro {
+    /// `#define NAME body`
+    Object(Vec<Token>),
+    /// `#define NAME(params) body`, where `__VA_ARGS__` is the last parameter if it is variadic
+    Function {
+        params: Vec<String>,
+        variadic: bool,
+        body: Vec<Token>,
+    },
+}
+
+pub struct Expander<'a> {
+    macros: &'a HashMap<String, Macro>,
+    /// for `__FILE__` and `__LINE__`
+    file: &'a str,
+    line: usize,
+    /// messages, and the names of the macros invoked wrongly
+    pub errors: Vec<(String, Token)>,
+}
+
+impl<'a> Expander<'a> {
+    pub fn new(macros: &'a HashMap<String, Macro>, file: &'a str, line: usize) -> Self {
+        Self {
+            macros,
+            file,
+            line,
+            errors: vec![],
+        }
+    }
+
+    /// Expand the macros in the tokens, except the ones hidden in their own expansions
+    pub fn expand(&mut self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut input = VecDeque::from(tokens);
+        let mut output = vec![];
+        while let Some(token) = input.pop_front() {
+            if token.kind != TokenKind::Ident || token.hidden.contains(&token.text) {
+                output.push(token);
+                continue;
+            }
+            let pos = token.origin.pos();
+            let origin = Origin::Expansion(pos.0, pos.1);
+            let builtin = match token.text.as_str() {
+                "__LINE__" => Some(self.line.to_string()),
+                "__FILE__" => Some(format!("\"{}\"", escape(self.file))),
+                _ => None,
+            };
+            if let Some(text) = builtin {
+                output.push(Token::new(TokenKind::Other, text, origin));
+                continue;
+            }
+
+            let expansion = match self.macros.get(&token.text) {
+                Some(Macro::Object(body)) => body
+                    .iter()
+                    .map(|t| Token::new(t.kind, &t.text, origin))
+                    .collect(),
+                Some(Macro::Function {
+                    params,
+                    variadic,
+                    body,
+                }) => match self.take_args(&mut input, &token) {
+                    Some(args) => match self.check_args(&token, params, *variadic, args) {
+                        Some(args) => self.substitute(params, body, args, origin),
+                        None => {
+                            output.push(token);
+                            continue;
+                        }
+                    },
+                    // a function-like macro not followed by `(` is an ordinary identifier
+                    None => {
+                        output.push(token);
+                        continue;
+                    }
+                },
+                None => {
+                    output.push(token);
+                    continue;
+                }
+            };
+
+            // the expansion is rescanned with the rest of the line, where the macro is hidden
+            let mut hidden = token.hidden.clone();
+            hidden.insert(token.text.clone());
+            let space = Token::new(TokenKind::Space, " ", origin);
+            for mut t in std::iter::once(space.clone())
+                .chain(expansion)
+                .chain(std::iter::once(space))
+                .rev()
+            {
+                t.hidden.extend(hidden.iter().cloned());
+                input.push_front(t);
+            }
+        }
+        output
+    }
+
+    /// Take the arguments of a function-like macro, or `None` if there is no `(` or no `)`
+    fn take_args(&mut self, input: &mut VecDeque<Token>, name: &Token) -> Option<Vec<Vec<Token>>> {
+        let open = input.iter().position(|t| t.kind != TokenKind::Space)?;
+        if !input[open].is("(") {
+            return None;
+        }
+
+        let mut depth = 0;
+        let mut close = None;
+        for (i, t) in input.iter().enumerate().skip(open) {
+            if t.is("(") {
+                depth += 1;
+            } else if t.is(")") {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+        }
+        let Some(close) = close else {
+            self.errors.push((
+                format!("unterminated argument list invoking macro `{}`", name.text),
+                name.clone(),
+            ));
+            return None;
+        };
+
+        let mut tokens: Vec<_> = input.drain(..=close).skip(open + 1).collect();
+        tokens.pop();
+        let mut args = vec![vec![]];
+        let mut depth = 0;
+        for t in tokens {
+            if t.is("(") {
+                depth += 1;
+            } else if t.is(")") {
+                depth -= 1;
+            } else if t.is(",") && depth == 0 {
+                args.push(vec![]);
+                continue;
+            }
+            args.last_mut().unwrap().push(t);
+        }
+        Some(args)
+    }
+
+    /// Check the number of the arguments, and join the variable ones into one
+    fn check_args(
+        &mut self,
+        name: &Token,
+        params: &[String],
+        variadic: bool,
+        mut args: Vec<Vec<Token>>,
+    ) -> Option<Vec<Vec<Token>>> {
+        let named = params.len() - variadic as usize;
+        // `F()` passes no argument rather than an empty one to a macro without parameters
+        if params.is_empty() && args.len() == 1 && spell(&args[0]).is_empty() {
+            args.clear();
+        }
+        if args.len() == named || (variadic && args.len() > named) {
+            if variadic {
+                let rest = args.split_off(named.min(args.len()));
+                let comma = Token::new(TokenKind::Other, ",", name.origin);
+                args.push(rest.join(&comma));
+            }
+            return Some(args);
+        }
+
+        let arguments = |n| match n {
+            1 => "1 argument".to_string(),
+            n => format!("{} arguments", n),
+        };
+        self.errors.push((
+            format!(
+                "macro `{}` takes {}{} but {} {} supplied",
+                name.text,
+                if variadic { "at least " } else { "" },
+                arguments(named),
+                arguments(args.len()),
+                if args.len() == 1 { "was" } else { "were" }
+            ),
+            name.clone(),
+        ));
+        None
+    }
+
+    /// Replace the parameters in the body with the arguments, expanded unless next to `#` or `##`
+    fn substitute(
+        &mut self,
+        params: &[String],
+        body: &[Token],
+        args: Vec<Vec<Token>>,
+        origin: Origin,
+    ) -> Vec<Token> {
+        let arg = |t: &Token| {
+            let i = params
+                .iter()
+                .position(|p| t.kind == TokenKind::Ident && p == &t.text)?;
+            Some(&args[i])
+        };
+        let is_paste = |t: Option<&Token>| t.is_some_and(|t| t.is("##"));
+        let solid = |i: usize| body[i].kind != TokenKind::Space;
+        let prev = |i: usize| (0..i).rev().find(|&j| solid(j)).map(|j| &body[j]);
+        let next = |i: usize| (i + 1..body.len()).find(|&j| solid(j)).map(|j| &body[j]);
+
+        let mut result: Vec<Token> = vec![];
+        let mut i = 0;
+        while i < body.len() {
+            let t = &body[i];
+            if t.is("#") {
+                if let Some(j) = (i + 1..body.len()).find(|&j| solid(j)) {
+                    if let Some(a) = arg(&body[j]) {
+                        let text = format!("\"{}\"", escape(&spell(a)));
+                        result.push(Token::new(TokenKind::Other, text, origin));
+                        i = j + 1;
+                        continue;
+                    }
+                }
+            }
+            match arg(t) {
+                Some(a) if is_paste(prev(i)) || is_paste(next(i)) => {
+                    result.extend(a.iter().cloned())
+                }
+                Some(a) => {
+                    let expanded = self.expand(a.clone());
+                    result.extend(expanded);
+                }
+                None => result.push(Token::new(t.kind, &t.text, origin)),
+            }
+            i += 1;
+        }
+        paste(result, origin)
+    }
+}
+
+fn paste(tokens: Vec<Token>, origin: Origin) -> Vec<Token> {
+    let mut result: Vec<Token> = vec![];
+    let mut pasting = false;
+    for t in tokens {
+        if t.is("##") {
+            while result.last().is_some_and(|t| t.kind == TokenKind::Space) {
+                result.pop();
+            }
+            pasting = true;
+            continue;
+        }
+        if pasting && t.kind == TokenKind::Space {
+            continue;
+        }
+        match result.last_mut() {
+            Some(last) if pasting => {
+                last.text += &t.text;
+                last.origin = origin;
+                let is_ident = last
+                    .text
+                    .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && last
+                        .text
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_');
+                last.kind = match is_ident {
+                    true => TokenKind::Ident,
+                    false => TokenKind::Other,
+                };
+            }
+            _ => result.push(t),
+        }
+        pasting = false;
+    }
+    result
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,523 @@
+mod expand;
+mod token;
+mod typename;
+
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::diag::{
+    Diagnostic, Diagnostics, ErrorCode, FileId, Segment, SourceFile, SourceMap, Span,
+};
+use crate::sema::eval::ConstEval;
+use crate::sema::Evaluator;
+use crate::sysy;
+use expand::*;
+use token::*;
+
+pub use token::{lex, LexemeKind};
+pub use typename::mark_type_names;
+
+/// Headers whose declarations are built in, unless they are found in the include directories
+const BUILTIN_HEADERS: &[&str] = &["sylib.h"];
+
+/// Depth of nested `#include`s beyond which a header is taken to include itself
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+/// Preprocess the source file, where each line of a file becomes one line of the output
+pub fn preprocess(file: SourceFile, include_dirs: &[PathBuf]) -> (SourceMap, Diagnostics) {
+    let mut pp = Preprocessor::new(include_dirs);
+    let id = pp.sources.add_file(file);
+    pp.process_file(id);
+    (pp.sources, pp.diags)
+}
+
+/// A conditional directive whose `#endif` is not seen yet
+struct Cond {
+    parent_active: bool,
+    active: bool,
+    /// whether a branch has been kept
+    taken: bool,
+    else_seen: bool,
+    /// position of the `#if`
+    line: usize,
+    col: usize,
+}
+
+struct Preprocessor<'a> {
+    include_dirs: &'a [PathBuf],
+    macros: HashMap<String, Macro>,
+    sources: SourceMap,
+    diags: Diagnostics,
+    depth: usize,
+}
+
+impl<'a> Preprocessor<'a> {
+    fn new(include_dirs: &'a [PathBuf]) -> Self {
+        let mut macros = HashMap::new();
+        for (name, func) in [
+            ("starttime", "_sysy_starttime"),
+            ("stoptime", "_sysy_stoptime"),
+        ] {
+            let body = tokenize(&format!("{}(__LINE__)", func), 0, &mut false);
+            let params = vec![];
+            let variadic = false;
+            macros.insert(
+                name.to_string(),
+                Macro::Function {
+                    params,
+                    variadic,
+                    body,
+                },
+            );
+        }
+        Self {
+            include_dirs,
+            macros,
+            sources: SourceMap::new(),
+            diags: Diagnostics::new(),
+            depth: 0,
+        }
+    }
+
+    fn process_file(&mut self, id: FileId) {
+        let src = self.sources.file(id).src().to_owned();
+        let lines: Vec<_> = src.lines().collect();
+        let mut conds: Vec<Cond> = vec![];
+        let mut in_comment = false;
+
+        let mut i = 0;
+        while i < lines.len() {
+            let line = i;
+            let active = conds.last().is_none_or(|c| c.active);
+            let mut tokens = tokenize(lines[i], i, &mut in_comment);
+            i += 1;
+
+            let hash = tokens.iter().position(|t| t.kind != TokenKind::Space);
+            if let Some(hash) = hash.filter(|&h| tokens[h].is("#")) {
+                // a directive goes on after a `\` at the end of the line
+                while lines[i - 1].ends_with('\\') && i < lines.len() {
+                    tokens.pop();
+                    tokens.extend(tokenize(lines[i], i, &mut in_comment));
+                    self.sources.push_line("", id, i - 1, vec![]);
+                    i += 1;
+                }
+                if lines[i - 1].ends_with('\\') {
+                    tokens.pop();
+                }
+                self.sources.push_line("", id, i - 1, vec![]);
+                self.directive(id, &tokens[hash..], active, &mut conds);
+                continue;
+            }
+            if !active {
+                self.sources.push_line("", id, line, vec![]);
+                continue;
+            }
+
+            // the arguments of a macro may span several lines
+            let mut joined = 0;
+            while self.is_open_invocation(&tokens)
+                && i < lines.len()
+                && !lines[i].trim_start().starts_with('#')
+            {
+                let end = Origin::Source(i - 1, lines[i - 1].len());
+                tokens.push(Token::new(TokenKind::Space, " ", end));
+                tokens.extend(tokenize(lines[i], i, &mut in_comment));
+                joined += 1;
+                i += 1;
+            }
+
+            let (text, segments) = self.expand_line(id, line, tokens);
+            self.sources.push_line(&text, id, line, segments);
+            for j in 1..=joined {
+                self.sources.push_line("", id, line + j, vec![]);
+            }
+        }
+
+        for cond in conds {
+            let message = "unterminated conditional directive";
+            self.error(ErrorCode::Directive, id, cond.line, cond.col, 1, message);
+        }
+    }
+
+    /// Whether the tokens invoke a function-like macro with more `(`s than `)`s
+    fn is_open_invocation(&self, tokens: &[Token]) -> bool {
+        let depth = tokens.iter().fold(0, |depth, t| match t.kind {
+            TokenKind::Other if t.is("(") => depth + 1,
+            TokenKind::Other if t.is(")") => depth - 1,
+            _ => depth,
+        });
+        depth > 0
+            && tokens.iter().any(|t| {
+                t.kind == TokenKind::Ident
+                    && matches!(self.macros.get(&t.text), Some(Macro::Function { .. }))
+            })
+    }
+
+    fn expand_line(
+        &mut self,
+        id: FileId,
+        line: usize,
+        tokens: Vec<Token>,
+    ) -> (String, Vec<Segment>) {
+        let name = self.sources.file(id).name().to_owned();
+        let mut expander = Expander::new(&self.macros, &name, line + 1);
+        let tokens = expander.expand(tokens);
+        for (message, token) in std::mem::take(&mut expander.errors) {
+            let (line, col) = token.origin.pos();
+            let code = ErrorCode::MacroArguments;
+            self.error(code, id, line, col, token.text.len(), message);
+        }
+
+        let mut text = String::new();
+        let mut segments: Vec<Segment> = vec![];
+        let mut end = None;
+        for token in tokens {
+            let (line, col) = token.origin.pos();
+            let verbatim = matches!(token.origin, Origin::Source(..));
+            let continued = match verbatim {
+                true => end == Some((line, col)),
+                false => segments
+                    .last()
+                    .is_some_and(|s| !s.verbatim && (s.line, s.col) == (line, col)),
+            };
+            if !continued {
+                segments.push(Segment {
+                    out: text.len(),
+                    line,
+                    col,
+                    verbatim,
+                });
+            }
+            end = verbatim.then_some((line, col + token.text.len()));
+
+            // a comment is blanked out to keep the columns
+            match token.kind {
+                TokenKind::Space if token.text.trim().is_empty() => text += &token.text,
+                TokenKind::Space => text += &" ".repeat(token.text.len()),
+                _ => text += &token.text,
+            }
+        }
+        (text, segments)
+    }
+
+    fn directive(&mut self, id: FileId, tokens: &[Token], active: bool, conds: &mut Vec<Cond>) {
+        let mut rest = tokens[1..].iter().filter(|t| t.kind != TokenKind::Space);
+        let Some(name) = rest.next() else {
+            return;
+        };
+        let (line, col) = tokens[0].origin.pos();
+        let after = tokens.iter().position(|t| std::ptr::eq(t, name)).unwrap() + 1;
+        let args = &tokens[after..];
+
+        match name.text.as_str() {
+            "if" | "ifdef" | "ifndef" => {
+                let value = active && self.condition(id, name, args);
+                conds.push(Cond {
+                    parent_active: active,
+                    active: value,
+                    taken: value,
+                    else_seen: false,
+                    line,
+                    col,
+                });
+            }
+            "elif" | "else" => {
+                let Some(cond) = conds.last_mut() else {
+                    return self.error_at(
+                        ErrorCode::Directive,
+                        id,
+                        name,
+                        format!("`#{}` without `#if`", name.text),
+                    );
+                };
+                if cond.else_seen {
+                    return self.error_at(
+                        ErrorCode::Directive,
+                        id,
+                        name,
+                        format!("`#{}` after `#else`", name.text),
+                    );
+                }
+                let (take, taken) = (cond.parent_active && !cond.taken, cond.taken);
+                let value = match name.text.as_str() {
+                    "elif" => take && self.condition(id, name, args),
+                    _ => take,
+                };
+                let cond = conds.last_mut().unwrap();
+                cond.active = value;
+                cond.taken = taken || value;
+                cond.else_seen = name.text == "else";
+            }
+            "endif" => {
+                if conds.pop().is_none() {
+                    self.error_at(ErrorCode::Directive, id, name, "`#endif` without `#if`");
+                }
+            }
+            // the other directives in skipped lines are not even checked
+            _ if !active => {}
+            "define" => self.define(id, name, args),
+            "undef" => match ident(args) {
+                Some(ident) => {
+                    self.macros.remove(&ident.text);
+                }
+                None => self.error_at(
+                    ErrorCode::Directive,
+                    id,
+                    name,
+                    "macro name must be an identifier",
+                ),
+            },
+            "include" => self.include(id, name, args),
+            "error" => {
+                let message = format!("#error {}", spell(args));
+                self.error_at(ErrorCode::ErrorDirective, id, name, message.trim_end());
+            }
+            "pragma" => {}
+            _ => self.error_at(
+                ErrorCode::Directive,
+                id,
+                name,
+                format!("invalid preprocessing directive `#{}`", name.text),
+            ),
+        }
+    }
+
+    /// Value of a conditional directive, which is false if it is invalid
+    fn condition(&mut self, id: FileId, directive: &Token, args: &[Token]) -> bool {
+        match directive.text.as_str() {
+            "ifdef" | "ifndef" => match ident(args) {
+                Some(ident) => self.macros.contains_key(&ident.text) == (directive.text == "ifdef"),
+                None => {
+                    self.error_at(
+                        ErrorCode::Directive,
+                        id,
+                        directive,
+                        "macro name must be an identifier",
+                    );
+                    false
+                }
+            },
+            _ => match self.eval(id, args) {
+                Ok(value) => value,
+                Err(message) => {
+                    self.error_at(ErrorCode::Directive, id, directive, message);
+                    false
+                }
+            },
+        }
+    }
+
+    /// Evaluate the expression of `#if`, where an identifier left after expansion is `0`
+    fn eval(&mut self, id: FileId, args: &[Token]) -> Result<bool, String> {
+        let mut tokens = vec![];
+        let mut iter = args.iter();
+        while let Some(t) = iter.next() {
+            if !(t.kind == TokenKind::Ident && t.text == "defined") {
+                tokens.push(t.clone());
+                continue;
+            }
+            let ident = match next_solid(&mut iter) {
+                Some(open) if open.is("(") => {
+                    let ident = next_solid(&mut iter).filter(|t| t.kind == TokenKind::Ident);
+                    match next_solid(&mut iter) {
+                        Some(close) if close.is(")") => ident,
+                        _ => return Err("missing `)` after `defined`".to_string()),
+                    }
+                }
+                ident => ident.filter(|t| t.kind == TokenKind::Ident),
+            };
+            let Some(ident) = ident else {
+                return Err("operator `defined` requires an identifier".to_string());
+            };
+            let value = self.macros.contains_key(&ident.text) as i32;
+            tokens.push(Token::new(TokenKind::Other, value.to_string(), t.origin));
+        }
+
+        let (line, _) = args.first().map_or((0, 0), |t| t.origin.pos());
+        let name = self.sources.file(id).name().to_owned();
+        let mut expander = Expander::new(&self.macros, &name, line + 1);
+        let tokens = expander.expand(tokens);
+        if let Some((message, _)) = expander.errors.into_iter().next() {
+            return Err(message);
+        }
+        let text = tokens
+            .iter()
+            .map(|t| match t.kind {
+                TokenKind::Ident => "0",
+                _ => t.text.as_str(),
+            })
+            .collect::<String>();
+        if text.trim().is_empty() {
+            return Err("expected an expression in the condition".to_string());
+        }
+
+        let mut errors = vec![];
+        let expr = sysy::CondExpParser::new().parse(&mut errors, &text, &text);
+        match expr {
+            Ok(expr) if errors.is_empty() => expr
+                .const_eval(&Evaluator::new())
+                .map(|value| value.is_true())
+                .ok_or_else(|| "expected a constant expression in the condition".to_string()),
+            _ => Err("invalid expression in the condition".to_string()),
+        }
+    }
+
+    fn define(&mut self, id: FileId, directive: &Token, args: &[Token]) {
+        let Some(start) = args.iter().position(|t| t.kind != TokenKind::Space) else {
+            return self.error_at(ErrorCode::Directive, id, directive, "macro name missing");
+        };
+        let name = &args[start];
+        if name.kind != TokenKind::Ident {
+            return self.error_at(
+                ErrorCode::Directive,
+                id,
+                name,
+                "macro name must be an identifier",
+            );
+        }
+        if matches!(name.text.as_str(), "defined" | "__LINE__" | "__FILE__") {
+            let message = format!("`{}` cannot be used as a macro name", name.text);
+            return self.error_at(ErrorCode::Directive, id, name, message);
+        }
+
+        let trim = |body: &[Token]| {
+            let solid = |t: &&Token| t.kind != TokenKind::Space;
+            let start = body.iter().position(|t| solid(&t)).unwrap_or(body.len());
+            let end = body
+                .iter()
+                .rposition(|t| solid(&t))
+                .map_or(start, |end| end + 1);
+            body[start..end].to_vec()
+        };
+        // the parameters follow the name without any blank
+        let rest = &args[start + 1..];
+        let m = match rest.first() {
+            Some(open) if open.is("(") => {
+                let Some(close) = rest.iter().position(|t| t.is(")")) else {
+                    return self.error_at(
+                        ErrorCode::Directive,
+                        id,
+                        open,
+                        "missing `)` in macro parameter list",
+                    );
+                };
+                let list = spell(&rest[1..close]);
+                let mut params: Vec<String> = vec![];
+                let mut variadic = false;
+                for param in list.split(',').map(str::trim).filter(|_| !list.is_empty()) {
+                    let is_ident = param.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                        && param.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                    let message = if variadic {
+                        "`...` must be the last macro parameter".to_string()
+                    } else if param == "..." {
+                        variadic = true;
+                        params.push("__VA_ARGS__".to_string());
+                        continue;
+                    } else if !is_ident {
+                        format!("invalid macro parameter `{}`", param)
+                    } else if params.iter().any(|p| p == param) {
+                        format!("duplicate macro parameter `{}`", param)
+                    } else {
+                        params.push(param.to_string());
+                        continue;
+                    };
+                    return self.error_at(ErrorCode::Directive, id, open, message);
+                }
+                let body = trim(&rest[close + 1..]);
+                Macro::Function {
+                    params,
+                    variadic,
+                    body,
+                }
+            }
+            _ => Macro::Object(trim(rest)),
+        };
+        self.macros.insert(name.text.clone(), m);
+    }
+
+    fn include(&mut self, id: FileId, directive: &Token, args: &[Token]) {
+        let spelling = spell(args);
+        let (header, quoted) = match spelling.as_bytes() {
+            [b'"', .., b'"'] => (&spelling[1..spelling.len() - 1], true),
+            [b'<', .., b'>'] => (&spelling[1..spelling.len() - 1], false),
+            _ => {
+                return self.error_at(
+                    ErrorCode::Include,
+                    id,
+                    directive,
+                    "`#include` expects \"FILENAME\" or <FILENAME>",
+                )
+            }
+        };
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            return self.error_at(
+                ErrorCode::Include,
+                id,
+                directive,
+                "`#include` nested too deeply",
+            );
+        }
+
+        // a quoted header is looked for next to the including file first
+        let current = Path::new(self.sources.file(id).name())
+            .parent()
+            .map(Path::to_path_buf);
+        let dirs = current
+            .filter(|_| quoted)
+            .into_iter()
+            .chain(self.include_dirs.iter().cloned());
+        let found = dirs
+            .map(|dir| dir.join(header))
+            .find_map(|path| Some((read_to_string(&path).ok()?, path)));
+        let Some((src, path)) = found else {
+            if BUILTIN_HEADERS.contains(&header) {
+                return;
+            }
+            return self.error_at(
+                ErrorCode::Include,
+                id,
+                directive,
+                format!("file `{}` not found", header),
+            );
+        };
+
+        let file = SourceFile::new(path.display().to_string(), src);
+        let included = self.sources.add_file(file);
+        self.depth += 1;
+        self.process_file(included);
+        self.depth -= 1;
+    }
+
+    fn error(
+        &mut self,
+        code: ErrorCode,
+        id: FileId,
+        line: usize,
+        col: usize,
+        len: usize,
+        message: impl Into<String>,
+    ) {
+        let start = self.sources.file(id).line_start(line) + col;
+        let diag = Diagnostic::error(code, message).with_span(Span::new(start, start + len));
+        self.diags.push(diag.in_file(id));
+    }
+
+    fn error_at(&mut self, code: ErrorCode, id: FileId, token: &Token, message: impl Into<String>) {
+        let (line, col) = token.origin.pos();
+        self.error(code, id, line, col, token.text.len(), message);
+    }
+}
+
+/// The only token of the arguments of a directive, if it is an identifier
+fn ident(args: &[Token]) -> Option<&Token> {
+    let mut solid = args.iter().filter(|t| t.kind != TokenKind::Space);
+    match (solid.next(), solid.next()) {
+        (Some(t), None) if t.kind == TokenKind::Ident => Some(t),
+        _ => None,
+    }
+}
+
+fn next_solid<'t>(iter: &mut std::slice::Iter<'t, Token>) -> Option<&'t Token> {
+    iter.find(|t| t.kind != TokenKind::Space)
+}
@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+
+use strum_macros::Display;
+
+use super::typename::{KEYWORDS, TYPE_KEYWORDS};
+use crate::diag::Span;
+
+/// Punctuators of more than one character, longest first
+const PUNCTUATORS: &[&str] = &[
+    "<<=", ">>=", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "&=", "|=", "^=",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident,
+    /// a number, a character or string literal, or a punctuator
+    Other,
+    /// blanks or a comment, which separate tokens
+    Space,
+}
+
+/// Where a token comes from, as the zero-based line and byte column in the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// written there
+    Source(usize, usize),
+    /// in the expansion of the macro invoked there
+    Expansion(usize, usize),
+}
+
+impl Origin {
+    pub fn pos(self) -> (usize, usize) {
+        match self {
+            Self::Source(line, col) | Self::Expansion(line, col) => (line, col),
+        }
+    }
+}
+
+/// A preprocessing token
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub origin: Origin,
+    /// macros whose expansion the token comes from, which are not expanded again in it
+    pub hidden: HashSet<String>,
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, text: impl Into<String>, origin: Origin) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+            origin,
+            hidden: HashSet::new(),
+        }
+    }
+
+    pub fn is(&self, text: &str) -> bool {
+        self.kind != TokenKind::Space && self.text == text
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Split a line into tokens, where `in_comment` tracks whether a block comment is open
+pub fn tokenize(text: &str, line: usize, in_comment: &mut bool) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let c = rest.chars().next().unwrap();
+        let (kind, len) = if *in_comment || rest.starts_with("/*") {
+            let from = if *in_comment { 0 } else { 2 };
+            *in_comment = false;
+            match rest[from..].find("*/") {
+                Some(end) => (TokenKind::Space, from + end + 2),
+                None => {
+                    *in_comment = true;
+                    (TokenKind::Space, rest.len())
+                }
+            }
+        } else if rest.starts_with("//") {
+            (TokenKind::Space, rest.len())
+        } else if c.is_whitespace() {
+            let len = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            (TokenKind::Space, len)
+        } else if c == '"' || c == '\'' {
+            // an unterminated literal is left to the parser to complain about
+            let mut escaped = false;
+            let end = rest[1..].find(|d: char| {
+                let end = !escaped && d == c;
+                escaped = !escaped && d == '\\';
+                end
+            });
+            (TokenKind::Other, end.map_or(rest.len(), |end| end + 2))
+        } else if is_ident_start(c) {
+            let len = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+            (TokenKind::Ident, len)
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|d: char| d.is_ascii_digit()))
+        {
+            // a number with its suffix and the sign of its exponent, e.g. `1.5e-3f`
+            let mut prev = c;
+            let len = rest
+                .char_indices()
+                .skip(1)
+                .find(|&(_, d)| {
+                    let exp_sign = matches!(d, '+' | '-') && matches!(prev, 'e' | 'E' | 'p' | 'P');
+                    prev = d;
+                    !(is_ident_char(d) || d == '.' || exp_sign)
+                })
+                .map_or(rest.len(), |(i, _)| i);
+            (TokenKind::Other, len)
+        } else if rest.starts_with("##") {
+            (TokenKind::Other, 2)
+        } else {
+            (TokenKind::Other, c.len_utf8())
+        };
+        tokens.push(Token::new(kind, &rest[..len], Origin::Source(line, i)));
+        i += len;
+    }
+    tokens
+}
+
+/// Text of the tokens with the blanks collapsed, e.g. for the argument of `#`
+pub fn spell(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut space = false;
+    for token in tokens {
+        match token.kind {
+            TokenKind::Space => space = !text.is_empty(),
+            _ => {
+                if space {
+                    text.push(' ');
+                    space = false;
+                }
+                text += &token.text;
+            }
+        }
+    }
+    text
+}
+
+/// The kind of a token of the parser, as listed by `--emit=tokens`
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum LexemeKind {
+    #[strum(serialize = "keyword")]
+    Keyword,
+    #[strum(serialize = "identifier")]
+    Identifier,
+    #[strum(serialize = "number")]
+    Number,
+    #[strum(serialize = "character")]
+    Character,
+    #[strum(serialize = "string")]
+    String,
+    #[strum(serialize = "punctuator")]
+    Punctuator,
+}
+
+/// Split the preprocessed text into the tokens of the parser, with their spans in the text
+pub fn lex(text: &str) -> Vec<(LexemeKind, Span)> {
+    let mut lexemes = vec![];
+    let mut in_comment = false;
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let mut tokens = tokenize(line, 0, &mut in_comment).into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            let (_, col) = token.origin.pos();
+            let mut len = token.text.len();
+            let kind = match token.kind {
+                TokenKind::Space => continue,
+                TokenKind::Ident
+                    if KEYWORDS.contains(&token.text.as_str())
+                        || TYPE_KEYWORDS.contains(&token.text.as_str()) =>
+                {
+                    LexemeKind::Keyword
+                }
+                TokenKind::Ident => LexemeKind::Identifier,
+                TokenKind::Other => match token.text.chars().next() {
+                    Some('"') => LexemeKind::String,
+                    Some('\'') => LexemeKind::Character,
+                    Some(c) if c.is_ascii_digit() || c == '.' && len > 1 => LexemeKind::Number,
+                    _ => {
+                        // the punctuator may go on in the following tokens
+                        if let Some(p) = PUNCTUATORS.iter().find(|p| line[col..].starts_with(*p)) {
+                            len = p.len();
+                            while tokens.next_if(|t| t.origin.pos().1 < col + len).is_some() {}
+                        }
+                        LexemeKind::Punctuator
+                    }
+                },
+            };
+            lexemes.push((kind, Span::new(start + col, start + col + len)));
+        }
+        start += line.len();
+    }
+    lexemes
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use super::token::*;
+
+/// Keywords after which an identifier is declared rather than used, e.g. `x` in `int x`
+pub(super) const TYPE_KEYWORDS: &[&str] = &["int", "unsigned", "long", "float", "void"];
+
+pub(super) const KEYWORDS: &[&str] = &[
+    "const", "typedef", "if", "else", "while", "for", "do", "return", "break", "continue",
+    "switch", "case", "default", "struct", "enum", "static", "extern",
+];
+
+/// Mark the uses of the names defined by `typedef` in the preprocessed text with a leading
+/// `$`, so that the parser can tell them from other identifiers
+pub fn mark_type_names(text: &str) -> String {
+    let mut marked = text.as_bytes().to_vec();
+    // whether each name is a type, in each scope from the outermost one
+    let mut scopes: Vec<HashMap<String, bool>> = vec![HashMap::new()];
+    // names declared inside parentheses, e.g. parameters, which are in the next block
+    let mut pending: Vec<(String, bool)> = vec![];
+    // nesting of the `typedef` being read, if any
+    let mut typedef = None;
+    // whether the previous tokens are a type, after which an identifier is declared
+    let mut after_type = false;
+    // nesting of the brackets, and that of the declaration being read
+    let mut depth = 0usize;
+    let mut parens = 0usize;
+    let mut decl_depth = None;
+    // the previous two tokens, where `->` is two tokens
+    let mut prev = (String::new(), String::new());
+
+    let mut in_comment = false;
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        for token in tokenize(line, 0, &mut in_comment) {
+            if token.kind == TokenKind::Space {
+                continue;
+            }
+            let text = token.text.as_str();
+            match token.kind {
+                // a member or a tag, which has a namespace of its own
+                _ if prev.1 == "." || (prev.0 == "-" && prev.1 == ">") => after_type = false,
+                TokenKind::Ident if matches!(prev.1.as_str(), "struct" | "enum") => {
+                    after_type = true
+                }
+                TokenKind::Ident if TYPE_KEYWORDS.contains(&text) => after_type = true,
+                TokenKind::Ident if text == "typedef" => typedef = Some(depth),
+                TokenKind::Ident if text == "const" => {}
+                TokenKind::Ident if KEYWORDS.contains(&text) => after_type = false,
+                TokenKind::Ident if after_type => {
+                    let is_type = typedef == Some(depth);
+                    match parens {
+                        0 => scopes.last_mut().unwrap().insert(text.to_string(), is_type),
+                        _ => {
+                            pending.push((text.to_string(), is_type));
+                            None
+                        }
+                    };
+                    after_type = false;
+                    decl_depth = Some(depth);
+                }
+                TokenKind::Ident => {
+                    let is_type = scopes.iter().rev().find_map(|s| s.get(text));
+                    after_type = is_type == Some(&true);
+                    if after_type {
+                        let (_, col) = token.origin.pos();
+                        marked[start + col] = b'$';
+                    }
+                }
+                _ => match text {
+                    "*" => {}
+                    "," => after_type = decl_depth == Some(depth),
+                    "(" | "[" => {
+                        depth += 1;
+                        parens += (text == "(") as usize;
+                        after_type = false;
+                    }
+                    ")" | "]" => {
+                        depth = depth.saturating_sub(1);
+                        parens = parens.saturating_sub((text == ")") as usize);
+                        after_type = false;
+                    }
+                    "{" => {
+                        depth += 1;
+                        scopes.push(pending.drain(..).collect());
+                        after_type = false;
+                    }
+                    "}" => {
+                        depth = depth.saturating_sub(1);
+                        if scopes.len() > 1 {
+                            scopes.pop();
+                        }
+                        // the names after a `struct` or an `enum` defined in a `typedef`
+                        after_type = typedef == Some(depth);
+                    }
+                    ";" => {
+                        if parens == 0 {
+                            pending.clear();
+                        }
+                        if typedef == Some(depth) {
+                            typedef = None;
+                        }
+                        decl_depth = None;
+                        after_type = false;
+                    }
+                    _ => after_type = false,
+                },
+            }
+            prev = (std::mem::take(&mut prev.1), token.text);
+        }
+        start += line.len();
+    }
+    String::from_utf8(marked).unwrap()
+}
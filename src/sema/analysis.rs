@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use crate::ast::*;
+use crate::diag::{Diagnostic, Diagnostics, SourceFile, SourceMap};
+use crate::preprocess::{mark_type_names, preprocess};
+use crate::sysy;
+
+use super::*;
+
+/// The result of the front end on a source file, which goes as far as it can
+#[derive(Debug)]
+pub struct Analysis {
+    pub sources: SourceMap,
+    /// all the diagnostics, before the warning options are applied
+    pub diags: Diagnostics,
+    /// the checked program and its symbols, unless it failed to be preprocessed or parsed
+    pub program: Option<(CompUnit, SymbolTable)>,
+    /// the program recovered from syntax errors, with only its names resolved
+    pub recovered: Option<CompUnit>,
+    /// the declarations and uses of the names, if it was parsed
+    pub refs: Vec<NameRef>,
+}
+
+/// Preprocess and parse the source file, where the headers it includes are also looked for in
+/// the given directories, recovering the program from syntax errors if possible
+pub fn parse(
+    file: SourceFile,
+    include_dirs: &[PathBuf],
+) -> (SourceMap, Diagnostics, Option<CompUnit>) {
+    let (sources, mut diags) = preprocess(file, include_dirs);
+    if diags.has_errors() {
+        return (sources, diags, None);
+    }
+
+    let text = mark_type_names(sources.text());
+    let mut errors = vec![];
+    let ast = sysy::CompUnitParser::new().parse(&mut errors, sources.text(), &text);
+    for recovery in errors {
+        diags.push(Diagnostic::from_parse_error(recovery.error, sources.text()));
+    }
+    let ast = match ast {
+        Ok(ast) => Some(ast),
+        Err(err) => {
+            diags.push(Diagnostic::from_parse_error(err, sources.text()));
+            None
+        }
+    };
+    (sources, diags, ast)
+}
+
+/// Preprocess, parse and check the source file
+pub fn analyze(file: SourceFile, include_dirs: &[PathBuf]) -> Analysis {
+    let (sources, mut diags, ast) = parse(file, include_dirs);
+    let Some(mut ast) = ast else {
+        return Analysis {
+            sources,
+            diags,
+            program: None,
+            recovered: None,
+            refs: vec![],
+        };
+    };
+    // semantic analysis on a partially parsed program only produces noise
+    if diags.has_errors() {
+        let mut name_manager = NameManager::new();
+        ast.accept(&mut name_manager);
+        return Analysis {
+            sources,
+            diags,
+            program: None,
+            recovered: Some(ast),
+            refs: name_manager.refs,
+        };
+    }
+
+    let mut name_manager = NameManager::new();
+    ast.accept(&mut name_manager);
+    diags.extend(name_manager.diags);
+
+    let mut typedef_resolver = TypedefResolver::new();
+    ast.accept(&mut typedef_resolver);
+    diags.extend(typedef_resolver.diags);
+
+    let mut evaluator = Evaluator::new();
+    ast.accept(&mut evaluator);
+    diags.extend(evaluator.diags);
+
+    let mut symbols = SymbolTable::new();
+    ast.accept(&mut symbols);
+    diags.extend(std::mem::take(&mut symbols.diags));
+
+    let mut type_checker = TypeChecker::new(&symbols);
+    ast.accept(&mut type_checker);
+    diags.extend(type_checker.diags);
+
+    let mut flow_checker = FlowChecker::new();
+    ast.accept(&mut flow_checker);
+    diags.extend(flow_checker.diags);
+
+    Analysis {
+        sources,
+        diags,
+        program: Some((ast, symbols)),
+        recovered: None,
+        refs: name_manager.refs,
+    }
+}
@@ -1,92 +1,675 @@
+use smallvec::{smallvec, SmallVec};
 use std::collections::HashMap;
 
+use super::init::check_init_list;
+use super::ty::{DimTy, Type, TypeKind};
 use crate::ast::*;
+use crate::diag::{Diagnostic, Diagnostics, ErrorCode, Warning};
+use crate::irgen::utils::eval_array;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue {
+    Int(i32),
+    UInt(u32),
+    Long(i64),
+    Float(f32),
+}
+
+impl ConstValue {
+    pub fn as_int(self) -> i32 {
+        match self {
+            Self::Int(i) => i,
+            Self::UInt(u) => u as i32,
+            Self::Long(l) => l as i32,
+            Self::Float(f) => f as i32,
+        }
+    }
+
+    pub fn as_uint(self) -> u32 {
+        match self {
+            Self::Int(i) => i as u32,
+            Self::UInt(u) => u,
+            Self::Long(l) => l as u32,
+            Self::Float(f) => f as u32,
+        }
+    }
+
+    pub fn as_long(self) -> i64 {
+        match self {
+            Self::Int(i) => i as i64,
+            Self::UInt(u) => u as i64,
+            Self::Long(l) => l,
+            Self::Float(f) => f as i64,
+        }
+    }
+
+    pub fn as_float(self) -> f32 {
+        match self {
+            Self::Int(i) => i as f32,
+            Self::UInt(u) => u as f32,
+            Self::Long(l) => l as f32,
+            Self::Float(f) => f,
+        }
+    }
+
+    pub fn is_true(self) -> bool {
+        match self {
+            Self::Int(i) => i != 0,
+            Self::UInt(u) => u != 0,
+            Self::Long(l) => l != 0,
+            Self::Float(f) => f != 0.0,
+        }
+    }
+
+    /// Convert the value to the given arithmetic type
+    pub fn cast(self, ty: &Type) -> Self {
+        match ty.kind() {
+            TypeKind::Float => Self::Float(self.as_float()),
+            TypeKind::LongLong => Self::Long(self.as_long()),
+            TypeKind::Unsigned => Self::UInt(self.as_uint()),
+            _ => Self::Int(self.as_int()),
+        }
+    }
+
+    /// Convert the value to the given arithmetic type, with a warning located at `span` if it is
+    /// a `long long` that does not fit the integer type
+    pub fn cast_checked(self, ty: &Type, span: Span) -> (Self, Option<Diagnostic>) {
+        let converted = self.cast(ty);
+        let fits = match (self, converted) {
+            (Self::Long(l), Self::Int(_)) => i32::try_from(l).is_ok(),
+            (Self::Long(l), Self::UInt(_)) => (i32::MIN as i64..=u32::MAX as i64).contains(&l),
+            _ => true,
+        };
+        if fits {
+            return (converted, None);
+        }
+        let message = format!(
+            "overflow in conversion from `long long` to `{}` changes value from `{}` to `{}`",
+            ty,
+            self.as_long(),
+            converted.as_long()
+        );
+        let diag = Diagnostic::warning(Warning::Overflow, message).with_span(span);
+        (converted, Some(diag))
+    }
+
+    /// Convert the value to the type of the other one
+    fn cast_like(self, other: Self) -> Self {
+        match other {
+            Self::Int(_) => Self::Int(self.as_int()),
+            Self::UInt(_) => Self::UInt(self.as_uint()),
+            Self::Long(_) => Self::Long(self.as_long()),
+            Self::Float(_) => Self::Float(self.as_float()),
+        }
+    }
+
+    /// Convert both values to their common type
+    fn promote(self, other: Self) -> (Self, Self) {
+        let rank = |v: Self| match v {
+            Self::Int(_) => 0,
+            Self::UInt(_) => 1,
+            Self::Long(_) => 2,
+            Self::Float(_) => 3,
+        };
+        match rank(self) >= rank(other) {
+            true => (self, other.cast_like(self)),
+            false => (self.cast_like(other), other),
+        }
+    }
+
+    /// The single word of the value converted to the type, as in Koopa IR
+    pub fn to_bits(self, ty: &Type) -> i32 {
+        self.to_words(ty)[0]
+    }
+
+    /// Value of the given type from its words in Koopa IR, from the lowest one
+    pub fn from_words(words: &[i32], ty: &Type) -> Self {
+        match ty.kind() {
+            TypeKind::Float => Self::Float(f32::from_bits(words[0] as u32)),
+            TypeKind::Unsigned => Self::UInt(words[0] as u32),
+            TypeKind::LongLong => Self::Long((words[0] as u32 as i64) | ((words[1] as i64) << 32)),
+            _ => Self::Int(words[0]),
+        }
+    }
+
+    /// The words of the value converted to the type, from the lowest one
+    pub fn to_words(self, ty: &Type) -> SmallVec<[i32; 2]> {
+        match self.cast(ty) {
+            Self::Int(i) => smallvec![i],
+            Self::UInt(u) => smallvec![u as i32],
+            Self::Long(l) => smallvec![l as i32, (l >> 32) as i32],
+            Self::Float(f) => smallvec![f.to_bits() as i32],
+        }
+    }
+}
+
+impl From<ConstValue> for ExprKind {
+    fn from(value: ConstValue) -> Self {
+        match value {
+            ConstValue::Int(i) => ExprKind::Integer(i),
+            ConstValue::UInt(u) => ExprKind::Unsigned(u),
+            ConstValue::Long(l) => ExprKind::Long(l),
+            ConstValue::Float(f) => ExprKind::Float(f),
+        }
+    }
+}
+
+pub fn eval_float_binary(op: BinaryOp, lhs: f32, rhs: f32) -> Option<ConstValue> {
+    Some(match op {
+        BinaryOp::Add => ConstValue::Float(lhs + rhs),
+        BinaryOp::Sub => ConstValue::Float(lhs - rhs),
+        BinaryOp::Mul => ConstValue::Float(lhs * rhs),
+        BinaryOp::Div => ConstValue::Float(lhs / rhs),
+        BinaryOp::Mod
+        | BinaryOp::BitAnd
+        | BinaryOp::BitOr
+        | BinaryOp::BitXor
+        | BinaryOp::Shl
+        | BinaryOp::Shr => return None,
+        BinaryOp::And => ConstValue::Int((lhs != 0.0 && rhs != 0.0) as i32),
+        BinaryOp::Or => ConstValue::Int((lhs != 0.0 || rhs != 0.0) as i32),
+        BinaryOp::Eq => ConstValue::Int((lhs == rhs) as i32),
+        BinaryOp::Neq => ConstValue::Int((lhs != rhs) as i32),
+        BinaryOp::Lt => ConstValue::Int((lhs < rhs) as i32),
+        BinaryOp::Le => ConstValue::Int((lhs <= rhs) as i32),
+        BinaryOp::Gt => ConstValue::Int((lhs > rhs) as i32),
+        BinaryOp::Ge => ConstValue::Int((lhs >= rhs) as i32),
+    })
+}
+
+/// Exact result of an arithmetic operation of integers, or `None` for a division by zero or
+/// another operator
+fn eval_exact(op: BinaryOp, lhs: i128, rhs: i128) -> Option<i128> {
+    match op {
+        BinaryOp::Add => Some(lhs + rhs),
+        BinaryOp::Sub => Some(lhs - rhs),
+        BinaryOp::Mul => Some(lhs * rhs),
+        BinaryOp::Div => lhs.checked_div(rhs),
+        _ => None,
+    }
+}
 
 pub trait ConstEval {
-    fn const_eval(&self, eval: &Evaluator) -> Option<i32>;
+    fn const_eval(&self, eval: &Evaluator) -> Option<ConstValue>;
 }
 
 impl ConstEval for UnaryExpr {
-    fn const_eval(&self, eval: &Evaluator) -> Option<i32> {
+    fn const_eval(&self, eval: &Evaluator) -> Option<ConstValue> {
         match self {
-            Self::Unary(op, exp) => exp.const_eval(eval).map(|opr| eval_unary(*op, opr)),
+            Self::Unary(op, exp) => exp.const_eval(eval).and_then(|opr| match (op, opr) {
+                (_, ConstValue::Int(i)) => Some(ConstValue::Int(eval_unary(*op, i))),
+                (UnaryOp::Not, ConstValue::UInt(u)) => Some(ConstValue::Int((u == 0) as i32)),
+                (_, ConstValue::UInt(u)) => Some(ConstValue::UInt(eval_unsigned_unary(*op, u))),
+                (UnaryOp::Not, ConstValue::Long(l)) => Some(ConstValue::Int((l == 0) as i32)),
+                (_, ConstValue::Long(l)) => Some(ConstValue::Long(eval_long_unary(*op, l))),
+                (UnaryOp::Nop, ConstValue::Float(f)) => Some(ConstValue::Float(f)),
+                (UnaryOp::Neg, ConstValue::Float(f)) => Some(ConstValue::Float(-f)),
+                (UnaryOp::Not, ConstValue::Float(f)) => Some(ConstValue::Int((f == 0.0) as i32)),
+                (UnaryOp::BitNot, ConstValue::Float(_)) => None,
+            }),
             Self::Call(_) => None,
         }
     }
 }
 
 impl ConstEval for BinaryExpr {
-    fn const_eval(&self, eval: &Evaluator) -> Option<i32> {
+    fn const_eval(&self, eval: &Evaluator) -> Option<ConstValue> {
         let lhs = self.lhs.const_eval(eval);
         let rhs = self.rhs.const_eval(eval);
         if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
-            return Some(eval_binary(self.op, lhs, rhs));
+            let (lhs, rhs) = match (self.op, lhs.promote(rhs)) {
+                // a shift of integers has the type of its left operand
+                (BinaryOp::Shl | BinaryOp::Shr, (_, r)) if !matches!(r, ConstValue::Float(_)) => {
+                    (lhs, rhs.cast_like(lhs))
+                }
+                (_, operands) => operands,
+            };
+            let value = match (lhs, rhs) {
+                (ConstValue::Int(lhs), ConstValue::Int(rhs)) => {
+                    ConstValue::Int(eval_binary(self.op, lhs, rhs)?)
+                }
+                (ConstValue::UInt(lhs), ConstValue::UInt(rhs)) => {
+                    ConstValue::UInt(eval_unsigned_binary(self.op, lhs, rhs)?)
+                }
+                (ConstValue::Long(lhs), ConstValue::Long(rhs)) => {
+                    ConstValue::Long(eval_long_binary(self.op, lhs, rhs)?)
+                }
+                (lhs, rhs) => return eval_float_binary(self.op, lhs.as_float(), rhs.as_float()),
+            };
+            return Some(match self.op.is_logical() {
+                true => ConstValue::Int(value.as_int()),
+                false => value,
+            });
         }
-        if matches!(self.op, BinaryOp::And) && (matches!(lhs, Some(0)) || matches!(rhs, Some(0))) {
-            Some(0)
+        let is_true = |v: Option<ConstValue>| v.map(ConstValue::is_true);
+        if matches!(self.op, BinaryOp::And)
+            && (is_true(lhs) == Some(false) || is_true(rhs) == Some(false))
+        {
+            Some(ConstValue::Int(0))
         } else if matches!(self.op, BinaryOp::Or)
-            && (matches!(lhs, Some(i) if i != 0) || matches!(rhs, Some(i) if i !=0))
+            && (is_true(lhs) == Some(true) || is_true(rhs) == Some(true))
         {
-            Some(1)
+            Some(ConstValue::Int(1))
         } else {
             None
         }
     }
 }
 
+impl ConstEval for Conditional {
+    fn const_eval(&self, eval: &Evaluator) -> Option<ConstValue> {
+        let cond = self.cond.const_eval(eval)?;
+        // both values are needed to know the type of the result
+        let (true_val, false_val) = (
+            self.true_val.const_eval(eval)?,
+            self.false_val.const_eval(eval)?,
+        );
+        let (true_val, false_val) = true_val.promote(false_val);
+        Some(if cond.is_true() { true_val } else { false_val })
+    }
+}
+
 impl ConstEval for Expr {
-    fn const_eval(&self, eval: &Evaluator) -> Option<i32> {
-        match self {
-            Self::Integer(i) => Some(*i),
-            Self::Binary(bxp) => bxp.const_eval(eval),
-            Self::Unary(uxp) => uxp.const_eval(eval),
-            Self::LVal(lval) => eval.get(lval.ident.as_str()).copied(),
-            Self::Error => panic!("expected an expression"),
+    fn const_eval(&self, eval: &Evaluator) -> Option<ConstValue> {
+        match &self.kind {
+            ExprKind::Integer(_)
+            | ExprKind::Unsigned(_)
+            | ExprKind::Long(_)
+            | ExprKind::Float(_) => self.as_const(),
+            ExprKind::Binary(bxp) => bxp.const_eval(eval),
+            ExprKind::Unary(uxp) => uxp.const_eval(eval),
+            ExprKind::LVal(lval) if lval.dims.is_empty() && lval.members.is_empty() => {
+                eval.get(lval.ident.as_str())
+            }
+            ExprKind::LVal(lval) if lval.members.is_empty() => eval.get_elem(lval),
+            ExprKind::LVal(_) | ExprKind::Member(..) | ExprKind::String(_) => None,
+            ExprKind::CompoundAssign(_) | ExprKind::Deref(_) | ExprKind::AddrOf(_) => None,
+            ExprKind::Conditional(c) => c.const_eval(eval),
+            ExprKind::Cast(e) => e.const_eval(eval).map(|v| v.cast(self.ty())),
+            ExprKind::Error => None,
         }
     }
 }
 
 impl Expr {
-    pub fn get_i32(&self) -> i32 {
-        if let Self::Integer(i) = self {
-            *i
-        } else {
-            panic!("attempt to retrieve an integer from a non-const expression")
+    pub fn get_const(&self) -> ConstValue {
+        self.as_const()
+            .expect("attempt to retrieve a constant from a non-const expression")
+    }
+
+    pub fn as_const(&self) -> Option<ConstValue> {
+        match self.kind {
+            ExprKind::Integer(i) => Some(ConstValue::Int(i)),
+            ExprKind::Unsigned(u) => Some(ConstValue::UInt(u)),
+            ExprKind::Long(l) => Some(ConstValue::Long(l)),
+            ExprKind::Float(f) => Some(ConstValue::Float(f)),
+            _ => None,
         }
     }
 }
 
-pub type Evaluator<'ast> = HashMap<&'ast str, i32>;
+#[derive(Debug, Default)]
+pub struct Evaluator<'ast> {
+    values: HashMap<&'ast str, ConstValue>,
+    /// `const` arrays of arithmetic elements
+    arrays: HashMap<String, ConstArray>,
+    /// numbers of dimensions of all the `const` arrays and the `extern const` variables
+    ranks: HashMap<String, usize>,
+    pub diags: Diagnostics,
+}
+
+#[derive(Debug)]
+struct ConstArray {
+    dims: DimTy,
+    elem_ty: Type,
+    /// the words of the elements in row-major order as in Koopa IR, where the trailing zeros
+    /// may be left out
+    words: Vec<i32>,
+}
+
+/// A constant index into a `const` array past its bounds
+#[derive(Debug)]
+struct OutOfBounds {
+    index: i64,
+    len: usize,
+    span: Span,
+}
+
+impl<'ast> Evaluator<'ast> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<ConstValue> {
+        self.values.get(name).copied()
+    }
+
+    /// Value of an element of a `const` array, if all the indices are constants in bounds
+    pub fn get_elem(&self, lval: &LVal) -> Option<ConstValue> {
+        let array = self.arrays.get(lval.ident.as_str())?;
+        let index = self.elem_index(lval)?.ok()?;
+        let size = array.elem_ty.size();
+        let mut words: SmallVec<[i32; 2]> = smallvec![0; size];
+        for (i, w) in words.iter_mut().enumerate() {
+            *w = array.words.get(index * size + i).copied().unwrap_or(0);
+        }
+        Some(ConstValue::from_words(&words, &array.elem_ty))
+    }
+
+    /// Position of an element of a `const` array in row-major order, if all the indices are
+    /// constants, or the first index out of bounds
+    fn elem_index(&self, lval: &LVal) -> Option<Result<usize, OutOfBounds>> {
+        let array = self.arrays.get(lval.ident.as_str())?;
+        if lval.dims.len() != array.dims.len() {
+            return None;
+        }
+        let mut index = 0;
+        for (d, &len) in lval.dims.iter().zip(&array.dims) {
+            let i = match d.const_eval(self)? {
+                ConstValue::Float(_) => return None,
+                i => i.as_long(),
+            };
+            if !(0..len as i64).contains(&i) {
+                let span = d.span;
+                return Some(Err(OutOfBounds {
+                    index: i,
+                    len,
+                    span,
+                }));
+            }
+            index = index * len + i as usize;
+        }
+        Some(Ok(index))
+    }
+
+    /// Evaluate a `const` array, unless its elements are not arithmetic or it is malformed,
+    /// which is reported later
+    fn eval_const_array(&self, c: &ConstDecl) -> Option<ConstArray> {
+        /// Copy of the initializer with the elements folded
+        fn fold(init: &InitVal, eval: &Evaluator) -> Option<InitVal> {
+            Some(match init {
+                InitVal::Expr(e) => InitVal::Expr(Expr::new(e.const_eval(eval)?.into(), e.span)),
+                InitVal::List(list) => {
+                    InitVal::List(list.iter().map(|i| fold(i, eval)).collect::<Option<_>>()?)
+                }
+            })
+        }
+
+        let elem_ty = match c.kind {
+            BType::Int | BType::Unsigned | BType::LongLong | BType::Float | BType::Enum(_) => {
+                Type::from_btype(&c.kind)
+            }
+            _ => return None,
+        };
+        let dims = c
+            .lval
+            .dims
+            .iter()
+            .map(|d| match d.const_eval(self)? {
+                ConstValue::Float(_) => None,
+                len => (len.as_long() > 0).then_some(len.as_long() as usize),
+            })
+            .collect::<Option<DimTy>>()?;
+        let ty = Type::infer_from_dims(&elem_ty, &dims);
+        let init = match &c.init {
+            InitVal::List(list) if check_init_list(list, &ty, c.span).is_empty() => {
+                fold(&c.init, self)?
+            }
+            _ => return None,
+        };
+        let words = eval_array(&init, &ty);
+        Some(ConstArray {
+            dims,
+            elem_ty,
+            words,
+        })
+    }
+
+    fn report(&mut self, diag: Diagnostic) {
+        self.diags.push(diag);
+    }
+
+    /// Report a division by a constant zero, including `/=` and `%=`, and an operation of signed
+    /// constants that overflows
+    fn check_arith(&mut self, e: &Expr) {
+        let (op, lhs, rhs) = match &e.kind {
+            ExprKind::Binary(b) => (b.op, b.lhs.const_eval(self), b.rhs.const_eval(self)),
+            // `-x` overflows as `0 - x` does
+            ExprKind::Unary(UnaryExpr::Unary(UnaryOp::Neg, opr)) => {
+                let opr = opr.const_eval(self);
+                (
+                    BinaryOp::Sub,
+                    opr.map(|v| ConstValue::Int(0).cast_like(v)),
+                    opr,
+                )
+            }
+            // only a division by zero is known without the value of the place
+            ExprKind::CompoundAssign(c) if matches!(c.op, BinaryOp::Div | BinaryOp::Mod) => {
+                (c.op, None, c.val.const_eval(self))
+            }
+            _ => return,
+        };
+        let is_zero = |v: ConstValue| !matches!(v, ConstValue::Float(_)) && !v.is_true();
+        if matches!(op, BinaryOp::Div | BinaryOp::Mod) && rhs.is_some_and(is_zero) {
+            let is_float = lhs.is_some_and(|v| matches!(v, ConstValue::Float(_)));
+            if !is_float {
+                self.report(
+                    Diagnostic::warning(Warning::DivByZero, "division by zero").with_span(e.span),
+                );
+            }
+            return;
+        }
+        let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+            return;
+        };
+        let (ty, exact) = match lhs.promote(rhs) {
+            (ConstValue::Int(l), ConstValue::Int(r)) => {
+                ("int", eval_exact(op, l as i128, r as i128))
+            }
+            (ConstValue::Long(l), ConstValue::Long(r)) => {
+                ("long long", eval_exact(op, l as i128, r as i128))
+            }
+            _ => return,
+        };
+        let Some(value) = e.const_eval(self) else {
+            return;
+        };
+        if exact.is_some_and(|exact| exact != value.as_long() as i128) {
+            let message = format!(
+                "integer overflow in expression of type `{}` results in `{}`",
+                ty,
+                value.as_long()
+            );
+            self.report(Diagnostic::warning(Warning::Overflow, message).with_span(e.span));
+        }
+    }
+
+    /// Check the operations of an expression to be folded, whose operands are not visited
+    fn check_folded(&mut self, e: &Expr) {
+        self.check_arith(e);
+        match &e.kind {
+            ExprKind::Binary(b) => {
+                self.check_folded(&b.lhs);
+                self.check_folded(&b.rhs);
+            }
+            ExprKind::Unary(UnaryExpr::Unary(_, opr)) => self.check_folded(opr),
+            ExprKind::Conditional(c) => {
+                self.check_folded(&c.cond);
+                self.check_folded(&c.true_val);
+                self.check_folded(&c.false_val);
+            }
+            ExprKind::Cast(opr) => self.check_folded(opr),
+            _ => {}
+        }
+    }
+
+    /// Check that the operand of `&` is not a constant, i.e. a scalar constant or an enumerator,
+    /// which is folded and has no storage, or a part of a `const` array, whose reads are folded
+    /// and would miss the writes through the pointer
+    fn check_addressable(&mut self, place: &Expr, span: Span) {
+        if let ExprKind::LVal(lval) = &place.kind {
+            let is_const_scalar = lval.dims.is_empty() && self.get(&lval.ident).is_some();
+            if is_const_scalar || self.ranks.contains_key(lval.ident.as_str()) {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::NotAssignable,
+                        "cannot take the address of a constant",
+                    )
+                    .with_span(span),
+                );
+            }
+        }
+    }
+
+    /// Check that a `const` array, or a row of it, is not converted to a pointer, which would
+    /// allow writes to it
+    fn check_not_decayed(&mut self, lval: &LVal, span: Span) {
+        if self
+            .ranks
+            .get(lval.ident.as_str())
+            .is_some_and(|&rank| lval.dims.len() < rank)
+        {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::NotAssignable,
+                    "a `const` array cannot be converted to a pointer",
+                )
+                .with_span(span),
+            );
+        }
+    }
+
+    /// Check that the assigned place is not a constant, which would be folded away, and fold
+    /// the place otherwise
+    fn visit_assigned(&mut self, lval: &'ast mut Expr) {
+        if let ExprKind::LVal(l) = &lval.kind {
+            let ident = l.ident.as_str();
+            // a part of an array is not assignable whether it is `const` or not
+            let is_elem = self.ranks.get(ident) == Some(&l.dims.len());
+            if self.values.contains_key(ident) || is_elem {
+                self.report(
+                    Diagnostic::error(ErrorCode::NotAssignable, "attempt to assign a const value")
+                        .with_span(lval.span),
+                );
+                return;
+            }
+            // an array is not assignable, which is reported later
+            if self.ranks.contains_key(ident) {
+                return walk_expr(self, lval);
+            }
+        }
+        self.visit_expr(lval);
+    }
+}
 
 impl<'ast> MutVisitor<'ast> for Evaluator<'ast> {
     fn visit_const_decl(&mut self, c: &'ast mut ConstDecl) {
-        if matches!(c.kind, ExprKind::Int) {
-            if let InitVal::Expr(e) = &c.init {
-                self.insert(&c.lval.ident, e.const_eval(self).unwrap());
+        if c.is_scalar() {
+            // `{}`, which a `static` constant without an initializer has, is zero
+            if matches!(&c.init, InitVal::List(l) if l.is_empty()) {
+                c.init = InitVal::Expr(Expr::new(ExprKind::Integer(0), c.lval.span));
+            }
+            if let InitVal::Expr(e) = &mut c.init {
+                self.check_folded(e);
+                match e.const_eval(self) {
+                    Some(value) => {
+                        let (value, diag) = value.cast_checked(&Type::from_btype(&c.kind), e.span);
+                        if let Some(diag) = diag {
+                            self.report(diag);
+                        }
+                        // the initializer is already converted, so it is not checked again
+                        e.kind = value.into();
+                        self.values.insert(&c.lval.ident, value);
+                    }
+                    None => {
+                        self.report(
+                            Diagnostic::error(
+                                ErrorCode::NotConstant,
+                                "initializer of a const value is not a constant",
+                            )
+                            .with_span(e.span),
+                        );
+                    }
+                }
             } else {
-                panic!("invalid initializer");
+                self.report(
+                    Diagnostic::error(ErrorCode::InvalidInitializer, "invalid initializer")
+                        .with_span(c.span),
+                );
             }
         } else {
+            if let Some(array) = self.eval_const_array(c) {
+                self.arrays.insert(c.lval.ident.clone(), array);
+            }
+            self.ranks.insert(c.lval.ident.clone(), c.lval.dims.len());
             walk_const_decl(self, c);
         }
     }
 
-    fn visit_assign(&mut self, a: &'ast mut Assign) {
-        if self.contains_key(a.lval.ident.as_str()) {
-            panic!("attempt to assign a const value");
+    fn visit_var_decl(&mut self, v: &'ast mut VarDecl) {
+        // an `extern` constant is read from memory like a variable, but is not assignable
+        if v.is_const {
+            let rank = v.lval.dims.len() + usize::from(v.unknown_len);
+            self.ranks.insert(v.lval.ident.clone(), rank);
+        }
+        walk_var_decl(self, v);
+    }
+
+    fn visit_enum_def(&mut self, e: &'ast mut EnumDef) {
+        for c in &mut e.consts {
+            if let InitVal::Expr(value) = &c.init {
+                if let Some(ConstValue::Float(_)) = value.const_eval(self) {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::NotConstant,
+                            "enumerator value is not an integer constant",
+                        )
+                        .with_span(value.span),
+                    );
+                }
+            }
+            self.visit_const_decl(c);
         }
-        walk_assign(self, a);
+    }
+
+    fn visit_assign(&mut self, a: &'ast mut Assign) {
+        self.visit_expr(&mut a.val);
+        self.visit_assigned(&mut a.lval);
+    }
+
+    fn visit_compound_assign(&mut self, c: &'ast mut CompoundAssign) {
+        self.visit_expr(&mut c.val);
+        self.visit_assigned(&mut c.lval);
     }
 
     fn visit_expr(&mut self, e: &'ast mut Expr) {
-        if matches!(e, Expr::Integer(_)) {
+        if e.as_const().is_some() {
             return;
         }
-        if let Some(i) = e.const_eval(self) {
-            *e = Expr::Integer(i);
+        if let Some(value) = e.const_eval(self) {
+            self.check_folded(e);
+            e.kind = value.into();
             return;
         }
-
-        walk_expr(self, e);
+        if let ExprKind::LVal(lval) = &e.kind {
+            if let Some(Err(OutOfBounds { index, len, span })) = self.elem_index(lval) {
+                let message = format!("index {} is out of bounds for length {}", index, len);
+                self.report(
+                    Diagnostic::error(ErrorCode::IndexOutOfBounds, message).with_span(span),
+                );
+                e.kind = ExprKind::Error;
+                return;
+            }
+            self.check_not_decayed(lval, e.span);
+        }
+        self.check_arith(e);
+        match &e.kind {
+            ExprKind::AddrOf(place) => self.check_addressable(place, e.span),
+            _ => return walk_expr(self, e),
+        }
+        // the operand of `&` is kept as a place, e.g. an element of a `const` array
+        if let ExprKind::AddrOf(place) = &mut e.kind {
+            walk_expr(self, place);
+        }
     }
 }
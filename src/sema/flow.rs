@@ -0,0 +1,141 @@
+use crate::ast::*;
+use crate::diag::{Diagnostic, Diagnostics, Warning};
+
+/// Find the statements that can never be executed, and the non-void functions whose end can
+/// be reached. It runs after the evaluator, which folds the conditions of loops.
+#[derive(Debug, Default)]
+pub struct FlowChecker {
+    /// the enclosing loops and `switch`es, from the outermost one
+    breakables: Vec<Breakable>,
+    pub diags: Diagnostics,
+}
+
+/// A loop or a `switch`, and how control has been seen to leave its body
+#[derive(Debug, Default)]
+struct Breakable {
+    is_loop: bool,
+    /// whether a reachable `break` of it has been seen
+    broken: bool,
+    /// whether a reachable `continue` of it has been seen, if it is a loop
+    continued: bool,
+}
+
+impl FlowChecker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn report(&mut self, diag: Diagnostic) {
+        self.diags.push(diag);
+    }
+
+    /// Whether control can reach the end of the body of a loop or a `switch`
+    fn flow_body(&mut self, stmt: &Stmt, is_loop: bool) -> (bool, Breakable) {
+        self.breakables.push(Breakable {
+            is_loop,
+            ..Default::default()
+        });
+        let flows = self.flow(stmt);
+        (flows, self.breakables.pop().unwrap())
+    }
+
+    fn flow(&mut self, stmt: &Stmt) -> bool {
+        match &stmt.kind {
+            StmtKind::Return(_) => false,
+            StmtKind::Break(_) => {
+                if let Some(b) = self.breakables.last_mut() {
+                    b.broken = true;
+                }
+                false
+            }
+            StmtKind::Continue(_) => {
+                if let Some(b) = self.breakables.iter_mut().rev().find(|b| b.is_loop) {
+                    b.continued = true;
+                }
+                false
+            }
+            StmtKind::Block(b) => self.flow_block(b),
+            StmtKind::Branch(b) => {
+                let if_flows = self.flow(&b.if_stmt);
+                match &b.el_stmt {
+                    Some(el_stmt) => self.flow(el_stmt) || if_flows,
+                    None => true,
+                }
+            }
+            StmtKind::While(w) => {
+                let (_, body) = self.flow_body(&w.stmt, true);
+                !is_true(Some(&w.cond)) || body.broken
+            }
+            StmtKind::DoWhile(d) => {
+                let (flows, body) = self.flow_body(&d.stmt, true);
+                ((flows || body.continued) && !is_true(Some(&d.cond))) || body.broken
+            }
+            StmtKind::For(f) => {
+                let (_, body) = self.flow_body(&f.stmt, true);
+                !is_true(f.cond.as_ref()) || body.broken
+            }
+            StmtKind::Switch(s) => {
+                // without a `default` label, no case may match
+                let (flows, body) = self.flow_body(&s.stmt, false);
+                flows || !s.labels().contains(&None) || body.broken
+            }
+            StmtKind::Assign(_) | StmtKind::Expr(_) | StmtKind::Case(_) | StmtKind::Default => true,
+        }
+    }
+
+    /// Whether control can reach the end of the block, reporting the first dead statement
+    fn flow_block(&mut self, block: &Block) -> bool {
+        let mut reachable = true;
+        let mut reported = false;
+        for item in &block.items {
+            let BlockItem::Stmt(stmt) = item else {
+                continue;
+            };
+            // a label of a `switch` can be jumped to
+            if matches!(stmt.kind, StmtKind::Case(_) | StmtKind::Default) {
+                reachable = true;
+            }
+            if !reachable {
+                if !reported {
+                    self.report(
+                        Diagnostic::warning(
+                            Warning::UnreachableCode,
+                            "code will never be executed",
+                        )
+                        .with_span(stmt.span),
+                    );
+                    reported = true;
+                }
+                continue;
+            }
+            reachable = self.flow(stmt);
+        }
+        reachable
+    }
+}
+
+impl<'ast> MutVisitor<'ast> for FlowChecker {
+    fn visit_func_def(&mut self, f: &'ast mut FuncDef) {
+        let Some(block) = &f.block else {
+            return;
+        };
+        // `main` returns 0 if control reaches its end
+        if self.flow_block(block) && !matches!(f.ret_kind, BType::Void) && f.ident != "main" {
+            self.report(
+                Diagnostic::warning(
+                    Warning::ReturnType,
+                    format!(
+                        "non-void function `{}` does not return a value in all control paths",
+                        f.ident
+                    ),
+                )
+                .with_span(f.span),
+            );
+        }
+    }
+}
+
+/// Whether the condition of a loop is a nonzero constant, or is left out in a `for` loop
+fn is_true(cond: Option<&Expr>) -> bool {
+    cond.is_none_or(|c| c.as_const().is_some_and(|v| v.is_true()))
+}
@@ -0,0 +1,114 @@
+use crate::ast::{Expr, InitVal, Span};
+
+use super::ty::{Type, TypeKind};
+
+/// A problem with the shape of an initializer list, located at the offending element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitError {
+    /// braces around a braced scalar, e.g. `{{1}}` for an `int`
+    Nesting(Span),
+    /// an element past the end of the object it initializes
+    Excess(Span),
+}
+
+/// How an initializer list lines up with the object it initializes
+#[derive(Debug)]
+struct Shape<'a> {
+    errors: Vec<InitError>,
+    /// the scalar elements in place, with the types of the scalars they initialize
+    elems: Vec<(&'a Expr, Type)>,
+}
+
+/// Check how an initializer list lines up with the object of the type, with the braces of its
+/// subobjects elided as in C. `span` is used for the lists without any element.
+pub fn check_init_list(list: &[InitVal], ty: &Type, span: Span) -> Vec<InitError> {
+    shape(list, ty, span).errors
+}
+
+/// The elements of an initializer list that are in place, with the types of the scalars they
+/// initialize
+pub fn init_elems<'a>(list: &'a [InitVal], ty: &Type, span: Span) -> Vec<(&'a Expr, Type)> {
+    shape(list, ty, span).elems
+}
+
+fn shape<'a>(list: &'a [InitVal], ty: &Type, span: Span) -> Shape<'a> {
+    let mut shape = Shape {
+        errors: vec![],
+        elems: vec![],
+    };
+    check_braced(list, ty, span, &mut shape);
+    shape
+}
+
+fn subobjects(ty: &Type) -> Option<Vec<Type>> {
+    match ty.kind() {
+        TypeKind::Array(base_ty, len) => Some(vec![base_ty.clone(); *len]),
+        TypeKind::Struct(_, _) => Some(ty.fields()?.iter().map(|f| f.ty.clone()).collect()),
+        _ => None,
+    }
+}
+
+fn check_braced<'a>(list: &'a [InitVal], ty: &Type, span: Span, shape: &mut Shape<'a>) {
+    let extra = match subobjects(ty) {
+        Some(_) => {
+            let mut next = 0;
+            check_elided(list, &mut next, ty, span, shape);
+            list.get(next)
+        }
+        None => match list {
+            [InitVal::List(inner), ..] => {
+                shape
+                    .errors
+                    .push(InitError::Nesting(first_span(inner, span)));
+                None
+            }
+            [InitVal::Expr(e), ..] => {
+                shape.elems.push((e, ty.clone()));
+                list.get(1)
+            }
+            [] => None,
+        },
+    };
+    if let Some(extra) = extra {
+        shape.errors.push(InitError::Excess(first_span(
+            std::slice::from_ref(extra),
+            span,
+        )));
+    }
+}
+
+/// Initialize an object of the type from the elements of a list starting at `next`
+fn check_elided<'a>(
+    list: &'a [InitVal],
+    next: &mut usize,
+    ty: &Type,
+    span: Span,
+    shape: &mut Shape<'a>,
+) {
+    let Some(subobjects) = subobjects(ty) else {
+        if let Some(InitVal::Expr(e)) = list.get(*next) {
+            shape.elems.push((e, ty.clone()));
+        }
+        *next += 1;
+        return;
+    };
+    for ty in subobjects {
+        match list.get(*next) {
+            None => break,
+            Some(InitVal::List(inner)) => {
+                check_braced(inner, &ty, span, shape);
+                *next += 1;
+            }
+            Some(InitVal::Expr(_)) => check_elided(list, next, &ty, span, shape),
+        }
+    }
+}
+
+/// Span of the first element of the list, if there is one
+fn first_span(list: &[InitVal], span: Span) -> Span {
+    match list.first() {
+        Some(InitVal::Expr(e)) => e.span,
+        Some(InitVal::List(inner)) => first_span(inner, span),
+        None => span,
+    }
+}
@@ -1,8 +1,17 @@
+pub(crate) mod analysis;
 pub(crate) mod eval;
+pub(crate) mod flow;
+pub(crate) mod init;
 pub(crate) mod name;
 pub(crate) mod symbol;
-pub(crate) mod ty;
+pub mod ty;
+pub(crate) mod typeck;
+pub(crate) mod typedef;
 
-pub use eval::Evaluator;
+pub use analysis::*;
+pub use eval::{ConstValue, Evaluator};
+pub use flow::FlowChecker;
 pub use name::*;
 pub use symbol::*;
+pub use typeck::TypeChecker;
+pub use typedef::TypedefResolver;
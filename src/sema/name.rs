@@ -1,71 +1,235 @@
 use std::collections::{HashMap, HashSet};
 
 use crate::ast::*;
+use crate::diag::{Diagnostic, Diagnostics, ErrorCode, Warning};
 
-#[derive(Debug)]
+/// Functions of the runtime library, which are declared before the program
+const LIB_FUNCS: &[&str] = &[
+    "getint",
+    "getch",
+    "getarray",
+    "putint",
+    "putch",
+    "putarray",
+    "getfloat",
+    "getfarray",
+    "putfloat",
+    "putfarray",
+    "putf",
+    "_sysy_starttime",
+    "_sysy_stoptime",
+    "mmemset",
+];
+
+/// Names of the blocks which every function has in Koopa IR
+const PRESERVED: &[&str] = &["entry", "end"];
+
+/// Prefix of the symbols the compiler generates, e.g. the intrinsics and the `static` and
+/// `extern` variables, which a declaration in the program would collide with
+const RESERVED_PREFIX: &str = "__sysy_";
+
+#[derive(Debug, Default)]
 pub struct NameManager {
     mapping: Vec<HashMap<String, u32>>,
     pool: HashSet<String>,
+    /// functions declared so far, and whether they have been defined
+    funcs: HashMap<String, bool>,
+    /// global variables declared so far, and whether they have been defined
+    externs: HashMap<String, bool>,
+    /// local variables not used so far by their new names, with their names as written
+    unused: HashMap<String, (String, Span)>,
+    /// where the names are declared, by their new names
+    decl_spans: HashMap<String, Span>,
+    /// the declarations and uses of the names in the program, in order
+    pub refs: Vec<NameRef>,
+    pub diags: Diagnostics,
+}
+
+/// A declaration or a use of a name, e.g. for the language server
+#[derive(Debug, Clone)]
+pub struct NameRef {
+    pub name: String,
+    pub new_name: String,
+    /// the span of the declaration or the expression the name is in, where its first
+    /// occurrence is the name itself, e.g. a function definition or a call
+    pub span: Span,
+    pub is_decl: bool,
 }
 
 impl NameManager {
     pub fn new() -> Self {
-        NameManager {
-            mapping: vec![],
-            pool: HashSet::new(),
-        }
+        Default::default()
     }
 
+    /// Declare the functions of the runtime library, which may be redeclared but not defined
     pub fn install_lib(&mut self) {
-        self.insert_name("getint");
-        self.insert_name("getch");
-        self.insert_name("getarray");
-        self.insert_name("putint");
-        self.insert_name("putch");
-        self.insert_name("putarray");
-        self.insert_name("starttime");
-        self.insert_name("stoptime");
-        self.insert_name("mmemset");
+        for name in LIB_FUNCS {
+            self.insert_name(name, Span::default());
+            self.funcs.insert(name.to_string(), true);
+        }
     }
 
     pub fn enter_scope(&mut self) {
         self.mapping.push(HashMap::new());
     }
 
+    /// Leave the innermost scope, warning about the variables declared in it but never used
     pub fn exit_scope(&mut self) {
-        self.mapping.pop();
+        let scope = self.mapping.pop().unwrap();
+        let mut unused: Vec<_> = scope
+            .into_iter()
+            .filter_map(|(name, suffix)| self.unused.remove(&with_suffix(&name, suffix)))
+            .collect();
+        unused.sort_by_key(|(_, span)| span.start);
+        for (name, span) in unused {
+            self.report(
+                Diagnostic::warning(
+                    Warning::UnusedVariable,
+                    format!("unused variable `{}`", name),
+                )
+                .with_span(span),
+            );
+        }
     }
 
-    pub fn insert_name(&mut self, old_name: &str) {
+    /// Whether the names are being declared at file scope
+    fn is_global(&self) -> bool {
+        self.mapping.len() == 1
+    }
+
+    pub fn insert_name(&mut self, old_name: &str, span: Span) {
+        if !self.is_global() {
+            self.check_shadow(old_name, span);
+        }
+
         let mut name = String::from(old_name);
         let mut possible_suffix = self.get_suffix(old_name);
 
-        while self.pool.contains(&name) {
+        // the names at file scope are kept as written, since other files refer to them
+        while self.pool.contains(&name) && !self.is_global() {
             possible_suffix += 1;
             name = format!("{}{}", old_name, possible_suffix);
         }
         self.pool.insert(name);
+        self.declare(old_name, possible_suffix, span);
+    }
 
-        if self
+    /// Map a name in the innermost scope to its new name with the suffix
+    fn declare(&mut self, old_name: &str, suffix: u32, span: Span) {
+        if old_name.starts_with(RESERVED_PREFIX) {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::ReservedName,
+                    format!("`{}` is reserved for the compiler", old_name),
+                )
+                .with_span(span),
+            );
+        }
+        let prev = self
             .mapping
             .last_mut()
             .unwrap()
-            .insert(old_name.to_owned(), possible_suffix)
-            .is_some()
-        {
-            panic!("redifinition of `{}`", old_name);
+            .insert(old_name.to_owned(), suffix);
+        if let Some(suffix) = prev {
+            let mut diag = Diagnostic::error(
+                ErrorCode::Redefinition,
+                format!("redefinition of `{}`", old_name),
+            )
+            .with_span(span);
+            if let Some(prev_span) = self.decl_span(&with_suffix(old_name, suffix)) {
+                let message = format!("previous definition of `{}` is here", old_name);
+                diag = diag.with_label(prev_span, message);
+            }
+            self.report(diag);
+        }
+        let new_name = with_suffix(old_name, suffix);
+        if span != Span::default() {
+            self.refs.push(NameRef {
+                name: old_name.to_owned(),
+                new_name: new_name.clone(),
+                span,
+                is_decl: true,
+            });
         }
+        self.decl_spans.entry(new_name).or_insert(span);
     }
 
-    pub fn rename(&self, name: &mut String) {
+    /// Span of the declaration of a new name, unless it is predefined
+    fn decl_span(&self, new_name: &str) -> Option<Span> {
+        self.decl_spans
+            .get(new_name)
+            .copied()
+            .filter(|&span| span != Span::default())
+    }
+
+    pub fn rename(&mut self, name: &mut String, span: Span) {
         let suffix = match self.mapping.iter().rev().find_map(|scope| scope.get(name)) {
-            Some(name) => name,
-            None => panic!("variable used before definition"),
+            Some(suffix) => *suffix,
+            None => {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::Undeclared,
+                        format!("`{}` is used before definition", name),
+                    )
+                    .with_span(span),
+                );
+                return;
+            }
+        };
+        let new_name = with_suffix(name, suffix);
+        self.refs.push(NameRef {
+            name: std::mem::replace(name, new_name),
+            new_name: name.clone(),
+            span,
+            is_decl: false,
+        });
+        self.unused.remove(name);
+    }
+
+    /// Warn if a local declaration hides one in an enclosing scope, other than a function
+    fn check_shadow(&mut self, name: &str, span: Span) {
+        let outer = &self.mapping[..self.mapping.len() - 1];
+        let Some(depth) = outer.iter().rposition(|scope| scope.contains_key(name)) else {
+            return;
+        };
+        let is_func =
+            self.funcs.contains_key(name) || LIB_FUNCS.contains(&name) || PRESERVED.contains(&name);
+        let what = match depth {
+            0 if is_func => return,
+            0 => "a global declaration",
+            // the parameters are in the scope of the function, outside its body
+            1 => "a parameter",
+            _ => "a previous local",
         };
-        match suffix {
-            0 => {}
-            _ => name.push_str(&suffix.to_string()),
+        let mut diag = Diagnostic::warning(
+            Warning::Shadow,
+            format!("declaration of `{}` shadows {}", name, what),
+        )
+        .with_span(span);
+        if let Some(shadowed) = self.decl_span(&with_suffix(name, outer[depth][name])) {
+            diag = diag.with_label(shadowed, "shadowed declaration is here");
         }
+        self.report(diag);
+    }
+
+    fn track_unused(&mut self, name: &str, span: Span) {
+        if !self.is_global() {
+            let new_name = with_suffix(name, self.get_suffix(name));
+            self.unused.insert(new_name, (name.to_owned(), span));
+        }
+    }
+
+    /// Rename the name of a `typedef` in the type of a declaration
+    fn rename_btype(&mut self, kind: &mut BType, span: Span) {
+        match kind {
+            BType::Named(name) => self.rename(name, span),
+            BType::Pointer(base) => self.rename_btype(base, span),
+            _ => {}
+        }
+    }
+
+    fn report(&mut self, diag: Diagnostic) {
+        self.diags.push(diag);
     }
 
     fn get_suffix(&self, name: &str) -> u32 {
@@ -83,23 +247,46 @@ impl<'ast> MutVisitor<'ast> for NameManager {
         self.enter_scope();
         self.install_lib();
         // preserved names
-        self.insert_name("entry");
-        self.insert_name("end");
+        for name in PRESERVED {
+            self.insert_name(name, Span::default());
+        }
+        self.pool.extend(global_names(c));
+        let mut externs = LocalExterns::default();
+        externs.visit_comp_unit(c);
+        self.pool.extend(externs.0);
         walk_comp_unit(self, c);
         self.exit_scope();
     }
 
     fn visit_func_def(&mut self, f: &'ast mut FuncDef) {
-        self.insert_name(&f.ident);
-        self.rename(&mut f.ident);
+        // a prototype may be repeated, and shares its name with the definition
+        let has_body = f.block.is_some();
+        match self.funcs.get(&f.ident) {
+            Some(&defined) if !(defined && has_body) => {}
+            _ => self.insert_name(&f.ident, f.span),
+        }
+        *self.funcs.entry(f.ident.clone()).or_default() |= has_body;
+        self.rename(&mut f.ident, f.span);
+        self.rename_btype(&mut f.ret_kind, f.span);
+        if has_body {
+            for p in f.params.iter().filter(|p| p.ident.is_none()) {
+                self.report(
+                    Diagnostic::error(ErrorCode::MissingParameterName, "parameter name omitted")
+                        .with_span(p.span),
+                );
+            }
+        }
         self.enter_scope();
         walk_func_def(self, f);
         self.exit_scope();
     }
 
     fn visit_func_param(&mut self, f: &'ast mut FuncParam) {
-        self.insert_name(&f.ident);
-        self.rename(&mut f.ident);
+        self.rename_btype(&mut f.kind, f.span);
+        if let Some(ident) = &mut f.ident {
+            self.insert_name(ident, f.span);
+            self.rename(ident, f.span);
+        }
     }
 
     fn visit_block(&mut self, b: &'ast mut Block) {
@@ -108,28 +295,121 @@ impl<'ast> MutVisitor<'ast> for NameManager {
         self.exit_scope()
     }
 
+    fn visit_for(&mut self, f: &'ast mut For) {
+        // a declaration in the header is only visible in the loop
+        self.enter_scope();
+        walk_for(self, f);
+        self.exit_scope()
+    }
+
     fn visit_const_decl(&mut self, c: &'ast mut ConstDecl) {
         // the order cannot be changed
+        self.rename_btype(&mut c.kind, c.span);
         self.visit_initval(&mut c.init);
-        self.insert_name(&c.lval.ident);
+        self.insert_name(&c.lval.ident, c.span);
+        let (ident, span) = (c.lval.ident.clone(), c.lval.span);
         self.visit_lval(&mut c.lval);
+        self.track_unused(&ident, span);
+    }
+
+    fn visit_enum_def(&mut self, e: &'ast mut EnumDef) {
+        // enumerators are not variables to warn about when unused
+        let idents: Vec<_> = e.consts.iter().map(|c| c.lval.ident.clone()).collect();
+        walk_enum_def(self, e);
+        for ident in idents {
+            let name = with_suffix(&ident, self.get_suffix(&ident));
+            self.unused.remove(&name);
+        }
     }
 
     fn visit_var_decl(&mut self, v: &'ast mut VarDecl) {
+        self.rename_btype(&mut v.kind, v.span);
         if let Some(init) = &mut v.init {
             self.visit_initval(init);
         }
-        self.insert_name(&v.lval.ident);
+        let (ident, span) = (v.lval.ident.clone(), v.lval.span);
+        if self.is_global() {
+            // an `extern` declaration may be repeated, and shares its name with the definition
+            let is_def = v.storage != Storage::Extern;
+            match self.externs.get(&ident) {
+                Some(&defined) if !(defined && is_def) => {}
+                _ => self.insert_name(&ident, v.span),
+            }
+            *self.externs.entry(ident.clone()).or_default() |= is_def;
+        } else if v.storage == Storage::Extern {
+            // refers to the global variable, whose name has been reserved
+            self.declare(&ident, 0, v.span);
+        } else {
+            self.insert_name(&ident, v.span);
+        }
         self.visit_lval(&mut v.lval);
+        if v.storage != Storage::Extern {
+            self.track_unused(&ident, span);
+        }
+    }
+
+    fn visit_typedef(&mut self, t: &'ast mut Typedef) {
+        self.rename_btype(&mut t.kind, t.span);
+        walk_typedef(self, t);
+        self.insert_name(&t.ident, t.span);
+        self.rename(&mut t.ident, t.span);
+    }
+
+    fn visit_field(&mut self, f: &'ast mut Field) {
+        self.rename_btype(&mut f.kind, f.span);
+        walk_field(self, f);
     }
 
     fn visit_lval(&mut self, l: &'ast mut LVal) {
-        self.rename(&mut l.ident);
+        self.rename(&mut l.ident, l.span);
         walk_lval(self, l);
     }
 
     fn visit_call(&mut self, c: &'ast mut Call) {
-        self.rename(&mut c.ident);
+        self.rename(&mut c.ident, c.span);
         walk_call(self, c);
     }
 }
+
+/// The new name of a declaration, e.g. `x1` for `x` with the suffix 1
+fn with_suffix(name: &str, suffix: u32) -> String {
+    match suffix {
+        0 => name.to_owned(),
+        _ => format!("{}{}", name, suffix),
+    }
+}
+
+/// Names of the variables declared `extern` in functions
+#[derive(Default)]
+struct LocalExterns(Vec<String>);
+
+impl<'ast> MutVisitor<'ast> for LocalExterns {
+    fn visit_var_decl(&mut self, v: &'ast mut VarDecl) {
+        if v.storage == Storage::Extern {
+            self.0.push(v.lval.ident.clone());
+        }
+    }
+}
+
+fn global_names(c: &CompUnit) -> Vec<String> {
+    let mut names = vec![];
+    for item in &c.items {
+        match item {
+            GlobalItem::Func(f) => names.push(f.ident.clone()),
+            GlobalItem::Decl(Decl::VarDecl(decls)) => {
+                names.extend(decls.iter().map(|d| d.lval.ident.clone()))
+            }
+            GlobalItem::Decl(Decl::ConstDecl(decls)) => {
+                names.extend(decls.iter().map(|d| d.lval.ident.clone()))
+            }
+            GlobalItem::Decl(Decl::Typedef(typedefs)) => {
+                names.extend(typedefs.iter().map(|t| t.ident.clone()))
+            }
+            GlobalItem::Decl(Decl::Enum(e)) => {
+                names.extend(e.consts.iter().map(|c| c.lval.ident.clone()))
+            }
+            GlobalItem::Struct(_) => {}
+        }
+    }
+    names
+}
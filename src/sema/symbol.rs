@@ -1,20 +1,38 @@
 use smallvec::{smallvec, SmallVec};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::ast::visit::MutVisitor;
 use crate::ast::*;
+use crate::diag::{Diagnostic, Diagnostics, ErrorCode};
 
-use super::ty::Type;
+use super::init::{check_init_list, InitError};
+use super::ty::{DimTy, Type, TypeKind};
 
 #[derive(Debug)]
 pub struct SymbolTable {
     pub data: HashMap<String, Type>,
+    /// `struct`s by their names, which live in a namespace of their own
+    pub structs: HashMap<String, Type>,
+    /// names of the `enum`s, which are all `int`s
+    enums: HashSet<String>,
+    /// names of the variables declared `extern const`
+    consts: HashSet<String>,
+    /// types of the parameters of the function being declared, which may have no names
+    param_tys: SmallVec<[Type; 6]>,
+    pub diags: Diagnostics,
+    is_global: bool,
 }
 
 impl SymbolTable {
     pub fn new() -> Self {
         Self {
             data: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashSet::new(),
+            consts: HashSet::new(),
+            param_tys: smallvec![],
+            diags: Diagnostics::new(),
+            is_global: true,
         }
     }
 
@@ -27,31 +45,169 @@ impl SymbolTable {
         // use before define is also prevented after the renaming process
         self.data.get(name).unwrap()
     }
+}
 
-    pub fn contains(&self, name: &str) -> bool {
-        self.data.contains_key(name)
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl Type {
-    pub fn from_const_decl(value: &ConstDecl) -> Self {
-        let dims: SmallVec<[_; 4]> = value
-            .lval
-            .dims
-            .iter()
-            .map(|d| d.get_i32() as usize)
-            .collect();
-        Self::infer_from_dims(&dims)
-    }
-
-    pub fn from_var_decl(value: &VarDecl) -> Self {
-        let dims: SmallVec<[_; 4]> = value
-            .lval
-            .dims
-            .iter()
-            .map(|d| d.get_i32() as usize)
-            .collect();
-        Type::infer_from_dims(&dims)
+impl SymbolTable {
+    fn report(&mut self, diag: Diagnostic) {
+        self.diags.push(diag);
+    }
+
+    /// Resolve the type named in a declaration
+    fn resolve(&mut self, kind: &BType, span: Span) -> Type {
+        match kind {
+            BType::Struct(ident) => match self.structs.get(ident) {
+                Some(ty) => ty.clone(),
+                None => {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::Undeclared,
+                            format!("unknown type `struct {}`", ident),
+                        )
+                        .with_span(span),
+                    );
+                    Type::get_int()
+                }
+            },
+            BType::Enum(ident) => {
+                if !self.enums.contains(ident) {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::Undeclared,
+                            format!("unknown type `enum {}`", ident),
+                        )
+                        .with_span(span),
+                    );
+                }
+                Type::get_int()
+            }
+            BType::Pointer(base) if matches!(**base, BType::Void) => {
+                self.report(
+                    Diagnostic::error(ErrorCode::Unsupported, "pointer to `void` is not supported")
+                        .with_span(span),
+                );
+                Type::get_pointer(Type::get_int())
+            }
+            BType::Pointer(base) => Type::get_pointer(self.resolve(base, span)),
+            _ => Type::from_btype(kind),
+        }
+    }
+
+    /// Evaluate the dimensions of an array, which must be folded into integers by now
+    fn eval_dims(&mut self, dims: &[Expr]) -> DimTy {
+        dims.iter()
+            .map(|d| match d.kind {
+                ExprKind::Integer(i) if i > 0 => i as usize,
+                ExprKind::Unsigned(u) if u > 0 => u as usize,
+                ExprKind::Long(l) if l > 0 => l as usize,
+                ExprKind::Integer(0) | ExprKind::Unsigned(0) | ExprKind::Long(0) => {
+                    self.report(
+                        Diagnostic::error(ErrorCode::ArraySize, "array size is zero")
+                            .with_span(d.span),
+                    );
+                    1
+                }
+                ExprKind::Integer(_) | ExprKind::Long(_) => {
+                    self.report(
+                        Diagnostic::error(ErrorCode::ArraySize, "array size is negative")
+                            .with_span(d.span),
+                    );
+                    1
+                }
+                ExprKind::Float(_) => {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::ArraySize,
+                            "size of array has non-integer type `float`",
+                        )
+                        .with_span(d.span),
+                    );
+                    1
+                }
+                // which has been reported
+                ExprKind::Error => 1,
+                _ => {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::NotConstant,
+                            "array size is not a constant expression",
+                        )
+                        .with_span(d.span),
+                    );
+                    1
+                }
+            })
+            .collect()
+    }
+
+    /// Check the shape of an initializer, and that it is constant if it is a list or the
+    /// initializer of a global or `static` variable
+    fn check_init(&mut self, init: &InitVal, ty: &Type, is_static: bool, span: Span) {
+        let is_array = matches!(ty.kind(), TypeKind::Array(_, _));
+        // a `struct` may also be initialized with another `struct`
+        let is_aggregate = is_array || matches!(ty.kind(), TypeKind::Struct(_, _));
+        match init {
+            InitVal::Expr(_) if is_array => self.report(
+                Diagnostic::error(
+                    ErrorCode::InvalidInitializer,
+                    "array must be initialized with an initializer list",
+                )
+                .with_span(span),
+            ),
+            InitVal::List(_) if !is_aggregate => self.report(
+                Diagnostic::error(ErrorCode::InvalidInitializer, "invalid initializer")
+                    .with_span(span),
+            ),
+            // Koopa IR has no constants of pointer types other than `zeroinit`
+            InitVal::List(_) if matches!(ty.elem_ty().kind(), TypeKind::Pointer(_)) => self.report(
+                Diagnostic::error(
+                    ErrorCode::InvalidInitializer,
+                    "an array of pointers cannot be initialized with a list",
+                )
+                .with_span(span),
+            ),
+            InitVal::List(list) => {
+                for err in check_init_list(list, ty, span) {
+                    if let InitError::Nesting(span) = err {
+                        self.report(
+                            Diagnostic::error(
+                                ErrorCode::InvalidInitializer,
+                                "too many braces around scalar initializer",
+                            )
+                            .with_span(span),
+                        );
+                    }
+                }
+                self.check_const_init(init);
+            }
+            InitVal::Expr(_) => {
+                if is_static {
+                    self.check_const_init(init);
+                }
+            }
+        }
+    }
+
+    fn check_const_init(&mut self, init: &InitVal) {
+        match init {
+            InitVal::Expr(e) => {
+                if e.as_const().is_none() {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::NotConstant,
+                            "initializer element is not a compile-time constant",
+                        )
+                        .with_span(e.span),
+                    );
+                }
+            }
+            InitVal::List(list) => list.iter().for_each(|i| self.check_const_init(i)),
+        }
     }
 }
 
@@ -81,49 +237,127 @@ impl<'ast> MutVisitor<'ast> for SymbolTable {
                 smallvec![Type::get_int(), Type::get_pointer(Type::get_int())],
             ),
         );
-        self.insert("starttime", Type::get_func(Type::get_void(), smallvec![]));
-        self.insert("stoptime", Type::get_func(Type::get_void(), smallvec![]));
+        self.insert("getfloat", Type::get_func(Type::get_float(), smallvec![]));
+        self.insert(
+            "getfarray",
+            Type::get_func(
+                Type::get_int(),
+                smallvec![Type::get_pointer(Type::get_float())],
+            ),
+        );
+        self.insert(
+            "putfloat",
+            Type::get_func(Type::get_void(), smallvec![Type::get_float()]),
+        );
+        self.insert(
+            "putfarray",
+            Type::get_func(
+                Type::get_void(),
+                smallvec![Type::get_int(), Type::get_pointer(Type::get_float())],
+            ),
+        );
+        self.insert(
+            "putf",
+            Type::get_variadic_func(
+                Type::get_void(),
+                smallvec![Type::get_pointer(Type::get_char())],
+            ),
+        );
+        self.insert(
+            "_sysy_starttime",
+            Type::get_func(Type::get_void(), smallvec![Type::get_int()]),
+        );
+        self.insert(
+            "_sysy_stoptime",
+            Type::get_func(Type::get_void(), smallvec![Type::get_int()]),
+        );
 
         walk_comp_unit(self, c);
 
-        if !self.contains("main") {
-            panic!("main function is not defined")
+        let has_main = c.items.iter().any(
+            |item| matches!(item, GlobalItem::Func(f) if f.ident == "main" && f.block.is_some()),
+        );
+        if !has_main {
+            self.report(Diagnostic::error(
+                ErrorCode::MissingMain,
+                "main function is not defined",
+            ));
         }
     }
 
     fn visit_func_def(&mut self, f: &'ast mut FuncDef) {
+        self.is_global = false;
         walk_func_def(self, f);
+        self.is_global = true;
 
-        let ret_ty = match &f.ret_kind {
-            ExprKind::Int => Type::get_int(),
-            ExprKind::Void => Type::get_void(),
-            _ => unreachable!(),
-        };
+        let ret_ty = self.resolve(&f.ret_kind, f.span);
+        if matches!(ret_ty.kind(), TypeKind::Struct(_, _)) {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::Unsupported,
+                    "returning a `struct` is not supported",
+                )
+                .with_span(f.span),
+            );
+        }
 
-        let param_tys: SmallVec<[_; 6]> = f
-            .params
-            .iter()
-            .map(|p| self.get(&p.ident).clone())
-            .collect();
-        self.insert(&f.ident, Type::get_func(ret_ty, param_tys));
+        let param_tys = std::mem::take(&mut self.param_tys);
+        let ty = Type::get_func(ret_ty, param_tys);
+        match self.data.get(&f.ident) {
+            // declared by a prototype before
+            Some(prev) if *prev != ty => self.report(
+                Diagnostic::error(
+                    ErrorCode::ConflictingTypes,
+                    format!("conflicting types for `{}`", f.ident),
+                )
+                .with_span(f.span),
+            ),
+            Some(_) => {}
+            None => self.insert(&f.ident, ty),
+        }
     }
 
     fn visit_func_param(&mut self, f: &'ast mut FuncParam) {
         walk_func_param(self, f);
 
-        let dims: SmallVec<[_; 4]> = f.dims.iter().map(|d| d.get_i32() as usize).collect();
-        let ty = match &f.kind {
-            ExprKind::Int => Type::get_int(),
-            ExprKind::Array => Type::get_pointer(Type::infer_from_dims(&dims)),
-            _ => unreachable!(),
+        if matches!(f.kind, BType::Void) {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::IncompleteType,
+                    "parameter has incomplete type `void`",
+                )
+                .with_span(f.span),
+            );
+        }
+        let elem_ty = self.resolve(&f.kind, f.span);
+        let ty = match &f.dims {
+            Some(dims) => {
+                let dims = self.eval_dims(dims);
+                Type::get_pointer(Type::infer_from_dims(&elem_ty, &dims))
+            }
+            None => elem_ty,
         };
-        self.insert(&f.ident, ty);
+        if let Some(ident) = &f.ident {
+            self.insert(ident, ty.clone());
+        }
+        self.param_tys.push(ty);
     }
 
     fn visit_const_decl(&mut self, c: &'ast mut ConstDecl) {
-        if matches!(c.kind, ExprKind::Array) {
+        if matches!(c.kind, BType::Void) {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::IncompleteType,
+                    "variable has incomplete type `void`",
+                )
+                .with_span(c.span),
+            );
+        }
+        if !c.is_scalar() {
             walk_const_decl(self, c);
-            let ty = Type::from_const_decl(c);
+            let dims = self.eval_dims(&c.lval.dims);
+            let ty = Type::infer_from_dims(&self.resolve(&c.kind, c.span), &dims);
+            self.check_init(&c.init, &ty, self.is_global, c.span);
             self.insert(&c.lval.ident, ty);
         }
     }
@@ -131,7 +365,159 @@ impl<'ast> MutVisitor<'ast> for SymbolTable {
     fn visit_var_decl(&mut self, v: &'ast mut VarDecl) {
         walk_var_decl(self, v);
 
-        let symbol = Type::from_var_decl(v);
-        self.insert(&v.lval.ident, symbol);
+        if matches!(v.kind, BType::Void) {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::IncompleteType,
+                    "variable has incomplete type `void`",
+                )
+                .with_span(v.span),
+            );
+        }
+        let mut dims = self.eval_dims(&v.lval.dims);
+        if v.unknown_len {
+            // only the definition, which may be in another file, allocates the array
+            if v.storage != Storage::Extern {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::ArraySize,
+                        format!("array size missing in `{}`", v.lval.ident),
+                    )
+                    .with_span(v.span),
+                );
+            }
+            dims.insert(0, 0);
+        }
+        let ty = Type::infer_from_dims(&self.resolve(&v.kind, v.span), &dims);
+        match v.storage {
+            Storage::Extern if v.init.is_some() => self.report(
+                Diagnostic::error(
+                    ErrorCode::InvalidInitializer,
+                    "`extern` variable cannot have an initializer",
+                )
+                .with_span(v.span),
+            ),
+            _ => {}
+        }
+        if let Some(init) = &v.init {
+            let is_static = self.is_global || v.storage == Storage::Static;
+            self.check_init(init, &ty, is_static, v.span);
+        }
+        // an `extern` declaration shares its name with the definition, and may leave out the
+        // length of the array, which the definition gives
+        let ident = &v.lval.ident;
+        if let Some(prev) = self.data.get(ident) {
+            let keeps_prev = has_unknown_len(&ty, prev);
+            let message = if *prev != ty && !keeps_prev && !has_unknown_len(prev, &ty) {
+                Some(format!("conflicting types for `{}`", ident))
+            } else if self.consts.contains(ident) != v.is_const {
+                Some(format!("conflicting `const` qualifiers for `{}`", ident))
+            } else {
+                None
+            };
+            if let Some(message) = message {
+                self.report(
+                    Diagnostic::error(ErrorCode::ConflictingTypes, message).with_span(v.span),
+                );
+            }
+            if keeps_prev {
+                return;
+            }
+        }
+        if v.is_const {
+            self.consts.insert(ident.clone());
+        }
+        self.insert(ident, ty);
+    }
+
+    fn visit_enum_def(&mut self, e: &'ast mut EnumDef) {
+        walk_enum_def(self, e);
+
+        if let Some(ident) = &e.ident {
+            if !self.enums.insert(ident.clone()) {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::Redefinition,
+                        format!("redefinition of `enum {}`", ident),
+                    )
+                    .with_span(e.span),
+                );
+            }
+        }
+    }
+
+    fn visit_struct_def(&mut self, s: &'ast mut StructDef) {
+        walk_struct_def(self, s);
+
+        // the `struct` is known in its own fields, to which only pointers may point
+        let redefined = self.structs.contains_key(&s.ident);
+        let struct_ty = Type::get_struct(s.ident.clone());
+        if redefined {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::Redefinition,
+                    format!("redefinition of `struct {}`", s.ident),
+                )
+                .with_span(s.span),
+            );
+        } else {
+            self.structs.insert(s.ident.clone(), struct_ty.clone());
+        }
+
+        let mut fields: Vec<(String, Type)> = vec![];
+        for f in &s.fields {
+            if matches!(f.kind, BType::Void) {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::IncompleteType,
+                        "field has incomplete type `void`",
+                    )
+                    .with_span(f.span),
+                );
+                continue;
+            }
+            if fields.iter().any(|(ident, _)| *ident == f.ident) {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::Redefinition,
+                        format!("duplicate member `{}`", f.ident),
+                    )
+                    .with_span(f.span),
+                );
+                continue;
+            }
+            let dims = self.eval_dims(&f.dims);
+            let ty = Type::infer_from_dims(&self.resolve(&f.kind, f.span), &dims);
+            let elem_ty = ty.elem_ty();
+            if matches!(elem_ty.kind(), TypeKind::Struct(_, _)) && elem_ty.fields().is_none() {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::IncompleteType,
+                        format!("field has incomplete type `{}`", elem_ty),
+                    )
+                    .with_span(f.span),
+                );
+                continue;
+            }
+            fields.push((f.ident.clone(), ty));
+        }
+        if fields.is_empty() {
+            self.report(
+                Diagnostic::error(ErrorCode::IncompleteType, "struct has no members")
+                    .with_span(s.span),
+            );
+        }
+        struct_ty.define_struct(fields);
+    }
+}
+
+/// Whether `ty` is an array of unknown length, as in `extern int a[];`, of the same elements
+/// as the array `other`
+fn has_unknown_len(ty: &Type, other: &Type) -> bool {
+    match (ty.kind(), other.kind()) {
+        (TypeKind::Array(base_ty, 0), TypeKind::Array(other_base_ty, _)) => {
+            base_ty == other_base_ty
+        }
+        _ => false,
     }
 }
@@ -1,9 +1,13 @@
 use koopa::ir::{Type as IrType, TypeKind as IrTypeKind};
 use smallvec::SmallVec;
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
 use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
+use crate::ast::BType;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Type(Rc<TypeKind>);
 
@@ -14,9 +18,49 @@ pub type ParamTy = SmallVec<[Type; 6]>;
 pub enum TypeKind {
     Void,
     Integer,
+    Unsigned,
+    LongLong,
+    Float,
+    /// the element of a string literal
+    Char,
+    /// the base type and the length, which is 0 if it is unknown
     Array(Type, usize),
     Pointer(Type),
-    Func(Type, ParamTy),
+    /// return type, parameter types, and whether it is variadic
+    Func(Type, ParamTy, bool),
+    /// name and fields of a `struct`
+    Struct(String, Fields),
+}
+
+/// Fields of a `struct`, set at the end of its definition so that a field may point to it
+#[derive(Clone, Default)]
+pub struct Fields(Rc<OnceCell<Vec<Field>>>);
+
+impl PartialEq for Fields {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Fields {}
+
+impl Hash for Fields {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.0).hash(state);
+    }
+}
+
+impl Debug for Fields {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{ .. }}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Field {
+    pub ident: String,
+    pub ty: Type,
+    pub offset: usize,
 }
 
 impl Type {
@@ -44,6 +88,34 @@ impl Type {
         Self::get(TypeKind::Integer)
     }
 
+    pub fn get_unsigned() -> Type {
+        Self::get(TypeKind::Unsigned)
+    }
+
+    pub fn get_long_long() -> Type {
+        Self::get(TypeKind::LongLong)
+    }
+
+    pub fn get_float() -> Type {
+        Self::get(TypeKind::Float)
+    }
+
+    pub fn get_char() -> Type {
+        Self::get(TypeKind::Char)
+    }
+
+    pub fn from_btype(kind: &BType) -> Type {
+        match kind {
+            BType::Int => Self::get_int(),
+            BType::Unsigned => Self::get_unsigned(),
+            BType::LongLong => Self::get_long_long(),
+            BType::Float => Self::get_float(),
+            BType::Void => Self::get_void(),
+            BType::Enum(_) => Self::get_int(),
+            BType::Struct(_) | BType::Named(_) | BType::Pointer(_) => unreachable!(),
+        }
+    }
+
     pub fn get_array(base_ty: Type, len: usize) -> Type {
         Self::get(TypeKind::Array(base_ty, len))
     }
@@ -53,40 +125,164 @@ impl Type {
     }
 
     pub fn get_func(ret_ty: Type, param_tys: ParamTy) -> Type {
-        Self::get(TypeKind::Func(ret_ty, param_tys))
+        Self::get(TypeKind::Func(ret_ty, param_tys, false))
+    }
+
+    pub fn get_variadic_func(ret_ty: Type, param_tys: ParamTy) -> Type {
+        Self::get(TypeKind::Func(ret_ty, param_tys, true))
+    }
+
+    pub fn get_struct(ident: String) -> Type {
+        Self::get(TypeKind::Struct(ident, Fields::default()))
+    }
+
+    pub fn define_struct(&self, fields: Vec<(String, Type)>) {
+        let TypeKind::Struct(_, cell) = self.kind() else {
+            unreachable!()
+        };
+        let mut offset = 0usize;
+        let fields = fields
+            .into_iter()
+            .map(|(ident, ty)| {
+                offset = offset.next_multiple_of(ty.align());
+                let field = Field { ident, offset, ty };
+                offset += field.ty.size();
+                field
+            })
+            .collect();
+        cell.0.set(fields).unwrap();
+    }
+
+    pub fn fields(&self) -> Option<&[Field]> {
+        match self.kind() {
+            TypeKind::Struct(_, fields) => fields.0.get().map(Vec::as_slice),
+            _ => None,
+        }
+    }
+
+    pub fn field(&self, ident: &str) -> Option<&Field> {
+        self.fields()?.iter().find(|f| f.ident == ident)
     }
 
     pub fn kind(&self) -> &TypeKind {
         &self.0
     }
 
+    pub fn is_arith(&self) -> bool {
+        self.is_integral() || matches!(self.kind(), TypeKind::Float)
+    }
+
+    pub fn is_integral(&self) -> bool {
+        matches!(
+            self.kind(),
+            TypeKind::Integer | TypeKind::Unsigned | TypeKind::LongLong
+        )
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self.kind(), TypeKind::Unsigned)
+    }
+
+    /// Whether a value of the type is referred to by the pointer to its first word
+    pub fn is_by_ref(&self) -> bool {
+        matches!(self.kind(), TypeKind::Struct(_, _) | TypeKind::LongLong)
+    }
+
+    pub fn elem_ty(&self) -> &Type {
+        match self.kind() {
+            TypeKind::Array(base_ty, _) => base_ty.elem_ty(),
+            _ => self,
+        }
+    }
+
+    /// Size in words
     pub fn size(&self) -> usize {
         match self.kind() {
-            TypeKind::Integer => 1,
+            TypeKind::Integer | TypeKind::Unsigned | TypeKind::Float | TypeKind::Char => 1,
+            TypeKind::LongLong => 2,
+            TypeKind::Pointer(_) => Self::ptr_words(),
             TypeKind::Array(base_ty, len) => len * base_ty.size(),
+            TypeKind::Struct(_, _) => self.fields().unwrap().last().map_or(0, |f| {
+                (f.offset + f.ty.size()).next_multiple_of(self.align())
+            }),
             _ => unreachable!(),
         }
     }
 
+    pub fn align(&self) -> usize {
+        match self.kind() {
+            // a `long long` is loaded whole on RV64
+            TypeKind::Pointer(_) | TypeKind::LongLong => Self::ptr_words(),
+            TypeKind::Array(base_ty, _) => base_ty.align(),
+            TypeKind::Struct(_, _) => {
+                let fields = self.fields().unwrap();
+                fields.iter().map(|f| f.ty.align()).max().unwrap_or(1)
+            }
+            _ => 1,
+        }
+    }
+
+    fn ptr_words() -> usize {
+        IrType::get_pointer(IrType::get_i32()).size() / 4
+    }
+
     pub fn get_ir_ty(&self) -> IrType {
         match self.kind() {
-            TypeKind::Integer => IrType::get_i32(),
-            TypeKind::Array(base_ty, len) => IrType::get_array(base_ty.get_ir_ty(), *len),
+            TypeKind::Integer | TypeKind::Unsigned | TypeKind::Float | TypeKind::Char => {
+                IrType::get_i32()
+            }
+            // an array of unknown length is only declared `extern`, and never allocated
+            TypeKind::Array(base_ty, len) => IrType::get_array(base_ty.get_ir_ty(), (*len).max(1)),
             TypeKind::Void => IrType::get_unit(),
             TypeKind::Pointer(base_ty) => IrType::get_pointer(base_ty.get_ir_ty()),
-            TypeKind::Func(ret_ty, param_tys) => {
-                let param_ir_tys: Vec<_> = param_tys.iter().map(|t| t.get_ir_ty()).collect();
-                IrType::get(IrTypeKind::Function(param_ir_tys, ret_ty.get_ir_ty()))
+            TypeKind::Func(ret_ty, param_tys, _) => {
+                let (param_ir_tys, ret_ir_ty) = Self::get_func_ir_tys(ret_ty, param_tys);
+                IrType::get(IrTypeKind::Function(param_ir_tys, ret_ir_ty))
+            }
+            TypeKind::Struct(_, _) | TypeKind::LongLong => {
+                IrType::get_array(IrType::get_i32(), self.size())
             }
         }
     }
 
-    pub fn infer_from_dims(dims: &[usize]) -> Self {
+    /// Whether a value of the type is passed to and returned from a function through the
+    /// pointer to its words, which a `long long` is not on RV64, where it fits in a register as
+    /// the calling convention requires
+    pub fn is_passed_by_ref(&self) -> bool {
+        match self.kind() {
+            TypeKind::LongLong => Self::ptr_words() == 1,
+            _ => self.is_by_ref(),
+        }
+    }
+
+    /// A `long long` in a register is an `i32` in Koopa IR, which is operated on by intrinsics
+    pub fn get_param_ir_ty(&self) -> IrType {
+        match self.is_by_ref() {
+            true if self.is_passed_by_ref() => IrType::get_pointer(IrType::get_i32()),
+            true => IrType::get_i32(),
+            false => self.get_ir_ty(),
+        }
+    }
+
+    /// A `long long` is returned through the pointer passed after the parameters, unless it
+    /// is returned in a register
+    pub fn get_func_ir_tys(ret_ty: &Type, param_tys: &[Type]) -> (Vec<IrType>, IrType) {
+        let mut param_ir_tys: Vec<_> = param_tys.iter().map(Type::get_param_ir_ty).collect();
+        match ret_ty.is_passed_by_ref() {
+            true => {
+                param_ir_tys.push(ret_ty.get_param_ir_ty());
+                (param_ir_tys, IrType::get_unit())
+            }
+            false => (param_ir_tys, ret_ty.get_param_ir_ty()),
+        }
+    }
+
+    pub fn infer_from_dims(elem_ty: &Type, dims: &[usize]) -> Self {
         if dims.is_empty() {
-            return Self::get_int();
+            return elem_ty.clone();
         }
         let len = *dims.first().unwrap();
-        let base_ty = Self::infer_from_dims(&dims[1..]);
+        let base_ty = Self::infer_from_dims(elem_ty, &dims[1..]);
         Self::get_array(base_ty, len)
     }
 
@@ -96,8 +292,62 @@ impl Type {
                 dims.push(*len);
                 base_ty.get_dims(dims);
             }
-            TypeKind::Integer => {}
+            TypeKind::Integer
+            | TypeKind::Unsigned
+            | TypeKind::LongLong
+            | TypeKind::Float
+            | TypeKind::Char
+            | TypeKind::Pointer(_)
+            | TypeKind::Struct(_, _) => {}
             _ => unreachable!(),
         }
     }
 }
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let write_dims = |f: &mut std::fmt::Formatter<'_>, ty: &Type| {
+            let mut dims = DimTy::new();
+            ty.get_dims(&mut dims);
+            dims.iter().try_for_each(|d| match d {
+                0 => write!(f, "[]"),
+                _ => write!(f, "[{}]", d),
+            })
+        };
+
+        match self.kind() {
+            TypeKind::Void => write!(f, "void"),
+            TypeKind::Integer => write!(f, "int"),
+            TypeKind::Unsigned => write!(f, "unsigned int"),
+            TypeKind::LongLong => write!(f, "long long"),
+            TypeKind::Float => write!(f, "float"),
+            TypeKind::Char => write!(f, "char"),
+            TypeKind::Struct(ident, _) => write!(f, "struct {}", ident),
+            TypeKind::Array(_, _) => {
+                write!(f, "{}", self.elem_ty())?;
+                write_dims(f, self)
+            }
+            TypeKind::Pointer(base_ty) => match base_ty.kind() {
+                TypeKind::Array(_, _) => {
+                    write!(f, "{} (*)", base_ty.elem_ty())?;
+                    write_dims(f, base_ty)
+                }
+                TypeKind::Pointer(_) => write!(f, "{}*", base_ty),
+                _ => write!(f, "{} *", base_ty),
+            },
+            TypeKind::Func(ret_ty, param_tys, variadic) => {
+                write!(f, "{} (", ret_ty)?;
+                for (i, param_ty) in param_tys.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param_ty)?;
+                }
+                if *variadic {
+                    write!(f, ", ...")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
@@ -0,0 +1,852 @@
+use std::collections::HashSet;
+
+use crate::ast::*;
+use crate::diag::{Diagnostic, Diagnostics, ErrorCode};
+
+use super::eval::ConstValue;
+use super::init::{check_init_list, init_elems, InitError};
+use super::symbol::SymbolTable;
+use super::ty::{Type, TypeKind};
+
+/// Annotate every expression with its type, check that values are used consistently
+/// with their types, and make the implicit conversions explicit. An expression whose type
+/// cannot be determined is annotated with `None`.
+#[derive(Debug)]
+pub struct TypeChecker<'s> {
+    symbols: &'s SymbolTable,
+    /// return type of the current function
+    ret_ty: Option<Type>,
+    loop_depth: usize,
+    /// promoted types of the conditions of the enclosing `switch`es, and their labels
+    switches: Vec<(Type, HashSet<Option<i64>>)>,
+    pub diags: Diagnostics,
+}
+
+impl<'s> TypeChecker<'s> {
+    pub fn new(symbols: &'s SymbolTable) -> Self {
+        Self {
+            symbols,
+            ret_ty: None,
+            loop_depth: 0,
+            switches: vec![],
+            diags: Diagnostics::new(),
+        }
+    }
+
+    fn report(&mut self, diag: Diagnostic) {
+        self.diags.push(diag);
+    }
+
+    fn mismatched(&mut self, expected: &str, e: &Expr) {
+        let diag = match e.ty().kind() {
+            TypeKind::Void => Diagnostic::error(
+                ErrorCode::TypeMismatch,
+                "`void` value cannot be used in an expression",
+            ),
+            _ => Diagnostic::error(
+                ErrorCode::TypeMismatch,
+                format!(
+                    "mismatched types: expected {}, found `{}`",
+                    expected,
+                    e.ty()
+                ),
+            ),
+        };
+        self.report(diag.with_span(e.span));
+    }
+
+    /// Check that the expression produces an integer value of any type
+    fn expect_integral(&mut self, e: &Expr) -> bool {
+        match &e.ty {
+            Some(ty) if ty.is_integral() => true,
+            Some(_) => {
+                self.mismatched("an integer type", e);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Check for an integer value, and narrow it to `int`, e.g. as an index or a shift amount
+    fn narrow_to_int(&mut self, e: &mut Expr) {
+        if self.expect_integral(e) {
+            self.coerce(e, &Type::get_int());
+        }
+    }
+
+    /// Check that the expression produces an arithmetic value
+    fn expect_arith(&mut self, e: &Expr) -> bool {
+        match &e.ty {
+            Some(ty) if ty.is_arith() => true,
+            Some(_) => {
+                self.mismatched("an arithmetic type", e);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Convert an arithmetic expression to the given arithmetic type
+    fn coerce(&mut self, e: &mut Expr, ty: &Type) {
+        if !self.expect_arith(e) || e.ty() == ty {
+            return;
+        }
+
+        match e.as_const() {
+            Some(value) => {
+                let (converted, diag) = value.cast_checked(ty, e.span);
+                if let Some(diag) = diag {
+                    self.report(diag);
+                }
+                e.kind = converted.into();
+            }
+            None => return self.cast(e, ty),
+        }
+        e.ty = Some(ty.clone());
+    }
+
+    /// Common type of arithmetic operands by the usual arithmetic conversions, i.e. the later
+    /// one of `int`, `unsigned int`, `long long` and `float`
+    fn common_ty(lhs: &Type, rhs: &Type) -> Type {
+        match (lhs.kind(), rhs.kind()) {
+            (TypeKind::Float, _) | (_, TypeKind::Float) => Type::get_float(),
+            (TypeKind::LongLong, _) | (_, TypeKind::LongLong) => Type::get_long_long(),
+            (TypeKind::Unsigned, _) | (_, TypeKind::Unsigned) => Type::get_unsigned(),
+            _ => Type::get_int(),
+        }
+    }
+
+    /// Wrap the expression in an implicit conversion to the given type
+    fn cast(&mut self, e: &mut Expr, ty: &Type) {
+        let span = e.span;
+        let inner = std::mem::replace(e, Expr::new(ExprKind::Error, span));
+        *e = Expr::new(ExprKind::Cast(Box::new(inner)), span);
+        e.ty = Some(ty.clone());
+    }
+
+    /// Decay an array to a pointer, and return whether the expression is a pointer
+    fn decay(&mut self, e: &mut Expr) -> bool {
+        match e.ty.as_ref().map(Type::kind) {
+            Some(TypeKind::Array(base_ty, _)) => {
+                let base_ty = base_ty.clone();
+                self.cast(e, &Type::get_pointer(base_ty));
+                true
+            }
+            Some(TypeKind::Pointer(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Check that an initializer list of an array or a `struct` has no more elements than it,
+    /// and that its constant elements fit the scalars they initialize
+    fn check_list(&mut self, init: &InitVal, ty: &Type, span: Span) {
+        let InitVal::List(list) = init else {
+            return;
+        };
+        if !matches!(ty.kind(), TypeKind::Array(_, _) | TypeKind::Struct(_, _)) {
+            return;
+        }
+        for err in check_init_list(list, ty, span) {
+            if let InitError::Excess(span) = err {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::InvalidInitializer,
+                        "excess elements in initializer",
+                    )
+                    .with_span(span),
+                );
+            }
+        }
+        for (e, elem_ty) in init_elems(list, ty, span) {
+            if let (Some(value), true) = (e.as_const(), elem_ty.is_arith()) {
+                let (_, diag) = value.cast_checked(&elem_ty, e.span);
+                if let Some(diag) = diag {
+                    self.report(diag);
+                }
+            }
+        }
+    }
+
+    /// Check that the bytes of a string are not accessed through a value of the type
+    fn check_not_char(&mut self, ty: &Type, span: Span) -> bool {
+        let is_char = match ty.kind() {
+            TypeKind::Pointer(base_ty) => matches!(base_ty.kind(), TypeKind::Char),
+            kind => matches!(kind, TypeKind::Char),
+        };
+        if is_char {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::Unsupported,
+                    "accessing the characters of a string is not supported",
+                )
+                .with_span(span),
+            );
+        }
+        !is_char
+    }
+
+    /// Check that a label, whose value is `None` for `default`, is unique in its `switch`
+    fn check_label(&mut self, value: Option<&mut Expr>, span: Span) {
+        let Some((ty, _)) = self.switches.last() else {
+            let label = if value.is_some() { "case" } else { "default" };
+            return self.report(
+                Diagnostic::error(
+                    ErrorCode::MisplacedStatement,
+                    format!("`{}` label not within a `switch` statement", label),
+                )
+                .with_span(span),
+            );
+        };
+        let ty = ty.clone();
+        let label = match value {
+            None => None,
+            Some(e) => {
+                let value = match e.kind {
+                    ExprKind::Integer(i) => ConstValue::Int(i),
+                    ExprKind::Unsigned(u) => ConstValue::UInt(u),
+                    ExprKind::Long(l) => ConstValue::Long(l),
+                    _ if e.ty.is_none() => return,
+                    ExprKind::Float(_) => return self.mismatched("an integer type", e),
+                    _ => {
+                        return self.report(
+                            Diagnostic::error(
+                                ErrorCode::NotConstant,
+                                "case value is not a constant",
+                            )
+                            .with_span(e.span),
+                        )
+                    }
+                };
+                let value = value.cast(&ty);
+                e.kind = value.into();
+                e.ty = Some(ty);
+                Some(value)
+            }
+        };
+        if !self
+            .switches
+            .last_mut()
+            .unwrap()
+            .1
+            .insert(label.map(ConstValue::as_long))
+        {
+            let diag = match label {
+                Some(v) => Diagnostic::error(
+                    ErrorCode::DuplicateCase,
+                    format!("duplicate case value `{}`", v.as_long()),
+                ),
+                None => Diagnostic::error(
+                    ErrorCode::MisplacedStatement,
+                    "multiple `default` labels in one `switch`",
+                ),
+            };
+            self.report(diag.with_span(span));
+        }
+    }
+
+    fn is_null(e: &Expr) -> bool {
+        matches!(e.kind, ExprKind::Integer(0))
+    }
+
+    /// Convert a value to be stored in a place of the given type
+    fn convert(&mut self, e: &mut Expr, ty: &Type) {
+        match ty.kind() {
+            TypeKind::Pointer(_) => self.convert_to_pointer(e, ty),
+            TypeKind::Struct(_, _) => self.expect_same(e, ty),
+            _ if ty.is_arith() => self.coerce(e, ty),
+            _ => {}
+        }
+    }
+
+    fn convert_to_pointer(&mut self, e: &mut Expr, ty: &Type) {
+        let e_ty = match &e.ty {
+            Some(e_ty) => e_ty.clone(),
+            None => return,
+        };
+        match e_ty.kind() {
+            _ if e_ty == *ty => {}
+            TypeKind::Array(base_ty, _) if Type::get_pointer(base_ty.clone()) == *ty => {
+                self.decay(e);
+            }
+            _ if Self::is_null(e) => self.cast(e, ty),
+            _ => self.mismatched(&format!("`{}`", ty), e),
+        }
+    }
+
+    /// Convert an expression used as a condition to an `int`, i.e. compare a `long long` or
+    /// a `float` with zero, or take the address of a pointer or the bits of an `unsigned int`
+    fn condition(&mut self, e: &mut Expr) {
+        if self.decay(e) {
+            return self.cast(e, &Type::get_int());
+        }
+        if e.ty.as_ref().is_some_and(Type::is_unsigned) {
+            return self.coerce(e, &Type::get_int());
+        }
+        if !self.expect_arith(e) || matches!(e.ty().kind(), TypeKind::Integer) {
+            return;
+        }
+
+        let span = e.span;
+        let ty = e.ty().clone();
+        let lhs = std::mem::replace(e, Expr::new(ExprKind::Error, span));
+        let mut rhs = Expr::new(ConstValue::Int(0).cast(&ty).into(), span);
+        rhs.ty = Some(ty);
+        *e = Expr::new(
+            ExprKind::Binary(BinaryExpr {
+                op: BinaryOp::Neq,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            }),
+            span,
+        );
+        e.ty = Some(Type::get_int());
+    }
+
+    /// Convert both operands to their common arithmetic type, which is returned
+    fn unify(&mut self, lhs: &mut Expr, rhs: &mut Expr) -> Option<Type> {
+        if !self.expect_arith(lhs) | !self.expect_arith(rhs) {
+            return None;
+        }
+        let ty = Self::common_ty(lhs.ty(), rhs.ty());
+        self.coerce(lhs, &ty);
+        self.coerce(rhs, &ty);
+        Some(ty)
+    }
+
+    /// Convert both values of a conditional expression to their common type
+    fn unify_values(&mut self, lhs: &mut Expr, rhs: &mut Expr) -> Option<Type> {
+        if self.decay(lhs) {
+            let ty = lhs.ty().clone();
+            self.convert_to_pointer(rhs, &ty);
+            Some(ty)
+        } else if self.decay(rhs) {
+            let ty = rhs.ty().clone();
+            self.convert_to_pointer(lhs, &ty);
+            Some(ty)
+        } else {
+            self.unify(lhs, rhs)
+        }
+    }
+
+    fn expect_same(&mut self, e: &Expr, ty: &Type) {
+        match &e.ty {
+            Some(e_ty) if e_ty != ty => self.mismatched(&format!("`{}`", ty), e),
+            _ => {}
+        }
+    }
+
+    /// Check that the expression can be assigned, and return its type
+    fn assigned_ty(&mut self, lval: &Expr) -> Option<Type> {
+        let ty = lval.ty.clone()?;
+        if !lval.is_place() {
+            self.report(
+                Diagnostic::error(ErrorCode::NotAssignable, "expression is not assignable")
+                    .with_span(lval.span),
+            );
+            return None;
+        }
+        Some(ty)
+    }
+
+    fn invalid_operands(&mut self, bxp: &BinaryExpr, span: Span) {
+        self.report(
+            Diagnostic::error(
+                ErrorCode::TypeMismatch,
+                format!(
+                    "invalid operands of types `{}` and `{}`",
+                    bxp.lhs.ty(),
+                    bxp.rhs.ty()
+                ),
+            )
+            .with_span(span),
+        );
+    }
+
+    /// Type of `lhs + rhs` or `lhs - rhs` where an operand is a pointer
+    fn pointer_arith_ty(&mut self, bxp: &mut BinaryExpr, span: Span) -> Option<Type> {
+        let lhs_ty = bxp.lhs.ty().clone();
+        let rhs_ty = bxp.rhs.ty().clone();
+        if !self.check_not_char(&lhs_ty, span) || !self.check_not_char(&rhs_ty, span) {
+            return None;
+        }
+        match (lhs_ty.kind(), rhs_ty.kind(), bxp.op) {
+            (TypeKind::Pointer(_), _, _) if rhs_ty.is_integral() => {
+                self.narrow_to_int(&mut bxp.rhs);
+                Some(lhs_ty)
+            }
+            (_, TypeKind::Pointer(_), BinaryOp::Add) if lhs_ty.is_integral() => {
+                self.narrow_to_int(&mut bxp.lhs);
+                Some(rhs_ty)
+            }
+            (TypeKind::Pointer(_), TypeKind::Pointer(_), BinaryOp::Sub) if lhs_ty == rhs_ty => {
+                Some(Type::get_int())
+            }
+            _ => {
+                self.invalid_operands(bxp, span);
+                None
+            }
+        }
+    }
+
+    fn compare_pointers(&mut self, bxp: &mut BinaryExpr, span: Span) {
+        let int = Type::get_int();
+        match (bxp.lhs.ty().kind(), bxp.rhs.ty().kind()) {
+            (TypeKind::Pointer(_), TypeKind::Pointer(_)) if bxp.lhs.ty() == bxp.rhs.ty() => {
+                self.cast(&mut bxp.lhs, &int);
+                self.cast(&mut bxp.rhs, &int);
+            }
+            (TypeKind::Pointer(_), _) if Self::is_null(&bxp.rhs) => self.cast(&mut bxp.lhs, &int),
+            (_, TypeKind::Pointer(_)) if Self::is_null(&bxp.lhs) => self.cast(&mut bxp.rhs, &int),
+            _ => self.invalid_operands(bxp, span),
+        }
+    }
+
+    fn index_ty(&mut self, ty: &Type, dims: &mut [Expr], span: Span) -> Option<Type> {
+        let mut ty = ty.clone();
+        for dim in dims {
+            self.narrow_to_int(dim);
+            ty = match ty.kind() {
+                TypeKind::Array(base_ty, _) | TypeKind::Pointer(base_ty) => base_ty.clone(),
+                _ => {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::TypeMismatch,
+                            format!("cannot index into a value of type `{}`", ty),
+                        )
+                        .with_span(span),
+                    );
+                    return None;
+                }
+            };
+        }
+        self.check_not_char(&ty, span).then_some(ty)
+    }
+
+    fn member_ty(&mut self, ty: &Type, member: &mut Member) -> Option<Type> {
+        let ty = match ty.kind() {
+            TypeKind::Pointer(base_ty) if member.arrow => base_ty,
+            _ if member.arrow => {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::TypeMismatch,
+                        format!("member reference type `{}` is not a pointer", ty),
+                    )
+                    .with_span(member.span),
+                );
+                return None;
+            }
+            _ => ty,
+        };
+        if !matches!(ty.kind(), TypeKind::Struct(_, _)) {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::TypeMismatch,
+                    format!("member reference base type `{}` is not a structure", ty),
+                )
+                .with_span(member.span),
+            );
+            return None;
+        }
+        match ty.field(&member.ident) {
+            Some(field) => self.index_ty(&field.ty, &mut member.dims, member.span),
+            None => {
+                self.report(
+                    Diagnostic::error(
+                        ErrorCode::NoMember,
+                        format!("no member named `{}` in `{}`", member.ident, ty),
+                    )
+                    .with_span(member.span),
+                );
+                None
+            }
+        }
+    }
+
+    fn lval_ty(&mut self, lval: &mut LVal) -> Option<Type> {
+        let mut ty = self.symbols.data.get(&lval.ident)?.clone();
+        if matches!(ty.kind(), TypeKind::Func(..)) {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::TypeMismatch,
+                    "function cannot be used as a value",
+                )
+                .with_span(lval.span),
+            );
+            return None;
+        }
+
+        ty = self.index_ty(&ty, &mut lval.dims, lval.span)?;
+        for member in &mut lval.members {
+            ty = self.member_ty(&ty, member)?;
+        }
+        Some(ty)
+    }
+
+    fn call_ty(&mut self, call: &mut Call) -> Option<Type> {
+        let ty = self.symbols.data.get(&call.ident)?;
+        let (ret_ty, param_tys, variadic) = match ty.kind() {
+            TypeKind::Func(ret_ty, param_tys, variadic) => (ret_ty, param_tys, *variadic),
+            _ => {
+                self.report(
+                    Diagnostic::error(ErrorCode::TypeMismatch, "called object is not a function")
+                        .with_span(call.span),
+                );
+                return None;
+            }
+        };
+
+        let (params, args) = (param_tys.len(), call.args.len());
+        if args != params && !(variadic && args > params) {
+            let arguments = |n| match n {
+                1 => "1 argument".to_string(),
+                n => format!("{} arguments", n),
+            };
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::ArgumentCount,
+                    format!(
+                        "this function takes {}{} but {} {} supplied",
+                        if variadic { "at least " } else { "" },
+                        arguments(params),
+                        arguments(args),
+                        if args == 1 { "was" } else { "were" }
+                    ),
+                )
+                .with_span(call.span),
+            );
+        } else {
+            for (arg, param_ty) in call.args.iter_mut().zip(param_tys.iter()) {
+                self.convert(arg, param_ty);
+            }
+            // the variable arguments are passed as they are, except that arrays decay
+            for arg in &mut call.args[params..] {
+                if !self.decay(arg) {
+                    self.expect_arith(arg);
+                }
+            }
+        }
+        Some(ret_ty.clone())
+    }
+
+    fn compound_assign_ty(&mut self, c: &mut CompoundAssign) -> Option<Type> {
+        let ty = self.assigned_ty(&c.lval)?;
+        if matches!(ty.kind(), TypeKind::Pointer(_))
+            && matches!(c.op, BinaryOp::Add | BinaryOp::Sub)
+        {
+            self.narrow_to_int(&mut c.val);
+            return Some(ty);
+        }
+        if !ty.is_arith() {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::NotAssignable,
+                    format!("cannot assign to a value of type `{}`", ty),
+                )
+                .with_span(c.lval.span),
+            );
+            return None;
+        }
+
+        if c.op.is_integral() && !ty.is_integral() {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::TypeMismatch,
+                    format!("mismatched types: expected an integer type, found `{}`", ty),
+                )
+                .with_span(c.lval.span),
+            );
+        }
+        let is_operand = match c.op {
+            BinaryOp::Shl | BinaryOp::Shr => {
+                self.narrow_to_int(&mut c.val);
+                false
+            }
+            op if op.is_integral() => self.expect_integral(&c.val),
+            _ => self.expect_arith(&c.val),
+        };
+        if is_operand {
+            // the operation is done in the common type of both sides, e.g. `i += 0.5` in `float`
+            let op_ty = Self::common_ty(&ty, c.val.ty());
+            self.coerce(&mut c.val, &op_ty);
+        }
+        Some(ty)
+    }
+
+    fn binary_ty(&mut self, bxp: &mut BinaryExpr, span: Span) -> Option<Type> {
+        let is_pointer = !matches!(bxp.op, BinaryOp::And | BinaryOp::Or)
+            && (self.decay(&mut bxp.lhs) | self.decay(&mut bxp.rhs));
+        match bxp.op {
+            BinaryOp::And | BinaryOp::Or => {
+                self.condition(&mut bxp.lhs);
+                self.condition(&mut bxp.rhs);
+                Some(Type::get_int())
+            }
+            BinaryOp::Mod | BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor => {
+                if self.expect_integral(&bxp.lhs) & self.expect_integral(&bxp.rhs) {
+                    self.unify(&mut bxp.lhs, &mut bxp.rhs)
+                } else {
+                    Some(Type::get_int())
+                }
+            }
+            // a shift has the type of its left operand
+            BinaryOp::Shl | BinaryOp::Shr => {
+                self.narrow_to_int(&mut bxp.rhs);
+                match self.expect_integral(&bxp.lhs) {
+                    true => Some(bxp.lhs.ty().clone()),
+                    false => Some(Type::get_int()),
+                }
+            }
+            BinaryOp::Add | BinaryOp::Sub if is_pointer => self.pointer_arith_ty(bxp, span),
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                self.unify(&mut bxp.lhs, &mut bxp.rhs)
+            }
+            BinaryOp::Eq
+            | BinaryOp::Neq
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => {
+                if is_pointer {
+                    self.compare_pointers(bxp, span);
+                } else {
+                    self.unify(&mut bxp.lhs, &mut bxp.rhs);
+                }
+                Some(Type::get_int())
+            }
+        }
+    }
+}
+
+impl<'ast, 's> MutVisitor<'ast> for TypeChecker<'s> {
+    fn visit_func_def(&mut self, f: &'ast mut FuncDef) {
+        if let Some(TypeKind::Func(ret_ty, ..)) = self.symbols.data.get(&f.ident).map(Type::kind) {
+            self.ret_ty = Some(ret_ty.clone());
+        }
+        walk_func_def(self, f);
+        self.ret_ty = None;
+    }
+
+    fn visit_var_decl(&mut self, v: &'ast mut VarDecl) {
+        walk_var_decl(self, v);
+        let ty = match self.symbols.data.get(&v.lval.ident) {
+            Some(ty) => ty.clone(),
+            None => return,
+        };
+        match &mut v.init {
+            Some(InitVal::Expr(e)) => self.convert(e, &ty),
+            Some(init) => self.check_list(init, &ty, v.span),
+            None => {}
+        }
+    }
+
+    fn visit_const_decl(&mut self, c: &'ast mut ConstDecl) {
+        walk_const_decl(self, c);
+        let ty = match c.is_scalar() {
+            true => Type::from_btype(&c.kind),
+            false => match self.symbols.data.get(&c.lval.ident) {
+                Some(ty) => ty.clone(),
+                None => return,
+            },
+        };
+        match &mut c.init {
+            InitVal::Expr(e) => self.convert(e, &ty),
+            init => self.check_list(init, &ty, c.span),
+        }
+    }
+
+    fn visit_stmt(&mut self, s: &'ast mut Stmt) {
+        match &s.kind {
+            StmtKind::Break(_) if self.loop_depth == 0 && self.switches.is_empty() => self.report(
+                Diagnostic::error(
+                    ErrorCode::MisplacedStatement,
+                    "`break` statement not within a loop or `switch`",
+                )
+                .with_span(s.span),
+            ),
+            StmtKind::Continue(_) if self.loop_depth == 0 => self.report(
+                Diagnostic::error(
+                    ErrorCode::MisplacedStatement,
+                    "`continue` statement not within a loop",
+                )
+                .with_span(s.span),
+            ),
+            StmtKind::Return(r) => match (&r.ret_val, self.ret_ty.as_ref().map(Type::kind)) {
+                (Some(_), Some(TypeKind::Void)) => self.report(
+                    Diagnostic::error(
+                        ErrorCode::ReturnValue,
+                        "void function should not return a value",
+                    )
+                    .with_span(s.span),
+                ),
+                (
+                    None,
+                    Some(
+                        TypeKind::Integer
+                        | TypeKind::Unsigned
+                        | TypeKind::LongLong
+                        | TypeKind::Float
+                        | TypeKind::Pointer(_),
+                    ),
+                ) => self.report(
+                    Diagnostic::error(
+                        ErrorCode::ReturnValue,
+                        "non-void function should return a value",
+                    )
+                    .with_span(s.span),
+                ),
+                _ => {}
+            },
+            _ => {}
+        }
+        walk_stmt(self, s);
+        let span = s.span;
+        match &mut s.kind {
+            StmtKind::Case(c) => self.check_label(Some(&mut c.value), span),
+            StmtKind::Default => self.check_label(None, span),
+            _ => {}
+        }
+    }
+
+    fn visit_assign(&mut self, a: &'ast mut Assign) {
+        walk_assign(self, a);
+        match self.assigned_ty(&a.lval) {
+            Some(ty) if matches!(ty.kind(), TypeKind::Array(_, _)) => self.report(
+                Diagnostic::error(
+                    ErrorCode::NotAssignable,
+                    format!("cannot assign to a value of type `{}`", ty),
+                )
+                .with_span(a.lval.span),
+            ),
+            Some(ty) => self.convert(&mut a.val, &ty),
+            None => {}
+        }
+    }
+
+    fn visit_branch(&mut self, b: &'ast mut Branch) {
+        walk_branch(self, b);
+        self.condition(&mut b.cond);
+    }
+
+    fn visit_while(&mut self, w: &'ast mut While) {
+        self.loop_depth += 1;
+        walk_while(self, w);
+        self.loop_depth -= 1;
+        self.condition(&mut w.cond);
+    }
+
+    fn visit_do_while(&mut self, d: &'ast mut DoWhile) {
+        self.loop_depth += 1;
+        walk_do_while(self, d);
+        self.loop_depth -= 1;
+        self.condition(&mut d.cond);
+    }
+
+    fn visit_for(&mut self, f: &'ast mut For) {
+        self.loop_depth += 1;
+        walk_for(self, f);
+        self.loop_depth -= 1;
+        if let Some(cond) = &mut f.cond {
+            self.condition(cond);
+        }
+    }
+
+    fn visit_switch(&mut self, s: &'ast mut Switch) {
+        self.visit_expr(&mut s.cond);
+        self.expect_integral(&s.cond);
+        let ty = match s.cond.ty.as_ref().map(Type::kind) {
+            Some(TypeKind::Unsigned) => Type::get_unsigned(),
+            Some(TypeKind::LongLong) => Type::get_long_long(),
+            _ => Type::get_int(),
+        };
+        self.switches.push((ty, HashSet::new()));
+        self.visit_stmt(&mut s.stmt);
+        self.switches.pop();
+    }
+
+    fn visit_return(&mut self, r: &'ast mut Return) {
+        walk_return(self, r);
+        if let (Some(e), Some(ret_ty)) = (&mut r.ret_val, self.ret_ty.clone()) {
+            self.convert(e, &ret_ty);
+        }
+    }
+
+    fn visit_expr(&mut self, e: &'ast mut Expr) {
+        walk_expr(self, e);
+
+        e.ty = match &mut e.kind {
+            ExprKind::Integer(_) => Some(Type::get_int()),
+            ExprKind::Unsigned(_) => Some(Type::get_unsigned()),
+            ExprKind::Long(_) => Some(Type::get_long_long()),
+            ExprKind::Float(_) => Some(Type::get_float()),
+            // with the terminating null character
+            ExprKind::String(s) => Some(Type::get_array(Type::get_char(), s.len() + 1)),
+            ExprKind::LVal(lval) => self.lval_ty(lval),
+            ExprKind::Member(base, member) => {
+                if member.arrow {
+                    self.decay(base);
+                }
+                let ty = base.ty.clone();
+                ty.and_then(|ty| self.member_ty(&ty, member))
+            }
+            ExprKind::Unary(UnaryExpr::Call(call)) => self.call_ty(call),
+            ExprKind::Unary(UnaryExpr::Unary(UnaryOp::Not, opr)) => {
+                self.condition(opr);
+                Some(Type::get_int())
+            }
+            ExprKind::Unary(UnaryExpr::Unary(UnaryOp::BitNot, opr)) => {
+                match self.expect_integral(opr) {
+                    true => Some(opr.ty().clone()),
+                    false => Some(Type::get_int()),
+                }
+            }
+            ExprKind::Unary(UnaryExpr::Unary(_, opr)) => {
+                self.expect_arith(opr).then(|| opr.ty().clone())
+            }
+            ExprKind::Binary(bxp) => self.binary_ty(bxp, e.span),
+            ExprKind::CompoundAssign(c) => self.compound_assign_ty(c),
+            ExprKind::Conditional(c) => {
+                self.condition(&mut c.cond);
+                self.unify_values(&mut c.true_val, &mut c.false_val)
+            }
+            ExprKind::Deref(opr) => {
+                self.decay(opr);
+                match opr.ty.as_ref().map(Type::kind) {
+                    Some(TypeKind::Pointer(base_ty)) => {
+                        let base_ty = base_ty.clone();
+                        self.check_not_char(&base_ty, e.span).then_some(base_ty)
+                    }
+                    Some(_) => {
+                        self.report(
+                            Diagnostic::error(
+                                ErrorCode::TypeMismatch,
+                                format!(
+                                    "indirection requires a pointer operand (`{}` invalid)",
+                                    opr.ty()
+                                ),
+                            )
+                            .with_span(e.span),
+                        );
+                        None
+                    }
+                    None => None,
+                }
+            }
+            ExprKind::AddrOf(opr) => match opr.ty.clone() {
+                Some(ty) if opr.is_place() => Some(Type::get_pointer(ty)),
+                Some(ty) => {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::TypeMismatch,
+                            format!("cannot take the address of an rvalue of type `{}`", ty),
+                        )
+                        .with_span(e.span),
+                    );
+                    None
+                }
+                None => None,
+            },
+            ExprKind::Cast(_) => e.ty.clone(),
+            ExprKind::Error => None,
+        };
+    }
+}
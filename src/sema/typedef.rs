@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::diag::{Diagnostic, Diagnostics, ErrorCode};
+
+/// Replace the names of `typedef`s in declarations with the types they name, e.g. `row a[2]`
+/// becomes `int a[2][16]` after `typedef int row[16];`
+#[derive(Debug, Default)]
+pub struct TypedefResolver {
+    /// element type and dimensions of the type named by each `typedef`
+    typedefs: HashMap<String, (BType, Vec<Expr>)>,
+    pub diags: Diagnostics,
+}
+
+impl TypedefResolver {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn report(&mut self, diag: Diagnostic) {
+        self.diags.push(diag);
+    }
+
+    /// Element type and dimensions of the type of a declaration
+    fn resolve(&mut self, kind: &BType, span: Span) -> (BType, Vec<Expr>) {
+        match kind {
+            BType::Named(ident) => match self.typedefs.get(ident) {
+                Some((kind, dims)) => (kind.clone(), dims.clone()),
+                None => {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::Undeclared,
+                            format!("unknown type name `{}`", ident),
+                        )
+                        .with_span(span),
+                    );
+                    (BType::Int, vec![])
+                }
+            },
+            BType::Pointer(base) => {
+                let (base, dims) = self.resolve(base, span);
+                if !dims.is_empty() {
+                    self.report(
+                        Diagnostic::error(
+                            ErrorCode::Unsupported,
+                            "pointer to an array type is not supported",
+                        )
+                        .with_span(span),
+                    );
+                }
+                (BType::Pointer(Box::new(base)), vec![])
+            }
+            _ => (kind.clone(), vec![]),
+        }
+    }
+
+    fn resolve_decl(&mut self, kind: &mut BType, dims: &mut Vec<Expr>, span: Span) {
+        let (elem, elem_dims) = self.resolve(kind, span);
+        *kind = elem;
+        dims.extend(elem_dims);
+    }
+}
+
+impl<'ast> MutVisitor<'ast> for TypedefResolver {
+    fn visit_typedef(&mut self, t: &'ast mut Typedef) {
+        self.resolve_decl(&mut t.kind, &mut t.dims, t.span);
+        self.typedefs
+            .insert(t.ident.clone(), (t.kind.clone(), t.dims.clone()));
+    }
+
+    fn visit_func_def(&mut self, f: &'ast mut FuncDef) {
+        let (ret_kind, dims) = self.resolve(&f.ret_kind, f.span);
+        if !dims.is_empty() {
+            self.report(
+                Diagnostic::error(
+                    ErrorCode::InvalidType,
+                    "function cannot return an array type",
+                )
+                .with_span(f.span),
+            );
+        }
+        f.ret_kind = ret_kind;
+        walk_func_def(self, f);
+    }
+
+    fn visit_func_param(&mut self, f: &'ast mut FuncParam) {
+        let (kind, dims) = self.resolve(&f.kind, f.span);
+        f.kind = kind;
+        match &mut f.dims {
+            Some(param_dims) => param_dims.extend(dims),
+            // an array parameter is a pointer to its first element
+            None if !dims.is_empty() => f.dims = Some(dims.into_iter().skip(1).collect()),
+            None => {}
+        }
+    }
+
+    fn visit_field(&mut self, f: &'ast mut Field) {
+        self.resolve_decl(&mut f.kind, &mut f.dims, f.span);
+    }
+
+    fn visit_const_decl(&mut self, c: &'ast mut ConstDecl) {
+        self.resolve_decl(&mut c.kind, &mut c.lval.dims, c.span);
+    }
+
+    fn visit_var_decl(&mut self, v: &'ast mut VarDecl) {
+        self.resolve_decl(&mut v.kind, &mut v.lval.dims, v.span);
+    }
+}
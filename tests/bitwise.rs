@@ -0,0 +1,57 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn bitwise_operators() {
+    let src = "int main() {
+                 int a = 12, b = 10;
+                 putint(a & b); putch(32);
+                 putint(a | b); putch(32);
+                 putint(a ^ b); putch(32);
+                 putint(~a); putch(32);
+                 putint(-1 & 255);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "8 14 6 -13 255");
+}
+
+#[test]
+fn shifts_are_arithmetic_on_int() {
+    let src = "int main() {
+                 int a = -16, s = 2;
+                 putint(a >> s); putch(32);
+                 putint(a << s); putch(32);
+                 putint(1 << 31); putch(32);
+                 putint(3 << 4 >> 1);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "-4 -64 -2147483648 24");
+}
+
+#[test]
+fn precedence_follows_c() {
+    let src = "int main() {
+                 putint(1 | 2 ^ 3 & 4); putch(32);
+                 putint(1 + 2 << 3); putch(32);
+                 putint((5 & 4) == 4); putch(32);
+                 putint(5 & 4 == 4);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "3 24 1 1");
+}
+
+#[test]
+fn constants_are_folded() {
+    let src = "const int N = (1 << 4) | 3; int a[N ^ 1]; int main() { return N; }";
+    assert_eq!(exit_code(src), 19);
+    assert!(koopa(src, rcompiler::OptLevel::O0).contains("[i32, 18]"));
+}
+
+#[test]
+fn operands_must_be_integers() {
+    assert_error(
+        "int main() { float f = 1; return f & 1; }",
+        "expected an integer type, found `float`",
+    );
+}
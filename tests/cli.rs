@@ -0,0 +1,160 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const PROGRAM: &str = "int main() { return 0; }\n";
+const BROKEN: &str = "int main() { return x; }\n";
+
+/// A new directory for the files of a test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rcompiler-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the compiler in the directory with the arguments, feeding the text to its standard
+/// input
+fn rcompiler(dir: &PathBuf, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rcompiler"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[test]
+fn prints_the_usage_and_the_version() {
+    let dir = temp_dir("help");
+    let output = rcompiler(&dir, &["--help"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(text(&output.stdout).starts_with("Usage: rcompiler"));
+    let output = rcompiler(&dir, &["-V"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        text(&output.stdout),
+        format!("rcompiler {}\n", env!("CARGO_PKG_VERSION"))
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rejects_bad_command_lines() {
+    let dir = temp_dir("args");
+    for (args, message) in [
+        (&[][..], "no input files"),
+        (&["a.c", "-o"], "missing argument after `-o`"),
+        (&["a.c", "-O3"], "unknown optimization level `-O3`"),
+        (&["a.c", "-Wfoo"], "unknown warning option `-Wfoo`"),
+        (&["a.c", "--emit=exe"], "unknown kind of output `exe`"),
+        (&["a.c", "--target=x86"], "unknown target `x86`"),
+        (
+            &["a.c", "--diagnostic-format=xml"],
+            "unknown diagnostic format `xml`",
+        ),
+        (&["a.c", "--frobnicate"], "unknown option `--frobnicate`"),
+        (
+            &["a.c", "b.c", "-o", "out.s"],
+            "`-o` cannot be given with more than one input",
+        ),
+        (&["-", "-"], "the standard input can only be read once"),
+    ] {
+        let output = rcompiler(&dir, args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        let stderr = text(&output.stderr);
+        assert!(
+            stderr.starts_with(&format!("error: {}", message)),
+            "{:?}: {}",
+            args,
+            stderr
+        );
+        assert!(stderr.contains("run `rcompiler --help` for the usage"));
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compiles_the_standard_input_to_the_standard_output() {
+    let dir = temp_dir("stdio");
+    let output = rcompiler(&dir, &["-"], PROGRAM);
+    assert_eq!(output.status.code(), Some(0), "{}", text(&output.stderr));
+    assert!(text(&output.stdout).contains("main:"));
+
+    let output = rcompiler(&dir, &["-koopa", "-", "-o", "-"], PROGRAM);
+    assert!(text(&output.stdout).contains("fun @main(): i32"));
+    let output = rcompiler(&dir, &["--emit=tokens", "-"], "int x;");
+    assert!(text(&output.stdout).starts_with("<stdin>:1:1: "));
+
+    let output = rcompiler(&dir, &["-"], BROKEN);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(text(&output.stdout).is_empty());
+    assert!(text(&output.stderr).contains(" --> <stdin>:1:21"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn writes_the_output_next_to_where_it_is_run() {
+    let dir = temp_dir("files");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/a.c"), PROGRAM).unwrap();
+    fs::write(dir.join("src/b.c"), PROGRAM).unwrap();
+
+    let output = rcompiler(&dir, &["src/a.c", "-koopa", "src/b.c"], "");
+    assert_eq!(output.status.code(), Some(0), "{}", text(&output.stderr));
+    assert!(fs::read_to_string(dir.join("a.koopa"))
+        .unwrap()
+        .contains("fun @main"));
+    assert!(dir.join("b.koopa").exists());
+
+    let output = rcompiler(&dir, &["src/a.c", "-o", "out/../a.s", "-O2"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(text(&output.stderr).starts_with("error: cannot write `out/../a.s`"));
+    let output = rcompiler(&dir, &["src/a.c", "-oa.s", "-O2"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(fs::read_to_string(dir.join("a.s"))
+        .unwrap()
+        .contains("main:"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compiles_the_other_inputs_after_a_failure() {
+    let dir = temp_dir("failure");
+    fs::write(dir.join("bad.c"), BROKEN).unwrap();
+    fs::write(dir.join("good.c"), PROGRAM).unwrap();
+    let output = rcompiler(
+        &dir,
+        &["missing.c", "bad.c", "good.c", "--diagnostic-format=json"],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = text(&output.stderr);
+    let lines: Vec<_> = stderr.lines().collect();
+    assert!(
+        lines[0].starts_with("error: cannot read `missing.c`"),
+        "{}",
+        stderr
+    );
+    let diag: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+    assert_eq!(diag["code"], "undeclared");
+    assert_eq!(diag["file"], "bad.c");
+    // a failed input leaves no output behind
+    assert!(!dir.join("bad.s").exists());
+    assert!(dir.join("good.s").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
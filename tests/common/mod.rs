@@ -0,0 +1,123 @@
+//! Helpers to compile SysY programs in memory, and to run or inspect what comes out
+#![allow(dead_code)]
+
+pub mod sim;
+
+use rcompiler::codegen::{generate_code, Target};
+use rcompiler::diag::{DiagnosticOptions, Severity, SourceFile};
+use rcompiler::irgen::generate_ir;
+use rcompiler::sema::analyze;
+use rcompiler::OptLevel;
+
+pub use sim::Output;
+
+fn source(src: &str) -> SourceFile {
+    SourceFile::new("test.c".to_owned(), src.to_owned())
+}
+
+/// The assembly of the program, or the rendered diagnostics if it fails to compile
+pub fn asm(src: &str, target: Target, opt: OptLevel) -> Result<String, String> {
+    let mut out = vec![];
+    let diag_opts = DiagnosticOptions::default();
+    match generate_code(source(src), &[], &diag_opts, &mut out, opt, target) {
+        Ok(_) => Ok(String::from_utf8(out).unwrap()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// The rendered diagnostics of a program that fails to compile
+pub fn compile_error(src: &str) -> String {
+    match asm(src, Target::Rv32, OptLevel::O0) {
+        Ok(_) => panic!("compiled unexpectedly"),
+        Err(err) => err,
+    }
+}
+
+/// The Koopa IR of the program
+pub fn koopa(src: &str, opt: OptLevel) -> String {
+    let mut out = vec![];
+    let diag_opts = DiagnosticOptions::default();
+    if let Err(err) = generate_ir(source(src), &[], &diag_opts, &mut out, opt) {
+        panic!("failed to compile:\n{}", err);
+    }
+    String::from_utf8(out).unwrap()
+}
+
+/// Run the program for the target at the optimization level on the input
+pub fn run_on(src: &str, target: Target, opt: OptLevel, input: &str) -> Output {
+    let asm = asm(src, target, opt).unwrap_or_else(|err| panic!("failed to compile:\n{}", err));
+    let xlen = match target {
+        Target::Rv32 => 32,
+        Target::Rv64 => 64,
+    };
+    sim::run(&asm, xlen, input)
+        .unwrap_or_else(|err| panic!("failed to run for {:?} at {:?}: {}", target, opt, err))
+}
+
+/// Run the program for both targets with and without optimizations, which must all agree
+pub fn run_with_input(src: &str, input: &str) -> Output {
+    let expected = run_on(src, Target::Rv32, OptLevel::O0, input);
+    for target in [Target::Rv32, Target::Rv64] {
+        for opt in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+            let output = run_on(src, target, opt, input);
+            assert_eq!(output, expected, "{:?} at {:?} disagrees", target, opt);
+        }
+    }
+    expected
+}
+
+pub fn run(src: &str) -> Output {
+    run_with_input(src, "")
+}
+
+/// What the program prints
+pub fn stdout(src: &str) -> String {
+    run(src).stdout
+}
+
+/// What `main` returns, as the low byte of the exit status
+pub fn exit_code(src: &str) -> i32 {
+    run(src).exit
+}
+
+/// The messages of the diagnostics of the given severity, with the warnings enabled by the
+/// options following `-W`
+pub fn messages(src: &str, severity: Severity, warnings: &[&str]) -> Vec<String> {
+    let mut analysis = analyze(source(src), &[]);
+    let mut diag_opts = DiagnosticOptions::default();
+    for w in warnings {
+        assert!(diag_opts.warnings.parse(w), "unknown warning `{}`", w);
+    }
+    diag_opts.warnings.apply(&mut analysis.diags);
+    analysis
+        .diags
+        .iter()
+        .filter(|d| d.severity == severity)
+        .map(|d| d.message.clone())
+        .collect()
+}
+
+pub fn errors(src: &str) -> Vec<String> {
+    messages(src, Severity::Error, &[])
+}
+
+pub fn warnings(src: &str, enabled: &[&str]) -> Vec<String> {
+    messages(src, Severity::Warning, enabled)
+}
+
+/// Assert that the program compiles without errors
+pub fn assert_ok(src: &str) {
+    let errors = errors(src);
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+}
+
+/// Assert that the program fails to compile with an error containing the text
+pub fn assert_error(src: &str, text: &str) {
+    let errors = errors(src);
+    assert!(
+        errors.iter().any(|e| e.contains(text)),
+        "expected an error containing `{}`, got {:?}",
+        text,
+        errors
+    );
+}
@@ -0,0 +1,654 @@
+//! A simulator of the subset of RV32IMF and RV64IMF that the compiler emits, with the SysY
+//! runtime library built in, so that the tests can run the programs they compile.
+
+use std::collections::HashMap;
+
+const MEM_SIZE: usize = 1 << 24;
+const STACK_TOP: i64 = 1 << 23;
+const DATA_START: usize = 0x1000;
+/// the return address of `main`, at which the simulation stops
+const EXIT_PC: i64 = -1;
+const MAX_STEPS: u64 = 50_000_000;
+
+/// What a program printed, and the low byte of what `main` returned
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output {
+    pub stdout: String,
+    pub exit: i32,
+}
+
+struct Inst {
+    op: String,
+    args: Vec<String>,
+}
+
+enum Label {
+    Text(usize),
+    Data(usize),
+}
+
+struct Machine<'a> {
+    xlen: u32,
+    mem: Vec<u8>,
+    labels: HashMap<String, Label>,
+    insts: Vec<Inst>,
+    regs: HashMap<String, i64>,
+    fregs: HashMap<String, u64>,
+    input: std::str::SplitWhitespace<'a>,
+    out: String,
+}
+
+/// Run the assembly of a program for the given width of the registers, with the whitespace
+/// separated numbers of the input
+pub fn run(asm: &str, xlen: u32, input: &str) -> Result<Output, String> {
+    let mut m = Machine {
+        xlen,
+        mem: vec![0; MEM_SIZE],
+        labels: HashMap::new(),
+        insts: vec![],
+        regs: HashMap::new(),
+        fregs: HashMap::new(),
+        input: input.split_whitespace(),
+        out: String::new(),
+    };
+    m.load(asm)?;
+    m.set("sp", STACK_TOP);
+    m.set("ra", EXIT_PC);
+    let mut pc = match m.labels.get("main") {
+        Some(Label::Text(pc)) => *pc as i64,
+        _ => return Err("no `main`".to_string()),
+    };
+    let mut steps = 0;
+    while pc != EXIT_PC {
+        steps += 1;
+        if steps > MAX_STEPS {
+            return Err("too many steps".to_string());
+        }
+        pc = m.step(pc as usize)?;
+    }
+    let exit = (m.reg("a0") & 0xff) as i32;
+    Ok(Output {
+        stdout: m.out,
+        exit,
+    })
+}
+
+fn parse_int(s: &str) -> Result<i64, String> {
+    let (neg, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let v = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).map(|v| v as i64),
+        None => s.parse::<i64>(),
+    }
+    .map_err(|_| format!("bad integer `{}`", s))?;
+    Ok(if neg { v.wrapping_neg() } else { v })
+}
+
+/// Split the operands of an instruction at the commas outside parentheses
+fn operands(rest: &str) -> Vec<String> {
+    let mut ops = vec![];
+    let mut depth = 0;
+    let mut cur = String::new();
+    for c in rest.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                ops.push(cur.trim().to_string());
+                cur.clear();
+                continue;
+            }
+            _ => {}
+        }
+        cur.push(c);
+    }
+    if !cur.trim().is_empty() {
+        ops.push(cur.trim().to_string());
+    }
+    ops
+}
+
+/// The bytes of a string literal of the assembler
+fn unescape(lit: &str) -> Vec<u8> {
+    let inner = &lit[1..lit.len() - 1];
+    let bytes = inner.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        match bytes[i] {
+            b'n' => out.push(b'\n'),
+            b't' => out.push(b'\t'),
+            b'0'..=b'7' => {
+                let mut v = 0u32;
+                let mut n = 0;
+                while n < 3 && i < bytes.len() && (b'0'..=b'7').contains(&bytes[i]) {
+                    v = v * 8 + (bytes[i] - b'0') as u32;
+                    i += 1;
+                    n += 1;
+                }
+                out.push(v as u8);
+                continue;
+            }
+            c => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
+
+fn sext(v: i64, bits: u32) -> i64 {
+    if bits >= 64 {
+        v
+    } else {
+        (v << (64 - bits)) >> (64 - bits)
+    }
+}
+
+fn f32_of(bits: u64) -> f32 {
+    f32::from_bits(bits as u32)
+}
+
+impl Machine<'_> {
+    fn load(&mut self, asm: &str) -> Result<(), String> {
+        let mut data = DATA_START;
+        let mut in_text = true;
+        let mut pending = vec![];
+        for line in asm.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_suffix(':') {
+                let label = if in_text {
+                    Label::Text(self.insts.len())
+                } else {
+                    Label::Data(data)
+                };
+                self.labels.insert(name.to_string(), label);
+                continue;
+            }
+            let (op, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match op {
+                ".data" | ".bss" => in_text = false,
+                ".text" => in_text = true,
+                ".section" => in_text = rest.contains("text"),
+                ".globl" | ".type" | ".size" => {}
+                ".align" | ".p2align" => {
+                    let a = 1 << parse_int(rest)?;
+                    data = data.div_ceil(a) * a;
+                }
+                _ if !in_text => match op {
+                    ".word" | ".dword" | ".quad" | ".half" => {
+                        let n = match op {
+                            ".word" => 4,
+                            ".half" => 2,
+                            _ => 8,
+                        };
+                        for x in rest.split(',') {
+                            pending.push((data, n, x.trim().to_string()));
+                            data += n;
+                        }
+                    }
+                    ".byte" => {
+                        for x in rest.split(',') {
+                            self.mem[data] = parse_int(x.trim())? as u8;
+                            data += 1;
+                        }
+                    }
+                    ".zero" => data += parse_int(rest)? as usize,
+                    ".asciz" | ".string" => {
+                        let s = unescape(rest);
+                        self.mem[data..data + s.len()].copy_from_slice(&s);
+                        data += s.len() + 1;
+                    }
+                    _ => return Err(format!("unknown directive `{}`", line)),
+                },
+                _ => self.insts.push(Inst {
+                    op: op.to_string(),
+                    args: operands(rest),
+                }),
+            }
+        }
+        for (addr, n, x) in pending {
+            // the address of code is the index of the instruction, e.g. in a jump table
+            let v = match self.labels.get(&x) {
+                Some(Label::Data(a)) => *a as i64,
+                Some(Label::Text(pc)) => *pc as i64,
+                None => parse_int(&x)?,
+            };
+            self.store(addr as i64, n, v);
+        }
+        Ok(())
+    }
+
+    fn reg(&self, r: &str) -> i64 {
+        if r == "zero" {
+            0
+        } else {
+            *self.regs.get(r).unwrap_or(&0)
+        }
+    }
+
+    fn set(&mut self, r: &str, v: i64) {
+        if r != "zero" {
+            let v = sext(v, self.xlen);
+            self.regs.insert(r.to_string(), v);
+        }
+    }
+
+    fn freg(&self, r: &str) -> u64 {
+        *self.fregs.get(r).unwrap_or(&0)
+    }
+
+    fn fset(&mut self, r: &str, bits: u64) {
+        self.fregs.insert(r.to_string(), bits);
+    }
+
+    fn fset_f32(&mut self, r: &str, f: f32) {
+        self.fset(r, f.to_bits() as u64);
+    }
+
+    fn mask(&self) -> u64 {
+        if self.xlen == 64 {
+            u64::MAX
+        } else {
+            (1 << self.xlen) - 1
+        }
+    }
+
+    fn load_mem(&self, addr: i64, n: usize, signed: bool) -> i64 {
+        let addr = addr as usize;
+        let mut v = 0u64;
+        for i in (0..n).rev() {
+            v = (v << 8) | self.mem[addr + i] as u64;
+        }
+        if signed {
+            sext(v as i64, 8 * n as u32)
+        } else {
+            v as i64
+        }
+    }
+
+    fn store(&mut self, addr: i64, n: usize, v: i64) {
+        let addr = addr as usize;
+        for i in 0..n {
+            self.mem[addr + i] = (v >> (8 * i)) as u8;
+        }
+    }
+
+    /// Address of an operand like `-8(sp)`, which must be aligned to the `n` bytes accessed
+    fn address(&self, s: &str, n: usize) -> Result<i64, String> {
+        let (offset, rest) = s.split_once('(').ok_or(format!("bad address `{}`", s))?;
+        let base = rest.trim_end_matches(')');
+        let addr = self.reg(base) + parse_int(offset)?;
+        match addr % n as i64 {
+            0 => Ok(addr),
+            _ => Err(format!("misaligned access to {:#x}", addr)),
+        }
+    }
+
+    fn label(&self, name: &str) -> Result<i64, String> {
+        match self.labels.get(name) {
+            Some(Label::Text(pc)) => Ok(*pc as i64),
+            Some(Label::Data(addr)) => Ok(*addr as i64),
+            None => Err(format!("unknown label `{}`", name)),
+        }
+    }
+
+    fn c_str(&self, mut addr: usize) -> Vec<u8> {
+        let mut s = vec![];
+        while self.mem[addr] != 0 {
+            s.push(self.mem[addr]);
+            addr += 1;
+        }
+        s
+    }
+
+    fn next_input(&mut self) -> Result<&str, String> {
+        self.input.next().ok_or("the input has run out".to_string())
+    }
+
+    /// Call a function of the runtime library
+    fn runtime(&mut self, name: &str) -> Result<(), String> {
+        let a: Vec<i64> = (0..8).map(|i| self.reg(&format!("a{}", i))).collect();
+        match name {
+            "putint" => self.out += &(a[0] as i32).to_string(),
+            "putch" => self.out.push(a[0] as u8 as char),
+            "getint" => {
+                let v = parse_int(self.next_input()?)?;
+                self.set("a0", v);
+            }
+            "getch" => self.set("a0", -1),
+            "getarray" => {
+                let n = parse_int(self.next_input()?)?;
+                for k in 0..n {
+                    let v = parse_int(self.next_input()?)?;
+                    self.store(a[0] + 4 * k, 4, v);
+                }
+                self.set("a0", n);
+            }
+            "putarray" => {
+                self.out += &format!("{}:", a[0] as i32);
+                for k in 0..a[0] {
+                    self.out += &format!(" {}", self.load_mem(a[1] + 4 * k, 4, true));
+                }
+                self.out.push('\n');
+            }
+            "getfloat" => {
+                let v: f32 = self
+                    .next_input()?
+                    .parse()
+                    .map_err(|_| "bad float".to_string())?;
+                self.fset_f32("fa0", v);
+            }
+            "putfloat" => self.out += &format!("{}", f32_of(self.freg("fa0"))),
+            "getfarray" => {
+                let n = parse_int(self.next_input()?)?;
+                for k in 0..n {
+                    let v: f32 = self
+                        .next_input()?
+                        .parse()
+                        .map_err(|_| "bad float".to_string())?;
+                    self.store(a[0] + 4 * k, 4, v.to_bits() as i64);
+                }
+                self.set("a0", n);
+            }
+            "putfarray" => {
+                self.out += &format!("{}:", a[0] as i32);
+                for k in 0..a[0] {
+                    let v = f32_of(self.load_mem(a[1] + 4 * k, 4, false) as u64);
+                    self.out += &format!(" {v}");
+                }
+                self.out.push('\n');
+            }
+            "memset" | "memset@plt" => {
+                for k in 0..a[2] {
+                    self.mem[(a[0] + k) as usize] = a[1] as u8;
+                }
+            }
+            "_sysy_starttime" | "_sysy_stoptime" => {}
+            "putf" => {
+                let ws = self.xlen as i64 / 8;
+                let sp = self.reg("sp");
+                let mut args: Vec<i64> = a[1..].to_vec();
+                args.extend((0..16).map(|k| self.load_mem(sp + ws * k, ws as usize, true)));
+                let text = self.format(a[0] as usize, &args);
+                self.out += &text;
+            }
+            _ => return Err(format!("unknown function `{}`", name)),
+        }
+        Ok(())
+    }
+
+    /// The text of `putf`, where a `long long` or a `double` takes an aligned pair of
+    /// arguments on RV32
+    fn format(&self, fmt: usize, args: &[i64]) -> String {
+        let fmt = self.c_str(fmt);
+        let pairs = self.xlen == 32;
+        let mut k = 0;
+        let take_wide = |k: &mut usize| -> u64 {
+            if pairs {
+                // the arguments start at a1, and a pair at an even register
+                *k += 1 - (*k & 1);
+                let v = (args[*k] as u32 as u64) | ((args[*k + 1] as u32 as u64) << 32);
+                *k += 2;
+                v
+            } else {
+                *k += 1;
+                args[*k - 1] as u64
+            }
+        };
+        let mut res = String::new();
+        let mut i = 0;
+        while i < fmt.len() {
+            if fmt[i] != b'%' {
+                res.push(fmt[i] as char);
+                i += 1;
+                continue;
+            }
+            i += 1;
+            let mut long = false;
+            while matches!(fmt[i], b'l' | b'0'..=b'9' | b'.' | b'-') {
+                long |= fmt[i] == b'l';
+                i += 1;
+            }
+            match fmt[i] {
+                b'd' if long => res += &(take_wide(&mut k) as i64).to_string(),
+                b'd' => {
+                    res += &(args[k] as i32).to_string();
+                    k += 1;
+                }
+                b'u' => {
+                    res += &(args[k] as u32).to_string();
+                    k += 1;
+                }
+                b'x' => {
+                    res += &format!("{:x}", args[k] as u32);
+                    k += 1;
+                }
+                b'c' => {
+                    res.push(args[k] as u8 as char);
+                    k += 1;
+                }
+                b's' => {
+                    res += &String::from_utf8_lossy(&self.c_str(args[k] as usize));
+                    k += 1;
+                }
+                b'f' => res += &format!("{:.6}", f64::from_bits(take_wide(&mut k))),
+                b'%' => res.push('%'),
+                c => res.push(c as char),
+            }
+            i += 1;
+        }
+        res
+    }
+
+    /// Execute the instruction at the index, and return the index of the next one
+    fn step(&mut self, pc: usize) -> Result<i64, String> {
+        let inst = self
+            .insts
+            .get(pc)
+            .ok_or(format!("jump out of the code to {}", pc))?;
+        let op = inst.op.clone();
+        let o = inst.args.clone();
+        let mut next = pc as i64 + 1;
+        let imm = |i: usize| parse_int(&o[i]);
+        let bits = |op: &str| if op.ends_with('w') { 32 } else { self.xlen };
+        let mask = self.mask();
+        match op.as_str() {
+            "li" => self.set(&o[0], imm(1)?),
+            "la" => self.set(&o[0], self.label(&o[1])?),
+            "lui" => self.set(&o[0], imm(1)? << 12),
+            "mv" => self.set(&o[0], self.reg(&o[1])),
+            "lb" | "lbu" | "lh" | "lhu" | "lw" | "lwu" | "ld" => {
+                let n = match &op[1..2] {
+                    "b" => 1,
+                    "h" => 2,
+                    "w" => 4,
+                    _ => 8,
+                };
+                let v = self.load_mem(self.address(&o[1], n)?, n, !op.ends_with('u'));
+                self.set(&o[0], v);
+            }
+            "sb" | "sh" | "sw" | "sd" => {
+                let n = match op.as_str() {
+                    "sb" => 1,
+                    "sh" => 2,
+                    "sw" => 4,
+                    _ => 8,
+                };
+                self.store(self.address(&o[1], n)?, n, self.reg(&o[0]));
+            }
+            "flw" => {
+                let v = self.load_mem(self.address(&o[1], 4)?, 4, false);
+                self.fset(&o[0], v as u64);
+            }
+            "fsw" => self.store(self.address(&o[1], 4)?, 4, self.freg(&o[0]) as i64),
+            "add" | "addw" | "sub" | "subw" | "mul" | "mulw" => {
+                let (x, y) = (self.reg(&o[1]), self.reg(&o[2]));
+                let v = match &op[..3] {
+                    "add" => x.wrapping_add(y),
+                    "sub" => x.wrapping_sub(y),
+                    _ => x.wrapping_mul(y),
+                };
+                self.set(&o[0], sext(v, bits(&op)));
+            }
+            "addi" | "addiw" => {
+                let v = self.reg(&o[1]).wrapping_add(imm(2)?);
+                self.set(&o[0], sext(v, bits(&op)));
+            }
+            "div" | "divw" | "rem" | "remw" => {
+                let b = bits(&op);
+                let (x, y) = (sext(self.reg(&o[1]), b), sext(self.reg(&o[2]), b));
+                let (q, r) = match y {
+                    0 => (-1, x),
+                    _ => (x.wrapping_div(y), x.wrapping_rem(y)),
+                };
+                let v = if op.starts_with("div") { q } else { r };
+                self.set(&o[0], sext(v, b));
+            }
+            "divu" | "divuw" | "remu" | "remuw" => {
+                let b = bits(&op);
+                let m = if b == 64 { u64::MAX } else { (1 << b) - 1 };
+                let (x, y) = (self.reg(&o[1]) as u64 & m, self.reg(&o[2]) as u64 & m);
+                let (q, r) = match y {
+                    0 => (m, x),
+                    _ => (x / y, x % y),
+                };
+                let v = if op.starts_with("div") { q } else { r };
+                self.set(&o[0], sext(v as i64, b));
+            }
+            "mulhu" => {
+                let (x, y) = (self.reg(&o[1]) as u64 & mask, self.reg(&o[2]) as u64 & mask);
+                let v = (x as u128 * y as u128) >> self.xlen;
+                self.set(&o[0], v as i64);
+            }
+            "and" => self.set(&o[0], self.reg(&o[1]) & self.reg(&o[2])),
+            "or" => self.set(&o[0], self.reg(&o[1]) | self.reg(&o[2])),
+            "xor" => self.set(&o[0], self.reg(&o[1]) ^ self.reg(&o[2])),
+            "andi" => self.set(&o[0], self.reg(&o[1]) & imm(2)?),
+            "ori" => self.set(&o[0], self.reg(&o[1]) | imm(2)?),
+            "xori" => self.set(&o[0], self.reg(&o[1]) ^ imm(2)?),
+            "not" => self.set(&o[0], !self.reg(&o[1])),
+            "neg" => self.set(&o[0], self.reg(&o[1]).wrapping_neg()),
+            "slt" => self.set(&o[0], (self.reg(&o[1]) < self.reg(&o[2])) as i64),
+            "sgt" => self.set(&o[0], (self.reg(&o[1]) > self.reg(&o[2])) as i64),
+            "slti" => self.set(&o[0], (self.reg(&o[1]) < imm(2)?) as i64),
+            "sltu" | "sgtu" | "sltiu" => {
+                let x = self.reg(&o[1]) as u64 & mask;
+                let y = match op.as_str() {
+                    "sltiu" => imm(2)? as u64 & mask,
+                    _ => self.reg(&o[2]) as u64 & mask,
+                };
+                let v = if op == "sgtu" { x > y } else { x < y };
+                self.set(&o[0], v as i64);
+            }
+            "seqz" => self.set(&o[0], (self.reg(&o[1]) == 0) as i64),
+            "snez" => self.set(&o[0], (self.reg(&o[1]) != 0) as i64),
+            "sll" | "slli" | "sllw" | "slliw" | "sra" | "srai" | "sraw" | "sraiw" | "srl"
+            | "srli" | "srlw" | "srliw" => {
+                let b = bits(&op);
+                let sh = match op.contains('i') {
+                    true => imm(2)?,
+                    false => self.reg(&o[2]),
+                } as u32
+                    & (b - 1);
+                let x = self.reg(&o[1]);
+                let m = if b == 64 { u64::MAX } else { (1 << b) - 1 };
+                let v = match &op[..3] {
+                    "sll" => x << sh,
+                    "sra" => sext(x, b) >> sh,
+                    _ => ((x as u64 & m) >> sh) as i64,
+                };
+                self.set(&o[0], sext(v, b));
+            }
+            "sext.w" => self.set(&o[0], sext(self.reg(&o[1]), 32)),
+            "beqz" | "bnez" | "bltz" | "bgez" | "blez" | "bgtz" => {
+                let v = self.reg(&o[0]);
+                let taken = match op.as_str() {
+                    "beqz" => v == 0,
+                    "bnez" => v != 0,
+                    "bltz" => v < 0,
+                    "bgez" => v >= 0,
+                    "blez" => v <= 0,
+                    _ => v > 0,
+                };
+                if taken {
+                    next = self.label(&o[1])?;
+                }
+            }
+            "beq" | "bne" | "blt" | "bgt" | "ble" | "bge" | "bltu" | "bgtu" | "bleu" | "bgeu" => {
+                let (x, y) = (self.reg(&o[0]), self.reg(&o[1]));
+                let (ux, uy) = (x as u64 & mask, y as u64 & mask);
+                let taken = match op.as_str() {
+                    "beq" => x == y,
+                    "bne" => x != y,
+                    "blt" => x < y,
+                    "bgt" => x > y,
+                    "ble" => x <= y,
+                    "bge" => x >= y,
+                    "bltu" => ux < uy,
+                    "bgtu" => ux > uy,
+                    "bleu" => ux <= uy,
+                    _ => ux >= uy,
+                };
+                if taken {
+                    next = self.label(&o[2])?;
+                }
+            }
+            "j" => next = self.label(&o[0])?,
+            "jr" => next = self.reg(&o[0]),
+            "call" => match self.labels.get(&o[0]) {
+                Some(&Label::Text(target)) => {
+                    self.set("ra", next);
+                    next = target as i64;
+                }
+                _ => self.runtime(&o[0])?,
+            },
+            "ret" => next = self.reg("ra"),
+            "fmv.w.x" => self.fset(&o[0], self.reg(&o[1]) as u32 as u64),
+            "fmv.x.w" => self.set(&o[0], sext(self.freg(&o[1]) as i64, 32)),
+            "fmv.s" => self.fset(&o[0], self.freg(&o[1])),
+            "fadd.s" | "fsub.s" | "fmul.s" | "fdiv.s" => {
+                let (x, y) = (f32_of(self.freg(&o[1])), f32_of(self.freg(&o[2])));
+                let v = match op.as_str() {
+                    "fadd.s" => x + y,
+                    "fsub.s" => x - y,
+                    "fmul.s" => x * y,
+                    _ => x / y,
+                };
+                self.fset_f32(&o[0], v);
+            }
+            "fneg.s" => self.fset(&o[0], self.freg(&o[1]) ^ 0x8000_0000),
+            "fabs.s" => self.fset(&o[0], self.freg(&o[1]) & 0x7fff_ffff),
+            "feq.s" | "flt.s" | "fle.s" => {
+                let (x, y) = (f32_of(self.freg(&o[1])), f32_of(self.freg(&o[2])));
+                let v = match op.as_str() {
+                    "feq.s" => x == y,
+                    "flt.s" => x < y,
+                    _ => x <= y,
+                };
+                self.set(&o[0], v as i64);
+            }
+            "fcvt.s.w" => self.fset_f32(&o[0], sext(self.reg(&o[1]), 32) as f32),
+            "fcvt.s.wu" => self.fset_f32(&o[0], self.reg(&o[1]) as u32 as f32),
+            "fcvt.s.l" => self.fset_f32(&o[0], self.reg(&o[1]) as f32),
+            "fcvt.w.s" => self.set(&o[0], f32_of(self.freg(&o[1])) as i32 as i64),
+            "fcvt.wu.s" => self.set(&o[0], f32_of(self.freg(&o[1])) as u32 as i32 as i64),
+            "fcvt.l.s" => self.set(&o[0], f32_of(self.freg(&o[1])) as i64),
+            "fcvt.d.s" => self.fset(&o[0], (f32_of(self.freg(&o[1])) as f64).to_bits()),
+            "fmv.x.d" => self.set(&o[0], self.freg(&o[1]) as i64),
+            _ => return Err(format!("unknown instruction `{} {}`", op, o.join(", "))),
+        }
+        Ok(next)
+    }
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn compound_assignments() {
+    let src = "int main() {
+                 int a = 7;
+                 a += 3; putint(a); putch(32);
+                 a -= 4; putint(a); putch(32);
+                 a *= 5; putint(a); putch(32);
+                 a /= 4; putint(a); putch(32);
+                 a %= 4; putint(a); putch(32);
+                 a <<= 3; putint(a); putch(32);
+                 a >>= 1; putint(a); putch(32);
+                 a &= 6; putint(a); putch(32);
+                 a |= 9; putint(a); putch(32);
+                 a ^= 3; putint(a);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "10 6 30 7 3 24 12 4 13 14");
+}
+
+#[test]
+fn increments_and_decrements() {
+    let src = "int main() {
+                 int i = 5;
+                 int a = i++;
+                 int b = ++i;
+                 int c = i--;
+                 int d = --i;
+                 putint(a); putint(b); putint(c); putint(d); putint(i);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "57755");
+}
+
+#[test]
+fn operand_is_evaluated_once() {
+    let src = "int n = 0;
+               int idx() { n = n + 1; return 1; }
+               int main() {
+                 int a[3] = {1, 2, 3};
+                 a[idx()] += 10;
+                 a[idx()]++;
+                 putint(a[1]); putch(32); putint(n);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "13 2");
+}
+
+#[test]
+fn float_compound_assignment() {
+    let src =
+        "int main() { float f = 1; f *= 2.5; f += 1; int i = 2; i *= 1.5; return f * 10 + i; }";
+    assert_eq!(exit_code(src), 38);
+}
+
+#[test]
+fn operand_must_be_assignable() {
+    assert_error("int main() { 1++; return 0; }", "not assignable");
+    assert_error(
+        "int main() { const int c = 1; c += 1; return c; }",
+        "attempt to assign a const value",
+    );
+}
+
+#[test]
+fn assignment_to_a_constant_is_reported_once() {
+    let src = "const long long a[2] = {1, 2};
+               int main() { const int c = 1; c = 2; c++; a[1] -= 1; return c; }";
+    assert_eq!(
+        errors(src),
+        [
+            "attempt to assign a const value",
+            "attempt to assign a const value",
+            "attempt to assign a const value",
+        ]
+    );
+}
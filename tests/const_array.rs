@@ -0,0 +1,82 @@
+mod common;
+
+use common::*;
+use rcompiler::OptLevel;
+
+#[test]
+fn folds_constant_indices_where_a_constant_is_required() {
+    let src = "const int t[3] = {1, 2, 3};
+               const int m[2][3] = {{4, 5}, 6};
+               const float f[2] = {1.5, 2.5};
+               int a[t[1]];
+               int g = m[1][0] + t[2];
+               float h = f[1] * 2;
+               enum E { A = m[0][1] + 1 };
+               int main() {
+                 const int l[2] = {t[0] * 10, m[0][1]};
+                 int b[l[1]];
+                 b[4] = l[0];
+                 a[1] = A;
+                 switch (getint()) { case t[1]: putint(9); }
+                 putint(a[1]); putch(32); putint(g); putch(32); putint(b[4]); putch(32);
+                 putint(m[0][2]); putch(32); putint(h);
+                 return 0;
+               }";
+    assert_eq!(run_with_input(src, "2").stdout, "96 9 10 0 5");
+}
+
+#[test]
+fn folds_elements_of_long_long() {
+    let src = "const long long t[2] = {1, 4294967298LL};
+               int a[t[0] + 1];
+               int main() { a[1] = t[1] - 4294967296LL; putf(\"%lld\", t[1] + 1); return a[1]; }";
+    let output = run(src);
+    assert_eq!(output.stdout, "4294967299");
+    assert_eq!(output.exit, 2);
+}
+
+#[test]
+fn loads_the_elements_at_other_indices() {
+    let ir = koopa(
+        "const int t[3] = {1, 2, 3};
+         int main() { int i = getint(); return t[2] + t[i]; }",
+        OptLevel::O0,
+    );
+    assert!(ir.contains("add 3, "), "{}", ir);
+    assert_eq!(ir.matches("getelemptr @t").count(), 1, "{}", ir);
+}
+
+#[test]
+fn rejects_indices_which_are_not_constants_or_in_bounds() {
+    assert_error(
+        "const int t[3] = {1, 2, 3};
+         int main() { int i = 1; int a[t[i]]; return 0; }",
+        "array size is not a constant expression",
+    );
+    assert_eq!(
+        errors("const int t[3] = {1, 2, 3}; int a[t[3]]; int main() { return t[-1]; }"),
+        [
+            "index 3 is out of bounds for length 3",
+            "index -1 is out of bounds for length 3",
+        ]
+    );
+    assert_error(
+        "int t[3] = {1, 2, 3}; int a[t[0]];",
+        "array size is not a constant expression",
+    );
+}
+
+#[test]
+fn does_not_fold_malformed_initializers() {
+    assert_eq!(
+        errors("const int a[2] = {{{1}}}; int b[a[0]]; int main() { return 0; }"),
+        [
+            "too many braces around scalar initializer",
+            "array size is not a constant expression",
+        ]
+    );
+    assert_eq!(
+        errors("int main() { const int a[2] = {1, 2, 3}; return a[1]; }"),
+        ["excess elements in initializer"]
+    );
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn arithmetic_and_conversions() {
+    let src = "int main() {
+                 float a = 1.5, b = 2;
+                 float c = a * b + 0.25;
+                 int i = c;
+                 putint(i); putch(32);
+                 putint(c * 4); putch(32);
+                 putint(7 / 2.0 * 2);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "3 13 7");
+}
+
+#[test]
+fn comparisons_and_conditions() {
+    let src = "int main() {
+                 float x = 0.5;
+                 if (x) putint(1);
+                 if (!x) putint(2);
+                 putint(x < 1);
+                 putint(x >= 0.75);
+                 putint(x == 0.5 && 1.0);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "1101");
+}
+
+#[test]
+fn parameters_and_return_values() {
+    let src = "float half(float x, int n) { return x / n; }
+               int scale(float a[], int n) {
+                 float s = 0;
+                 int i = 0;
+                 while (i < n) { s = s + a[i]; i = i + 1; }
+                 return s * 10;
+               }
+               float g = 3;
+               int main() {
+                 float a[3] = {0.5, 0, 1};
+                 a[1] = half(g, 2);
+                 putint(scale(a, 3));
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "30");
+}
+
+#[test]
+fn constant_folding() {
+    let src = "const float PI = 3.14159;
+               const int R = PI * 100;
+               int a[R / 100 + 1];
+               int main() { putint(R); return 0; }";
+    assert_eq!(stdout(src), "314");
+}
+
+#[test]
+fn reads_floats() {
+    let src = "int main() { float f = getfloat(); putint(f * 2); return 0; }";
+    assert_eq!(run_with_input(src, "2.25").stdout, "4");
+}
+
+#[test]
+fn round_trips_float_arrays() {
+    let src = "int main() {
+                 float a[4];
+                 int n = getfarray(a);
+                 a[0] = a[0] * 2;
+                 putfarray(n, a);
+                 return n;
+               }";
+    let out = run_with_input(src, "3 1.5 -2 0.25");
+    assert_eq!(out.stdout, "3: 3 -2 0.25\n");
+    assert_eq!(out.exit, 3);
+}
+
+#[test]
+fn hexadecimal_literals_with_huge_exponents() {
+    let src = "int main() {
+                 float big = 0x1p99999999999, small = 0x1p-99999999999, zero = 0x0p99999999999;
+                 putint(big > 3.4e38); putint(small == 0); putint(zero == 0);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "111");
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn runs_a_program() {
+    assert_eq!(exit_code("int main() { return 3 * 4 + 1; }"), 13);
+}
+
+#[test]
+fn feeds_the_input_and_collects_the_output() {
+    let src = "int main() { int a = getint(); putint(a + 1); putch(10); return a; }";
+    let output = run_with_input(src, "41");
+    assert_eq!(output.stdout, "42\n");
+    assert_eq!(output.exit, 41);
+}
@@ -0,0 +1,99 @@
+/// The diagnostics of the program as JSON objects, as `--diagnostic-format=json` writes them
+fn json_diagnostics(src: &str) -> Vec<serde_json::Value> {
+    let file = rcompiler::diag::SourceFile::new("test.c".to_owned(), src.to_owned());
+    let analysis = rcompiler::sema::analyze(file, &[]);
+    let sources = &analysis.sources;
+    analysis.diags.iter().map(|d| d.to_json(sources)).collect()
+}
+
+#[test]
+fn json_diagnostics_have_codes_and_spans() {
+    let diags = json_diagnostics("int main() {\n  int x;\n  return y;\n}\n");
+    assert_eq!(
+        diags[0],
+        serde_json::json!({
+            "severity": "error",
+            "code": "undeclared",
+            "message": "`y` is used before definition",
+            "file": "test.c",
+            "span": {
+                "file": "test.c",
+                "byte_start": 31,
+                "byte_end": 32,
+                "line_start": 3,
+                "column_start": 10,
+                "line_end": 3,
+                "column_end": 11,
+            },
+            "labels": [],
+            "fixes": [],
+        })
+    );
+    let codes: Vec<_> = ["int main() { return 0 }", "int main() { break; }"]
+        .iter()
+        .flat_map(|src| json_diagnostics(src))
+        .map(|d| d["code"].clone())
+        .collect();
+    assert_eq!(codes, ["syntax", "misplaced-statement"]);
+}
+
+#[test]
+fn json_diagnostics_have_secondary_labels() {
+    let diags: Vec<_> = json_diagnostics("int main() {\n  int x;\n  int x;\n  return 0;\n}\n")
+        .into_iter()
+        .filter(|d| d["severity"] == "error")
+        .collect();
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0]["message"], "redefinition of `x`");
+    assert_eq!(
+        diags[0]["span"],
+        serde_json::json!({
+            "file": "test.c",
+            "byte_start": 28,
+            "byte_end": 29,
+            "line_start": 3,
+            "column_start": 7,
+            "line_end": 3,
+            "column_end": 8,
+        })
+    );
+    assert_eq!(
+        diags[0]["labels"],
+        serde_json::json!([{
+            "message": "previous definition of `x` is here",
+            "span": {
+                "file": "test.c",
+                "byte_start": 19,
+                "byte_end": 20,
+                "line_start": 2,
+                "column_start": 7,
+                "line_end": 2,
+                "column_end": 8,
+            },
+        }])
+    );
+}
+
+#[test]
+fn json_diagnostics_have_fixes() {
+    // the columns are counted in characters, so they fall behind the bytes after the `é`
+    let diags = json_diagnostics("int main() {\n  putf(\"é\")\n}\n");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0]["code"], "syntax");
+    assert_eq!(
+        diags[0]["fixes"],
+        serde_json::json!([{
+            "message": "insert `;`",
+            "replacement": ";",
+            "span": {
+                "file": "test.c",
+                "byte_start": 25,
+                "byte_end": 25,
+                "line_start": 2,
+                "column_start": 12,
+                "line_end": 2,
+                "column_end": 12,
+            },
+        }])
+    );
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use common::*;
+use rcompiler::codegen::Target;
+use rcompiler::OptLevel;
+
+#[test]
+fn computes_in_64_bits() {
+    let src = r#"int main() {
+                   long long a = getint(), b = getint();
+                   a = a * 100000 + 7;
+                   putf("%lld %lld %lld %lld %lld\n", a + b, a - b, a * b, a / b, a % b);
+                   putf("%lld %lld %lld\n", a & b, a | b, a ^ b);
+                   putf("%lld %lld %lld %lld\n", a << 20, -a >> 3, -a, ~a);
+                   putint(a > b); putint(a < b); putint(a == a);
+                   putint(a != b); putint(a >= b); putint(b <= a);
+                   return 0;
+                 }"#;
+    assert_eq!(
+        run_with_input(src, "123456 -98765").stdout,
+        "12345501242 12345698772 -1219313184691355 -124999 73772\n\
+         12345567235 -65993 -12345633228\n\
+         12945299872940032 -1543200001 -12345600007 -12345600008\n\
+         101111"
+    );
+}
+
+#[test]
+fn converts_from_and_to_other_types() {
+    let src = r#"int main() {
+                   int i = getint();
+                   unsigned u = i;
+                   long long a = i, b = u, c = 4294967301LL;
+                   int t = c;
+                   float f = -3000000000.0;
+                   long long d = f;
+                   float g = c;
+                   putf("%lld %lld %d %lld %f", a, b, t, d, g);
+                   return 0;
+                 }"#;
+    assert_eq!(
+        run_with_input(src, "-5").stdout,
+        "-5 4294967291 5 -3000000000 4294967296.000000"
+    );
+}
+
+#[test]
+fn passes_and_returns_long_longs() {
+    let src = r#"struct S { int a; long long b; };
+                 struct S arr[3];
+                 long long sum(long long x, int n, long long y) { return x * n + y; }
+                 int main() {
+                   int i = 0;
+                   while (i < 3) {
+                     arr[i].a = i;
+                     arr[i].b = sum(1LL << 40, i, arr[i].a);
+                     i = i + 1;
+                   }
+                   long long l[2];
+                   l[0] = arr[2].b; l[1] = -arr[1].b;
+                   putf("%lld %lld %d", l[0], l[1], arr[2].a);
+                   return 0;
+                 }"#;
+    assert_eq!(stdout(src), "2199023255554 -1099511627777 2");
+}
+
+#[test]
+fn aligns_members_to_whole_registers_on_rv64() {
+    let src = "struct S { int a; long long b; };
+               struct S s;
+               int main() { s.b = 1; return s.a; }";
+    let rv32 = asm(src, Target::Rv32, OptLevel::O0).unwrap();
+    assert!(rv32.contains("s:\n  .zero 12\n"), "{}", rv32);
+    let rv64 = asm(src, Target::Rv64, OptLevel::O0).unwrap();
+    assert!(rv64.contains("s:\n  .zero 16\n"), "{}", rv64);
+}
+
+#[test]
+fn operates_in_registers_on_rv64() {
+    let src = "long long f(long long a, long long b) { return a * b + (a >> 3) - b; }
+               int main() { return f(5, 6) > 0; }";
+    let rv64 = asm(src, Target::Rv64, OptLevel::O2).unwrap();
+    assert!(!rv64.contains("__sysy"), "{}", rv64);
+    for inst in ["ld ", "sd ", "mul ", "sra ", "add ", "sub ", "slt "] {
+        assert!(rv64.contains(inst), "no `{}` in\n{}", inst, rv64);
+    }
+    // the routines are left to RV32
+    let rv32 = asm(src, Target::Rv32, OptLevel::O2).unwrap();
+    assert!(rv32.contains("call __sysy_lmul"), "{}", rv32);
+}
+
+#[test]
+fn passes_long_longs_in_registers_on_rv64() {
+    let src = "long long ext(long long x, int n);
+               int main() { return ext(1LL << 33, 2) >> 33; }";
+    let rv64 = asm(src, Target::Rv64, OptLevel::O2).unwrap();
+    let call = rv64.find("call ext").expect(&rv64);
+    // the argument goes whole in `a0` rather than as the address of its words
+    let arg = rv64[..call]
+        .lines()
+        .rev()
+        .find(|l| l.contains(" a0,"))
+        .unwrap();
+    assert!(!arg.contains("addi"), "{}", rv64);
+    assert!(rv64[call..].contains("sd a0, "), "{}", rv64);
+    // the words of a pointer cannot be passed as a pair of registers
+    let err = asm(src, Target::Rv32, OptLevel::O0).unwrap_err();
+    assert!(
+        err.contains("`ext` is defined in another file, which cannot pass `long long` on RV32"),
+        "{}",
+        err
+    );
+}
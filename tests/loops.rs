@@ -0,0 +1,70 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn for_loop_with_every_clause() {
+    let src = "int main() {
+                 int s = 0;
+                 for (int i = 0; i < 10; i = i + 1) {
+                   if (i == 2) continue;
+                   if (i == 7) break;
+                   s = s + i;
+                 }
+                 return s;
+               }";
+    assert_eq!(exit_code(src), 19);
+}
+
+#[test]
+fn for_loop_without_clauses() {
+    let src = "int main() {
+                 int i = 0;
+                 for (;;) { i = i + 1; if (i > 4) break; }
+                 return i;
+               }";
+    assert_eq!(exit_code(src), 5);
+}
+
+#[test]
+fn header_declaration_is_scoped_to_the_loop() {
+    let src = "int main() {
+                 int i = 100;
+                 for (int i = 0; i < 3; i = i + 1) {}
+                 return i;
+               }";
+    assert_eq!(exit_code(src), 100);
+    assert_error(
+        "int main() { for (int i = 0; i < 3; i = i + 1) {} return i; }",
+        "`i` is used before definition",
+    );
+}
+
+#[test]
+fn do_while_runs_the_body_first() {
+    let src = "int main() {
+                 int n = 0;
+                 do { n = n + 1; } while (0);
+                 do { n = n + 10; if (n > 30) break; continue; } while (n < 100);
+                 return n;
+               }";
+    assert_eq!(exit_code(src), 31);
+}
+
+#[test]
+fn continue_in_do_while_checks_the_condition() {
+    let src = "int main() {
+                 int i = 0, s = 0;
+                 do { i = i + 1; if (i % 2) continue; s = s + i; } while (i < 6);
+                 return s;
+               }";
+    assert_eq!(exit_code(src), 12);
+}
+
+#[test]
+fn compiles_an_empty_infinite_loop() {
+    let src = "void forever() { while (1) {} }
+               int spin(int a) { for (;;) {} return a; }
+               int main() { if (getint()) forever(); return 7; }";
+    assert_eq!(run_with_input(src, "0").exit, 7);
+}
@@ -0,0 +1,160 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+const URI: &str = "file:///tmp/rcompiler-lsp-test/main.c";
+
+/// A language server run over its standard input and output
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rcompiler-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut client = Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        };
+        client.request("initialize", json!({ "capabilities": {} }));
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, msg: Value) {
+        let body = msg.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut len = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            match line.trim_end().strip_prefix("Content-Length: ") {
+                Some(n) => len = n.parse().unwrap(),
+                None if line.trim_end().is_empty() => break,
+                None => {}
+            }
+        }
+        let mut body = vec![0; len];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// The result of the request, skipping the notifications before it
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let msg = self.receive();
+            if msg["id"] == id {
+                return msg["result"].clone();
+            }
+        }
+    }
+
+    /// The diagnostics published for the text of the document
+    fn change(&mut self, text: &str) -> Vec<Value> {
+        let params = json!({
+            "textDocument": { "uri": URI, "languageId": "c", "version": 1, "text": text },
+        });
+        self.notify("textDocument/didOpen", params);
+        loop {
+            let msg = self.receive();
+            if msg["method"] == "textDocument/publishDiagnostics" {
+                return msg["params"]["diagnostics"].as_array().unwrap().clone();
+            }
+        }
+    }
+
+    /// The names and ranges of the symbols of the document
+    fn symbols(&mut self) -> Vec<(String, Value)> {
+        let params = json!({ "textDocument": { "uri": URI } });
+        let result = self.request("textDocument/documentSymbol", params);
+        let symbols = result.as_array().cloned().unwrap_or_default();
+        symbols
+            .into_iter()
+            .map(|s| (s["name"].as_str().unwrap().to_owned(), s["range"].clone()))
+            .collect()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        self.child.wait().unwrap();
+    }
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Value {
+    json!({
+        "start": { "line": start.0, "character": start.1 },
+        "end": { "line": end.0, "character": end.1 },
+    })
+}
+
+#[test]
+fn publishes_diagnostics_with_codes() {
+    let mut client = Client::start();
+    let diags = client.change("int main() {\n  return x;\n}\n");
+    assert_eq!(diags.len(), 1, "{:?}", diags);
+    assert_eq!(diags[0]["code"], "undeclared");
+    assert_eq!(diags[0]["range"], range((1, 9), (1, 10)));
+}
+
+#[test]
+fn function_symbols_cover_the_bodies() {
+    let mut client = Client::start();
+    client.change("int g;\nint f(int a);\nint f(int a) {\n  return a;\n}\n");
+    assert_eq!(
+        client.symbols(),
+        [
+            ("g".to_owned(), range((0, 4), (0, 5))),
+            ("f".to_owned(), range((2, 0), (4, 1))),
+        ]
+    );
+}
+
+#[test]
+fn indexes_a_program_with_syntax_errors() {
+    let mut client = Client::start();
+    let diags = client.change("int g;\nint f() {\n  g = ;\n  return 0;\n}\n");
+    assert_eq!(diags[0]["code"], "syntax");
+    let names: Vec<_> = client.symbols().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["g", "f"]);
+}
+
+#[test]
+fn keeps_the_names_while_the_program_cannot_be_parsed() {
+    let mut client = Client::start();
+    client.change("int g;\nint main() { return g; }\n");
+    let diags = client.change("#if 1\nint g;\nint main() { return g; }\n");
+    assert_eq!(diags[0]["code"], "directive");
+    let symbols = client.symbols();
+    assert_eq!(symbols[0], ("g".to_owned(), range((1, 4), (1, 5))));
+    assert_eq!(symbols[1].0, "main");
+    // the names in the text changed since are dropped
+    client.change("#if 1\nint h;\nint main() { return g; }\n");
+    let names: Vec<_> = client.symbols().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["main"]);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn wraps_around_when_folding() {
+    let src = "const int a = 2147483647 + 1;
+               int main() {
+                 int b = -2147483647 - 2, c = 65536 * 65536;
+                 unsigned u = 0u - 1;
+                 putint(a); putch(32); putint(b); putch(32); putint(c); putch(32);
+                 putint(u == 4294967295);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "-2147483648 2147483647 0 1");
+    assert_eq!(
+        warnings(src, &[]),
+        [
+            "integer overflow in expression of type `int` results in `-2147483648`",
+            "integer overflow in expression of type `int` results in `2147483647`",
+            "integer overflow in expression of type `int` results in `0`",
+        ]
+    );
+}
+
+#[test]
+fn negates_a_literal_out_of_the_int_range_alike() {
+    let src = "int main() {
+                 int x = -2147483648, y = - 2147483648, z = -(2147483648);
+                 long long l = -2147483648LL - 1;
+                 putint(x == y && y == z); putch(32); putf(\"%lld\", l);
+                 return x == -2147483648;
+               }";
+    let output = run(src);
+    assert_eq!(output.stdout, "1 -2147483649");
+    assert_eq!(output.exit, 1);
+    assert!(warnings(src, &[]).is_empty(), "{:?}", warnings(src, &[]));
+}
+
+#[test]
+fn warns_about_conversions_changing_the_value() {
+    let src = "int a = 2147483648;
+               unsigned b = -1LL, c = 4294967296LL;
+               int f() { return 2147483648; }
+               int main() { return f() == a; }";
+    assert_eq!(exit_code(src), 1);
+    assert_eq!(
+        warnings(src, &[]),
+        [
+            "overflow in conversion from `long long` to `int` changes value from `2147483648` to `-2147483648`",
+            "overflow in conversion from `long long` to `unsigned int` changes value from `4294967296` to `0`",
+            "overflow in conversion from `long long` to `int` changes value from `2147483648` to `-2147483648`",
+        ]
+    );
+}
+
+#[test]
+fn leaves_division_by_zero_to_run_time() {
+    let src = "int main() { int a = 1; if (a) return 3; return 1 / 0; }";
+    assert_eq!(exit_code(src), 3);
+    assert_eq!(warnings(src, &[]), ["division by zero"]);
+}
+
+#[test]
+fn warns_about_compound_division_by_zero() {
+    let src = "int main() { int a = 6; if (a) return 3; a /= 0; a %= 0; a *= 0; return a; }";
+    assert_eq!(exit_code(src), 3);
+    assert_eq!(warnings(src, &[]), ["division by zero", "division by zero"]);
+}
+
+#[test]
+fn rejects_literals_out_of_range() {
+    assert_error(
+        "int main() { return 9223372036854775808; }",
+        "integer literal is too large to be represented in any integer type",
+    );
+}
+
+#[test]
+fn warns_about_narrowed_initializers_of_constants_and_arrays() {
+    let src = "const int a = -2147483648 / -1;
+               int b[1] = {2147483648};
+               const int c[2][1] = {{1}, 2147483648};
+               int main() { return a == b[0] && b[0] == c[1][0]; }";
+    assert_eq!(exit_code(src), 1);
+    assert_eq!(
+        warnings(src, &[]),
+        [
+            "overflow in conversion from `long long` to `int` changes value from `2147483648` to `-2147483648`",
+            "overflow in conversion from `long long` to `int` changes value from `2147483648` to `-2147483648`",
+            "overflow in conversion from `long long` to `int` changes value from `2147483648` to `-2147483648`",
+        ]
+    );
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::*;
+use rcompiler::codegen::Target;
+use rcompiler::OptLevel;
+
+#[test]
+fn takes_addresses_and_dereferences() {
+    let src = "void set(int *p, int v) { *p = v; }
+               int main() {
+                 int x = 1;
+                 int *p = &x;
+                 *p = *p + 1;
+                 set(&x, x * 10);
+                 int **pp = &p;
+                 **pp = **pp + 3;
+                 return x;
+               }";
+    assert_eq!(exit_code(src), 23);
+}
+
+#[test]
+fn does_pointer_arithmetic_and_comparisons() {
+    let src = "int a[5] = {1, 2, 3, 4, 5};
+               int main() {
+                 int *p = a, *end = a + 5, sum = 0;
+                 while (p < end) { sum = sum + *p; p = p + 1; }
+                 int *q = &a[4];
+                 putint(sum); putch(32);
+                 putint(q - a); putch(32);
+                 putint(*(q - 2)); putch(32);
+                 putint(p == end); putint(p != 0); putint(q[-1]);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "15 4 3 114");
+}
+
+#[test]
+fn rejects_mixing_pointers_and_integers() {
+    assert_error(
+        "int main() { int x; int *p = x; return 0; }",
+        "mismatched types",
+    );
+    assert_error(
+        "int main() { int x; float *p = &x; return 0; }",
+        "mismatched types",
+    );
+    assert_error(
+        "int main() { int x; return *x; }",
+        "indirection requires a pointer operand",
+    );
+    assert_error(
+        "int main() { return &3 == 0; }",
+        "cannot take the address of an rvalue",
+    );
+}
+
+#[test]
+fn follows_a_linked_list() {
+    let src = "struct Node { int val; struct Node *next; };
+               struct Node nodes[5];
+               int main() {
+                 int i = 0;
+                 while (i < 5) {
+                   nodes[i].val = i * 10;
+                   if (i < 4) nodes[i].next = &nodes[i + 1];
+                   i = i + 1;
+                 }
+                 struct Node *p = &nodes[0];
+                 int sum = 0;
+                 while (p) { sum = sum + p->val; p = p->next; }
+                 putint(sum); putch(32);
+                 putint(nodes[0].next->next->val); putch(32);
+                 putint(nodes[4].next == 0);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "100 20 1");
+}
+
+#[test]
+fn stores_pointers_in_members() {
+    let src = "struct S { int *a; int b; int *c[2]; };
+               int main() {
+                 int x = 3, y = 4;
+                 struct S s;
+                 s.a = &x; s.b = 7; s.c[0] = &y; s.c[1] = &s.b;
+                 *s.a = *s.a + 1;
+                 *s.c[1] = 8;
+                 struct S t = s;
+                 int **pp = s.c;
+                 putint(x + *t.c[0] + t.a[0]); putch(32);
+                 putint(*pp[1]); putch(32);
+                 putint(*t.c[1]);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "12 8 8");
+}
+
+#[test]
+fn takes_addresses_of_members() {
+    let src = "struct In { int v[2]; };
+               struct S { int a; struct In in; };
+               int get(struct In *p) { return p->v[0] + p->v[1]; }
+               int main() {
+                 struct S s = {1, {2, 3}};
+                 int *pa = &s.a;
+                 *pa = 10;
+                 struct In *pi = &s.in;
+                 pi->v[1] = 20;
+                 int *pv = s.in.v;
+                 return s.a + get(&s.in) + pv[0];
+               }";
+    assert_eq!(exit_code(src), 34);
+}
+
+#[test]
+fn gives_pointer_members_pointer_sized_slots() {
+    let src = "struct Node { int val; struct Node *next; int tag; };
+               struct Node n;
+               int main() { n.next = &n; return n.next->tag; }";
+    let rv32 = asm(src, Target::Rv32, OptLevel::O0).unwrap();
+    assert!(rv32.contains("n:\n  .zero 12\n"), "{}", rv32);
+    let rv64 = asm(src, Target::Rv64, OptLevel::O0).unwrap();
+    assert!(rv64.contains("n:\n  .zero 24\n"), "{}", rv64);
+}
+
+#[test]
+fn rejects_incomplete_members() {
+    assert_error(
+        "struct Node { int val; struct Node next; };",
+        "field has incomplete type `struct Node`",
+    );
+}
+
+#[test]
+fn rejects_addresses_of_constants() {
+    for src in [
+        "const int c = 1; int main() { int *p = &c; return *p; }",
+        "int main() { const int c = 3; return *&c; }",
+        "enum E { A }; int main() { int *p = &A; return *p; }",
+        "const int a[2] = {1, 2}; int main() { int *p = &a[1]; return *p; }",
+    ] {
+        assert_eq!(
+            errors(src),
+            ["cannot take the address of a constant"],
+            "{src}"
+        );
+    }
+    for src in [
+        "const int t[1] = {1}; int main() { int *p = t; *p = 9; return t[0]; }",
+        "const int m[2][2] = {}; int main() { putarray(2, m[1]); return m[1][0]; }",
+    ] {
+        assert_eq!(
+            errors(src),
+            ["a `const` array cannot be converted to a pointer"],
+            "{src}"
+        );
+    }
+}
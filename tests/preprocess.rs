@@ -0,0 +1,162 @@
+mod common;
+
+use std::fs;
+
+use common::*;
+use rcompiler::diag::SourceFile;
+use rcompiler::preprocess::preprocess;
+
+/// Write the files into a new directory, and preprocess `main.c` there with `include` as the
+/// include directory, giving the text and the error messages
+fn preprocess_files(name: &str, files: &[(&str, &str)]) -> (String, Vec<String>) {
+    let dir = std::env::temp_dir().join(format!("rcompiler-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, src) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, src).unwrap();
+    }
+    let main = dir.join("main.c");
+    let file = SourceFile::new(
+        main.display().to_string(),
+        fs::read_to_string(&main).unwrap(),
+    );
+    let (sources, diags) = preprocess(file, &[dir.join("include")]);
+    let errors = diags.iter().map(|d| d.message.clone()).collect();
+    let text = sources.text().to_owned();
+    fs::remove_dir_all(&dir).unwrap();
+    (text, errors)
+}
+
+#[test]
+fn stringizes_and_pastes() {
+    let src = r#"#define STR(x) #x
+                 #define CAT(a, b) a##b
+                 #define NUM(a, b) CAT(a, b)
+                 int main() {
+                   int CAT(v, 1) = 5;
+                   putf(STR(v1 is "%d"), v1);
+                   putint(NUM(1, 2) + NUM(, 3));
+                   return 0;
+                 }"#;
+    assert_eq!(stdout(src), "v1 is \"5\"15");
+}
+
+#[test]
+fn expands_variadic_macros() {
+    let src = r#"#define SHOW(fmt, ...) putf(fmt, __VA_ARGS__)
+                 #define FIRST(x, ...) x
+                 #define COUNT(...) count(0, __VA_ARGS__)
+                 int count(int a, int b, int c) { return a + b * 10 + c * 100; }
+                 int main() {
+                   SHOW("%d %d ", 1, FIRST(2, 3, 4));
+                   putint(COUNT(1, count(1, 0, 0)));
+                   return 0;
+                 }"#;
+    assert_eq!(stdout(src), "1 2 110");
+}
+
+#[test]
+fn hides_a_macro_in_its_own_expansion() {
+    // `F(2)` is `2 + F(1)`, where the inner `F` is the function
+    let src = "int F(int x) { return x * 100; }
+               int v = 2;
+               #define F(x) x + G
+               #define G F(1)
+               #define v v + 1
+               int main() { putint(F(2)); putch(32); putint(v); return 0; }";
+    assert_eq!(stdout(src), "102 3");
+}
+
+#[test]
+fn keeps_the_taken_branches() {
+    let src = "#define A
+               #ifdef A
+               int a = 1;
+               #else
+               int a = 2;
+               #endif
+               #ifndef A
+               int b = 1;
+               #elif defined(B) || 1 + 1 == 3
+               int b = 2;
+               #else
+               int b = 3;
+               #endif
+               #if 0
+               #error not taken
+               #endif
+               int main() { return a * 10 + b; }";
+    assert_eq!(exit_code(src), 13);
+    assert_error(
+        "#ifdef A\nint a;\n#else\n#else\n#endif",
+        "`#else` after `#else`",
+    );
+    assert_error("#if 1\nint a;", "unterminated conditional directive");
+}
+
+#[test]
+fn prefers_a_sylib_header_in_the_include_directories() {
+    let (text, errors) = preprocess_files(
+        "sylib",
+        &[
+            ("main.c", "#include \"sylib.h\"\nint x = FROM_HEADER;\n"),
+            ("include/sylib.h", "#define FROM_HEADER 7\nint getint();\n"),
+        ],
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert!(text.contains("int getint();"), "{}", text);
+    // the expansion is kept apart from the tokens around
+    assert!(text.contains("int x =  7 ;"), "{}", text);
+
+    // the built-in declarations are used without one
+    let (text, errors) = preprocess_files("no-sylib", &[("main.c", "#include <sylib.h>\n")]);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert!(text.trim().is_empty(), "{}", text);
+}
+
+#[test]
+fn finds_quoted_headers_next_to_the_file_first() {
+    let (text, errors) = preprocess_files(
+        "quoted",
+        &[
+            ("main.c", "#include \"a.h\"\n#include <a.h>\n"),
+            ("a.h", "int near;\n"),
+            ("include/a.h", "int far;\n"),
+        ],
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+    let (near, far) = (text.find("int near;"), text.find("int far;"));
+    assert!(near.is_some() && far.is_some() && near < far, "{}", text);
+}
+
+#[test]
+fn stops_include_cycles() {
+    let (text, errors) = preprocess_files(
+        "guarded",
+        &[
+            ("main.c", "#include \"a.h\"\n#include \"b.h\"\n"),
+            (
+                "a.h",
+                "#ifndef A_H\n#define A_H\n#include \"b.h\"\nint a;\n#endif\n",
+            ),
+            (
+                "b.h",
+                "#ifndef B_H\n#define B_H\n#include \"a.h\"\nint b;\n#endif\n",
+            ),
+        ],
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(text.matches("int a;").count(), 1, "{}", text);
+    assert_eq!(text.matches("int b;").count(), 1, "{}", text);
+
+    let (_, errors) = preprocess_files(
+        "cycle",
+        &[
+            ("main.c", "#include \"a.h\"\n"),
+            ("a.h", "#include \"b.h\"\n"),
+            ("b.h", "#include \"a.h\"\n"),
+        ],
+    );
+    assert_eq!(errors, ["`#include` nested too deeply"]);
+}
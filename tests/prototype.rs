@@ -0,0 +1,75 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn forward_declaration_allows_mutual_recursion() {
+    let src = "int odd(int n);
+               int even(int n) { if (n == 0) return 1; return odd(n - 1); }
+               int odd(int n) { if (n == 0) return 0; return even(n - 1); }
+               int main() { putint(even(10)); putint(odd(7)); return 0; }";
+    assert_eq!(stdout(src), "11");
+}
+
+#[test]
+fn parameters_may_be_unnamed_in_a_prototype() {
+    let src = "int sum(int, int[], int *);
+               typedef int T;
+               void show(T, float);
+               int main() {
+                 int a[2] = {3, 4};
+                 int x = 5;
+                 show(sum(2, a, &x), 1.5);
+                 return 0;
+               }
+               int sum(int n, int a[], int *p) { return a[0] + a[n - 1] + *p; }
+               void show(T v, float f) { putint(v); putint(f * 2); }";
+    assert_eq!(stdout(src), "123");
+}
+
+#[test]
+fn parameters_must_be_named_in_a_definition() {
+    assert_error(
+        "int f(int) { return 0; } int main() { return f(1); }",
+        "parameter name omitted",
+    );
+}
+
+#[test]
+fn runtime_functions_may_be_declared_again() {
+    let src = "int getint();
+               void putint(int);
+               void putch(int c);
+               int main() { putint(getint() + 1); putch(10); return 0; }";
+    assert_eq!(run_with_input(src, "41").stdout, "42\n");
+}
+
+#[test]
+fn runtime_functions_cannot_be_redeclared_differently_or_defined() {
+    assert_error(
+        "float getint(); int main() { return 0; }",
+        "conflicting types for `getint`",
+    );
+    assert_error(
+        "int getint() { return 0; } int main() { return 0; }",
+        "redefinition of `getint`",
+    );
+}
+
+#[test]
+fn prototypes_must_agree() {
+    assert_error(
+        "int f(int a); int f(float a) { return 0; } int main() { return 0; }",
+        "conflicting types for `f`",
+    );
+    assert_ok("int f(int); int f(int a); int f(int b) { return b; } int main() { return f(0); }");
+}
+
+#[test]
+fn prototype_without_a_definition_is_external() {
+    let ir = koopa(
+        "int ext(int, int); int main() { return 0; }",
+        rcompiler::OptLevel::O0,
+    );
+    assert!(ir.contains("decl @ext(i32, i32): i32"), "{}", ir);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn recovers_at_each_statement() {
+    let errors = errors(
+        "int main() {
+           int a = 1 +;
+           int b = 2;
+           b = (3 * ;
+           return a + b
+         }
+         int g() { return 1 }",
+    );
+    assert_eq!(
+        errors,
+        [
+            "expected an expression, found `;`",
+            "expected an expression, found `;`",
+            "expected `;`, found `}`",
+            "expected `;`, found `}`",
+        ]
+    );
+}
+
+#[test]
+fn recovers_at_a_declaration() {
+    let errors = errors("int a = ;\nint main() { return 0; }\nint b = 1 1;");
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+}
+
+#[test]
+fn missing_semicolon_is_inserted_after_the_previous_token() {
+    let err = compile_error("int main() {\n  return 0\n}\n");
+    assert!(err.contains("--> test.c:3:1"), "{}", err);
+    assert!(err.contains("help: insert `;`"), "{}", err);
+}
+
+#[test]
+fn unterminated_block() {
+    let errors = errors("int main() { return 0;");
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn all_errors_are_collected() {
+    let errors = errors(
+        "int f(int a) { return a; }
+         int main() {
+           int x = f(1, 2);
+           undefined(3);
+           return f();
+         }",
+    );
+    assert_eq!(
+        errors,
+        [
+            "`undefined` is used before definition",
+            "this function takes 1 argument but 2 arguments were supplied",
+            "this function takes 1 argument but 0 arguments were supplied",
+        ]
+    );
+}
+
+#[test]
+fn assignment_to_a_constant() {
+    assert_error(
+        "int main() { const int c = 1; c = 2; return c; }",
+        "attempt to assign a const value",
+    );
+}
+
+#[test]
+fn assignment_to_an_array() {
+    assert_error(
+        "int main() { int a[2]; a = 3; return 0; }",
+        "cannot assign to a value of type `int[2]`",
+    );
+}
+
+#[test]
+fn missing_main() {
+    assert_error("int f() { return 0; }", "main function is not defined");
+}
+
+#[test]
+fn redefinition_in_the_same_scope() {
+    assert_error(
+        "int main() { int a = 1; int a = 2; return a; }",
+        "redefinition of `a`",
+    );
+    assert_ok("int main() { int a = 1; { int a = 2; } return a; }");
+}
+
+#[test]
+fn arrays_of_size_zero() {
+    assert_error("int a[0]; int main() { return 0; }", "array size is zero");
+    assert_error(
+        "int f(int a[][0]) { return 0; } int main() { return 0; }",
+        "array size is zero",
+    );
+    assert_error(
+        "const int n = 0; int main() { int a[2][n]; return 0; }",
+        "array size is zero",
+    );
+}
+
+#[test]
+fn too_many_braces_around_a_scalar() {
+    assert_eq!(
+        errors("int main() { int a[2] = {{{1}}}; return 0; }"),
+        ["too many braces around scalar initializer"]
+    );
+    assert_error(
+        "struct S { int x; }; struct S s = {{{1}}}; int main() { return 0; }",
+        "too many braces around scalar initializer",
+    );
+    let src = "int a[2][2] = {1, {2}, {3}};
+               int main() { int b[3] = {{4}, {}, 5}; putint(a[0][1] + a[1][0] + b[0] + b[2]); return 0; }";
+    assert_eq!(stdout(src), "14");
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn error_points_at_the_expression() {
+    let err = compile_error("int main() {\n  int a = 1;\n  return a + b;\n}\n");
+    assert!(
+        err.contains("error: `b` is used before definition"),
+        "{}",
+        err
+    );
+    assert!(err.contains("--> test.c:3:14"), "{}", err);
+    assert!(
+        err.contains("  return a + b;\n  |              ^"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn error_underlines_the_statement() {
+    let err = compile_error("int main() {\n  break;\n  return 0;\n}\n");
+    assert!(err.contains("--> test.c:2:3"), "{}", err);
+    assert!(err.contains("  break;\n  |   ^^^^^^"), "{}", err);
+}
+
+#[test]
+fn summary_counts_the_errors() {
+    let err = compile_error("int main() { return x + y; }");
+    assert!(
+        err.ends_with("could not compile `test.c` due to 2 previous errors"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn error_in_a_macro_points_at_the_invocation() {
+    let src = "#define NEXT(a) (a + z)\nint main() {\n  return NEXT(1) * 2;\n}\n";
+    let err = compile_error(src);
+    assert!(err.contains("--> test.c:3:10"), "{}", err);
+    assert!(
+        err.contains("  return NEXT(1) * 2;\n  |          ^^^^^^^"),
+        "{}",
+        err
+    );
+    // an argument is located where it is written
+    let err = compile_error("#define ID(a) a\nint main() { return ID(y); }\n");
+    assert!(err.contains("--> test.c:2:24"), "{}", err);
+}
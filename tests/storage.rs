@@ -0,0 +1,159 @@
+mod common;
+
+use common::*;
+use rcompiler::codegen::Target;
+use rcompiler::OptLevel;
+
+#[test]
+fn keeps_static_locals_across_calls() {
+    let src = "int next() { static int n = 10; n = n + 1; return n; }
+               int other() { static int n; n = n + 5; return n; }
+               int main() {
+                 next(); next();
+                 putint(next()); putch(32); putint(other()); putint(other());
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "13 510");
+}
+
+#[test]
+fn shares_a_global_with_its_extern_declarations() {
+    let src = "int a = 4; extern int a;
+               extern int b; int b = 5;
+               extern int b;
+               int main() { a = a + 1; return a * 10 + b; }";
+    assert_eq!(exit_code(src), 55);
+}
+
+#[test]
+fn refers_to_the_global_from_an_extern_in_a_block() {
+    let src = "int e = 4;
+               int f() { extern int g; return g; }
+               int main() {
+                 int e = 1;
+                 {
+                   extern int e;
+                   e = e + 2;
+                 }
+                 putint(e); putint(f());
+                 return 0;
+               }
+               int g = 7;";
+    assert_eq!(stdout(src), "17");
+    // the global is neither shadowed nor unused
+    let warnings = warnings("int e; int main() { extern int e; return 0; }", &["all"]);
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+#[test]
+fn leaves_an_undefined_extern_to_other_files() {
+    let src = "int main() { extern int ext; return ext; }
+               int f() { int ext = 2; return ext; }";
+    let asm = asm(src, Target::Rv32, OptLevel::O0).unwrap();
+    assert!(asm.contains("la t0, ext"), "{}", asm);
+    assert!(!asm.contains("ext:"), "{}", asm);
+}
+
+#[test]
+fn accepts_extern_prototypes_and_arrays_of_unknown_length() {
+    let src = "extern int f(int);
+               extern int a[];
+               extern int b[][2];
+               int main() {
+                 extern int a[];
+                 putint(f(a[2] + b[1][0]));
+                 return 0;
+               }
+               int a[3] = {1, 2, 3};
+               int b[2][2] = {{1, 2}, {3, 4}};
+               extern int f(int x) { return x * 2; }";
+    assert_eq!(stdout(src), "12");
+    let src = "extern int ext[];
+               extern int ext_f(int);
+               int main() { return ext_f(ext[1]); }";
+    let asm = asm(src, Target::Rv64, OptLevel::O2).unwrap();
+    assert!(asm.contains("call ext_f"), "{}", asm);
+    assert!(asm.contains("la t0, ext"), "{}", asm);
+    assert!(!asm.contains("ext:"), "{}", asm);
+}
+
+#[test]
+fn zero_initializes_static_constants() {
+    let src = "static const int z, s = 3, t[3] = {4};
+               static const float f;
+               int main() {
+                 static const int l, m[2];
+                 return z + s + t[0] + t[2] + l + m[1] + (f == 0);
+               }";
+    assert_eq!(exit_code(src), 8);
+}
+
+#[test]
+fn reads_extern_constants_from_memory() {
+    let src = "extern const int c, d[];
+               int main() { extern const int c; return c + d[1]; }";
+    let asm = asm(src, Target::Rv32, OptLevel::O2).unwrap();
+    assert!(asm.contains("la t0, c"), "{}", asm);
+    assert!(asm.contains("la t0, d"), "{}", asm);
+    assert_error(
+        "extern const int c; int main() { c = 1; return 0; }",
+        "attempt to assign a const value",
+    );
+    assert_error(
+        "extern const int c[2]; int main() { c[0] += 1; return 0; }",
+        "attempt to assign a const value",
+    );
+    assert_error(
+        "extern const int c; int main() { int *p = &c; return 0; }",
+        "cannot take the address of a constant",
+    );
+    assert_error(
+        "extern const int c[]; int main() { int *p = c; return 0; }",
+        "a `const` array cannot be converted to a pointer",
+    );
+    assert_error(
+        "int c; extern const int c;",
+        "conflicting `const` qualifiers for `c`",
+    );
+}
+
+#[test]
+fn rejects_invalid_extern_declarations() {
+    assert_error("int a; extern float a;", "conflicting types for `a`");
+    assert_error(
+        "int a; int main() { extern int a[2]; return 0; }",
+        "conflicting types for `a`",
+    );
+    assert_error(
+        "extern int a = 1;",
+        "`extern` variable cannot have an initializer",
+    );
+    assert_error(
+        "int main() { extern int a = 1; return 0; }",
+        "`extern` variable cannot have an initializer",
+    );
+    assert_error("int a = 1; int a = 2;", "redefinition of `a`");
+    assert_error(
+        "int main() { int a; extern int a; return 0; }",
+        "redefinition of `a`",
+    );
+    assert_error("int a[3]; extern int a[][3];", "conflicting types for `a`");
+    assert_error("extern int a[]; float a[2];", "conflicting types for `a`");
+    assert_error("int a[];", "array size missing in `a`");
+    assert_error(
+        "static int f() { return 0; }",
+        "`static` functions are not supported",
+    );
+}
+
+#[test]
+fn rejects_the_names_of_static_and_extern_symbols() {
+    assert_error(
+        "int __sysy_static_x; int main() { static int x; return x; }",
+        "`__sysy_static_x` is reserved for the compiler",
+    );
+    assert_error(
+        "extern int __sysy_extern_y; int main() { return 0; }",
+        "`__sysy_extern_y` is reserved for the compiler",
+    );
+}
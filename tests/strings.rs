@@ -0,0 +1,68 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn character_literals_are_ints() {
+    let src = r"int main() {
+                  putch('h'); putch('i'); putch('\n');
+                  putint('a' + 1); putch(' ');
+                  putint('\0'); putint('\t'); putint('\\'); putint('\''); putint('\x41');
+                  return 0;
+                }";
+    assert_eq!(stdout(src), "hi\n98 09923965");
+}
+
+#[test]
+fn formats_with_putf() {
+    let src = r#"int main() {
+                   int n = -42;
+                   float f = 1.5;
+                   putf("n = %d, c = %c, x = %x\n", n, 'z', 255);
+                   putf("%f%%\n", f);
+                   putf("no arguments\t|\"quoted\"|\n");
+                   return 0;
+                 }"#;
+    assert_eq!(
+        stdout(src),
+        "n = -42, c = z, x = ff\n1.500000%\nno arguments\t|\"quoted\"|\n"
+    );
+}
+
+#[test]
+fn passes_many_variadic_arguments() {
+    let src = r#"int main() {
+                   float f = 0.25;
+                   putf("%d %d %d %d %d %d %d %d %d %f %d\n", 1, 2, 3, 4, 5, 6, 7, 8, 9, f, 10);
+                   return 0;
+                 }"#;
+    assert_eq!(stdout(src), "1 2 3 4 5 6 7 8 9 0.250000 10\n");
+}
+
+#[test]
+fn emits_strings_as_read_only_bytes() {
+    let src = r#"int main() { putf("ab\n"); putf("ab\n"); putf("c"); return 0; }"#;
+    let asm = asm(
+        src,
+        rcompiler::codegen::Target::Rv32,
+        rcompiler::OptLevel::O0,
+    )
+    .unwrap();
+    assert!(asm.contains(".rodata"), "{}", asm);
+    // equal literals share one array
+    assert_eq!(asm.matches(".asciz").count(), 2, "{}", asm);
+}
+
+#[test]
+fn rejects_invalid_literals() {
+    assert_error(
+        r#"int main() { int a[2] = "x"; return 0; }"#,
+        "array must be initialized with an initializer list",
+    );
+    assert_error(
+        r#"int main() { return *"abc"; }"#,
+        "accessing the characters of a string is not supported",
+    );
+    assert!(!errors("int main() { return 'ab'; }").is_empty());
+    assert!(!errors("int main() { putf(1); return 0; }").is_empty());
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn accesses_members() {
+    let src = "struct Point { int x; float y; };
+               int main() {
+                 struct Point p;
+                 p.x = 3;
+                 p.y = 1.5;
+                 putint(p.x * 2); putch(32); putfloat(p.y * 2);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "6 3");
+}
+
+#[test]
+fn initializes_globals_locals_and_arrays_of_structs() {
+    let src = "struct Pair { int a; int b[2]; };
+               struct Pair g = {1, {2, 3}};
+               struct Pair arr[2] = {{4, 5, 6}, {7}};
+               int main() {
+                 struct Pair l = {8, 9};
+                 putint(g.a + g.b[0] + g.b[1]); putch(32);
+                 putint(arr[0].b[1] + arr[1].a + arr[1].b[0]); putch(32);
+                 putint(l.a + l.b[0] + l.b[1]);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "6 13 17");
+}
+
+#[test]
+fn nests_structs_and_indexes_members() {
+    let src = "struct Inner { int v[3]; };
+               struct Outer { int tag; struct Inner in[2]; };
+               struct Outer o;
+               int main() {
+                 int i = 0;
+                 while (i < 6) { o.in[i / 3].v[i % 3] = i * i; i = i + 1; }
+                 o.tag = o.in[1].v[2] - o.in[0].v[1];
+                 return o.tag;
+               }";
+    assert_eq!(exit_code(src), 24);
+}
+
+#[test]
+fn copies_on_assignment() {
+    let src = "struct S { int a; int b; };
+               int main() {
+                 struct S s = {1, 2}, t;
+                 t = s;
+                 s.a = 10;
+                 putint(t.a); putint(t.b); putint(s.a);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "1210");
+}
+
+#[test]
+fn passes_structs_by_value() {
+    let src = "struct S { int a; int b; };
+               int sum(struct S s) { s.a = s.a + s.b; return s.a; }
+               int main() {
+                 struct S s = {3, 4};
+                 putint(sum(s)); putch(32); putint(s.a);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "7 3");
+}
+
+#[test]
+fn accesses_members_through_pointers() {
+    let src = "struct S { int a; int b; };
+               void bump(struct S *p) { p->a = p->a + 1; p[0].b = 9; }
+               int main() {
+                 struct S s = {1, 2};
+                 bump(&s);
+                 return s.a * 10 + s.b;
+               }";
+    assert_eq!(exit_code(src), 29);
+}
+
+#[test]
+fn accesses_members_of_any_place() {
+    let src = "struct S { int a; int b[2]; struct S *next; };
+               struct S g = {1, {2, 3}};
+               struct S *get() { return &g; }
+               int main() {
+                 struct S s = {4, {5, 6}};
+                 struct S *p = &s;
+                 s.next = &g;
+                 (*p).a = 7;
+                 (*p).b[1] = (*p).a + 1;
+                 putint(s.a); putch(32); putint(s.b[1]); putch(32);
+                 putint(get()->b[0]); putch(32);
+                 putint((*(*p).next).a + (p->next)->b[1] + (&s)->b[0]); putch(32);
+                 int *q = &(*p).b[1];
+                 (*get()).a += 10;
+                 return *q + g.a;
+               }";
+    let out = run(src);
+    assert_eq!(out.stdout, "7 8 2 9 ");
+    assert_eq!(out.exit, 19);
+}
+
+#[test]
+fn lays_out_members_in_order() {
+    let src = "struct S { int a; int b[2]; int c; };
+               struct S g = {1, 2, 3, 4};
+               int main() { return g.c; }";
+    assert!(
+        koopa(src, rcompiler::OptLevel::O0).contains("global @g = alloc [i32, 4], {1, 2, 3, 4}")
+    );
+}
+
+#[test]
+fn rejects_invalid_members() {
+    assert_error(
+        "struct S { int a; }; int main() { struct S s; return s.b; }",
+        "no member named `b` in `struct S`",
+    );
+    assert_error(
+        "int main() { int x; return x.a; }",
+        "member reference base type `int` is not a structure",
+    );
+    assert_error(
+        "struct S { int a; }; int main() { struct S s; return s->a; }",
+        "member reference type `struct S` is not a pointer",
+    );
+    assert_error(
+        "int *f(); int main() { return (*f()).a; }",
+        "member reference base type `int` is not a structure",
+    );
+    assert_error(
+        "struct S { int a; }; struct S *f(); int main() { return f().a; }",
+        "member reference base type `struct S *` is not a structure",
+    );
+    assert_error("struct S { int a; float a; };", "duplicate member `a`");
+}
+
+#[test]
+fn rejects_invalid_struct_types() {
+    assert_error(
+        "struct S { int a; }; struct S { int b; };",
+        "redefinition of `struct S`",
+    );
+    assert_error("struct T t;", "unknown type `struct T`");
+    assert_error("struct S { void v; };", "field has incomplete type `void`");
+    assert_error(
+        "struct S { int a; }; struct T { int a; };
+         int main() { struct S s; struct T t; s = t; return 0; }",
+        "mismatched types",
+    );
+}
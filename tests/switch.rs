@@ -0,0 +1,217 @@
+mod common;
+
+use common::*;
+use rcompiler::codegen::Target;
+use rcompiler::OptLevel;
+
+/// `switch` on the input with dense cases 0 to 4, where `x` is live across pressure enough to
+/// be spilled
+fn spilled_switch() -> String {
+    let decls: String = (0..30)
+        .map(|i| format!("int v{} = getint() + {};\n", i, i))
+        .collect();
+    let sum: String = (0..30).map(|i| format!("v{} + ", i)).collect();
+    format!(
+        "int main() {{
+           {}
+           int x = getint(), r;
+           switch (x) {{
+             case 0: r = 10; break;
+             case 1: r = 11; break;
+             case 2: r = 12; break;
+             case 3: r = 13; break;
+             case 4: r = 14; break;
+             default: r = 99;
+           }}
+           putint(r); putch(32); putint({}x);
+           return 0;
+         }}",
+        decls, sum
+    )
+}
+
+#[test]
+fn falls_through_until_break() {
+    let src = "int f(int x) {
+                 int r = 0;
+                 switch (x) {
+                   case 1: r = r + 1;
+                   case 2: r = r + 10; break;
+                   case 3: r = r + 100;
+                   default: r = r + 1000;
+                 }
+                 return r;
+               }
+               int main() {
+                 putint(f(1)); putch(32); putint(f(2)); putch(32);
+                 putint(f(3)); putch(32); putint(f(4));
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "11 10 1100 1000");
+}
+
+#[test]
+fn dispatches_dense_and_sparse_cases() {
+    let src = "int dense(int x) {
+                 switch (x) {
+                   case -1: return 9; case 0: return 10; case 1: return 11; case 2: return 12;
+                   case 3: return 13; case 5: return 15;
+                 }
+                 return 0;
+               }
+               int sparse(int x) {
+                 switch (x) {
+                   case -100000: return 1; case 7: return 2; case 300: return 3;
+                   case 4096: return 4; case 99999: return 5; case 2147483647: return 6;
+                 }
+                 return 0;
+               }
+               int main() {
+                 int i = -2;
+                 while (i < 7) { putint(dense(i)); i = i + 1; }
+                 putch(32);
+                 putint(sparse(-100000)); putint(sparse(7)); putint(sparse(300));
+                 putint(sparse(4096)); putint(sparse(99999)); putint(sparse(2147483647));
+                 putint(sparse(8));
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "09101112130150 1234560");
+}
+
+#[test]
+fn breaks_out_of_the_switch_inside_a_loop() {
+    let src = "int main() {
+                 int i = 0, n = 0;
+                 while (i < 10) {
+                   i = i + 1;
+                   switch (i % 4) {
+                     case 0: continue;
+                     case 1: n = n + 1; break;
+                     default: n = n + 10;
+                   }
+                   n = n + 100;
+                 }
+                 return n % 256;
+               }";
+    // 3 ones, 5 tens, and 8 hundreds
+    assert_eq!(exit_code(src), (3 + 50 + 800) % 256);
+}
+
+#[test]
+fn jumps_through_a_table_on_a_spilled_value() {
+    let src = spilled_switch();
+    let input = |x: i32| {
+        let mut input: Vec<String> = (0..30).map(|i| i.to_string()).collect();
+        input.push(x.to_string());
+        input.join(" ")
+    };
+    for (x, r) in [(0, 10), (3, 13), (4, 14), (5, 99), (-1, 99)] {
+        let sum = 2 * (0..30).sum::<i32>() + x;
+        let expected = format!("{} {}", r, sum);
+        assert_eq!(run_with_input(&src, &input(x)).stdout, expected);
+    }
+
+    for target in [Target::Rv32, Target::Rv64] {
+        let asm = asm(&src, target, OptLevel::O2).unwrap();
+        assert!(asm.contains("_table"), "{}", asm);
+        // the bound is not loaded over the index
+        assert!(!asm.contains("bgeu t0, t0"), "{}", asm);
+    }
+}
+
+#[test]
+fn rejects_invalid_labels() {
+    assert_error(
+        "int main() { int x = 1; switch (x) { case 1: case 1: break; } return 0; }",
+        "duplicate case value",
+    );
+    assert_error(
+        "int main() { switch (1) { default: default: break; } return 0; }",
+        "multiple `default` labels in one `switch`",
+    );
+    assert_error(
+        "int main() { case 1: return 0; }",
+        "`case` label not within a `switch` statement",
+    );
+    assert_error(
+        "int main() { break; }",
+        "`break` statement not within a loop or `switch`",
+    );
+}
+
+#[test]
+fn converts_case_values_to_the_type_of_the_condition() {
+    let src = "int f(unsigned u) {
+                 switch (u) {
+                   case 4000000000: return 1;
+                   case -1: return 2;
+                   case 2 + 3: return 3;
+                 }
+                 return 0;
+               }
+               int main() {
+                 unsigned big = 4000000000;
+                 putint(f(big)); putint(f(-1)); putint(f(5)); putint(f(6));
+                 switch (getint()) { case 4294967297: putint(7); }
+                 return 0;
+               }";
+    assert_eq!(run_with_input(src, "1").stdout, "12307");
+}
+
+#[test]
+fn switches_on_long_long() {
+    let src = "int f(long long x) {
+                 switch (x) {
+                   case 1: return 1;
+                   case 4294967297: return 2;
+                   case -4294967296: return 3;
+                   case -1: return 4;
+                   case 9000000000000000000: return 5;
+                   case 0: x = 7;
+                   case 2: return x;
+                   default: return 9;
+                 }
+               }
+               int main() {
+                 long long big = 4294967296;
+                 putint(f(1)); putint(f(big + 1)); putint(f(-big)); putint(f(-1));
+                 putint(f(9000000000000000000)); putint(f(0)); putint(f(2));
+                 putint(f(big)); putint(f(getint()));
+                 return 0;
+               }";
+    assert_eq!(run_with_input(src, "3").stdout, "123457299");
+    assert_error(
+        "int main() { long long x = 1; switch (x) { case 4294967297: case 4294967297: break; } return 0; }",
+        "duplicate case value `4294967297`",
+    );
+    assert_ok(
+        "int main() { long long x = 1; switch (x) { case 1: case 4294967297: break; } return 0; }",
+    );
+}
+
+#[test]
+fn finds_duplicates_after_the_conversion() {
+    assert_error(
+        "int main() { int x = 1; switch (x) { case 1: case 4294967297: break; } return 0; }",
+        "duplicate case value `1`",
+    );
+    assert_error(
+        "int main() { unsigned x = 1; switch (x) { case -1: case 4294967295: break; } return 0; }",
+        "duplicate case value `4294967295`",
+    );
+}
+
+#[test]
+fn reports_labels_which_are_not_integer_constants() {
+    for (label, message) in [
+        ("x", "case value is not a constant"),
+        ("getint()", "case value is not a constant"),
+        ("1.5", "expected an integer type, found `float`"),
+    ] {
+        let src = format!(
+            "int main() {{ int x = 1; switch (x) {{ case {}: return 1; default: break; }} return 0; }}",
+            label
+        );
+        assert_error(&src, message);
+    }
+}
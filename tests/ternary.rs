@@ -0,0 +1,46 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn selects_a_branch() {
+    let src = "int main() {
+                 int a = 3, b = 5;
+                 putint(a > b ? a : b);
+                 putint(a < b ? a : b);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "53");
+}
+
+#[test]
+fn evaluates_only_the_chosen_branch() {
+    let src = "int n = 0;
+               int hit(int v) { n = n + v; return v; }
+               int main() {
+                 int r = 1 ? hit(1) : hit(10);
+                 r = r + (0 ? hit(100) : hit(1000));
+                 putint(r); putch(32); putint(n);
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "1001 1001");
+}
+
+#[test]
+fn nests_to_the_right() {
+    let src = "int sign(int x) { return x > 0 ? 1 : x < 0 ? -1 : 0; }
+               int main() { putint(sign(5)); putint(sign(-5)); putint(sign(0)); return 0; }";
+    assert_eq!(stdout(src), "1-10");
+}
+
+#[test]
+fn branches_are_converted_to_a_common_type() {
+    let src = "int main() { int c = 1; float f = c ? 1 : 2.5; return (c ? 0.5 : 1) * 4 + f; }";
+    assert_eq!(exit_code(src), 3);
+}
+
+#[test]
+fn folds_in_constant_expressions() {
+    let src = "const int N = 2 > 1 ? 4 : 8; int a[N]; int main() { return N; }";
+    assert_eq!(exit_code(src), 4);
+}
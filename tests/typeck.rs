@@ -0,0 +1,74 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn void_value_in_an_expression() {
+    assert_error(
+        "void v() {} int main() { int x = v(); return x; }",
+        "`void` value cannot be used in an expression",
+    );
+    assert_error(
+        "void v() {} int main() { if (v()) {} return 0; }",
+        "`void` value cannot be used in an expression",
+    );
+}
+
+#[test]
+fn array_argument_of_the_wrong_shape() {
+    let src = "int f(int a[]) { return a[0]; }
+               int main() { int a[2][3]; return f(a); }";
+    assert_error(src, "mismatched types: expected `int *`, found `int[2][3]`");
+    assert_error(
+        "int f(int a[]) { return a[0]; } int main() { return f(1); }",
+        "mismatched types: expected `int *`, found `int`",
+    );
+    assert_ok("int f(int a[]) { return a[0]; } int main() { int a[2][3]; return f(a[1]); }");
+}
+
+#[test]
+fn remainder_of_a_float() {
+    assert_error(
+        "int main() { float q = 1.5; return q % 2; }",
+        "mismatched types: expected an integer type, found `float`",
+    );
+}
+
+#[test]
+fn return_without_a_value() {
+    assert_error(
+        "int main() { return; }",
+        "non-void function should return a value",
+    );
+}
+
+#[test]
+fn errors_in_the_same_program_are_all_reported() {
+    let errors = errors(
+        "void v() {}
+         int main() { int x = v(); float q = 1.5; int r = q % 2; return; }",
+    );
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+}
+
+#[test]
+fn excess_elements_in_initializers() {
+    let errors = errors(
+        "struct S { int x; int y[2]; };
+         int a[2] = {1, 2, 3};
+         struct S s = {1, 2, 3, 4};
+         int main() {
+           int b[2][2] = {{1, 2, 3}, 4};
+           const int c[1] = {{5, 6}};
+           return 0;
+         }",
+    );
+    assert_eq!(errors, ["excess elements in initializer"; 4]);
+    let diag = compile_error("int a[2] = {1, 2, 3};\nint main() { return 0; }");
+    assert!(diag.contains("test.c:1:19"), "{}", diag);
+    assert_ok(
+        "struct S { int x; int y[2]; };
+         struct S s[2] = {1, 2, 3, {4, {5}}};
+         int main() { int b[2][2] = {1, 2, {3}}; return 0; }",
+    );
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn numbers_enumerators_in_order() {
+    let src = "enum Color { RED, GREEN = 5, BLUE, BLACK = -2, WHITE };
+               typedef enum { LOW = BLUE * 2, HIGH } Level;
+               int main() {
+                 int arr[BLUE];
+                 Level l = HIGH;
+                 putint(RED); putch(32); putint(BLUE); putch(32); putint(WHITE); putch(32);
+                 putint(l); putch(32);
+                 switch (l) { case LOW: putint(1); break; case HIGH: putint(2); }
+                 arr[BLUE - 1] = GREEN;
+                 return arr[5];
+               }";
+    let output = run(src);
+    assert_eq!(output.stdout, "0 6 -1 13 2");
+    assert_eq!(output.exit, 5);
+}
+
+#[test]
+fn names_types() {
+    let src = "typedef int row[4];
+               typedef struct P { int x; int y; } Point;
+               typedef Point *PointPtr;
+               row grid[2];
+               int sum(row r) {
+                 int i = 0, s = 0;
+                 while (i < 4) { s = s + r[i]; i = i + 1; }
+                 return s;
+               }
+               int main() {
+                 Point p;
+                 PointPtr pp = &p;
+                 pp->x = 4; pp->y = 5;
+                 grid[1][0] = 1; grid[1][3] = p.x * p.y;
+                 putint(sum(grid[1])); putch(32); putint(sum(grid[0]));
+                 return 0;
+               }";
+    assert_eq!(stdout(src), "21 0");
+}
+
+#[test]
+fn tells_type_names_from_variables_by_scope() {
+    let src = "typedef int T;
+               int main() {
+                 T a = 2;
+                 T *p = &a;
+                 {
+                   int T = 3;
+                   a = T * a;
+                 }
+                 T b = *p;
+                 return b;
+               }";
+    assert_eq!(exit_code(src), 6);
+    assert!(!errors("typedef int T; int main() { T = 3; return 0; }").is_empty());
+}
+
+#[test]
+fn rejects_invalid_enums_and_typedefs() {
+    assert_error("enum E { A, A };", "redefinition of `A`");
+    assert_error("enum E { A }; int A;", "redefinition of `A`");
+    assert_error("typedef int T; typedef float T;", "redefinition of `T`");
+    assert_error("enum E { A }; enum E { B };", "redefinition of `enum E`");
+    assert_error("enum E e;", "unknown type `enum E`");
+    assert_error(
+        "int x; enum E { A = x };",
+        "initializer of a const value is not a constant",
+    );
+    assert_error(
+        "enum E { A = 1, B = A + 0.5 };",
+        "enumerator value is not an integer constant",
+    );
+}
+
+#[test]
+fn expects_a_type_name_where_a_type_goes() {
+    assert_error(
+        "typedef int T; int f(1) { return 0; }",
+        "expected one of `)`, `enum`, `float`, `int`, `long`, `struct`, `unsigned`, `void` or type name, found `1`",
+    );
+}
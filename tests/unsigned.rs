@@ -0,0 +1,75 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn divides_and_takes_remainders_without_sign() {
+    let src = "int main() {
+                 unsigned a = getint(), b = 7;
+                 putint(a / b); putch(32);
+                 putint(a % b); putch(32);
+                 putint(4294967295u / 7); putch(32);
+                 putint(4294967295u % 7); putch(32);
+                 int i = -7;
+                 putint(i / 2); putch(32); putint(i % 2);
+                 return 0;
+               }";
+    assert_eq!(
+        run_with_input(src, "-1").stdout,
+        "613566756 3 613566756 3 -3 -1"
+    );
+}
+
+#[test]
+fn compares_without_sign() {
+    let src = "int main() {
+                 unsigned big = getint(), one = 1;
+                 int minus = -1;
+                 putint(big > one); putch(32);
+                 putint(one < big); putch(32);
+                 putint(minus < one); putch(32);
+                 putint(minus < 1); putch(32);
+                 putint(-1 > 0u); putch(32);
+                 putint(big >= 4294967295u);
+                 return 0;
+               }";
+    assert_eq!(run_with_input(src, "-1").stdout, "1 1 0 1 1 1");
+}
+
+#[test]
+fn shifts_right_logically() {
+    let src = "int main() {
+                 unsigned a = getint();
+                 int s = 28, i = a;
+                 putint(a >> s); putch(32);
+                 putint(i >> s); putch(32);
+                 putint(4294967280u >> 4); putch(32);
+                 putint((a << 4) >> 4 == 268435440);
+                 return 0;
+               }";
+    assert_eq!(run_with_input(src, "-16").stdout, "15 -1 268435455 1");
+}
+
+#[test]
+fn converts_to_and_from_other_types() {
+    let src = "int main() {
+                 unsigned u = getint();
+                 long long l = u;
+                 float f = u;
+                 unsigned back = 3000000000.0;
+                 putf(\"%lld %d\", l, f > 4e9);
+                 putch(32);
+                 putint(back / 1000);
+                 return 0;
+               }";
+    assert_eq!(run_with_input(src, "-2").stdout, "4294967294 1 3000000");
+}
+
+#[test]
+fn rejects_functions_named_like_intrinsics() {
+    assert_error(
+        "unsigned __sysy_udiv(unsigned a, unsigned b) { return a; }
+         int main() { return __sysy_udiv(6u, 3u); }",
+        "`__sysy_udiv` is reserved for the compiler",
+    );
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn warns_about_unused_variables_when_enabled() {
+    let src = "int g;
+               int main() {
+                 int a = 1, b;
+                 static int s;
+                 const int c = 2;
+                 int used = 3;
+                 { int inner; }
+                 return used;
+               }";
+    assert!(warnings(src, &[]).is_empty());
+    assert_eq!(
+        warnings(src, &["all"]),
+        [
+            "unused variable `inner`",
+            "unused variable `a`",
+            "unused variable `b`",
+            "unused variable `s`",
+            "unused variable `c`",
+        ]
+    );
+    assert!(warnings(src, &["all", "no-unused-variable"]).is_empty());
+}
+
+#[test]
+fn warns_about_shadowed_names() {
+    let src = "int x;
+               int main() {
+                 int x = 1;
+                 { int x = 2; putint(x); }
+                 return x;
+               }";
+    assert_eq!(warnings(src, &["shadow"]).len(), 2);
+    assert!(warnings(src, &["shadow"])[0].starts_with("declaration of `x` shadows "));
+}
+
+#[test]
+fn warns_about_code_after_jumps() {
+    let src = "int main() {
+                 int i = 0;
+                 while (i < 3) {
+                   i = i + 1;
+                   continue;
+                   putint(i);
+                   putint(i);
+                 }
+                 switch (i) {
+                   case 1: return 1;
+                   putint(2);
+                   default: break;
+                 }
+                 return i;
+                 putint(i);
+               }";
+    assert_eq!(exit_code(src), 3);
+    assert_eq!(
+        warnings(src, &["unreachable-code"]),
+        ["code will never be executed"; 3]
+    );
+}
+
+#[test]
+fn follows_infinite_loops_and_branches_for_missing_returns() {
+    let src = "int forever() { while (1) {} }
+               int both(int a) { if (a) return 1; else return 2; }
+               int until(int a) { for (;;) { if (a) break; } }
+               int half(int a) { if (a) return 1; }
+               int dead(int a) { do { return a; } while (1); }
+               int main() { return both(0); }";
+    assert_eq!(
+        warnings(src, &[]),
+        [
+            "non-void function `until` does not return a value in all control paths",
+            "non-void function `half` does not return a value in all control paths",
+        ]
+    );
+}
+
+#[test]
+fn turns_warnings_into_errors() {
+    let src = "int f() {} int main() { return 0; }";
+    let messages = messages(src, rcompiler::diag::Severity::Error, &["error"]);
+    assert_eq!(
+        messages,
+        ["non-void function `f` does not return a value in all control paths"]
+    );
+    assert!(errors(src).is_empty());
+}
+
+#[test]
+fn returns_the_warnings_to_the_caller() {
+    let src = "int main() { return 1 / 0; }";
+    let mut out = vec![];
+    let diag_opts = rcompiler::diag::DiagnosticOptions::default();
+    let warnings = rcompiler::codegen::generate_code(
+        rcompiler::diag::SourceFile::new("test.c".to_owned(), src.to_owned()),
+        &[],
+        &diag_opts,
+        &mut out,
+        rcompiler::OptLevel::O0,
+        rcompiler::codegen::Target::Rv32,
+    )
+    .unwrap();
+    let messages: Vec<_> = warnings.diags.iter().map(|d| d.message.clone()).collect();
+    assert_eq!(messages, ["division by zero"]);
+}